@@ -1,4 +1,4 @@
-use super::selector::{Selector, SelectorComponent};
+use super::selector::{AttributeOperator, Selector, SelectorComponent};
 use super::{Color, Declaration, Rule, StyleSheet, Unit, Value};
 
 pub struct CssParser {
@@ -162,7 +162,11 @@ impl CssParser {
         let mut keyframe_iterations = 0;
         const MAX_KEYFRAMES: usize = 100; // Prevent excessive keyframes
 
-        while self.peek_char() != '}' && !self.eof() && keyframe_iterations < MAX_KEYFRAMES {
+        loop {
+            self.consume_whitespace();
+            if self.peek_char() == '}' || self.eof() || keyframe_iterations >= MAX_KEYFRAMES {
+                break;
+            }
             let selector = self.parse_identifier();
             self.consume_whitespace();
             if !self.expect_char('{') {
@@ -265,6 +269,14 @@ impl CssParser {
         }
     }
 
+    /// Parse the whole input as a comma-separated selector list, as given to
+    /// `querySelector`. `None` if it is empty or has anything after the list.
+    pub fn parse_selector_list(&mut self) -> Option<Vec<Selector>> {
+        let selectors = self.parse_selectors()?;
+        self.consume_whitespace();
+        self.eof().then_some(selectors)
+    }
+
     fn parse_selectors(&mut self) -> Option<Vec<Selector>> {
         let mut selectors = Vec::new();
         let mut iterations = 0;
@@ -298,7 +310,7 @@ impl CssParser {
     }
 
     fn parse_selector_components(&mut self) -> Option<Vec<SelectorComponent>> {
-        let mut components = Vec::new();
+        let mut components: Vec<SelectorComponent> = Vec::new();
         let mut iterations = 0;
         const MAX_ITERATIONS: usize = 100; // Prevent infinite loops
 
@@ -309,11 +321,20 @@ impl CssParser {
                 break;
             }
 
-            self.consume_whitespace();
+            let separated = self.consume_whitespace_and_comments();
             if self.eof() || self.peek_char() == '{' || self.peek_char() == ',' {
                 break;
             }
 
+            // Whitespace between two compound selectors is the descendant combinator;
+            // around an explicit combinator it is just formatting
+            if separated
+                && !matches!(self.peek_char(), '>' | '+' | '~')
+                && matches!(components.last(), Some(last) if !last.is_combinator())
+            {
+                components.push(SelectorComponent::Descendant);
+            }
+
             match self.peek_char() {
                 '#' => {
                     self.next_char();
@@ -360,29 +381,31 @@ impl CssParser {
                     }
                     self.consume_whitespace();
 
-                    let attr_value = if self.peek_char() == '=' || self.peek_char() == '~' || self.peek_char() == '|' || self.peek_char() == '^' || self.peek_char() == '$' || self.peek_char() == '*' {
-                        let operator = self.next_char();
+                    let operator = match self.peek_char() {
+                        '=' => Some(AttributeOperator::Equals),
+                        c => AttributeOperator::from_prefix(c).filter(|_| self.peek_next_char() == Some('=')),
+                    };
+                    let condition = operator.map(|operator| {
+                        if operator != AttributeOperator::Equals {
+                            self.next_char();
+                        }
+                        self.next_char(); // consume '='
                         self.consume_whitespace();
-                        if self.peek_char() == '"' || self.peek_char() == '\'' {
+                        let value = if self.peek_char() == '"' || self.peek_char() == '\'' {
                             let quote_char = self.next_char();
                             let value = self.consume_while(|c| c != quote_char);
-                            if self.peek_char() == quote_char {
-                                self.next_char(); // consume closing quote
-                                Some(format!("{}{}", operator, value))
-                            } else {
-                                // Malformed - no closing quote, use what we have
-                                Some(format!("{}{}", operator, value))
-                            }
+                            // Malformed input without a closing quote keeps what we have
+                            self.expect_char(quote_char);
+                            value
                         } else {
-                            let value = self.parse_identifier();
-                            Some(format!("{}{}", operator, value))
-                        }
-                    } else {
-                        None
-                    };
+                            self.parse_identifier()
+                        };
+                        self.consume_whitespace();
+                        (operator, value)
+                    });
 
                     if self.expect_char(']') {
-                        components.push(SelectorComponent::Attribute(attr_name, attr_value));
+                        components.push(SelectorComponent::Attribute(attr_name, condition));
                     }
                 }
                 c if c.is_ascii_alphabetic() => {
@@ -392,6 +415,10 @@ impl CssParser {
             }
         }
 
+        if components.last() == Some(&SelectorComponent::Descendant) {
+            components.pop();
+        }
+
         if components.is_empty() {
             None
         } else {
@@ -399,6 +426,11 @@ impl CssParser {
         }
     }
 
+    /// Parse a bare declaration list, as found in a `style` attribute.
+    pub fn parse_inline_style(&mut self) -> Vec<Declaration> {
+        self.parse_declarations()
+    }
+
     fn parse_declarations(&mut self) -> Vec<Declaration> {
         let mut declarations = Vec::new();
         let mut iterations = 0;
@@ -414,7 +446,7 @@ impl CssParser {
             }
 
             self.consume_whitespace();
-            if self.peek_char() == '}' {
+            if self.eof() || self.peek_char() == '}' {
                 break;
             }
 
@@ -454,7 +486,24 @@ impl CssParser {
         }
 
         self.consume_whitespace();
-        let value = self.parse_value()?;
+        let mut value = self.parse_value()?;
+
+        // A top-level `/` separates value groups, as in `border-radius: 10px / 20px`.
+        // It is kept as a "/" keyword between them.
+        while self.peek_char() == '/' {
+            self.next_char();
+            let mut values = match value {
+                Value::Multiple(values) => values,
+                single => vec![single],
+            };
+            values.push(Value::Keyword("/".to_string()));
+            match self.parse_value() {
+                Some(Value::Multiple(rest)) => values.extend(rest),
+                Some(rest) => values.push(rest),
+                None => {}
+            }
+            value = Value::Multiple(values);
+        }
 
         // Check for !important
         let important = self.check_important();
@@ -668,10 +717,8 @@ impl CssParser {
     }
 
     fn parse_length(&mut self) -> Option<Value> {
-        let num = self
-            .consume_while(|c| c.is_ascii_digit() || c == '.')
-            .parse()
-            .ok()?;
+        let digits = self.consume_while(|c| c.is_ascii_digit() || c == '.');
+        let num: f32 = digits.parse().ok()?;
         
         // Check for percentage first (it's a single character, not an identifier)
         if self.peek_char() == '%' {
@@ -680,14 +727,17 @@ impl CssParser {
         }
         
         // Otherwise, parse as identifier for other units
-        let unit_str = self.parse_identifier().to_lowercase();
-        let unit = match unit_str.as_str() {
-            "px" => Unit::Px,
-            "em" => Unit::Em,
-            "rem" => Unit::Rem,
-            _ => return None,
-        };
-        Some(Value::Length(num, unit))
+        let unit = self.parse_identifier();
+        if unit.is_empty() {
+            // A bare number: zero may stand for any length, anything else
+            // (opacity, line-height, font-weight, z-index) is kept as written
+            return Some(if num == 0.0 {
+                Value::Length(0.0, Unit::Px)
+            } else {
+                Value::Keyword(digits)
+            });
+        }
+        Some(Value::Length(num, Unit::from_string(&unit)?))
     }
 
     fn parse_color(&mut self) -> Option<Value> {
@@ -867,44 +917,37 @@ impl CssParser {
     }
 
     fn consume_whitespace(&mut self) {
+        self.consume_whitespace_and_comments();
+    }
+
+    /// Skip whitespace and comments, returning whether any actual whitespace was
+    /// seen. Comments are not whitespace: `.a/* note */.b` is still one compound.
+    fn consume_whitespace_and_comments(&mut self) -> bool {
+        let mut saw_whitespace = false;
         loop {
-            self.consume_while(|c| c.is_whitespace());
-            
-            // Skip CSS comments /* ... */
-            if self.peek_char() == '/' && self.position + 1 < self.input.len() {
-                let next_char = self.input.chars().nth(self.position + 1);
-                if next_char == Some('*') {
-                    // Found comment start, skip until */
-                    self.next_char(); // consume '/'
-                    self.next_char(); // consume '*'
-                    
-                    let mut iterations = 0;
-                    const MAX_COMMENT_ITERATIONS: usize = 10000;
-                    
-                    while !self.eof() && iterations < MAX_COMMENT_ITERATIONS {
-                        if self.peek_char() == '*' && self.position + 1 < self.input.len() {
-                            let next = self.input.chars().nth(self.position + 1);
-                            if next == Some('/') {
-                                self.next_char(); // consume '*'
-                                self.next_char(); // consume '/'
-                                break; // Comment ended
-                            }
-                        }
-                        self.next_char();
-                        iterations += 1;
-                    }
-                    
-                    if iterations >= MAX_COMMENT_ITERATIONS {
-                        log::warn!(target: "css", "Comment parsing exceeded maximum iterations, stopping");
-                        break;
-                    }
-                    continue; // Check for more whitespace/comments
-                }
+            saw_whitespace |= !self.consume_while(|c| c.is_whitespace()).is_empty();
+            if !self.consume_comment() {
+                return saw_whitespace;
             }
-            break; // No more whitespace or comments
         }
     }
 
+    // Skip a single `/* ... */` comment at the current position; an unterminated
+    // comment runs to the end of the input.
+    fn consume_comment(&mut self) -> bool {
+        if self.peek_char() != '/' || self.peek_next_char() != Some('*') {
+            return false;
+        }
+        self.next_char(); // consume '/'
+        self.next_char(); // consume '*'
+
+        match self.input[self.position..].find("*/") {
+            Some(end) => self.position += end + 2,
+            None => self.position = self.input.len(),
+        }
+        true
+    }
+
     fn consume_while<F>(&mut self, test: F) -> String
     where
         F: Fn(char) -> bool,
@@ -955,6 +998,10 @@ impl CssParser {
         self.input[self.position..].chars().next().unwrap_or('\0')
     }
 
+    fn peek_next_char(&self) -> Option<char> {
+        self.input[self.position..].chars().nth(1)
+    }
+
     fn expect_char(&mut self, expected: char) -> bool {
         if self.peek_char() == expected {
             self.next_char();
@@ -1,6 +1,6 @@
 //! CSS property definitions and categorization
 
-use super::values::{Value, Color};
+use super::values::{Value, Color, Unit};
 
 /// Known CSS property names for better type safety and validation
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -31,6 +31,10 @@ pub enum Property {
     BorderStyle,
     BorderColor,
     BorderRadius,
+    BorderTopLeftRadius,
+    BorderTopRightRadius,
+    BorderBottomRightRadius,
+    BorderBottomLeftRadius,
 
     // Flexbox
     FlexDirection,
@@ -157,6 +161,10 @@ impl Property {
             "border-style" => Property::BorderStyle,
             "border-color" => Property::BorderColor,
             "border-radius" => Property::BorderRadius,
+            "border-top-left-radius" => Property::BorderTopLeftRadius,
+            "border-top-right-radius" => Property::BorderTopRightRadius,
+            "border-bottom-right-radius" => Property::BorderBottomRightRadius,
+            "border-bottom-left-radius" => Property::BorderBottomLeftRadius,
 
             // Flexbox
             "flex-direction" => Property::FlexDirection,
@@ -283,6 +291,10 @@ impl Property {
             Property::BorderStyle => "border-style".to_string(),
             Property::BorderColor => "border-color".to_string(),
             Property::BorderRadius => "border-radius".to_string(),
+            Property::BorderTopLeftRadius => "border-top-left-radius".to_string(),
+            Property::BorderTopRightRadius => "border-top-right-radius".to_string(),
+            Property::BorderBottomRightRadius => "border-bottom-right-radius".to_string(),
+            Property::BorderBottomLeftRadius => "border-bottom-left-radius".to_string(),
 
             // Flexbox
             Property::FlexDirection => "flex-direction".to_string(),
@@ -395,6 +407,159 @@ impl Property {
         )
     }
 
+    /// The property's initial value, as `initial` resolves to. None for
+    /// properties whose initial value has no representation here.
+    pub fn initial_value(&self) -> Option<Value> {
+        let keyword = |k: &str| Some(Value::Keyword(k.to_string()));
+        let zero = Some(Value::Length(0.0, Unit::Px));
+        match self {
+            Property::Display => keyword("inline"),
+            Property::Position => keyword("static"),
+            Property::Width
+            | Property::Height
+            | Property::FlexBasis
+            | Property::Top
+            | Property::Right
+            | Property::Bottom
+            | Property::Left
+            | Property::ZIndex
+            | Property::Cursor => keyword("auto"),
+            Property::MaxWidth | Property::MaxHeight => keyword("none"),
+            Property::MinWidth
+            | Property::MinHeight
+            | Property::Margin
+            | Property::MarginTop
+            | Property::MarginRight
+            | Property::MarginBottom
+            | Property::MarginLeft
+            | Property::Padding
+            | Property::PaddingTop
+            | Property::PaddingRight
+            | Property::PaddingBottom
+            | Property::PaddingLeft
+            | Property::BorderRadius
+            | Property::BorderTopLeftRadius
+            | Property::BorderTopRightRadius
+            | Property::BorderBottomRightRadius
+            | Property::BorderBottomLeftRadius
+            | Property::Gap => zero,
+            Property::BorderWidth => keyword("medium"),
+            Property::BorderStyle
+            | Property::TextDecoration
+            | Property::TextTransform
+            | Property::BackgroundImage
+            | Property::Transform
+            | Property::BoxShadow
+            | Property::TextShadow
+            | Property::AnimationName => keyword("none"),
+            Property::BorderColor => keyword("currentcolor"),
+            Property::Color => Some(Value::Color(Color::new(0, 0, 0, 255))),
+            Property::BackgroundColor => Some(Value::Color(Color::new(0, 0, 0, 0))),
+            Property::FontSize => keyword("medium"),
+            Property::FontWeight => keyword("400"),
+            Property::LineHeight
+            | Property::LetterSpacing
+            | Property::WordSpacing
+            | Property::WhiteSpace
+            | Property::WordBreak
+            | Property::Content => keyword("normal"),
+            Property::TextAlign => keyword("start"),
+            Property::BackgroundRepeat => keyword("repeat"),
+            Property::Visibility | Property::Overflow | Property::OverflowX | Property::OverflowY => keyword("visible"),
+            Property::VerticalAlign => keyword("baseline"),
+            Property::FlexDirection => keyword("row"),
+            Property::FlexWrap => keyword("nowrap"),
+            Property::TableLayout => keyword("auto"),
+            Property::BorderCollapse => keyword("separate"),
+            Property::ListStyleType => keyword("disc"),
+            Property::ListStylePosition => keyword("outside"),
+            Property::TextOverflow => keyword("clip"),
+            _ => None,
+        }
+    }
+
+    /// The canonical spelling of `value` for this property, so equivalent
+    /// values compare equal: `font-weight: bold` is `700` and `normal` is `400`.
+    /// Values without an alternative spelling are returned unchanged.
+    pub fn normalize_value(&self, value: Value) -> Value {
+        match (self, value) {
+            (Property::FontWeight, Value::Keyword(keyword)) => {
+                let normalized = match keyword.to_ascii_lowercase().as_str() {
+                    "normal" => "400".to_string(),
+                    "bold" => "700".to_string(),
+                    "bolder" | "lighter" => keyword.to_ascii_lowercase(),
+                    number => match number.parse::<f32>() {
+                        Ok(weight) if (1.0..=1000.0).contains(&weight) => weight.to_string(),
+                        _ => keyword,
+                    },
+                };
+                Value::Keyword(normalized)
+            }
+            (_, value) => value,
+        }
+    }
+
+    /// The longhands a shorthand `value` sets, or None when this property isn't
+    /// an expanded shorthand or `value` doesn't fit its grammar.
+    ///
+    /// `margin` and `padding` take 1-4 values for the top, right, bottom and left
+    /// sides. `border-radius` sets the corners top-left, top-right, bottom-right
+    /// and bottom-left from 1-4 horizontal radii, optionally followed by `/` and
+    /// 1-4 vertical ones. A corner whose radii differ gets both, as `Multiple`.
+    pub fn expand_shorthand(&self, value: &Value) -> Option<Vec<(Property, Value)>> {
+        let values = match value {
+            Value::Multiple(values) => values.as_slice(),
+            single => std::slice::from_ref(single),
+        };
+        // A var() may stand for any number of values, so it can't be split up
+        if values.iter().any(|v| matches!(v, Value::Variable(_))) {
+            return None;
+        }
+        let sides = |longhands: [Property; 4]| {
+            four_sides(values).map(|values| longhands.into_iter().zip(values).collect())
+        };
+        match self {
+            Property::Margin => sides([
+                Property::MarginTop,
+                Property::MarginRight,
+                Property::MarginBottom,
+                Property::MarginLeft,
+            ]),
+            Property::Padding => sides([
+                Property::PaddingTop,
+                Property::PaddingRight,
+                Property::PaddingBottom,
+                Property::PaddingLeft,
+            ]),
+            Property::BorderRadius => {
+                let is_slash = |v: &Value| matches!(v, Value::Keyword(k) if k == "/");
+                let (horizontal, vertical) = match values.iter().position(is_slash) {
+                    Some(slash) => (&values[..slash], &values[slash + 1..]),
+                    None => (values, values),
+                };
+                let horizontal = four_sides(horizontal)?;
+                let vertical = four_sides(vertical)?;
+                let corners = [
+                    Property::BorderTopLeftRadius,
+                    Property::BorderTopRightRadius,
+                    Property::BorderBottomRightRadius,
+                    Property::BorderBottomLeftRadius,
+                ];
+                Some(
+                    corners
+                        .into_iter()
+                        .zip(horizontal.into_iter().zip(vertical))
+                        .map(|(corner, (h, v))| {
+                            let radius = if h == v { h } else { Value::Multiple(vec![h, v]) };
+                            (corner, radius)
+                        })
+                        .collect(),
+                )
+            }
+            _ => None,
+        }
+    }
+
     /// Check if this property can be inherited
     pub fn is_inherited(&self) -> bool {
         matches!(
@@ -415,7 +580,61 @@ impl Property {
     }
 }
 
+/// Spread 1-4 values over four sides (or corners) the way `margin` does: one
+/// value sets all four, two set top/bottom and left/right, three set top,
+/// left/right and bottom. None for an empty list, more than four values or a
+/// `/` among them.
+fn four_sides(values: &[Value]) -> Option<[Value; 4]> {
+    if values.iter().any(|v| matches!(v, Value::Keyword(k) if k == "/")) {
+        return None;
+    }
+    match values {
+        [all] => Some([all.clone(), all.clone(), all.clone(), all.clone()]),
+        [vertical, horizontal] => Some([vertical.clone(), horizontal.clone(), vertical.clone(), horizontal.clone()]),
+        [top, horizontal, bottom] => Some([top.clone(), horizontal.clone(), bottom.clone(), horizontal.clone()]),
+        [top, right, bottom, left] => Some([top.clone(), right.clone(), bottom.clone(), left.clone()]),
+        _ => None,
+    }
+}
 
+#[cfg(test)]
+mod tests {
+    use super::*;
 
+    fn px(value: f32) -> Value {
+        Value::Length(value, Unit::Px)
+    }
 
+    fn parse_value(css: &str) -> Value {
+        let declarations = crate::css::parser::CssParser::new(format!("border-radius: {}", css)).parse_inline_style();
+        declarations.into_iter().next().expect("one declaration").value
+    }
 
+    #[test]
+    fn test_border_radius_two_values() {
+        let expanded = Property::BorderRadius.expand_shorthand(&parse_value("10px 20px")).expect("valid radius");
+        assert_eq!(
+            expanded,
+            vec![
+                (Property::BorderTopLeftRadius, px(10.0)),
+                (Property::BorderTopRightRadius, px(20.0)),
+                (Property::BorderBottomRightRadius, px(10.0)),
+                (Property::BorderBottomLeftRadius, px(20.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_border_radius_slash_syntax() {
+        let elliptical = |h, v| Value::Multiple(vec![px(h), px(v)]);
+        let expanded = Property::BorderRadius.expand_shorthand(&parse_value("10px / 20px")).expect("valid radius");
+        assert!(expanded.iter().all(|(_, radius)| *radius == elliptical(10.0, 20.0)), "{:?}", expanded);
+
+        let expanded = Property::BorderRadius.expand_shorthand(&parse_value("1px 2px 3px / 4px 5px")).expect("valid radius");
+        let radii: Vec<Value> = expanded.into_iter().map(|(_, radius)| radius).collect();
+        assert_eq!(radii, vec![elliptical(1.0, 4.0), elliptical(2.0, 5.0), elliptical(3.0, 4.0), elliptical(2.0, 5.0)]);
+
+        assert_eq!(Property::BorderRadius.expand_shorthand(&parse_value("10px /")), None);
+        assert_eq!(Property::BorderRadius.expand_shorthand(&parse_value("1px 2px 3px 4px 5px")), None);
+    }
+}
@@ -1,5 +1,6 @@
 //! CSS rule types and structures
 
+use super::properties::Property;
 use super::selector::Selector;
 use super::values::Value;
 
@@ -51,28 +52,56 @@ pub struct Keyframe {
     pub declarations: Vec<Declaration>,
 }
 
+/// Where a rule came from. Ordered by cascade precedence for normal
+/// declarations: author rules override user rules, which override the UA.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Origin {
+    UserAgent,
+    User,
+    #[default]
+    Author,
+}
+
 /// CSS stylesheet containing all rules
 #[derive(Clone, Debug, Default)]
 pub struct StyleSheet {
     rules: Vec<Rule>,
+    origins: Vec<Origin>, // Parallel to `rules`
 }
 
 impl StyleSheet {
     pub fn new() -> Self {
-        Self { rules: Vec::new() }
+        Self { rules: Vec::new(), origins: Vec::new() }
     }
 
+    /// Add a rule with the author origin.
     pub fn add_rule(&mut self, rule: Rule) {
+        self.add_rule_with_origin(rule, Origin::Author);
+    }
+
+    pub fn add_rule_with_origin(&mut self, rule: Rule, origin: Origin) {
         self.rules.push(rule);
+        self.origins.push(origin);
+    }
+
+    /// Append every rule of `other`, tagging each with `origin`.
+    pub fn merge(&mut self, other: StyleSheet, origin: Origin) {
+        for rule in other.rules {
+            self.add_rule_with_origin(rule, origin);
+        }
     }
 
     pub fn rules(&self) -> &[Rule] {
         &self.rules
     }
 
-    /// Get all style rules (filtering out at-rules)
-    pub fn style_rules(&self) -> Vec<&Rule> {
-        self.rules.iter().filter(|rule| matches!(rule, Rule::StyleRule { .. })).collect()
+    /// Rules paired with their origin, in the order they should be applied:
+    /// by origin precedence, then document order within each origin.
+    pub fn cascade_order(&self) -> Vec<(&Rule, Origin)> {
+        let mut ordered: Vec<_> = self.rules.iter().zip(self.origins.iter().copied()).collect();
+        // Stable, so source order is kept within an origin
+        ordered.sort_by_key(|(_, origin)| *origin);
+        ordered
     }
 
     /// Get all at-rules
@@ -124,6 +153,17 @@ impl Declaration {
         self.important = important;
         self
     }
+
+    /// The longhand declarations this shorthand expands to, each as important
+    /// as the shorthand. Empty for longhands and for values that can't be split.
+    pub fn longhands(&self) -> Vec<Declaration> {
+        Property::from_string(&self.property)
+            .expand_shorthand(&self.value)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(longhand, value)| Declaration::new(longhand.to_string(), value).important(self.important))
+            .collect()
+    }
 }
 
 impl Default for Declaration {
@@ -136,7 +176,55 @@ impl Default for Declaration {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::parser::CssParser;
+
+    #[test]
+    fn test_important_carries_to_longhands() {
+        let sheet = CssParser::new("p { padding: 5px !important; margin: 1px 2px; }".to_string()).parse();
+        let Some(Rule::StyleRule { declarations, .. }) = sheet.rules().first() else {
+            panic!("expected a style rule");
+        };
+        let five = Value::Length(5.0, crate::css::Unit::Px);
+        let padding = declarations[0].longhands();
+        let expected: Vec<Declaration> = ["padding-top", "padding-right", "padding-bottom", "padding-left"]
+            .into_iter()
+            .map(|longhand| Declaration::new(longhand.to_string(), five.clone()).important(true))
+            .collect();
+        assert_eq!(padding, expected);
+
+        let margin = declarations[1].longhands();
+        assert_eq!(margin.len(), 4);
+        assert!(margin.iter().all(|decl| !decl.important));
+        assert!(Declaration::new("color".to_string(), Value::Keyword("red".to_string())).longhands().is_empty());
+    }
 
-
-
-
+    #[test]
+    fn test_merge_preserves_origin() {
+        let mut sheet = CssParser::new("p { color: blue; }".to_string()).parse();
+        let ua = CssParser::new("p { color: black; } div { display: block; }".to_string()).parse();
+        sheet.merge(ua, Origin::UserAgent);
+
+        assert_eq!(sheet.rules().len(), 3);
+        // Each rule keeps its origin; rules are identified by their first property value
+        let order: Vec<(Option<Value>, Origin)> = sheet
+            .cascade_order()
+            .into_iter()
+            .map(|(rule, origin)| match rule {
+                Rule::StyleRule { declarations, .. } => (declarations.first().map(|d| d.value.clone()), origin),
+                Rule::AtRule(_) => (None, origin),
+            })
+            .collect();
+        let keyword = |name: &str| Some(Value::Keyword(name.to_string()));
+        assert_eq!(
+            order,
+            vec![
+                (keyword("black"), Origin::UserAgent),
+                (keyword("block"), Origin::UserAgent),
+                (keyword("blue"), Origin::Author),
+            ]
+        );
+    }
+}
@@ -15,10 +15,62 @@ pub enum SelectorComponent {
     Adjacent,
     PseudoClass(String), // :hover, :active, :first-child, etc.
     PseudoElement(String), // ::before, ::after
-    Attribute(String, Option<String>), // [attr], [attr=value], [attr~=value], etc.
+    Attribute(String, Option<(AttributeOperator, String)>), // [attr], [attr=value], [attr~=value], etc.
 }
 
-#[derive(Clone, Debug, Default, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttributeOperator {
+    Equals,    // =
+    Includes,  // ~=
+    DashMatch, // |=
+    Prefix,    // ^=
+    Suffix,    // $=
+    Substring, // *=
+}
+
+impl AttributeOperator {
+    /// Operator for the character preceding `=`, or `None` for a plain `=`.
+    pub fn from_prefix(prefix: char) -> Option<Self> {
+        match prefix {
+            '~' => Some(AttributeOperator::Includes),
+            '|' => Some(AttributeOperator::DashMatch),
+            '^' => Some(AttributeOperator::Prefix),
+            '$' => Some(AttributeOperator::Suffix),
+            '*' => Some(AttributeOperator::Substring),
+            _ => None,
+        }
+    }
+
+    pub fn matches(&self, actual: &str, expected: &str) -> bool {
+        match self {
+            AttributeOperator::Equals => actual == expected,
+            AttributeOperator::Includes => {
+                !expected.is_empty() && actual.split_whitespace().any(|word| word == expected)
+            }
+            AttributeOperator::DashMatch => {
+                actual == expected
+                    || actual.strip_prefix(expected).is_some_and(|rest| rest.starts_with('-'))
+            }
+            // An empty value never matches the substring operators
+            AttributeOperator::Prefix => !expected.is_empty() && actual.starts_with(expected),
+            AttributeOperator::Suffix => !expected.is_empty() && actual.ends_with(expected),
+            AttributeOperator::Substring => !expected.is_empty() && actual.contains(expected),
+        }
+    }
+}
+
+impl SelectorComponent {
+    pub fn is_combinator(&self) -> bool {
+        matches!(
+            self,
+            SelectorComponent::Descendant | SelectorComponent::Child | SelectorComponent::Adjacent
+        )
+    }
+}
+
+/// Selector specificity as (ids, classes/attributes/pseudo-classes, types/pseudo-elements).
+/// Compared lexicographically, so the derived ordering is the cascade ordering.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Specificity(pub u32, pub u32, pub u32);
 
 impl Selector {
@@ -30,6 +82,10 @@ impl Selector {
         }
     }
 
+    pub fn specificity(&self) -> Specificity {
+        self.specificity
+    }
+
     fn calculate_specificity(components: &[SelectorComponent]) -> Specificity {
         let mut ids = 0;
         let mut classes = 0;
@@ -38,8 +94,11 @@ impl Selector {
         for component in components {
             match component {
                 SelectorComponent::Id(_) => ids += 1,
-                SelectorComponent::Class(_) => classes += 1,
-                SelectorComponent::Type(_) => types += 1,
+                SelectorComponent::Class(_)
+                | SelectorComponent::Attribute(..)
+                | SelectorComponent::PseudoClass(_) => classes += 1,
+                SelectorComponent::Type(_) | SelectorComponent::PseudoElement(_) => types += 1,
+                // The universal selector and combinators add nothing
                 _ => {}
             }
         }
@@ -47,3 +106,125 @@ impl Selector {
         Specificity(ids, classes, types)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::{parser::CssParser, style::StyleEngine, Rule, StyleSheet};
+    use crate::dom::{Attribute, Node, NodeType};
+
+    fn parse_selector(selector: &str) -> Selector {
+        let sheet = CssParser::new(format!("{} {{ color: red; }}", selector)).parse();
+        match sheet.rules().first() {
+            Some(Rule::StyleRule { selectors, .. }) => selectors[0].clone(),
+            other => panic!("expected a style rule for {:?}, got {:?}", selector, other),
+        }
+    }
+
+    fn specificity_of(selector: &str) -> Specificity {
+        parse_selector(selector).specificity()
+    }
+
+    fn element(tag_name: &str, class: &str) -> Node {
+        Node::new(NodeType::Element {
+            tag_name: tag_name.into(),
+            attributes: vec![Attribute {
+                name: "class".into(),
+                value: class.to_string(),
+            }],
+            events: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn test_specificity_ordering() {
+        let id = specificity_of("#id");
+        let two_classes = specificity_of(".class.class");
+        let two_types = specificity_of("div div");
+
+        assert_eq!(id, Specificity(1, 0, 0));
+        assert_eq!(two_classes, Specificity(0, 2, 0));
+        assert_eq!(two_types, Specificity(0, 0, 2));
+        assert!(id > two_classes);
+        assert!(two_classes > two_types);
+    }
+
+    #[test]
+    fn test_specificity_counts_attributes_and_pseudos() {
+        assert_eq!(specificity_of("a[href]:hover::before"), Specificity(0, 2, 2));
+        assert_eq!(specificity_of("*"), Specificity(0, 0, 0));
+    }
+
+    #[test]
+    fn test_descendant_combinator() {
+        let selector = parse_selector(".a .b");
+        assert_eq!(
+            selector.components,
+            vec![
+                SelectorComponent::Class("a".to_string()),
+                SelectorComponent::Descendant,
+                SelectorComponent::Class("b".to_string()),
+            ]
+        );
+        // Whitespace around an explicit combinator is not a descendant combinator
+        assert!(!parse_selector(".a > .b").components.contains(&SelectorComponent::Descendant));
+
+        let outer = element("div", "a");
+        let middle = element("section", "other");
+        let target = element("span", "b");
+        let engine = StyleEngine::new(StyleSheet::new());
+
+        assert!(engine.matches(&target, &[&outer, &middle], &selector));
+        assert!(engine.matches(&target, &[&outer], &selector));
+        assert!(!engine.matches(&target, &[&middle], &selector));
+        assert!(!engine.matches(&target, &[], &selector));
+        assert!(!engine.matches(&outer, &[], &selector));
+    }
+
+    #[test]
+    fn test_multiple_attribute_selectors() {
+        let selector = parse_selector(r#"input[type="text"][data-validation="required"]"#);
+        assert_eq!(
+            selector.components,
+            vec![
+                SelectorComponent::Type("input".to_string()),
+                SelectorComponent::Attribute("type".to_string(), Some((AttributeOperator::Equals, "text".to_string()))),
+                SelectorComponent::Attribute(
+                    "data-validation".to_string(),
+                    Some((AttributeOperator::Equals, "required".to_string()))
+                ),
+            ]
+        );
+
+        let input = |attributes: &[(&str, &str)]| {
+            Node::new(NodeType::Element {
+                tag_name: "input".into(),
+                attributes: attributes
+                    .iter()
+                    .map(|(name, value)| Attribute {
+                        name: (*name).into(),
+                        value: value.to_string(),
+                    })
+                    .collect(),
+                events: Vec::new(),
+            })
+        };
+        let engine = StyleEngine::new(StyleSheet::new());
+
+        let both = input(&[("type", "text"), ("data-validation", "required")]);
+        assert!(engine.matches(&both, &[], &selector));
+        let one = input(&[("type", "text")]);
+        assert!(!engine.matches(&one, &[], &selector));
+    }
+
+    #[test]
+    fn test_attribute_operators() {
+        assert!(AttributeOperator::Includes.matches("btn primary", "primary"));
+        assert!(AttributeOperator::DashMatch.matches("en-US", "en"));
+        assert!(!AttributeOperator::DashMatch.matches("english", "en"));
+        assert!(AttributeOperator::Prefix.matches("https://a", "https://"));
+        assert!(AttributeOperator::Suffix.matches("doc.pdf", ".pdf"));
+        assert!(AttributeOperator::Substring.matches("login-form", "login"));
+        assert!(!AttributeOperator::Substring.matches("anything", ""));
+    }
+}
@@ -1,5 +1,6 @@
 use super::selector::{Selector, SelectorComponent};
-use super::{Declaration, Rule, StyleSheet};
+use super::rules::Origin;
+use super::{Declaration, Property, Rule, StyleSheet, Value};
 use crate::dom::{Node, NodeType};
 
 pub struct StyleEngine {
@@ -11,48 +12,222 @@ impl StyleEngine {
         Self { stylesheet }
     }
 
+    /// Style `node` and everything rendered below it, as the root of a document.
     pub fn apply_styles(&self, node: &Node) -> StyledNode {
-        let mut styled_node = StyledNode::new(node.clone());
+        self.apply_styles_in_context(node, &[])
+    }
+
+    /// Style `node` and its subtree as they sit in the document, with `ancestors`
+    /// from the root down, so selectors with combinators can match.
+    pub fn apply_styles_in_context(&self, node: &Node, ancestors: &[&Node]) -> StyledNode {
+        let parents = self.cascade_ancestors(ancestors);
+        self.style_subtree(node, ancestors, &parents.iter().collect::<Vec<_>>())
+    }
+
+    /// Styles of `node` alone, leaving its children unstyled.
+    pub fn style_node(&self, node: &Node, ancestors: &[&Node]) -> StyledNode {
+        let parents = self.cascade_ancestors(ancestors);
+        self.style_node_under(node, ancestors, &parents.iter().collect::<Vec<_>>())
+    }
+
+    // `node` and its subtree, where `parents` are the styled `ancestors` that
+    // inherited values are read from. Each child inherits from the node styled
+    // here instead of cascading its ancestors again.
+    fn style_subtree(&self, node: &Node, ancestors: &[&Node], parents: &[&StyledNode]) -> StyledNode {
+        let mut styled_node = self.style_node_under(node, ancestors, parents);
+        let children = {
+            let mut context = ancestors.to_vec();
+            context.push(node);
+            let mut styled_parents = parents.to_vec();
+            styled_parents.push(&styled_node);
+            node.rendered_children().map(|child| self.style_subtree(child, &context, &styled_parents)).collect()
+        };
+        styled_node.children = children;
+        styled_node
+    }
+
+    // Cascaded styles of each of `ancestors`, from the root down, without
+    // their generated content
+    fn cascade_ancestors(&self, ancestors: &[&Node]) -> Vec<StyledNode> {
+        let mut styled: Vec<StyledNode> = Vec::with_capacity(ancestors.len());
+        for (depth, ancestor) in ancestors.iter().enumerate() {
+            let parents: Vec<&StyledNode> = styled.iter().collect();
+            let cascaded = self.cascade(ancestor, &ancestors[..depth], &parents);
+            styled.push(cascaded);
+        }
+        styled
+    }
 
-        for rule in self.stylesheet.style_rules() {
+    // `node` alone with its generated content; `parents` as in `style_subtree`
+    fn style_node_under(&self, node: &Node, ancestors: &[&Node], parents: &[&StyledNode]) -> StyledNode {
+        let mut styled_node = self.cascade(node, ancestors, parents);
+        let (before, after) = {
+            // The originating element is the pseudo-element's parent for inheritance
+            let mut originating = parents.to_vec();
+            originating.push(&styled_node);
+            (
+                self.pseudo_element(node, ancestors, &originating, "before"),
+                self.pseudo_element(node, ancestors, &originating, "after"),
+            )
+        };
+        styled_node.before = before;
+        styled_node.after = after;
+        styled_node
+    }
+
+    // Declarations of `node` from the stylesheet and its inline style, with
+    // CSS-wide keywords resolved against `parents`
+    fn cascade(&self, node: &Node, ancestors: &[&Node], parents: &[&StyledNode]) -> StyledNode {
+        let mut styled_node = StyledNode::leaf(node.clone());
+        let inline = std::mem::take(&mut styled_node.styles);
+        styled_node.add_declarations(self.matched_declarations(node, ancestors, None, inline));
+        Self::resolve_css_wide_keywords(&mut styled_node, parents);
+        styled_node
+    }
+
+    // Declarations of every rule matching `node`, or its `pseudo` element, and
+    // `inline` ones, in cascade order: origin, then specificity of the best
+    // matching selector, then source order. Inline declarations beat normal
+    // author rules, so they come after them. `!important` reverses origin
+    // precedence, so important user and user-agent declarations come last.
+    fn matched_declarations(&self, node: &Node, ancestors: &[&Node], pseudo: Option<&str>, inline: Vec<Declaration>) -> Vec<Declaration> {
+        let mut matched = Vec::new();
+        for (rule, origin) in self.stylesheet.cascade_order() {
             if let super::Rule::StyleRule { selectors, declarations } = rule {
-                if selectors.iter().any(|selector| self.matches_selector(node, selector)) {
-                    styled_node.add_declarations(declarations.clone());
+                let best = selectors
+                    .iter()
+                    .filter(|selector| match (pseudo, selector.components.split_last()) {
+                        (None, _) => self.matches(node, ancestors, selector),
+                        (Some(pseudo), Some((SelectorComponent::PseudoElement(name), originating))) => {
+                            name.eq_ignore_ascii_case(pseudo) && self.matches_components(node, ancestors, originating)
+                        }
+                        _ => false,
+                    })
+                    .max_by_key(|selector| selector.specificity());
+                if let Some(selector) = best {
+                    log::trace!(target: "css", "Matched {:?} with specificity {:?}", selector.components, selector.specificity());
+                    matched.push((origin, selector.specificity(), declarations));
                 }
             }
         }
+        matched.sort_by_key(|(origin, specificity, _)| (*origin, *specificity));
 
-        styled_node
+        let mut ordered = Vec::new();
+        let mut overriding = Vec::new();
+        for (origin, _, declarations) in matched {
+            for decl in declarations {
+                if decl.important && origin != Origin::Author {
+                    overriding.push((origin, decl.clone()));
+                } else {
+                    ordered.push(decl.clone());
+                }
+            }
+        }
+        ordered.extend(inline);
+        // Stable, so cascade order is kept within an origin
+        overriding.sort_by_key(|(origin, _)| std::cmp::Reverse(*origin));
+        ordered.extend(overriding.into_iter().map(|(_, decl)| decl));
+        ordered
     }
 
-    fn matches_selector(&self, node: &Node, selector: &Selector) -> bool {
-        match node.node_type() {
-            NodeType::Element {
-                tag_name,
-                attributes,
-                ..
-            } => self.matches_complex_selector(node, selector, attributes),
+    // The `::before` or `::after` box of `node`: a text node holding its
+    // generated content, styled by the pseudo-element's rules. None unless
+    // `content` generates something.
+    fn pseudo_element(&self, node: &Node, ancestors: &[&Node], parents: &[&StyledNode], pseudo: &str) -> Option<Box<StyledNode>> {
+        let declarations = self.matched_declarations(node, ancestors, Some(pseudo), Vec::new());
+        if declarations.is_empty() {
+            return None;
+        }
+        let mut styles = StyledNode::leaf(Node::new(NodeType::Text(String::new())));
+        styles.add_declarations(declarations);
+        Self::resolve_css_wide_keywords(&mut styles, parents);
+        let text = generated_content(styles.get("content")?, node)?;
+        styles.node = Node::new(NodeType::Text(text));
+        Some(Box::new(styles))
+    }
+
+    // Replace `inherit`, `initial` and `unset` with the values they stand for.
+    // `unset` inherits for inherited properties and is `initial` otherwise.
+    // Declarations that resolve to nothing are dropped.
+    fn resolve_css_wide_keywords(styled_node: &mut StyledNode, parents: &[&StyledNode]) {
+        styled_node.styles.retain_mut(|decl| {
+            let Value::Keyword(keyword) = &decl.value else {
+                return true;
+            };
+            let property = Property::from_string(&decl.property);
+            let inherit = match keyword.to_ascii_lowercase().as_str() {
+                "inherit" => true,
+                "initial" => false,
+                "unset" => property.is_inherited(),
+                _ => return true,
+            };
+            let resolved = if inherit {
+                Self::inherited_value(&decl.property, &property, parents)
+            } else {
+                property.initial_value()
+            };
+            match resolved {
+                Some(value) => {
+                    decl.value = value;
+                    true
+                }
+                None => false,
+            }
+        });
+    }
+
+    // The value `inherit` takes for `name`: the parent's value, looking further
+    // up for inherited properties, or the initial value at the root.
+    fn inherited_value(name: &str, property: &Property, parents: &[&StyledNode]) -> Option<Value> {
+        let Some((parent, above)) = parents.split_last() else {
+            return property.initial_value();
+        };
+        match parent.get(name) {
+            Some(value) => Some(value.clone()),
+            None if property.is_inherited() => Self::inherited_value(name, property, above),
+            None => property.initial_value(),
+        }
+    }
+
+    /// Whether `selector` matches `node`, given its ancestors from the root down.
+    pub fn matches(&self, node: &Node, ancestors: &[&Node], selector: &Selector) -> bool {
+        self.matches_components(node, ancestors, &selector.components)
+    }
+
+    // Match right to left: the rightmost compound against `node`, then the
+    // combinator decides which ancestor or sibling the remainder must match.
+    fn matches_components(&self, node: &Node, ancestors: &[&Node], components: &[SelectorComponent]) -> bool {
+        let Some(split) = components.iter().rposition(SelectorComponent::is_combinator) else {
+            return self.matches_compound(node, components);
+        };
+        let (rest, combinator, compound) = (&components[..split], &components[split], &components[split + 1..]);
+        if !self.matches_compound(node, compound) {
+            return false;
+        }
+
+        match combinator {
+            SelectorComponent::Descendant => (0..ancestors.len())
+                .rev()
+                .any(|i| self.matches_components(ancestors[i], &ancestors[..i], rest)),
+            SelectorComponent::Child => ancestors
+                .split_last()
+                .is_some_and(|(parent, above)| self.matches_components(parent, above, rest)),
+            SelectorComponent::Adjacent => ancestors
+                .last()
+                .and_then(|parent| previous_element_sibling(parent, node))
+                .is_some_and(|sibling| self.matches_components(sibling, ancestors, rest)),
             _ => false,
         }
     }
 
-    fn matches_complex_selector(
-        &self,
-        node: &Node,
-        selector: &Selector,
-        attributes: &[crate::dom::Attribute],
-    ) -> bool {
-        // For now, check if any component matches - this is a simplified implementation
-        // A full implementation would need to handle combinator logic
-        selector.components.iter().any(|component| {
+    fn matches_compound(&self, node: &Node, compound: &[SelectorComponent]) -> bool {
+        let NodeType::Element { tag_name, attributes, .. } = node.node_type() else {
+            return false;
+        };
+
+        compound.iter().all(|component| {
             match component {
-                SelectorComponent::Type(name) => {
-                    if let NodeType::Element { tag_name, .. } = node.node_type() {
-                        name.eq_ignore_ascii_case(name)
-                    } else {
-                        false
-                    }
-                }
+                SelectorComponent::Type(name) => name.eq_ignore_ascii_case(tag_name),
                 SelectorComponent::Id(id) => attributes
                     .iter()
                     .any(|attr| attr.name == "id" && attr.value == *id),
@@ -61,22 +236,21 @@ impl StyleEngine {
                 }),
                 SelectorComponent::Universal => true,
                 SelectorComponent::PseudoClass(pseudo) => {
-                    // Basic pseudo-class support - simplified for now since we don't have parent access
+                    // Basic pseudo-class support - simplified for now
                     match pseudo.as_str() {
                         "hover" | "active" | "focus" | "visited" => false, // These need interaction state
-                        "first-child" | "last-child" => false, // Would need parent access
+                        "first-child" | "last-child" => false, // Would need sibling position
                         _ => false,
                     }
                 }
                 SelectorComponent::PseudoElement(_) => false, // Pseudo-elements need special handling
-                SelectorComponent::Attribute(attr_name, attr_value) => {
-                    if let Some(expected_value) = attr_value {
-                        attributes.iter().any(|attr| {
-                            attr.name == *attr_name && attr.value == *expected_value
-                        })
-                    } else {
-                        attributes.iter().any(|attr| attr.name == *attr_name)
-                    }
+                SelectorComponent::Attribute(attr_name, condition) => {
+                    attributes.iter().any(|attr| {
+                        attr.name == *attr_name
+                            && condition
+                                .as_ref()
+                                .is_none_or(|(operator, expected)| operator.matches(&attr.value, expected))
+                    })
                 }
                 _ => false,
             }
@@ -85,25 +259,253 @@ impl StyleEngine {
 
 }
 
+// Text generated by a `content` value: strings as written, `attr(name)` as the
+// element's attribute (empty if unset). `none` and `normal` generate nothing.
+fn generated_content(value: &Value, element: &Node) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Function(name, args) if name.eq_ignore_ascii_case("attr") => {
+            let attribute = match args.first()? {
+                Value::Keyword(attribute) | Value::String(attribute) => attribute,
+                _ => return None,
+            };
+            Some(element.get_attribute(attribute).unwrap_or_default().to_string())
+        }
+        Value::Multiple(parts) => parts.iter().map(|part| generated_content(part, element)).collect(),
+        _ => None,
+    }
+}
+
+fn previous_element_sibling<'a>(parent: &'a Node, node: &Node) -> Option<&'a Node> {
+    let index = parent.children().iter().position(|child| child.id() == node.id())?;
+    parent.children()[..index]
+        .iter()
+        .rev()
+        .find(|child| matches!(child.node_type(), NodeType::Element { .. }))
+}
+
 #[derive(Clone)]
 pub struct StyledNode {
     pub node: Node,
     pub styles: Vec<Declaration>,
+    /// Generated `::before` and `::after` content, as styled text nodes.
+    pub before: Option<Box<StyledNode>>,
+    pub after: Option<Box<StyledNode>>,
+    /// The styled nodes of `node.rendered_children()`, in the same order.
+    pub children: Vec<StyledNode>,
 }
 
 impl StyledNode {
+    /// Wrap `node` and its subtree with the declarations from their inline
+    /// `style` attributes, without any stylesheet.
     pub fn new(node: Node) -> Self {
+        let mut styled = Self::leaf(node);
+        styled.children = styled.node.rendered_children().map(|child| Self::new(child.clone())).collect();
+        styled
+    }
+
+    /// A copy of this node's styles and generated content, without its
+    /// styled children.
+    pub fn without_children(&self) -> Self {
         Self {
-            node,
-            styles: Vec::new(),
+            node: self.node.clone(),
+            styles: self.styles.clone(),
+            before: self.before.clone(),
+            after: self.after.clone(),
+            children: Vec::new(),
         }
     }
 
+    // `node` with its inline declarations and no children
+    fn leaf(node: Node) -> Self {
+        let styles = match node.get_attribute("style") {
+            Some(style) => super::parser::CssParser::new(style.to_string()).parse_inline_style(),
+            None => Vec::new(),
+        };
+        let mut styled = Self { node, styles: Vec::new(), before: None, after: None, children: Vec::new() };
+        styled.add_declarations(styles);
+        styled
+    }
+
+    /// Append `declarations` to the cascade, with values in their canonical form.
+    /// A shorthand is kept and followed by the longhands it expands to.
     pub fn add_declarations(&mut self, declarations: Vec<Declaration>) {
-        self.styles.extend(declarations);
+        for mut decl in declarations {
+            decl.value = Property::from_string(&decl.property).normalize_value(decl.value);
+            let longhands = decl.longhands();
+            self.styles.push(decl);
+            self.styles.extend(longhands);
+        }
     }
 
+    /// The declaration that wins the cascade for `property`: the last
+    /// `!important` one if any, otherwise the last one applied.
     pub fn get_style(&self, property: &str) -> Option<&Declaration> {
-        self.styles.iter().find(|decl| decl.property == property)
+        let mut winner: Option<&Declaration> = None;
+        for decl in self.styles.iter().filter(|decl| decl.property.eq_ignore_ascii_case(property)) {
+            if decl.important || !winner.is_some_and(|w| w.important) {
+                winner = Some(decl);
+            }
+        }
+        winner
+    }
+
+    /// Computed value of `property`, if any rule set it.
+    pub fn get(&self, property: &str) -> Option<&Value> {
+        self.get_style(property).map(|decl| &decl.value)
+    }
+
+    /// Every property set on this node with its computed value, in the order
+    /// each property was first declared.
+    pub fn computed(&self) -> impl Iterator<Item = (&str, &Value)> + '_ {
+        let mut seen: Vec<&str> = Vec::new();
+        self.styles.iter().filter_map(move |decl| {
+            if seen.iter().any(|p| p.eq_ignore_ascii_case(&decl.property)) {
+                return None;
+            }
+            seen.push(&decl.property);
+            self.get(&decl.property).map(|value| (decl.property.as_str(), value))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::parser::CssParser;
+    use crate::css::Color;
+    use crate::dom::Attribute;
+
+    fn styled(css: &str, node: &Node) -> StyledNode {
+        StyleEngine::new(CssParser::new(css.to_string()).parse()).apply_styles(node)
+    }
+
+    fn paragraph(class: &str) -> Node {
+        Node::new(NodeType::Element {
+            tag_name: "p".into(),
+            attributes: vec![Attribute {
+                name: "class".into(),
+                value: class.to_string(),
+            }],
+            events: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn test_computed_style_after_cascade() {
+        let node = paragraph("note");
+        let styled = styled(".note { color: #ff0000; } p { color: blue; margin: 4px; } div { color: green; }", &node);
+
+        // The class selector outranks the type selector despite coming first
+        let red = Color::from_hex("#ff0000").expect("valid hex");
+        assert_eq!(styled.get("color"), Some(&Value::Color(red)));
+        assert_eq!(styled.get("background"), None);
+
+        let properties: Vec<&str> = styled.computed().map(|(property, _)| property).collect();
+        // The shorthand is followed by the longhands it expands to
+        assert_eq!(properties, vec!["color", "margin", "margin-top", "margin-right", "margin-bottom", "margin-left"]);
+    }
+
+    #[test]
+    fn test_important_wins_over_later_declarations() {
+        let node = paragraph("note");
+        let styled = styled("p { color: blue !important; } .note { color: green; }", &node);
+        assert_eq!(styled.get("color"), Some(&Value::Keyword("blue".to_string())));
+    }
+
+    #[test]
+    fn test_important_reverses_origin_precedence() {
+        let mut node = paragraph("note");
+        node.set_attribute("style", "color: green !important");
+        let sheet = |css: &str| CssParser::new(css.to_string()).parse();
+        let mut stylesheet = StyleSheet::new();
+        stylesheet.merge(sheet("p { color: red !important; margin: 1px !important; }"), Origin::UserAgent);
+        stylesheet.merge(sheet("p { margin: 2px !important; padding: 3px !important; }"), Origin::User);
+        stylesheet.merge(sheet(".note { color: blue !important; margin: 4px !important; padding: 5px !important; }"), Origin::Author);
+
+        let styled = StyleEngine::new(stylesheet).apply_styles(&node);
+        // User-agent beats user, which beats author and inline styles
+        assert_eq!(styled.get("color"), Some(&Value::Keyword("red".to_string())));
+        assert_eq!(styled.get("margin"), Some(&Value::Length(1.0, crate::css::Unit::Px)));
+        assert_eq!(styled.get("padding"), Some(&Value::Length(3.0, crate::css::Unit::Px)));
+    }
+
+    #[test]
+    fn test_font_weight_keywords_normalize_to_numbers() {
+        let node = paragraph("note");
+        let named = styled("p { font-weight: bold; }", &node);
+        let numeric = styled("p { font-weight: 700; }", &node);
+        assert_eq!(named.get("font-weight"), numeric.get("font-weight"));
+        assert_eq!(named.get("font-weight"), Some(&Value::Keyword("700".to_string())));
+
+        let normal = styled("p { font-weight: normal; }", &node);
+        let initial = styled("p { font-weight: initial; }", &node);
+        assert_eq!(normal.get("font-weight"), Some(&Value::Keyword("400".to_string())));
+        assert_eq!(initial.get("font-weight"), normal.get("font-weight"));
+        // Relative weights depend on the parent and stay as written
+        let bolder = styled("p { font-weight: Bolder; }", &node);
+        assert_eq!(bolder.get("font-weight"), Some(&Value::Keyword("bolder".to_string())));
+    }
+
+    #[test]
+    fn test_css_wide_keywords_resolve_in_cascade() {
+        let html = "<section><div><p>text</p></div></section>";
+        let dom = crate::html::parser::Parser::new(html.to_string()).parse();
+        let root = dom.root().expect("root");
+        fn find<'a>(node: &'a Node, tag: &str, path: &mut Vec<&'a Node>) -> Option<&'a Node> {
+            if node.is_element(tag) {
+                return Some(node);
+            }
+            path.push(node);
+            for child in node.children() {
+                if let Some(found) = find(child, tag, path) {
+                    return Some(found);
+                }
+            }
+            path.pop();
+            None
+        }
+        let mut ancestors = Vec::new();
+        let p = find(root, "p", &mut ancestors).expect("paragraph");
+
+        let engine = StyleEngine::new(
+            CssParser::new(
+                "section { color: blue; margin: 8px; } \
+                 p { color: inherit; margin: inherit; background-color: red; padding: 2px; } \
+                 p { background-color: unset; padding: initial; font-size: unset; }"
+                    .to_string(),
+            )
+            .parse(),
+        );
+        let styled = engine.apply_styles_in_context(p, &ancestors);
+        // color inherits through the div, which doesn't set it; margin doesn't
+        assert_eq!(styled.get("color"), Some(&Value::Keyword("blue".to_string())));
+        assert_eq!(styled.get("margin"), Some(&Value::Length(0.0, crate::css::Unit::Px)));
+        assert_eq!(styled.get("background-color"), Some(&Value::Color(Color::new(0, 0, 0, 0))));
+        assert_eq!(styled.get("padding"), Some(&Value::Length(0.0, crate::css::Unit::Px)));
+        assert_eq!(styled.get("font-size"), Some(&Value::Keyword("medium".to_string())));
+
+        // Without ancestors, inherit falls back to the initial value
+        let alone = engine.apply_styles(p);
+        assert_eq!(alone.get("color"), Some(&Value::Color(Color::new(0, 0, 0, 255))));
+    }
+
+    #[test]
+    fn test_inherit_reads_each_styled_parent() {
+        let depth = 40;
+        let html = format!("<section>{}text{}</section>", "<div>".repeat(depth), "</div>".repeat(depth));
+        let dom = crate::html::parser::Parser::new(html).parse();
+        let section = crate::dom::query::query_selector(dom.root().expect("root"), "section").expect("section");
+
+        let engine = StyleEngine::new(CssParser::new("section { color: blue; } div { color: inherit; }".to_string()).parse());
+        let styled = engine.apply_styles(section);
+        let mut innermost = &styled;
+        let mut levels = 0;
+        while let Some(child) = innermost.children.iter().find(|child| child.node.is_element("div")) {
+            innermost = child;
+            levels += 1;
+        }
+        assert_eq!(levels, depth);
+        assert_eq!(innermost.get("color"), Some(&Value::Keyword("blue".to_string())));
     }
 }
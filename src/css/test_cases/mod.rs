@@ -215,6 +215,7 @@ fn assert_at_rules_match(parsed: &AtRule, expected: &AtRule, test_name: &str, ru
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::css::selector::AttributeOperator;
     use std::path::Path;
 
     fn get_test_dir() -> &'static Path {
@@ -268,33 +269,42 @@ mod tests {
         });
 
         // .bg-blue-500 { --tw-bg-opacity: 1; background-color: rgb(59 130 246 / var(--tw-bg-opacity)); }
-        // Note: Parser appears to skip CSS custom properties (--tw-bg-opacity) and only parses background-color
-        // TODO: Investigate why CSS custom properties starting with -- are not being parsed
         expected.add_rule(Rule::StyleRule {
             selectors: vec![Selector::new(vec![SelectorComponent::Class("bg-blue-500".to_string())])],
             declarations: vec![
-                Declaration::new("background-color".to_string(), Value::Function("rgb".to_string(), vec![Value::Variable("--tw-bg-opacity".to_string())])),
+                Declaration::new("--tw-bg-opacity".to_string(), Value::Keyword("1".to_string())),
+                Declaration::new("background-color".to_string(), Value::Function("rgb".to_string(), vec![
+                    Value::Multiple(vec![Value::Keyword("59".to_string()), Value::Keyword("130".to_string()), Value::Keyword("246".to_string())]),
+                    Value::Variable("--tw-bg-opacity".to_string()),
+                ])),
             ],
         });
 
         // .text-white { --tw-text-opacity: 1; color: rgb(255 255 255 / var(--tw-text-opacity)); }
-        // Note: Parser appears to skip CSS custom properties (--tw-text-opacity) and only parses color
         expected.add_rule(Rule::StyleRule {
             selectors: vec![Selector::new(vec![SelectorComponent::Class("text-white".to_string())])],
             declarations: vec![
-                Declaration::new("color".to_string(), Value::Function("rgb".to_string(), vec![Value::Variable("--tw-text-opacity".to_string())])),
+                Declaration::new("--tw-text-opacity".to_string(), Value::Keyword("1".to_string())),
+                Declaration::new("color".to_string(), Value::Function("rgb".to_string(), vec![
+                    Value::Multiple(vec![Value::Keyword("255".to_string()), Value::Keyword("255".to_string()), Value::Keyword("255".to_string())]),
+                    Value::Variable("--tw-text-opacity".to_string()),
+                ])),
             ],
         });
 
         // .hover\:bg-blue-600:hover { --tw-bg-opacity: 1; background-color: rgb(37 99 235 / var(--tw-bg-opacity)); }
-        // Note: Parser unescapes the backslash in class names and skips CSS custom properties
+        // Note: Parser unescapes the backslash in class names
         expected.add_rule(Rule::StyleRule {
             selectors: vec![Selector::new(vec![
                 SelectorComponent::Class("hover:bg-blue-600".to_string()), // Parser unescapes the backslash
                 SelectorComponent::PseudoClass("hover".to_string()),
             ])],
             declarations: vec![
-                Declaration::new("background-color".to_string(), Value::Function("rgb".to_string(), vec![Value::Variable("--tw-bg-opacity".to_string())])),
+                Declaration::new("--tw-bg-opacity".to_string(), Value::Keyword("1".to_string())),
+                Declaration::new("background-color".to_string(), Value::Function("rgb".to_string(), vec![
+                    Value::Multiple(vec![Value::Keyword("37".to_string()), Value::Keyword("99".to_string()), Value::Keyword("235".to_string())]),
+                    Value::Variable("--tw-bg-opacity".to_string()),
+                ])),
             ],
         });
 
@@ -343,6 +353,57 @@ mod tests {
         println!("Minimal CSS test passed with full rule matching");
     }
 
+    #[test]
+    fn test_comments_inside_rules() {
+        let plain = ".a.b, p { color: red; margin: 1px 2px; }";
+        let commented = ".a/* one */.b, /* two */ p /* three */ { color: red /* inline note */ ; margin: /* x */ 1px/* y */2px; }";
+
+        let expected = parser::CssParser::new(plain.to_string()).parse();
+        let parsed = parser::CssParser::new(commented.to_string()).parse();
+        assert_stylesheets_match(&parsed, &expected, "test_comments_inside_rules");
+
+        // Non-ASCII text before a comment must not confuse comment detection
+        let css = "p { content: \"é\"; /* é */ color: red; }";
+        let parsed = parser::CssParser::new(css.to_string()).parse();
+        match &parsed.rules()[0] {
+            Rule::StyleRule { declarations, .. } => assert_eq!(declarations.len(), 2),
+            other => panic!("expected a style rule, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_license_banner_and_comments_between_declarations() {
+        let css = "/*! Library v1.0 | MIT License\n * { color: red; } .not-a-rule { }\n */\n\
+                   .a{color:red}/* between rules */.b{a:1;/*x*/b:2}\n\
+                   .c{margin:0;/* } */padding:1px/* before ; */;}\n\
+                   @keyframes fade{/* from */from{opacity:0} /* to */ to{opacity:1}}\n\
+                   .d{/*a*/color/*b*/:/*c*/blue/*d*/;/*e*/}";
+        let parsed = parser::CssParser::new(css.to_string()).parse();
+
+        let summary: Vec<(String, Vec<String>)> = parsed
+            .rules()
+            .iter()
+            .map(|rule| match rule {
+                Rule::StyleRule { selectors, declarations } => (
+                    format!("{:?}", selectors[0].components),
+                    declarations.iter().map(|d| d.property.clone()).collect(),
+                ),
+                Rule::AtRule(AtRule::Keyframes { name, keyframes }) => {
+                    (name.clone(), keyframes.iter().map(|k| k.selector.clone()).collect())
+                }
+                other => panic!("unexpected rule {:?}", other),
+            })
+            .collect();
+        let properties: Vec<Vec<&str>> = summary.iter().map(|(_, p)| p.iter().map(String::as_str).collect()).collect();
+        assert_eq!(summary.len(), 5, "rules: {:?}", summary);
+        assert_eq!(properties[0], vec!["color"]);
+        assert_eq!(properties[1], vec!["a", "b"]);
+        assert_eq!(properties[2], vec!["margin", "padding"]);
+        assert_eq!(summary[3].0, "fade");
+        assert_eq!(properties[3], vec!["from", "to"]);
+        assert_eq!(properties[4], vec!["color"]);
+    }
+
     // Test memory protection with malformed CSS
     #[test]
     fn test_memory_protection() {
@@ -591,7 +652,17 @@ mod tests {
         // .shadow { box-shadow: ... !important; }
         expected.add_rule(Rule::StyleRule {
             selectors: vec![Selector::new(vec![SelectorComponent::Class("shadow".to_string())])],
-            declarations: vec![Declaration::new("box-shadow".to_string(), Value::Function("rgba".to_string(), vec![])).important(true)],
+            declarations: vec![Declaration::new("box-shadow".to_string(), Value::Multiple(vec![
+                Value::Length(0.0, Unit::Px),
+                Value::Length(0.125, Unit::Rem),
+                Value::Length(0.25, Unit::Rem),
+                Value::Function("rgba".to_string(), vec![
+                    Value::Length(0.0, Unit::Px),
+                    Value::Length(0.0, Unit::Px),
+                    Value::Length(0.0, Unit::Px),
+                    Value::Keyword("0.075".to_string()),
+                ]),
+            ])).important(true)],
         });
 
         // .position-relative { position: relative !important; }
@@ -730,13 +801,12 @@ mod tests {
         }));
 
         // @media (prefers-color-scheme: dark) { .dark-mode-toggle { opacity: 1; } }
-        // Note: Parser may fail to parse opacity: 1 (numeric keyword value)
-        // If this rule has 0 declarations, it indicates a parser issue with numeric keywords
+        // Unitless numbers are kept as keywords
         expected.add_rule(Rule::AtRule(AtRule::Media {
             condition: "(prefers-color-scheme: dark)".to_string(),
             rules: vec![Box::new(Rule::StyleRule {
                 selectors: vec![Selector::new(vec![SelectorComponent::Class("dark-mode-toggle".to_string())])],
-                declarations: vec![], // Parser currently fails to parse this - should be: Declaration::new("opacity".to_string(), Value::Keyword("1".to_string()))
+                declarations: vec![Declaration::new("opacity".to_string(), Value::Keyword("1".to_string()))],
             })],
         }));
 
@@ -750,13 +820,11 @@ mod tests {
         }));
 
         // @media (orientation: landscape) { .hero-image { height: 60vh; } }
-        // Note: Parser may fail to parse vh units
-        // If this rule has 0 declarations, it indicates a parser issue with vh units
         expected.add_rule(Rule::AtRule(AtRule::Media {
             condition: "(orientation: landscape)".to_string(),
             rules: vec![Box::new(Rule::StyleRule {
                 selectors: vec![Selector::new(vec![SelectorComponent::Class("hero-image".to_string())])],
-                declarations: vec![], // Parser currently fails to parse this - should be: Declaration::new("height".to_string(), Value::Length(60.0, Unit::Vh))
+                declarations: vec![Declaration::new("height".to_string(), Value::Length(60.0, Unit::Vh))],
             })],
         }));
 
@@ -1042,7 +1110,7 @@ mod tests {
         expected.add_rule(Rule::StyleRule {
             selectors: vec![Selector::new(vec![
                 SelectorComponent::Type("input".to_string()),
-                SelectorComponent::Attribute("type".to_string(), Some("=text".to_string())), // Parser includes "="
+                SelectorComponent::Attribute("type".to_string(), Some((AttributeOperator::Equals, "text".to_string()))),
             ])],
             declarations: vec![Declaration::new("border".to_string(), Value::Multiple(vec![
                 Value::Length(1.0, Unit::Px),
@@ -1055,17 +1123,17 @@ mod tests {
         expected.add_rule(Rule::StyleRule {
             selectors: vec![Selector::new(vec![
                 SelectorComponent::Type("input".to_string()),
-                SelectorComponent::Attribute("type".to_string(), Some("=email".to_string())), // Parser includes "="
+                SelectorComponent::Attribute("type".to_string(), Some((AttributeOperator::Equals, "email".to_string()))),
             ])],
             declarations: vec![Declaration::new("background-image".to_string(), Value::Function("url".to_string(), vec![Value::String("data:image/svg+xml,%3csvg...".to_string())]))],
         });
 
         // a[href^="https://"]::after { content: "[SECURE]"; margin-left: 4px; }
-        // Note: Parser may fail to parse complex attribute selector with ^= operator
-        // It may only parse the ::after pseudo-element
         expected.add_rule(Rule::StyleRule {
             selectors: vec![Selector::new(vec![
-                SelectorComponent::PseudoElement("after".to_string()), // Parser only gets this part
+                SelectorComponent::Type("a".to_string()),
+                SelectorComponent::Attribute("href".to_string(), Some((AttributeOperator::Prefix, "https://".to_string()))),
+                SelectorComponent::PseudoElement("after".to_string()),
             ])],
             declarations: vec![
                 Declaration::new("content".to_string(), Value::String("[SECURE]".to_string())),
@@ -1099,24 +1167,84 @@ mod tests {
             ]))],
         });
 
-        // Note: div[data-toggle~="collapse"] is skipped by parser (trouble with ~= operator)
-        
+        // div[data-toggle~="collapse"] { cursor: pointer; }
+        expected.add_rule(Rule::StyleRule {
+            selectors: vec![Selector::new(vec![
+                SelectorComponent::Type("div".to_string()),
+                SelectorComponent::Attribute("data-toggle".to_string(), Some((AttributeOperator::Includes, "collapse".to_string()))),
+            ])],
+            declarations: vec![Declaration::new("cursor".to_string(), Value::Keyword("pointer".to_string()))],
+        });
+
         // button[data-bs-toggle="modal"] { background-color: #007bff; }
         expected.add_rule(Rule::StyleRule {
             selectors: vec![Selector::new(vec![
                 SelectorComponent::Type("button".to_string()),
-                SelectorComponent::Attribute("data-bs-toggle".to_string(), Some("=modal".to_string())), // Parser includes "="
+                SelectorComponent::Attribute("data-bs-toggle".to_string(), Some((AttributeOperator::Equals, "modal".to_string()))),
             ])],
             declarations: vec![Declaration::new("background-color".to_string(), Value::Color(Color::from_hex("#007bff").unwrap()))],
         });
 
-        // Note: The following rules are skipped by parser due to complex attribute operators or selectors:
-        // - input[value*="search"] (trouble with *= operator)
-        // - a[href$=".pdf"]::after (trouble with $= operator)
-        // - input[name|="phone"] (trouble with |= operator)
-        // - form[action*="login"] (trouble with *= operator)
-        // - input[type="text"][data-validation="required"] (trouble with multiple attributes)
-        // - a[href]:not([href^="mailto:"]) (trouble with :not() pseudo-class)
+        // input[value*="search"] { background-image: url("search-icon.svg"); background-repeat: no-repeat; background-position: right 10px center; }
+        expected.add_rule(Rule::StyleRule {
+            selectors: vec![Selector::new(vec![
+                SelectorComponent::Type("input".to_string()),
+                SelectorComponent::Attribute("value".to_string(), Some((AttributeOperator::Substring, "search".to_string()))),
+            ])],
+            declarations: vec![
+                Declaration::new("background-image".to_string(), Value::Function("url".to_string(), vec![Value::String("search-icon.svg".to_string())])),
+                Declaration::new("background-repeat".to_string(), Value::Keyword("no-repeat".to_string())),
+                Declaration::new("background-position".to_string(), Value::Multiple(vec![
+                    Value::Keyword("right".to_string()),
+                    Value::Length(10.0, Unit::Px),
+                    Value::Keyword("center".to_string()),
+                ])),
+            ],
+        });
+
+        // a[href$=".pdf"]::after { content: "[PDF]"; }
+        expected.add_rule(Rule::StyleRule {
+            selectors: vec![Selector::new(vec![
+                SelectorComponent::Type("a".to_string()),
+                SelectorComponent::Attribute("href".to_string(), Some((AttributeOperator::Suffix, ".pdf".to_string()))),
+                SelectorComponent::PseudoElement("after".to_string()),
+            ])],
+            declarations: vec![Declaration::new("content".to_string(), Value::String("[PDF]".to_string()))],
+        });
+
+        // input[name|="phone"] { width: 200px; }
+        expected.add_rule(Rule::StyleRule {
+            selectors: vec![Selector::new(vec![
+                SelectorComponent::Type("input".to_string()),
+                SelectorComponent::Attribute("name".to_string(), Some((AttributeOperator::DashMatch, "phone".to_string()))),
+            ])],
+            declarations: vec![Declaration::new("width".to_string(), Value::Length(200.0, Unit::Px))],
+        });
+
+        // form[action*="login"] { border: 2px solid #28a745; }
+        expected.add_rule(Rule::StyleRule {
+            selectors: vec![Selector::new(vec![
+                SelectorComponent::Type("form".to_string()),
+                SelectorComponent::Attribute("action".to_string(), Some((AttributeOperator::Substring, "login".to_string()))),
+            ])],
+            declarations: vec![Declaration::new("border".to_string(), Value::Multiple(vec![
+                Value::Length(2.0, Unit::Px),
+                Value::Keyword("solid".to_string()),
+                Value::Color(Color::from_hex("#28a745").unwrap()),
+            ]))],
+        });
+
+        // input[type="text"][data-validation="required"] { border-color: #dc3545; }
+        expected.add_rule(Rule::StyleRule {
+            selectors: vec![Selector::new(vec![
+                SelectorComponent::Type("input".to_string()),
+                SelectorComponent::Attribute("type".to_string(), Some((AttributeOperator::Equals, "text".to_string()))),
+                SelectorComponent::Attribute("data-validation".to_string(), Some((AttributeOperator::Equals, "required".to_string()))),
+            ])],
+            declarations: vec![Declaration::new("border-color".to_string(), Value::Color(Color::from_hex("#dc3545").unwrap()))],
+        });
+
+        // Note: a[href]:not([href^="mailto:"]) is skipped by parser (trouble with :not() pseudo-class)
 
         expected
     }
@@ -1,5 +1,7 @@
 //! CSS value types and parsing
 
+use std::fmt;
+
 /// CSS value types
 #[derive(Clone, Debug, PartialEq)]
 pub enum Value {
@@ -76,6 +78,47 @@ impl Color {
     }
 }
 
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Keyword(keyword) => write!(f, "{}", keyword),
+            Value::Length(value, unit) => write!(f, "{}{}", value, unit.to_string()),
+            Value::Color(color) => write!(f, "{}", color),
+            Value::Multiple(values) => write_joined(f, values, " "),
+            Value::Function(name, args) => {
+                write!(f, "{}(", name)?;
+                write_joined(f, args, ", ")?;
+                write!(f, ")")
+            }
+            Value::Variable(name) => write!(f, "var({})", name),
+            Value::String(s) => write!(f, "\"{}\"", s),
+            Value::None => Ok(()),
+        }
+    }
+}
+
+fn write_joined(f: &mut fmt::Formatter<'_>, values: &[Value], separator: &str) -> fmt::Result {
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            write!(f, "{}", separator)?;
+        }
+        write!(f, "{}", value)?;
+    }
+    Ok(())
+}
+
+/// Serialized the way computed styles report colors.
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.a == 255 {
+            write!(f, "rgb({}, {}, {})", self.r, self.g, self.b)
+        } else {
+            let alpha = (self.a as f32 / 255.0 * 100.0).round() / 100.0;
+            write!(f, "rgba({}, {}, {}, {})", self.r, self.g, self.b, alpha)
+        }
+    }
+}
+
 impl Default for Color {
     fn default() -> Self {
         Self::new(0, 0, 0, 255)
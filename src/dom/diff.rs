@@ -0,0 +1,154 @@
+// Structural diff between two DOM trees, so a re-render after script
+// mutations can touch only what changed. Nodes are addressed by their path of
+// child indices from the root; node ids are ignored since a re-parse assigns
+// fresh ones.
+
+use super::{Node, NodeType};
+
+/// One change that turns the old tree into the new one. Patches apply in
+/// order; each path refers to the tree as earlier patches left it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Patch {
+    /// Insert `node` as child `index` of the node at `parent`.
+    Insert { parent: Vec<usize>, index: usize, node: Node },
+    /// Remove child `index` of the node at `parent`.
+    Remove { parent: Vec<usize>, index: usize },
+    /// Swap the node at `path` for a different kind of node.
+    Replace { path: Vec<usize>, node: Node },
+    SetAttribute { path: Vec<usize>, name: String, value: String },
+    RemoveAttribute { path: Vec<usize>, name: String },
+    /// Change the contents of the text or comment node at `path`.
+    SetText { path: Vec<usize>, text: String },
+}
+
+impl Patch {
+    /// Path of the node whose subtree this patch changes.
+    pub fn target(&self) -> &[usize] {
+        match self {
+            Patch::Insert { parent, .. } | Patch::Remove { parent, .. } => parent,
+            Patch::Replace { path, .. }
+            | Patch::SetAttribute { path, .. }
+            | Patch::RemoveAttribute { path, .. }
+            | Patch::SetText { path, .. } => path,
+        }
+    }
+}
+
+/// The patches that turn `old` into `new`. Children are matched by position.
+pub fn diff(old: &Node, new: &Node) -> Vec<Patch> {
+    let mut patches = Vec::new();
+    diff_node(old, new, &mut Vec::new(), &mut patches);
+    patches
+}
+
+/// Apply patches from `diff` to `root`. Patches whose path no longer exists
+/// are skipped.
+pub fn apply(root: &mut Node, patches: &[Patch]) {
+    for patch in patches {
+        let Some(target) = node_at(root, patch.target()) else {
+            log::debug!(target: "dom", "Skipping patch with stale path: {:?}", patch);
+            continue;
+        };
+        match patch {
+            Patch::Insert { index, node, .. } => {
+                let index = (*index).min(target.children.len());
+                target.children.insert(index, node.clone());
+            }
+            Patch::Remove { index, .. } => {
+                if *index < target.children.len() {
+                    target.children.remove(*index);
+                }
+            }
+            Patch::Replace { node, .. } => *target = node.clone(),
+            Patch::SetAttribute { name, value, .. } => target.set_attribute(name, value),
+            Patch::RemoveAttribute { name, .. } => target.remove_attribute(name),
+            Patch::SetText { text, .. } => match &mut target.node_type {
+                NodeType::Text(content) | NodeType::Comment(content) => *content = text.clone(),
+                NodeType::Element { .. } => {}
+            },
+        }
+    }
+}
+
+fn node_at<'a>(root: &'a mut Node, path: &[usize]) -> Option<&'a mut Node> {
+    path.iter().try_fold(root, |node, &index| node.children.get_mut(index))
+}
+
+fn diff_node(old: &Node, new: &Node, path: &mut Vec<usize>, patches: &mut Vec<Patch>) {
+    match (&old.node_type, &new.node_type) {
+        (NodeType::Text(a), NodeType::Text(b)) | (NodeType::Comment(a), NodeType::Comment(b)) => {
+            if a != b {
+                patches.push(Patch::SetText { path: path.clone(), text: b.clone() });
+            }
+        }
+        (
+            NodeType::Element { tag_name: old_tag, attributes: old_attributes, .. },
+            NodeType::Element { tag_name: new_tag, attributes: new_attributes, .. },
+        ) if old_tag == new_tag && old.namespace == new.namespace => {
+            for attr in new_attributes {
+                if old.get_attribute(&attr.name) != Some(attr.value.as_str()) {
+                    patches.push(Patch::SetAttribute {
+                        path: path.clone(),
+                        name: attr.name.to_string(),
+                        value: attr.value.clone(),
+                    });
+                }
+            }
+            for attr in old_attributes {
+                if new.get_attribute(&attr.name).is_none() {
+                    patches.push(Patch::RemoveAttribute { path: path.clone(), name: attr.name.to_string() });
+                }
+            }
+            diff_children(old, new, path, patches);
+        }
+        _ => patches.push(Patch::Replace { path: path.clone(), node: new.clone() }),
+    }
+}
+
+fn diff_children(old: &Node, new: &Node, path: &mut Vec<usize>, patches: &mut Vec<Patch>) {
+    let common = old.children.len().min(new.children.len());
+    for index in 0..common {
+        path.push(index);
+        diff_node(&old.children[index], &new.children[index], path, patches);
+        path.pop();
+    }
+    // Surplus old children go from the end, so earlier indices stay valid
+    for index in (common..old.children.len()).rev() {
+        patches.push(Patch::Remove { parent: path.clone(), index });
+    }
+    for (index, node) in new.children.iter().enumerate().skip(common) {
+        patches.push(Patch::Insert { parent: path.clone(), index, node: node.clone() });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(html: &str) -> Node {
+        let dom = crate::html::parser::Parser::new(html.to_string()).parse();
+        dom.root().expect("root").clone()
+    }
+
+    #[test]
+    fn test_diff_of_changed_text_is_one_patch() {
+        let old = parse("<ul><li>one</li><li>two</li></ul>");
+        let new = parse("<ul><li>one</li><li>three</li></ul>");
+        let patches = diff(&old, &new);
+        assert_eq!(patches.len(), 1);
+        assert!(matches!(&patches[0], Patch::SetText { text, .. } if text == "three"));
+    }
+
+    #[test]
+    fn test_applying_diff_reproduces_new_tree() {
+        let old = parse("<div id=\"a\" class=\"x\"><p>keep</p><p>drop</p><!--c--></div>");
+        let new = parse("<div id=\"a\" title=\"t\"><p>keep</p><span>new</span></div><p>added</p>");
+        let patches = diff(&old, &new);
+
+        let mut patched = old.clone();
+        apply(&mut patched, &patches);
+        assert!(diff(&patched, &new).is_empty(), "left over: {:?}", diff(&patched, &new));
+        assert!(patches.iter().any(|p| matches!(p, Patch::RemoveAttribute { name, .. } if name == "class")));
+        assert!(patches.iter().any(|p| matches!(p, Patch::Replace { .. })));
+    }
+}
@@ -1,19 +1,132 @@
+use crate::html::Namespace;
 use log::{debug, info, warn};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Deref;
+use std::rc::Rc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
+mod diff;
+pub mod query;
+
+pub use diff::{Patch, apply, diff};
+
 static NODE_ID_COUNTER: AtomicUsize = AtomicUsize::new(0);
 
+// Past this many distinct names, new ones are allocated without being interned,
+// so a page inventing endless tag names can't grow the table without bound
+const MAX_INTERNED_NAMES: usize = 4096;
+
+thread_local! {
+    static INTERNED_NAMES: RefCell<HashSet<Rc<str>>> = RefCell::new(HashSet::new());
+}
+
+/// An interned tag or attribute name. Equal names share one allocation, so
+/// cloning is a reference count bump and most comparisons are a pointer check.
+#[derive(Clone, Eq)]
+pub struct Name(Rc<str>);
+
+impl Name {
+    pub fn new(name: &str) -> Self {
+        INTERNED_NAMES.with(|table| {
+            let mut table = table.borrow_mut();
+            if let Some(existing) = table.get(name) {
+                return Name(Rc::clone(existing));
+            }
+            let interned: Rc<str> = Rc::from(name);
+            if table.len() < MAX_INTERNED_NAMES {
+                table.insert(Rc::clone(&interned));
+            }
+            Name(interned)
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether both names share the same interned storage.
+    pub fn ptr_eq(a: &Name, b: &Name) -> bool {
+        Rc::ptr_eq(&a.0, &b.0)
+    }
+}
+
+impl Deref for Name {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for Name {
+    fn eq(&self, other: &Name) -> bool {
+        Rc::ptr_eq(&self.0, &other.0) || self.0 == other.0
+    }
+}
+
+impl PartialEq<str> for Name {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Name {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl PartialEq<String> for Name {
+    fn eq(&self, other: &String) -> bool {
+        &*self.0 == other.as_str()
+    }
+}
+
+impl std::hash::Hash for Name {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl From<&str> for Name {
+    fn from(name: &str) -> Self {
+        Name::new(name)
+    }
+}
+
+impl From<String> for Name {
+    fn from(name: String) -> Self {
+        Name::new(&name)
+    }
+}
+
+impl fmt::Debug for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Node {
     node_type: NodeType,
     children: Vec<Node>,
     id: usize, // Unique ID for each node
+    template_content: Option<Box<Node>>, // Inert contents of a <template>
+    namespace: Namespace, // Meaningful for elements only
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum NodeType {
     Element {
-        tag_name: String,
+        tag_name: Name,
         attributes: Vec<Attribute>,
         events: Vec<EventHandler>,
     },
@@ -23,7 +136,7 @@ pub enum NodeType {
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Attribute {
-    pub name: String,
+    pub name: Name,
     pub value: String,
 }
 
@@ -45,6 +158,8 @@ impl Node {
             node_type,
             children: Vec::new(),
             id,
+            template_content: None,
+            namespace: Namespace::Html,
         }
     }
 
@@ -67,6 +182,29 @@ impl Node {
         log::trace!(target: "dom", "New children count: {}", self.children.len());
     }
 
+    /// Merge adjacent text children into one and drop empty ones, throughout
+    /// this subtree (including template contents), like DOM `normalize()`.
+    pub fn normalize(&mut self) {
+        let mut merged: Vec<Node> = Vec::with_capacity(self.children.len());
+        for mut child in std::mem::take(&mut self.children) {
+            if let NodeType::Text(text) = &child.node_type {
+                if text.is_empty() {
+                    continue;
+                }
+                if let Some(Node { node_type: NodeType::Text(previous), .. }) = merged.last_mut() {
+                    previous.push_str(text);
+                    continue;
+                }
+            }
+            child.normalize();
+            merged.push(child);
+        }
+        self.children = merged;
+        if let Some(content) = &mut self.template_content {
+            content.normalize();
+        }
+    }
+
     pub fn node_type(&self) -> &NodeType {
         &self.node_type
     }
@@ -75,10 +213,45 @@ impl Node {
         &self.children
     }
 
+    /// The children that are laid out, painted and extracted. A `<details>`
+    /// without `open` shows only its first `<summary>`.
+    pub fn rendered_children(&self) -> impl Iterator<Item = &Node> {
+        let closed_details = self.is_element("details") && self.get_attribute("open").is_none();
+        let summary = if closed_details {
+            self.children.iter().position(|child| child.is_element("summary"))
+        } else {
+            None
+        };
+        self.children
+            .iter()
+            .enumerate()
+            .filter(move |(index, _)| !closed_details || summary == Some(*index))
+            .map(|(_, child)| child)
+    }
+
     pub fn id(&self) -> usize {
         self.id
     }
     
+    /// Namespace of an element: SVG and MathML content sits in its own namespace.
+    pub fn namespace(&self) -> &Namespace {
+        &self.namespace
+    }
+
+    pub fn set_namespace(&mut self, namespace: Namespace) {
+        self.namespace = namespace;
+    }
+
+    /// The document fragment holding a `<template>`'s parsed contents. It sits
+    /// outside `children`, so it is never styled, rendered or searched.
+    pub fn template_content(&self) -> Option<&Node> {
+        self.template_content.as_deref()
+    }
+
+    pub fn set_template_content(&mut self, content: Node) {
+        self.template_content = Some(Box::new(content));
+    }
+
     pub fn children_mut(&mut self) -> &mut Vec<Node> {
         &mut self.children
     }
@@ -87,6 +260,14 @@ impl Node {
         self.children.clear();
     }
     
+    /// Concatenated text of every descendant text node, in document order.
+    pub fn text_content(&self) -> String {
+        match &self.node_type {
+            NodeType::Text(text) => text.clone(),
+            _ => self.children.iter().map(Node::text_content).collect(),
+        }
+    }
+    
     pub fn set_text_content(&mut self, text: &str) {
         // Clear children and add a single text node
         self.children.clear();
@@ -144,6 +325,40 @@ impl Node {
         None
     }
 
+    /// The first element in this subtree, in document order, whose `id`
+    /// attribute is `id`.
+    pub fn find_by_id(&self, id: &str) -> Option<&Node> {
+        if self.get_attribute("id") == Some(id) {
+            return Some(self);
+        }
+        self.children.iter().find_map(|child| child.find_by_id(id))
+    }
+
+    /// The node in this subtree whose `id()` is `node_id`. Unlike the `id`
+    /// attribute, every node has one and it never changes.
+    pub fn find_by_node_id_mut(&mut self, node_id: usize) -> Option<&mut Node> {
+        if self.id == node_id {
+            return Some(self);
+        }
+        self.children.iter_mut().find_map(|child| child.find_by_node_id_mut(node_id))
+    }
+
+    /// Like `find_by_node_id_mut`, pushing the found node's ancestors onto
+    /// `ancestors` from `self` down.
+    pub fn find_by_node_id_with_ancestors<'a>(&'a self, node_id: usize, ancestors: &mut Vec<&'a Node>) -> Option<&'a Node> {
+        if self.id == node_id {
+            return Some(self);
+        }
+        ancestors.push(self);
+        for child in &self.children {
+            if let Some(found) = child.find_by_node_id_with_ancestors(node_id, ancestors) {
+                return Some(found);
+            }
+        }
+        ancestors.pop();
+        None
+    }
+
     pub fn get_attribute(&self, name: &str) -> Option<&str> {
         match &self.node_type {
             NodeType::Element { attributes, .. } => {
@@ -158,6 +373,94 @@ impl Node {
         }
     }
 
+    /// Set an attribute, replacing any existing value. No-op on non-elements.
+    pub fn set_attribute(&mut self, name: &str, value: &str) {
+        if let NodeType::Element { attributes, .. } = &mut self.node_type {
+            match attributes.iter_mut().find(|attr| attr.name == name) {
+                Some(attr) => attr.value = value.to_string(),
+                None => attributes.push(Attribute { name: name.into(), value: value.to_string() }),
+            }
+        }
+    }
+
+    /// Remove an attribute if present. No-op on non-elements.
+    pub fn remove_attribute(&mut self, name: &str) {
+        if let NodeType::Element { attributes, .. } = &mut self.node_type {
+            attributes.retain(|attr| attr.name != name);
+        }
+    }
+
+    /// Declarations in the inline `style` attribute, as (property, value) text pairs.
+    pub fn inline_style(&self) -> Vec<(String, String)> {
+        let Some(style) = self.get_attribute("style") else {
+            return Vec::new();
+        };
+        split_declarations(style)
+            .into_iter()
+            .filter_map(|declaration| {
+                let (name, value) = declaration.split_once(':')?;
+                let name = name.trim().to_ascii_lowercase();
+                (!name.is_empty()).then(|| (name, value.trim().to_string()))
+            })
+            .collect()
+    }
+
+    /// Set one property in the inline `style` attribute; an empty value removes it.
+    pub fn set_inline_style_property(&mut self, property: &str, value: &str) {
+        let mut declarations = self.inline_style();
+        let value = value.trim();
+        match declarations.iter().position(|(name, _)| name == property) {
+            Some(index) if value.is_empty() => {
+                declarations.remove(index);
+            }
+            Some(index) => declarations[index].1 = value.to_string(),
+            None if value.is_empty() => return,
+            None => declarations.push((property.to_string(), value.to_string())),
+        }
+        let serialized: Vec<String> = declarations.iter().map(|(name, value)| format!("{}: {}", name, value)).collect();
+        self.set_attribute("style", &serialized.join("; "));
+    }
+
+    // Class list helpers: `class` is a whitespace-separated token set
+
+    pub fn has_class(&self, class: &str) -> bool {
+        self.get_attribute("class")
+            .is_some_and(|classes| classes.split_whitespace().any(|c| c == class))
+    }
+
+    pub fn add_class(&mut self, class: &str) {
+        if !self.has_class(class) {
+            let mut classes: Vec<&str> = self.get_attribute("class").unwrap_or("").split_whitespace().collect();
+            classes.push(class);
+            let joined = classes.join(" ");
+            self.set_attribute("class", &joined);
+        }
+    }
+
+    pub fn remove_class(&mut self, class: &str) {
+        if self.has_class(class) {
+            let classes: Vec<&str> = self
+                .get_attribute("class")
+                .unwrap_or("")
+                .split_whitespace()
+                .filter(|c| *c != class)
+                .collect();
+            let joined = classes.join(" ");
+            self.set_attribute("class", &joined);
+        }
+    }
+
+    /// Add `class` if absent, otherwise remove it. Returns whether it is now present.
+    pub fn toggle_class(&mut self, class: &str) -> bool {
+        if self.has_class(class) {
+            self.remove_class(class);
+            false
+        } else {
+            self.add_class(class);
+            true
+        }
+    }
+
     pub fn is_element(&self, tag_name: &str) -> bool {
         match &self.node_type {
             NodeType::Element { tag_name: t, .. } => {
@@ -267,3 +570,98 @@ impl DomTree {
         info!(target: "dom", "=== End DOM Tree ===");
     }
 }
+
+// Split a `style` attribute on the `;`s that end declarations, leaving those
+// inside quotes or parentheses, as in `url("data:image/png;base64,...")`
+fn split_declarations(style: &str) -> Vec<&str> {
+    let mut declarations = Vec::new();
+    let mut start = 0;
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut escaped = false;
+    for (index, c) in style.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match (c, quote) {
+            ('\\', _) => escaped = true,
+            (_, Some(open)) if c == open => quote = None,
+            (_, Some(_)) => {}
+            ('"' | '\'', None) => quote = Some(c),
+            ('(', None) => depth += 1,
+            (')', None) => depth = depth.saturating_sub(1),
+            (';', None) if depth == 0 => {
+                declarations.push(&style[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    declarations.push(&style[start..]);
+    declarations
+}
+
+impl Default for DomTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect_names<'a>(node: &'a Node, names: &mut Vec<&'a Name>) {
+        if let NodeType::Element { tag_name, attributes, .. } = node.node_type() {
+            names.push(tag_name);
+            names.extend(attributes.iter().map(|attr| &attr.name));
+        }
+        for child in node.children() {
+            collect_names(child, names);
+        }
+    }
+
+    #[test]
+    fn test_parsed_names_share_storage() {
+        let rows = "<tr><td class=\"c\">x</td><td class=\"c\">y</td></tr>".repeat(2000);
+        let html = format!("<table>{}</table>", rows);
+        let dom = crate::html::parser::Parser::new(html).parse();
+        let mut names = Vec::new();
+        collect_names(dom.root().expect("root"), &mut names);
+
+        // 4000 cells and their class attributes, but only one allocation per distinct name
+        let td = Name::new("td");
+        let class = Name::new("class");
+        let cells: Vec<_> = names.iter().filter(|name| ***name == td).collect();
+        assert_eq!(cells.len(), 4000);
+        assert!(cells.iter().all(|name| Name::ptr_eq(name, &td)));
+        assert!(names.iter().filter(|name| ***name == "class").all(|name| Name::ptr_eq(name, &class)));
+        assert!(!Name::ptr_eq(&td, &Name::new("tr")));
+        assert_eq!(td.as_str(), "td");
+    }
+
+    #[test]
+    fn test_inline_style_keeps_semicolons_in_quotes_and_parentheses() {
+        let mut node = Node::new(NodeType::Element { tag_name: "div".into(), attributes: Vec::new(), events: Vec::new() });
+        node.set_attribute(
+            "style",
+            "background: url(\"data:image/png;base64,AAAA\"); content: 'a;b'; mask: url(x;y); color: red",
+        );
+        assert_eq!(
+            node.inline_style(),
+            vec![
+                ("background".to_string(), "url(\"data:image/png;base64,AAAA\")".to_string()),
+                ("content".to_string(), "'a;b'".to_string()),
+                ("mask".to_string(), "url(x;y)".to_string()),
+                ("color".to_string(), "red".to_string()),
+            ]
+        );
+
+        node.set_inline_style_property("color", "blue");
+        assert_eq!(
+            node.get_attribute("style"),
+            Some("background: url(\"data:image/png;base64,AAAA\"); content: 'a;b'; mask: url(x;y); color: blue")
+        );
+    }
+}
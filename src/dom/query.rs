@@ -0,0 +1,76 @@
+// Selector queries over a parsed document, matching with the same engine the
+// cascade uses.
+
+use super::{Node, NodeType};
+use crate::css::parser::CssParser;
+use crate::css::style::StyleEngine;
+use crate::css::{Selector, StyleSheet};
+use crate::html::entities::decode_html_entities;
+
+/// What a query hands back about a matched element.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElementSummary {
+    pub tag_name: String,
+    /// Attributes in source order.
+    pub attributes: Vec<(String, String)>,
+    /// Text content of the element and its descendants, entities decoded.
+    pub text: String,
+}
+
+impl ElementSummary {
+    pub fn from_node(node: &Node) -> Option<Self> {
+        let NodeType::Element { tag_name, attributes, .. } = node.node_type() else {
+            return None;
+        };
+        Some(Self {
+            tag_name: tag_name.to_string(),
+            attributes: attributes.iter().map(|attr| (attr.name.to_string(), attr.value.clone())).collect(),
+            text: decode_html_entities(&node.text_content()),
+        })
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Every element under `root` (including it) matching `selector`, in document
+/// order. An unparsable selector matches nothing.
+pub fn query_selector_all<'a>(root: &'a Node, selector: &str) -> Vec<&'a Node> {
+    let Some(selectors) = CssParser::new(selector.to_string()).parse_selector_list() else {
+        log::debug!(target: "dom", "Invalid selector: {}", selector);
+        return Vec::new();
+    };
+    let engine = StyleEngine::new(StyleSheet::new());
+    let mut matches = Vec::new();
+    collect_matches(root, &mut Vec::new(), &engine, &selectors, &mut matches);
+    matches
+}
+
+/// The first element under `root` matching `selector`, in document order.
+pub fn query_selector<'a>(root: &'a Node, selector: &str) -> Option<&'a Node> {
+    query_selector_all(root, selector).into_iter().next()
+}
+
+fn collect_matches<'a>(
+    node: &'a Node,
+    ancestors: &mut Vec<&'a Node>,
+    engine: &StyleEngine,
+    selectors: &[Selector],
+    matches: &mut Vec<&'a Node>,
+) {
+    if !matches!(node.node_type(), NodeType::Element { .. }) {
+        return;
+    }
+    if selectors.iter().any(|selector| engine.matches(node, ancestors, selector)) {
+        matches.push(node);
+    }
+    ancestors.push(node);
+    for child in node.children() {
+        collect_matches(child, ancestors, engine, selectors, matches);
+    }
+    ancestors.pop();
+}
@@ -0,0 +1,36 @@
+// Session history: the URLs visited in this browser, with a cursor on the
+// current entry so back/forward can move through them.
+
+#[derive(Debug, Default)]
+pub struct History {
+    entries: Vec<String>,
+    index: usize,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a new navigation. Any forward entries are discarded.
+    pub fn push(&mut self, url: String) {
+        if !self.entries.is_empty() {
+            self.entries.truncate(self.index + 1);
+        }
+        self.entries.push(url);
+        self.index = self.entries.len() - 1;
+    }
+
+    /// The entry `delta` steps from the current one, if it exists.
+    pub fn peek(&self, delta: isize) -> Option<&str> {
+        let target = self.index.checked_add_signed(delta)?;
+        self.entries.get(target).map(String::as_str)
+    }
+
+    /// Move the cursor by `delta`; callers check the target with `peek` first.
+    pub fn go(&mut self, delta: isize) {
+        if self.peek(delta).is_some() {
+            self.index = self.index.saturating_add_signed(delta);
+        }
+    }
+}
@@ -0,0 +1,196 @@
+// Character encoding detection and decoding for HTML documents.
+//
+// Order of precedence follows the usual browser behaviour (simplified):
+// 1. charset parameter in the HTTP Content-Type header
+// 2. <meta charset> / <meta http-equiv="Content-Type"> in the first ~1KB of the body
+// 3. UTF-8 (lossy) as the default guess
+
+/// How many bytes of the body are scanned for a <meta> charset declaration.
+const META_PRESCAN_BYTES: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    // ISO-8859-1 labels are decoded as windows-1252, like every browser does.
+    Windows1252,
+}
+
+impl Encoding {
+    /// Map an encoding label (e.g. "utf-8", "ISO-8859-1") to a supported encoding.
+    pub fn from_label(label: &str) -> Option<Self> {
+        let label = label.trim().trim_matches(|c| c == '"' || c == '\'').to_ascii_lowercase();
+        match label.as_str() {
+            "utf-8" | "utf8" | "unicode-1-1-utf-8" => Some(Encoding::Utf8),
+            "iso-8859-1" | "iso8859-1" | "iso_8859-1" | "latin1" | "l1" | "windows-1252"
+            | "cp1252" | "x-cp1252" | "us-ascii" | "ascii" => Some(Encoding::Windows1252),
+            _ => None,
+        }
+    }
+
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+            Encoding::Windows1252 => bytes.iter().map(|&b| windows_1252_char(b)).collect(),
+        }
+    }
+}
+
+/// Decode an HTML response body, choosing the encoding from the Content-Type header
+/// first and falling back to a <meta> pre-scan of the body.
+pub fn decode_document(body: &[u8], content_type: Option<&str>) -> String {
+    if let Some(encoding) = content_type.and_then(charset_from_content_type).and_then(|c| Encoding::from_label(&c)) {
+        log::debug!(target: "html", "Decoding document as {:?} (from Content-Type header)", encoding);
+        return encoding.decode(body);
+    }
+
+    // Initial guess is UTF-8; only re-decode when the document says otherwise
+    let guess = Encoding::Utf8;
+    match sniff_meta_charset(body).and_then(|c| Encoding::from_label(&c)) {
+        Some(encoding) if encoding != guess => {
+            log::debug!(target: "html", "Re-decoding document as {:?} (from <meta> charset)", encoding);
+            encoding.decode(body)
+        }
+        _ => guess.decode(body),
+    }
+}
+
+/// Extract the charset parameter from a Content-Type header value.
+pub fn charset_from_content_type(value: &str) -> Option<String> {
+    value.split(';').skip(1).find_map(|param| {
+        let (name, val) = param.split_once('=')?;
+        if name.trim().eq_ignore_ascii_case("charset") {
+            let val = val.trim().trim_matches(|c| c == '"' || c == '\'');
+            if !val.is_empty() {
+                return Some(val.to_string());
+            }
+        }
+        None
+    })
+}
+
+/// Scan the start of the body for <meta charset=...> or
+/// <meta http-equiv="Content-Type" content="...; charset=...">.
+pub fn sniff_meta_charset(body: &[u8]) -> Option<String> {
+    let prefix = &body[..body.len().min(META_PRESCAN_BYTES)];
+    // Meta declarations are ASCII-compatible, so a lossy view of the prefix is enough
+    let text = String::from_utf8_lossy(prefix).to_ascii_lowercase();
+
+    let mut pos = 0;
+    while let Some(start) = text[pos..].find("<meta") {
+        let tag_start = pos + start + "<meta".len();
+        let tag_end = text[tag_start..].find('>').map(|e| tag_start + e).unwrap_or(text.len());
+        let tag = &text[tag_start..tag_end];
+
+        if let Some(charset) = attribute_value(tag, "charset").filter(|c| !c.is_empty()) {
+            return Some(charset);
+        }
+
+        let is_content_type = attribute_value(tag, "http-equiv")
+            .map(|v| v == "content-type")
+            .unwrap_or(false);
+        let content_charset = attribute_value(tag, "content").and_then(|c| charset_from_content_type(&c));
+        if let (true, Some(charset)) = (is_content_type, content_charset) {
+            return Some(charset);
+        }
+
+        pos = tag_end;
+    }
+    None
+}
+
+// Find the value of attribute `name` in the (lowercased) attribute text of a tag.
+// Quoted values are skipped as a whole so "charset=" inside content="..." isn't
+// mistaken for a charset attribute.
+fn attribute_value(tag: &str, name: &str) -> Option<String> {
+    let chars: Vec<char> = tag.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        // Skip separators between attributes
+        while i < chars.len() && (chars[i].is_ascii_whitespace() || chars[i] == '/') {
+            i += 1;
+        }
+
+        let name_start = i;
+        while i < chars.len() && !chars[i].is_ascii_whitespace() && !matches!(chars[i], '=' | '/' | '>') {
+            i += 1;
+        }
+        let attr_name: String = chars[name_start..i].iter().collect();
+
+        while i < chars.len() && chars[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        let mut value = String::new();
+        if i < chars.len() && chars[i] == '=' {
+            i += 1;
+            while i < chars.len() && chars[i].is_ascii_whitespace() {
+                i += 1;
+            }
+            if i < chars.len() && (chars[i] == '"' || chars[i] == '\'') {
+                let quote = chars[i];
+                i += 1;
+                while i < chars.len() && chars[i] != quote {
+                    value.push(chars[i]);
+                    i += 1;
+                }
+                i += 1; // closing quote
+            } else {
+                while i < chars.len() && !chars[i].is_ascii_whitespace() && chars[i] != '>' {
+                    value.push(chars[i]);
+                    i += 1;
+                }
+            }
+        }
+
+        if attr_name == name {
+            return Some(value.trim().to_string());
+        }
+        if i == name_start {
+            // No progress (stray character) - skip it to avoid looping forever
+            i += 1;
+        }
+    }
+    None
+}
+
+fn windows_1252_char(byte: u8) -> char {
+    // 0x80..=0x9F differ from ISO-8859-1; everything else maps directly to U+00XX
+    const HIGH: [char; 32] = [
+        '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+        '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}',
+        '\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+        '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+    ];
+    match byte {
+        0x80..=0x9F => HIGH[(byte - 0x80) as usize],
+        _ => byte as char,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_meta_charset_drives_latin1_decoding() {
+        // "café" encoded as ISO-8859-1, no charset in the HTTP header
+        let mut body = b"<html><head><meta charset=\"iso-8859-1\"></head><body>caf".to_vec();
+        body.push(0xE9);
+        body.extend_from_slice(b"</body></html>");
+
+        let decoded = decode_document(&body, Some("text/html"));
+        assert!(decoded.contains("café"));
+    }
+
+    #[test]
+    fn test_http_equiv_content_type() {
+        let body = b"<meta http-equiv=\"Content-Type\" content=\"text/html; charset=windows-1252\">";
+        assert_eq!(sniff_meta_charset(body), Some("windows-1252".to_string()));
+    }
+
+    #[test]
+    fn test_header_charset_takes_precedence() {
+        let body = "<meta charset=\"iso-8859-1\">café".as_bytes();
+        let decoded = decode_document(body, Some("text/html; charset=utf-8"));
+        assert!(decoded.contains("café"));
+    }
+}
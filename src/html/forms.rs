@@ -0,0 +1,153 @@
+// Form discovery and submission encoding: which controls a form would submit
+// and how their values go on the wire (application/x-www-form-urlencoded).
+
+use super::entities::decode_html_entities;
+use crate::dom::{Node, NodeType};
+use crate::networking::Uri;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FormMethod {
+    Get,
+    Post,
+}
+
+/// A `<form>` on a page and the values it would submit as it stands.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormInfo {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    /// Absolute URL the form submits to.
+    pub action: String,
+    pub method: FormMethod,
+    /// Name/value pairs of the successful controls, in document order.
+    pub fields: Vec<(String, String)>,
+}
+
+impl FormInfo {
+    /// The fields with `overrides` applied: an override replaces the first field
+    /// of the same name, or is appended when the form has no such field.
+    pub fn fields_with(&self, overrides: &[(String, String)]) -> Vec<(String, String)> {
+        let mut fields = self.fields.clone();
+        for (name, value) in overrides {
+            match fields.iter_mut().find(|(field, _)| field == name) {
+                Some(field) => field.1 = value.clone(),
+                None => fields.push((name.clone(), value.clone())),
+            }
+        }
+        fields
+    }
+}
+
+/// Every form under `root`, in document order. Relative actions resolve against
+/// `base`, the URL of the page; a form without an action submits to the page itself.
+pub fn extract_forms(root: &Node, base: &Uri) -> Vec<FormInfo> {
+    let mut forms = Vec::new();
+    collect_forms(root, base, &mut forms);
+    forms
+}
+
+fn collect_forms(node: &Node, base: &Uri, forms: &mut Vec<FormInfo>) {
+    if node.is_element("form") {
+        let action = match node.get_attribute("action").map(str::trim) {
+            Some(action) if !action.is_empty() => base.resolve_reference(action).unwrap_or_else(|_| base.to_string()),
+            _ => base.to_string(),
+        };
+        let method = match node.get_attribute("method") {
+            Some(method) if method.trim().eq_ignore_ascii_case("post") => FormMethod::Post,
+            _ => FormMethod::Get,
+        };
+        let mut fields = Vec::new();
+        collect_fields(node, &mut fields);
+        forms.push(FormInfo {
+            id: node.get_attribute("id").map(str::to_string),
+            name: node.get_attribute("name").map(str::to_string),
+            action,
+            method,
+            fields,
+        });
+        // Forms don't nest
+        return;
+    }
+    for child in node.children() {
+        collect_forms(child, base, forms);
+    }
+}
+
+fn collect_fields(node: &Node, fields: &mut Vec<(String, String)>) {
+    for child in node.children() {
+        let NodeType::Element { tag_name, .. } = child.node_type() else {
+            continue;
+        };
+        let name = child.get_attribute("name").filter(|name| !name.is_empty());
+        let disabled = child.get_attribute("disabled").is_some();
+        match (tag_name.to_ascii_lowercase().as_str(), name) {
+            (_, Some(_)) if disabled => {}
+            ("input", Some(name)) => {
+                let kind = child.get_attribute("type").unwrap_or("text").to_ascii_lowercase();
+                match kind.as_str() {
+                    // Buttons only count when they are the submitter
+                    "submit" | "button" | "reset" | "image" | "file" => {}
+                    "checkbox" | "radio" => {
+                        if child.get_attribute("checked").is_some() {
+                            fields.push((name.to_string(), child.get_attribute("value").unwrap_or("on").to_string()));
+                        }
+                    }
+                    _ => fields.push((name.to_string(), child.get_attribute("value").unwrap_or_default().to_string())),
+                }
+            }
+            ("textarea", Some(name)) => fields.push((name.to_string(), decode_html_entities(&child.text_content()))),
+            ("select", Some(name)) => {
+                let mut options = Vec::new();
+                collect_options(child, &mut options);
+                let selected: Vec<&Node> = options.iter().copied().filter(|o| o.get_attribute("selected").is_some()).collect();
+                let chosen = if !selected.is_empty() {
+                    selected
+                } else if child.get_attribute("multiple").is_none() {
+                    options.into_iter().take(1).collect()
+                } else {
+                    Vec::new()
+                };
+                for option in chosen {
+                    let value = match option.get_attribute("value") {
+                        Some(value) => value.to_string(),
+                        None => decode_html_entities(option.text_content().trim()),
+                    };
+                    fields.push((name.to_string(), value));
+                }
+            }
+            ("button" | "textarea" | "select", _) => {}
+            _ => collect_fields(child, fields),
+        }
+    }
+}
+
+fn collect_options<'a>(node: &'a Node, options: &mut Vec<&'a Node>) {
+    for child in node.children() {
+        if child.is_element("option") {
+            options.push(child);
+        } else {
+            collect_options(child, options);
+        }
+    }
+}
+
+/// Encode `fields` as application/x-www-form-urlencoded.
+pub fn urlencoded(fields: &[(String, String)]) -> String {
+    fields
+        .iter()
+        .map(|(name, value)| format!("{}={}", encode_component(name), encode_component(value)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn encode_component(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'*' | b'-' | b'.' | b'_' => encoded.push(byte as char),
+            b' ' => encoded.push('+'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
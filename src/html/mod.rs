@@ -1,7 +1,10 @@
 pub mod parser;
 pub mod tokenizer;
 pub mod entities;
+pub mod charset;
+pub mod forms;
 
+#[derive(Default)]
 pub struct HtmlDocument {
     pub doctype: Option<String>,
     pub root_element: Option<crate::dom::Node>,
@@ -1,9 +1,18 @@
 use super::tokenizer::{Token, Tokenizer};
+use super::{HtmlDocument, Namespace};
 use crate::dom::{DomTree, Node, NodeType};
 use log::{debug, info};
 
+/// Deepest element nesting the parser builds. Start tags past it close the
+/// innermost open element first, so the new element becomes its sibling; this
+/// keeps recursive tree walks (styling, layout, drop) within the stack.
+pub const MAX_TREE_DEPTH: usize = 512;
+
 pub struct Parser {
     tokenizer: Tokenizer,
+    doctype: Option<String>,
+    // Open elements; the bottom entry is the document root
+    stack: Vec<Node>,
 }
 
 impl Parser {
@@ -11,138 +20,206 @@ impl Parser {
         debug!(target: "html", "Creating new HTML parser");
         Self {
             tokenizer: Tokenizer::new(html),
+            doctype: None,
+            stack: vec![element("#document")],
         }
     }
 
+    /// Parse into an `HtmlDocument`, inserting the `<html>`, `<head>` and `<body>`
+    /// elements the markup left implied.
+    pub fn parse_document(&mut self) -> HtmlDocument {
+        let dom = self.parse();
+        let mut document = HtmlDocument::new();
+        if let Some(doctype) = self.doctype.take() {
+            document.set_doctype(doctype);
+        }
+        let Some(root) = dom.root() else {
+            return document;
+        };
+
+        let html = ensure_head_and_body(document_element(root));
+        for child in html.children() {
+            if node_is_element_named(child, "head") {
+                document.set_head(child.clone());
+            } else if node_is_element_named(child, "body") {
+                document.set_body(child.clone());
+            }
+        }
+        document.set_root_element(html);
+        document
+    }
+
+    /// A parser that receives its input in pieces through `feed`, building the
+    /// tree as complete tokens arrive. Call `finish` once the input is exhausted.
+    pub fn new_streaming() -> Self {
+        Self::new(String::new())
+    }
+
+    /// Append the next piece of input. Tokens cut off by the end of `chunk` wait
+    /// for the following call.
+    pub fn feed(&mut self, chunk: &str) {
+        self.tokenizer.push_input(chunk);
+        while let Some(token) = self.tokenizer.next_complete_token() {
+            self.process_token(token);
+        }
+    }
+
+    /// Parse whatever input is left and close the document.
+    pub fn finish(&mut self) -> DomTree {
+        self.parse()
+    }
+
     pub fn parse(&mut self) -> DomTree {
         info!(target: "html", "Starting HTML parsing");
         let mut dom = DomTree::new();
-        // Build a simple DOM tree with a stack of open elements.
-        //
-        // We keep a document root to avoid duplicating <html> when the input contains
-        // an explicit <html> element (most pages do).
-        let mut stack: Vec<Node> = Vec::new();
-        stack.push(Node::new(NodeType::Element {
-            tag_name: String::from("#document"),
-            attributes: Vec::new(),
-            events: Vec::new(),
-        }));
-
         while let Some(token) = self.tokenizer.next_token() {
-            log::trace!(target: "html", "Processing token: {:?}", token);
-            match token {
-                Token::StartTag { name, attributes, self_closing, namespace } => {
-                    log::trace!(target: "html", "Found start tag: <{}> (namespace: {:?}, self_closing: {})",
-                           name, namespace, self_closing);
-                    let new_node = Node::new(NodeType::Element {
-                        tag_name: name.clone(),
-                        attributes,
-                        events: Vec::new(),
-                    });
-
-                    let is_void = is_void_element(&name) || self_closing;
-                    if !is_void {
-                        stack.push(new_node);
-                    } else {
-                        if let Some(parent) = stack.last_mut() {
-                            parent.add_child(new_node);
-                        }
+            self.process_token(token);
+        }
+
+        // Close any still-open elements.
+        while self.stack.len() > 1 {
+            let Some(node) = self.stack.pop() else {
+                break;
+            };
+            let node = finish_element(node);
+            if let Some(parent) = self.stack.last_mut() {
+                parent.add_child(node);
+            }
+        }
+
+        if let Some(mut root) = self.stack.pop() {
+            // CDATA sections arrive as their own tokens, splitting runs of text
+            root.normalize();
+            dom.set_root(root);
+        }
+        info!(target: "html", "HTML parsing complete");
+        dom
+    }
+
+    fn process_token(&mut self, token: Token) {
+        log::trace!(target: "html", "Processing token: {:?}", token);
+        match token {
+            Token::StartTag { name, attributes, self_closing, namespace } => {
+                log::trace!(target: "html", "Found start tag: <{}> (namespace: {:?}, self_closing: {})",
+                       name, namespace, self_closing);
+                open_implied_table_section(&mut self.stack, &name);
+                let mut new_node = Node::new(NodeType::Element {
+                    tag_name: name.as_str().into(),
+                    attributes,
+                    events: Vec::new(),
+                });
+                let element_namespace = element_namespace(&name, self.stack.last());
+                new_node.set_namespace(element_namespace.clone());
+
+                // `/>` only closes foreign elements; on HTML elements it is ignored
+                let is_void = is_void_element(&name) || (self_closing && element_namespace != Namespace::Html);
+                if !is_void {
+                    self.limit_depth();
+                    self.stack.push(new_node);
+                } else {
+                    if let Some(parent) = self.stack.last_mut() {
+                        parent.add_child(new_node);
                     }
                 }
-                Token::EndTag { name, namespace } => {
-                    log::trace!(
-                        target: "html",
-                        "Found end tag: </{}> (namespace: {:?}, open elements: {})",
-                        name, namespace, stack.len()
-                    );
-
-                    if is_void_element(&name) {
-                        continue;
-                    }
+            }
+            Token::EndTag { name, namespace } => {
+                log::trace!(
+                    target: "html",
+                    "Found end tag: </{}> (namespace: {:?}, open elements: {})",
+                    name, namespace, self.stack.len()
+                );
 
-                    // Pop and attach nodes until we close a matching start tag, or we hit the
-                    // document root (basic error recovery for mismatched tags).
-                    while stack.len() > 1 {
-                        let Some(node) = stack.pop() else {
-                            break;
-                        };
-                        let is_match = node_is_element_named(&node, &name);
-                        if let Some(parent) = stack.last_mut() {
-                            parent.add_child(node);
-                        }
-                        if is_match {
-                            break;
-                        }
-                    }
+                if is_void_element(&name) {
+                    return;
                 }
-                Token::Text(content) => {
-                    if !content.trim().is_empty() {
-                        log::trace!(target: "html", "Found text node: {}", 
-                            content.chars().take(30).collect::<String>());
-                        let text_node = Node::new(NodeType::Text(content));
-                        if let Some(parent) = stack.last_mut() {
-                            parent.add_child(text_node);
-                        }
+
+                // Pop and attach nodes until we close a matching start tag, or we hit the
+                // document root (basic error recovery for mismatched tags).
+                while self.stack.len() > 1 {
+                    let Some(node) = self.stack.pop() else {
+                        break;
+                    };
+                    let node = finish_element(node);
+                    let is_match = node_is_element_named(&node, &name);
+                    if let Some(parent) = self.stack.last_mut() {
+                        parent.add_child(node);
+                    }
+                    if is_match {
+                        break;
                     }
                 }
-                Token::Comment(content) => {
-                    log::trace!(target: "html", "Found comment: {}", 
+            }
+            Token::Text(content) => {
+                if !content.trim().is_empty() {
+                    log::trace!(target: "html", "Found text node: {}", 
                         content.chars().take(30).collect::<String>());
-                    let comment_node = Node::new(NodeType::Comment(content));
-                    if let Some(parent) = stack.last_mut() {
-                        parent.add_child(comment_node);
+                    let text_node = Node::new(NodeType::Text(content));
+                    if let Some(parent) = self.stack.last_mut() {
+                        parent.add_child(text_node);
                     }
                 }
-                Token::Doctype { name, public_id, system_id, force_quirks } => {
-                    log::trace!(target: "html", "Found doctype: {:?} (public: {:?}, system: {:?}, quirks: {})",
-                           name, public_id, system_id, force_quirks);
-                    // Store doctype information - could be used for rendering mode detection
+            }
+            Token::Comment(content) => {
+                log::trace!(target: "html", "Found comment: {}", 
+                    content.chars().take(30).collect::<String>());
+                let comment_node = Node::new(NodeType::Comment(content));
+                if let Some(parent) = self.stack.last_mut() {
+                    parent.add_child(comment_node);
                 }
-                Token::CData(content) => {
-                    log::trace!(target: "html", "Found CDATA section with {} characters", content.len());
-                    let cdata_node = Node::new(NodeType::Text(content));
-                    if let Some(parent) = stack.last_mut() {
-                        parent.add_child(cdata_node);
-                    }
+            }
+            Token::Doctype { name, public_id, system_id, force_quirks } => {
+                log::trace!(target: "html", "Found doctype: {:?} (public: {:?}, system: {:?}, quirks: {})",
+                       name, public_id, system_id, force_quirks);
+                // The tokenizer hands over everything after "<!", e.g. "DOCTYPE html"
+                if let Some(name) = name {
+                    let keyword = name.get(..7).filter(|kw| kw.eq_ignore_ascii_case("doctype"));
+                    let rest = keyword.and_then(|_| name.get(7..)).unwrap_or(&name);
+                    self.doctype = Some(rest.trim().to_string());
                 }
-                Token::ProcessingInstruction { target, data } => {
-                    log::trace!(target: "html", "Found processing instruction: <?{} {}>", target, data);
-                    // Processing instructions are typically ignored in HTML rendering
+            }
+            Token::CData(content) => {
+                log::trace!(target: "html", "Found CDATA section with {} characters", content.len());
+                let cdata_node = Node::new(NodeType::Text(content));
+                if let Some(parent) = self.stack.last_mut() {
+                    parent.add_child(cdata_node);
                 }
-                Token::CharacterReference(ref_value) => {
-                    log::trace!(target: "html", "Found character reference: &#{};", ref_value);
-                    let text_node = Node::new(NodeType::Text(ref_value.clone()));
-                    if let Some(parent) = stack.last_mut() {
-                        parent.add_child(text_node);
-                    }
+            }
+            Token::ProcessingInstruction { target, data } => {
+                log::trace!(target: "html", "Found processing instruction: <?{} {}>", target, data);
+                // Processing instructions are typically ignored in HTML rendering
+            }
+            Token::CharacterReference(ref_value) => {
+                log::trace!(target: "html", "Found character reference: &#{};", ref_value);
+                let text_node = Node::new(NodeType::Text(ref_value.clone()));
+                if let Some(parent) = self.stack.last_mut() {
+                    parent.add_child(text_node);
                 }
-                Token::EntityReference(entity) => {
-                    log::trace!(target: "html", "Found entity reference: &{};", entity);
-                    // Entity references should be resolved to their character equivalents
-                    let resolved = super::entities::resolve_entity(&entity).unwrap_or(entity.clone());
-                    let text_node = Node::new(NodeType::Text(resolved));
-                    if let Some(parent) = stack.last_mut() {
-                        parent.add_child(text_node);
-                    }
+            }
+            Token::EntityReference(entity) => {
+                log::trace!(target: "html", "Found entity reference: &{};", entity);
+                // Entity references should be resolved to their character equivalents
+                let resolved = super::entities::resolve_entity(&entity).unwrap_or(entity.clone());
+                let text_node = Node::new(NodeType::Text(resolved));
+                if let Some(parent) = self.stack.last_mut() {
+                    parent.add_child(text_node);
                 }
             }
         }
+    }
 
-        // Close any still-open elements.
-        while stack.len() > 1 {
-            let Some(node) = stack.pop() else {
-                break;
-            };
-            if let Some(parent) = stack.last_mut() {
-                parent.add_child(node);
-            }
+    // Before opening an element at the depth limit, close the innermost one
+    fn limit_depth(&mut self) {
+        // The stack also holds the document root
+        if self.stack.len() <= MAX_TREE_DEPTH {
+            return;
         }
-
-        if let Some(root) = stack.pop() {
-            dom.set_root(root);
+        if let Some(innermost) = self.stack.pop() {
+            debug!(target: "html", "Nesting deeper than {} elements, flattening", MAX_TREE_DEPTH);
+            let innermost = finish_element(innermost);
+            if let Some(parent) = self.stack.last_mut() {
+                parent.add_child(innermost);
+            }
         }
-        info!(target: "html", "HTML parsing complete");
-        dom
     }
 }
 
@@ -166,9 +243,312 @@ fn is_void_element(tag_name: &str) -> bool {
     )
 }
 
+fn element(tag_name: &str) -> Node {
+    Node::new(NodeType::Element {
+        tag_name: tag_name.into(),
+        attributes: Vec::new(),
+        events: Vec::new(),
+    })
+}
+
+// The <html> element under the document root, wrapping the root's children in
+// one when the markup had none
+fn document_element(root: &Node) -> Node {
+    if let Some(html) = root.children().iter().find(|child| node_is_element_named(child, "html")) {
+        return html.clone();
+    }
+    let mut html = element("html");
+    for child in root.children() {
+        if !matches!(child.node_type(), NodeType::Comment(_)) {
+            html.add_child(child.clone());
+        }
+    }
+    html
+}
+
+// Metadata that belongs in an implied <head>
+fn is_head_content(node: &Node) -> bool {
+    ["base", "link", "meta", "script", "style", "template", "title"]
+        .iter()
+        .any(|tag| node_is_element_named(node, tag))
+}
+
+// Give <html> exactly a <head> followed by a <body>. Leading metadata moves into
+// the head; any other content outside the two moves into the body, keeping its
+// position relative to the body's own children.
+fn ensure_head_and_body(mut html: Node) -> Node {
+    let mut head = None;
+    let mut body = None;
+    let mut head_content = Vec::new();
+    let mut before_body = Vec::new();
+    let mut after_body = Vec::new();
+
+    for child in std::mem::take(html.children_mut()) {
+        if node_is_element_named(&child, "head") && head.is_none() {
+            head = Some(child);
+        } else if node_is_element_named(&child, "body") && body.is_none() {
+            body = Some(child);
+        } else if body.is_some() {
+            after_body.push(child);
+        } else if before_body.is_empty() && is_head_content(&child) {
+            head_content.push(child);
+        } else if !matches!(child.node_type(), NodeType::Comment(_)) || !before_body.is_empty() {
+            before_body.push(child);
+        }
+    }
+
+    let mut head = head.unwrap_or_else(|| element("head"));
+    head.children_mut().extend(head_content);
+    let mut body = body.unwrap_or_else(|| element("body"));
+    let existing = std::mem::take(body.children_mut());
+    body.children_mut().extend(before_body.into_iter().chain(existing).chain(after_body));
+
+    html.add_child(head);
+    html.add_child(body);
+    html
+}
+
+// Namespace from the tree position: <svg> and <math> open foreign content, which
+// their descendants inherit until a <foreignObject> switches back to HTML.
+fn element_namespace(name: &str, parent: Option<&Node>) -> Namespace {
+    if name.eq_ignore_ascii_case("svg") {
+        return Namespace::Svg;
+    }
+    if name.eq_ignore_ascii_case("math") {
+        return Namespace::MathMl;
+    }
+    match parent {
+        Some(parent) if node_is_element_named(parent, "foreignObject") => Namespace::Html,
+        Some(parent) if matches!(parent.namespace(), Namespace::Svg | Namespace::MathMl) => parent.namespace().clone(),
+        _ => Namespace::Html,
+    }
+}
+
+// Rows directly inside a <table> get an implied <tbody>; a new table section
+// closes the body section that is still open.
+fn open_implied_table_section(stack: &mut Vec<Node>, name: &str) {
+    let name = name.to_ascii_lowercase();
+    match name.as_str() {
+        "tr" if stack.last().is_some_and(|n| node_is_element_named(n, "table")) => {
+            stack.push(element("tbody"));
+        }
+        "thead" | "tbody" | "tfoot" if stack.last().is_some_and(|n| node_is_element_named(n, "tbody")) => {
+            if let Some(section) = stack.pop()
+                && let Some(table) = stack.last_mut()
+            {
+                table.add_child(section);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Called as an element is closed. A <template>'s children move into its inert
+// content fragment, keeping them out of the rendered tree.
+fn finish_element(mut node: Node) -> Node {
+    if node_is_element_named(&node, "template") {
+        let mut content = element("#document-fragment");
+        for child in std::mem::take(node.children_mut()) {
+            content.add_child(child);
+        }
+        node.set_template_content(content);
+    }
+    node
+}
+
 fn node_is_element_named(node: &Node, expected: &str) -> bool {
     match node.node_type() {
         NodeType::Element { tag_name, .. } => tag_name.eq_ignore_ascii_case(expected),
         _ => false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn element_children<'a>(node: &'a Node) -> Vec<&'a str> {
+        node.children()
+            .iter()
+            .filter_map(|child| match child.node_type() {
+                NodeType::Element { tag_name, .. } => Some(tag_name.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn find<'a>(node: &'a Node, tag: &str) -> Option<&'a Node> {
+        if node_is_element_named(node, tag) {
+            return Some(node);
+        }
+        node.children().iter().find_map(|child| find(child, tag))
+    }
+
+    #[test]
+    fn test_rows_get_implied_tbody() {
+        let dom = Parser::new("<table><tr><td>a</td></tr><tr><td>b</td></tr></table>".to_string()).parse();
+        let table = find(dom.root().expect("root"), "table").expect("table");
+        assert_eq!(element_children(table), vec!["tbody"]);
+        assert_eq!(element_children(&table.children()[0]), vec!["tr", "tr"]);
+
+        let dom = Parser::new(
+            "<table><thead><tr><th>h</th></tr></thead><tr><td>a</td></tr><tfoot><tr><td>f</td></tr></tfoot></table>"
+                .to_string(),
+        )
+        .parse();
+        let table = find(dom.root().expect("root"), "table").expect("table");
+        assert_eq!(element_children(table), vec!["thead", "tbody", "tfoot"]);
+    }
+
+    #[test]
+    fn test_cdata_is_literal_text() {
+        let dom = Parser::new("<svg><![CDATA[a < b & c]]></svg><p>after</p>".to_string()).parse();
+        let root = dom.root().expect("root");
+        let svg = find(root, "svg").expect("svg");
+        assert_eq!(svg.children().len(), 1);
+        assert_eq!(svg.children()[0].node_type(), &NodeType::Text("a < b & c".to_string()));
+        assert!(find(root, "p").is_some());
+    }
+
+    #[test]
+    fn test_foreign_content_namespaces() {
+        let dom = Parser::new(
+            "<div/><svg><rect/><circle/><foreignObject><p>html</p></foreignObject></svg><math><mi>x</mi></math>"
+                .to_string(),
+        )
+        .parse();
+        let root = dom.root().expect("root");
+
+        let svg = find(root, "svg").expect("svg");
+        assert_eq!(element_children(svg), vec!["rect", "circle", "foreignObject"]);
+        let rect = find(root, "rect").expect("rect");
+        assert_eq!(rect.namespace(), &Namespace::Svg);
+        assert!(rect.children().is_empty());
+        assert_eq!(find(root, "p").expect("p").namespace(), &Namespace::Html);
+        assert_eq!(find(root, "mi").expect("mi").namespace(), &Namespace::MathMl);
+
+        // A self-closing HTML element still contains what follows it
+        let div = find(root, "div").expect("div");
+        assert_eq!(div.namespace(), &Namespace::Html);
+        assert_eq!(element_children(div), vec!["svg", "math"]);
+    }
+
+    #[test]
+    fn test_parse_document_inserts_head_and_body() {
+        let document = Parser::new("<!DOCTYPE html><title>t</title><meta charset=\"utf-8\"><p>hi</p><div>more</div>".to_string())
+            .parse_document();
+        assert_eq!(document.doctype.as_deref(), Some("html"));
+
+        let html = document.root_element.as_ref().expect("html element");
+        assert!(node_is_element_named(html, "html"));
+        assert_eq!(element_children(html), vec!["head", "body"]);
+        assert_eq!(element_children(document.head.as_ref().expect("head")), vec!["title", "meta"]);
+        assert_eq!(element_children(document.body.as_ref().expect("body")), vec!["p", "div"]);
+
+        // Explicit sections are kept as they are
+        let document = Parser::new("<html><head><title>t</title></head><body><p>x</p></body></html>".to_string())
+            .parse_document();
+        assert_eq!(element_children(document.body.as_ref().expect("body")), vec!["p"]);
+        assert_eq!(element_children(document.head.as_ref().expect("head")), vec!["title"]);
+    }
+
+    #[test]
+    fn test_streaming_matches_one_shot_parse() {
+        let html = "<html><head><title>t</title><script>if (a < b) {}</script></head>\
+                    <body><p class=\"x\">one <b>two</b></p><!-- note --><img src=\"i.png\"></body></html>";
+
+        // Structure only: node ids differ between the two trees
+        fn shape(node: &Node) -> String {
+            let children: String = node.children().iter().map(shape).collect();
+            match node.node_type() {
+                NodeType::Element { tag_name, attributes, .. } => {
+                    let attrs: Vec<String> = attributes.iter().map(|a| format!("{}={}", a.name, a.value)).collect();
+                    format!("<{} {}>{}</>", tag_name, attrs.join(","), children)
+                }
+                NodeType::Text(text) => format!("[{}]", text),
+                NodeType::Comment(comment) => format!("(!{})", comment),
+            }
+        }
+        let expected = shape(Parser::new(html.to_string()).parse().root().expect("root"));
+
+        for split in [5, 20, html.find("class").expect("attribute"), html.find("if (").expect("script")] {
+            let mut parser = Parser::new_streaming();
+            parser.feed(&html[..split]);
+            parser.feed(&html[split..]);
+            let dom = parser.finish();
+            assert_eq!(shape(dom.root().expect("root")), expected, "split at {}", split);
+        }
+    }
+
+    #[test]
+    fn test_adjacent_text_is_merged() {
+        // CDATA arrives as its own token between the two text runs
+        let html = "<svg>x<![CDATA[<y>]]>z</svg><div>a<!--c-->b</div>";
+        let dom = Parser::new(html.to_string()).parse();
+        let root = dom.root().expect("root");
+        let svg = find(root, "svg").expect("svg");
+        assert_eq!(svg.children().len(), 1);
+        assert_eq!(svg.children()[0].node_type(), &NodeType::Text("x<y>z".to_string()));
+        // A comment in between keeps the text nodes apart
+        assert_eq!(find(root, "div").expect("div").children().len(), 3);
+    }
+
+    #[test]
+    fn test_attribute_entities_are_decoded() {
+        let html = "<a href=\"a.html?x=1&amp;y=2\" title='&lt;tip&gt; &#169; &unknown; a&b'>link</a>";
+        let dom = Parser::new(html.to_string()).parse();
+        let link = find(dom.root().expect("root"), "a").expect("a");
+        assert_eq!(link.get_attribute("href"), Some("a.html?x=1&y=2"));
+        assert_eq!(link.get_attribute("title"), Some("<tip> © &unknown; a&b"));
+    }
+
+    #[test]
+    fn test_deep_nesting_is_flattened() {
+        let depth = 100_000;
+        let html = format!("{}deep{}", "<div>".repeat(depth), "</div>".repeat(depth));
+        let dom = Parser::new(html).parse();
+        let root = dom.root().expect("root");
+
+        // Walk iteratively: the point is that nothing recursion-bound sees the raw depth
+        let (mut deepest, mut elements, mut text) = (0, 0, String::new());
+        let mut pending = vec![(root, 0)];
+        while let Some((node, level)) = pending.pop() {
+            deepest = deepest.max(level);
+            match node.node_type() {
+                NodeType::Element { .. } => elements += 1,
+                NodeType::Text(content) => text.push_str(content),
+                NodeType::Comment(_) => {}
+            }
+            pending.extend(node.children().iter().map(|child| (child, level + 1)));
+        }
+        assert!(deepest <= MAX_TREE_DEPTH + 1, "depth {}", deepest);
+        // #document plus every <div>, none lost
+        assert_eq!(elements, depth + 1);
+        assert_eq!(text, "deep");
+    }
+
+    #[test]
+    fn test_conditional_and_bogus_comments() {
+        let html = "<div><p>before</p><!--[if IE]><p>ie only</p><![endif]--><p>after</p>\
+                    <![if !IE]><p>not ie</p><![endif]><!--><p>end</p></div><!-- never closed <p>lost</p>";
+        let dom = Parser::new(html.to_string()).parse();
+        let root = dom.root().expect("root");
+        let div = find(root, "div").expect("div");
+        let shape: Vec<String> = div
+            .children()
+            .iter()
+            .map(|child| match child.node_type() {
+                NodeType::Element { tag_name, .. } => format!("<{}>{}", tag_name, child.text_content()),
+                NodeType::Comment(text) => format!("#{}", text),
+                NodeType::Text(text) => text.clone(),
+            })
+            .collect();
+        assert_eq!(
+            shape,
+            vec!["<p>before", "#[if IE]><p>ie only</p><![endif]", "<p>after", "#[if !IE]", "<p>not ie", "#[endif]", "#", "<p>end"]
+        );
+        assert!(dom.root().expect("root").children().iter().any(|child| {
+            matches!(child.node_type(), NodeType::Comment(text) if text == " never closed <p>lost</p>")
+        }));
+    }
+}
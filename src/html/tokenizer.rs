@@ -1,4 +1,4 @@
-use crate::dom::Attribute;
+use crate::dom::{Attribute, Name};
 
 #[derive(Debug, PartialEq)]
 pub enum Token {
@@ -33,6 +33,9 @@ pub struct Tokenizer {
     input: Vec<char>,
     position: usize,
     pending_text: Option<String>,
+    // Scratch space for attribute names; interning borrows from it so a
+    // name already in the table costs no allocation
+    name_buffer: String,
 }
 
 impl Tokenizer {
@@ -41,6 +44,7 @@ impl Tokenizer {
             input: input.chars().collect(),
             position: 0,
             pending_text: None,
+            name_buffer: String::new(),
         }
     }
 
@@ -69,6 +73,30 @@ impl Tokenizer {
         }
     }
 
+    /// Append streamed input, dropping what has already been tokenized.
+    pub fn push_input(&mut self, chunk: &str) {
+        self.input.drain(..self.position);
+        self.position = 0;
+        self.input.extend(chunk.chars());
+    }
+
+    /// Like `next_token`, but only returns tokens known to be complete. A token
+    /// that runs into the end of the buffered input may continue in the next
+    /// chunk, so it is rewound and left for a later call.
+    pub fn next_complete_token(&mut self) -> Option<Token> {
+        if let Some(text) = self.pending_text.take() {
+            return Some(Token::Text(text));
+        }
+        let start = self.position;
+        let token = self.next_token()?;
+        if self.eof() {
+            self.position = start;
+            self.pending_text = None;
+            return None;
+        }
+        Some(token)
+    }
+
     fn consume_start_tag(&mut self) -> Option<Token> {
         let mut name = String::new();
         let mut attributes = Vec::new();
@@ -106,9 +134,10 @@ impl Tokenizer {
         // Check if self-closing (ends with />)
         let self_closing = self.peek_back(2) == Some('/') && self.peek_back(1) == Some('>');
 
-        // For script/style/svg/math tags, capture their content as raw text
+        // For script/style tags, capture their content as raw text. SVG and MathML
+        // content is markup and is tokenized normally.
         let tag_lower = name.to_lowercase();
-        if tag_lower == "script" || tag_lower == "style" || tag_lower == "svg" || tag_lower == "math" {
+        if tag_lower == "script" || tag_lower == "style" {
             if let Some(text_content) = self.consume_raw_text(&tag_lower) {
                 // Store it to be returned as the next token
                 self.pending_text = Some(text_content);
@@ -156,7 +185,13 @@ impl Tokenizer {
     }
 
     fn consume_attribute(&mut self) -> Option<Attribute> {
-        let mut name = String::new();
+        let mut name = std::mem::take(&mut self.name_buffer);
+        name.clear();
+
+        // A stray '=' where a name should start is part of the name, as in browsers
+        if self.current_char() == '=' {
+            name.push(self.consume_char());
+        }
 
         // Parse attribute name (stop at '/' for self-closing tags)
         while !self.eof()
@@ -168,10 +203,11 @@ impl Tokenizer {
             name.push(self.consume_char());
         }
 
+        let interned = (!name.is_empty()).then(|| Name::new(&name));
+        self.name_buffer = name;
+
         // Empty name means we hit '/' or '>' immediately
-        if name.is_empty() {
-            return None;
-        }
+        let name = interned?;
 
         self.consume_whitespace();
 
@@ -198,14 +234,22 @@ impl Tokenizer {
             }
             value
         } else {
+            // Unquoted values run to whitespace or '>'; a '/' is part of the
+            // value, so `href=/a/b` keeps its path
             let mut value = String::new();
-            while !self.eof() && !self.current_char().is_whitespace() && self.current_char() != '>' && self.current_char() != '/'
-            {
+            while !self.eof() && !self.current_char().is_whitespace() && self.current_char() != '>' {
                 value.push(self.consume_char());
             }
             value
         };
 
+        // Values are stored decoded; `&amp;` in an href means a literal '&'
+        let value = if value.contains('&') {
+            super::entities::decode_html_entities(&value)
+        } else {
+            value
+        };
+
         Some(Attribute { name, value })
     }
 
@@ -214,36 +258,59 @@ impl Tokenizer {
         if self.current_char() == '-' && self.peek_char() == '-' {
             self.position += 2; // Consume '--'
             self.consume_comment()
-        } else if self.current_char() == '[' {
-            let cdata_check = self.peek_chars(6);
-            if cdata_check == Some("CDATA[".to_string()) {
-                self.position += 6; // Consume "CDATA["
-                self.consume_cdata()
-            } else {
-                self.consume_doctype()
-            }
-        } else if self.current_char() == 'D' || self.current_char() == 'd' {
+        } else if self.peek_chars(7).as_deref() == Some("[CDATA[") {
+            self.position += 7; // Consume "[CDATA["
+            self.consume_cdata()
+        } else if self.peek_chars(7).is_some_and(|keyword| keyword.eq_ignore_ascii_case("doctype")) {
             self.consume_doctype()
         } else {
-            None
+            // Anything else after "<!" (e.g. `<![if !IE]>`) is a bogus comment up to the next '>'
+            self.consume_bogus_comment()
         }
     }
 
+    // Comment body after "<!--". Conditional comments (`<!--[if IE]>...<![endif]-->`)
+    // are ordinary comments; an unterminated one runs to the end of input.
     fn consume_comment(&mut self) -> Option<Token> {
         let mut content = String::new();
 
+        // `<!-->` and `<!--->` close at once
+        if self.current_char() == '>' {
+            self.position += 1;
+            return Some(Token::Comment(content));
+        }
+        if self.current_char() == '-' && self.peek_char() == '>' {
+            self.position += 2;
+            return Some(Token::Comment(content));
+        }
+
         while !self.eof() {
             if self.current_char() == '-' && self.peek_char() == '-' && self.peek_next_char() == '>'
             {
                 self.position += 3; // Consume '-->'
                 break;
             }
+            if self.peek_chars(4).as_deref() == Some("--!>") {
+                self.position += 4;
+                break;
+            }
             content.push(self.consume_char());
         }
 
         Some(Token::Comment(content))
     }
 
+    fn consume_bogus_comment(&mut self) -> Option<Token> {
+        let mut content = String::new();
+        while !self.eof() && self.current_char() != '>' {
+            content.push(self.consume_char());
+        }
+        if !self.eof() {
+            self.position += 1; // Consume '>'
+        }
+        Some(Token::Comment(content))
+    }
+
     fn consume_doctype(&mut self) -> Option<Token> {
         let mut content = String::new();
 
@@ -340,6 +407,7 @@ impl Tokenizer {
         }
     }
 
+    // Everything up to "]]>" is literal text: no tags, no character references
     fn consume_cdata(&mut self) -> Option<Token> {
         let mut content = String::new();
         while !self.eof() {
@@ -380,3 +448,66 @@ impl Tokenizer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start_tag_attributes(html: &str) -> Vec<(String, String)> {
+        match Tokenizer::new(html.to_string()).next_token() {
+            Some(Token::StartTag { attributes, .. }) => {
+                attributes.into_iter().map(|attr| (attr.name.to_string(), attr.value)).collect()
+            }
+            other => panic!("expected a start tag, got {:?}", other),
+        }
+    }
+
+    fn pair(name: &str, value: &str) -> (String, String) {
+        (name.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn test_boolean_attribute_has_empty_value() {
+        assert_eq!(
+            start_tag_attributes("<input disabled type=\"checkbox\" checked>"),
+            vec![pair("disabled", ""), pair("type", "checkbox"), pair("checked", "")]
+        );
+        assert_eq!(start_tag_attributes("<a href=\"\">"), vec![pair("href", "")]);
+    }
+
+    #[test]
+    fn test_unquoted_value_ends_at_whitespace_or_gt() {
+        assert_eq!(
+            start_tag_attributes("<div data-x=foo id=bar>"),
+            vec![pair("data-x", "foo"), pair("id", "bar")]
+        );
+        assert_eq!(start_tag_attributes("<a href=/docs/page.html>"), vec![pair("href", "/docs/page.html")]);
+    }
+
+    #[test]
+    fn test_single_quoted_value() {
+        assert_eq!(
+            start_tag_attributes("<p title='say \"hi\"' class='a b'>"),
+            vec![pair("title", "say \"hi\""), pair("class", "a b")]
+        );
+    }
+
+    #[test]
+    fn test_stray_equals_does_not_stall() {
+        assert_eq!(start_tag_attributes("<div =x id=y>"), vec![pair("=x", ""), pair("id", "y")]);
+    }
+
+    #[test]
+    fn test_repeated_attribute_names_share_interned_storage() {
+        let mut tokenizer = Tokenizer::new("<td class=a><td class=b>".to_string());
+        let mut class_names = Vec::new();
+        while let Some(token) = tokenizer.next_token() {
+            if let Token::StartTag { attributes, .. } = token {
+                class_names.extend(attributes.into_iter().map(|attr| attr.name));
+            }
+        }
+        assert_eq!(class_names.len(), 2);
+        assert!(Name::ptr_eq(&class_names[0], &class_names[1]));
+        assert!(Name::ptr_eq(&class_names[0], &Name::new("class")));
+    }
+}
@@ -4,27 +4,29 @@ pub enum Node {
     Number(f64),
     String(String),
     Boolean(bool),
+    RegexLiteral { pattern: String, flags: String },
     Null,
     Undefined,
     
     // Variables and Functions
     Identifier(String),
     VariableDecl {
-        name: String,
+        kind: DeclarationKind,
+        pattern: Pattern,
         init: Option<Box<Node>>,
     },
     FunctionDecl {
         name: String,
-        params: Vec<String>,
+        params: Vec<Param>,
         body: Vec<Node>,
     },
     FunctionExpr {
         name: Option<String>,  // Optional for anonymous functions
-        params: Vec<String>,
+        params: Vec<Param>,
         body: Vec<Node>,
     },
     ArrowFunction {
-        params: Vec<String>,
+        params: Vec<Param>,
         body: Box<Node>,  // Can be expression or block
     },
     
@@ -53,6 +55,10 @@ pub enum Node {
         condition: Box<Node>,
         body: Box<Node>,
     },
+    DoWhileLoop {
+        body: Box<Node>,
+        condition: Box<Node>,
+    },
     ForLoop {
         init: Option<Box<Node>>,
         condition: Option<Box<Node>>,
@@ -71,8 +77,12 @@ pub enum Node {
     },
     Block(Vec<Node>),
     ReturnStatement(Option<Box<Node>>),
-    BreakStatement,
-    ContinueStatement,
+    BreakStatement(Option<String>),
+    ContinueStatement(Option<String>),
+    Labeled {
+        label: String,
+        body: Box<Node>,
+    },
     ThrowStatement(Box<Node>),
     TryCatch {
         try_block: Box<Node>,
@@ -104,6 +114,9 @@ pub enum Node {
     
     // Add array literal
     ArrayLiteral(Vec<Node>),
+
+    // `...expr` inside an array literal or argument list
+    Spread(Box<Node>),
     
     // Add new expression
     NewExpr {
@@ -125,6 +138,39 @@ pub enum Node {
     },
 }
 
+/// A function parameter: `name`, `name = default` or `...name`.
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub name: String,
+    pub default: Option<Box<Node>>,
+    pub rest: bool,
+}
+
+impl Param {
+    pub fn new(name: String) -> Self {
+        Self { name, default: None, rest: false }
+    }
+}
+
+/// Keyword a variable declaration was introduced with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeclarationKind {
+    Var,
+    // `let` and `const` are block-scoped
+    Let,
+    Const,
+}
+
+/// Binding target of a variable declaration: a plain name or a destructuring pattern.
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Identifier(String),
+    // { key: target = default, ... } (shorthand `{ a }` uses `a` as both key and target)
+    Object(Vec<(String, Pattern, Option<Box<Node>>)>),
+    // [target = default, , ...] - `None` marks a hole
+    Array(Vec<Option<(Pattern, Option<Box<Node>>)>>),
+}
+
 #[derive(Debug, Clone)]
 pub enum BinaryOperator {
     Add,
@@ -150,6 +196,8 @@ pub enum BinaryOperator {
 pub enum UnaryOperator {
     Not,           // !
     Typeof,
+    Void,          // void: evaluate, then undefined
+    Delete,        // delete obj.prop
     Negative,      // -
     PostIncrement, // ++ (postfix)
     PostDecrement, // -- (postfix)
@@ -0,0 +1,120 @@
+// Calendar arithmetic for the Date built-in.
+// Times are milliseconds since the Unix epoch; there is no time zone database,
+// so every local-time accessor reports UTC.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MS_PER_DAY: f64 = 86_400_000.0;
+
+/// Broken-down UTC time. `month` is 0-based, as in JavaScript.
+#[derive(Debug, PartialEq)]
+pub struct DateParts {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hours: u32,
+    pub minutes: u32,
+    pub seconds: u32,
+    pub millis: u32,
+}
+
+pub fn now_ms() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as f64)
+        .unwrap_or(0.0)
+}
+
+/// Split a time value into calendar fields, or None for NaN/infinite times.
+pub fn parts_from_ms(ms: f64) -> Option<DateParts> {
+    if !ms.is_finite() {
+        return None;
+    }
+    let days = (ms / MS_PER_DAY).floor();
+    let ms_in_day = (ms - days * MS_PER_DAY) as u32;
+    let (year, month, day) = civil_from_days(days as i64);
+    Some(DateParts {
+        year,
+        month: month - 1,
+        day,
+        hours: ms_in_day / 3_600_000,
+        minutes: ms_in_day / 60_000 % 60,
+        seconds: ms_in_day / 1000 % 60,
+        millis: ms_in_day % 1000,
+    })
+}
+
+/// Time value for UTC calendar fields; `month` is 0-based and may overflow
+/// into following years, like `Date.UTC`.
+pub fn ms_from_parts(year: f64, month: f64, day: f64, hours: f64, minutes: f64, seconds: f64, millis: f64) -> f64 {
+    if ![year, month, day, hours, minutes, seconds, millis].iter().all(|field| field.is_finite()) {
+        return f64::NAN;
+    }
+    let year = year + (month / 12.0).floor();
+    let month = month.rem_euclid(12.0);
+    let days = days_from_civil(year as i64, month as u32 + 1, 1) as f64 + day - 1.0;
+    days * MS_PER_DAY + hours * 3_600_000.0 + minutes * 60_000.0 + seconds * 1000.0 + millis
+}
+
+pub fn to_iso_string(ms: f64) -> Option<String> {
+    let p = parts_from_ms(ms)?;
+    Some(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        p.year, p.month + 1, p.day, p.hours, p.minutes, p.seconds, p.millis
+    ))
+}
+
+/// The form `Date()` and `toString` give, e.g.
+/// `Tue Mar 05 2024 09:08:07 GMT+0000 (Coordinated Universal Time)`.
+pub fn to_date_string(ms: f64) -> String {
+    const DAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+    let Some(p) = parts_from_ms(ms) else {
+        return "Invalid Date".to_string();
+    };
+    // The epoch was a Thursday
+    let weekday = ((ms / MS_PER_DAY).floor() as i64 + 4).rem_euclid(7) as usize;
+    format!(
+        "{} {} {:02} {:04} {:02}:{:02}:{:02} GMT+0000 (Coordinated Universal Time)",
+        DAYS[weekday], MONTHS[p.month as usize], p.day, p.year, p.hours, p.minutes, p.seconds
+    )
+}
+
+// Howard Hinnant's days_from_civil / civil_from_days, over the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trips_calendar_fields() {
+        // 2024-02-29T13:45:30.250Z, a leap day
+        let ms = ms_from_parts(2024.0, 1.0, 29.0, 13.0, 45.0, 30.0, 250.0);
+        assert_eq!(ms, 1_709_214_330_250.0);
+        assert_eq!(to_iso_string(ms).as_deref(), Some("2024-02-29T13:45:30.250Z"));
+        assert_eq!(to_iso_string(-1.0).as_deref(), Some("1969-12-31T23:59:59.999Z"));
+        assert_eq!(parts_from_ms(f64::NAN), None);
+    }
+}
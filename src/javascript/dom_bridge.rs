@@ -21,9 +21,4 @@ impl DomBridge {
         // Implement DOM binding logic here
         Ok(())
     }
-
-    pub fn handle_event(&mut self, event_name: &str, target: &DomNode) -> Result<(), Box<dyn Error>> {
-        // Implement event handling logic here
-        Ok(())
-    }
 } 
\ No newline at end of file
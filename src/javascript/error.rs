@@ -0,0 +1,55 @@
+use super::value::JsValue;
+use std::fmt;
+
+/// A value thrown by script code (`throw expr`, or a TypeError raised by the runtime).
+///
+/// Exceptions are the only errors `try`/`catch` intercepts; any other error coming out of
+/// the runtime is an engine-internal failure and propagates to the caller untouched.
+#[derive(Debug, Clone)]
+pub struct JsException {
+    pub value: JsValue,
+}
+
+impl JsException {
+    pub fn new(value: JsValue) -> Self {
+        Self { value }
+    }
+}
+
+impl fmt::Display for JsException {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            JsValue::Object(obj) => {
+                // Error-like objects print as "Name: message", like browser consoles do
+                let obj = obj.borrow();
+                match (obj.get_property("name"), obj.get_property("message")) {
+                    (Some(JsValue::String(name)), Some(JsValue::String(message))) => {
+                        write!(f, "Uncaught {}: {}", name, message)
+                    }
+                    _ => write!(f, "Uncaught [object Object]"),
+                }
+            }
+            JsValue::String(s) => write!(f, "Uncaught {}", s),
+            other => write!(f, "Uncaught {:?}", other),
+        }
+    }
+}
+
+impl std::error::Error for JsException {}
+
+/// Raised when a script outruns the runtime's loop, depth or time budget.
+///
+/// Script code cannot catch it, so the whole script unwinds; the runtime itself stays
+/// usable and later scripts run with a fresh budget.
+#[derive(Debug, Clone)]
+pub struct ScriptAborted {
+    pub reason: String,
+}
+
+impl fmt::Display for ScriptAborted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Script aborted: {}", self.reason)
+    }
+}
+
+impl std::error::Error for ScriptAborted {}
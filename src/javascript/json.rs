@@ -0,0 +1,257 @@
+// JSON text to script values, for JSON.parse and Response.json().
+
+use super::value::{JsObject, JsValue};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Deepest nesting of arrays and objects accepted, so that hostile input
+/// cannot exhaust the stack of the recursive parser.
+const MAX_DEPTH: usize = 512;
+
+/// Parse `text` as a single JSON value. The message names what was wrong and
+/// where, for the SyntaxError the caller throws.
+pub fn parse(text: &str) -> Result<JsValue, String> {
+    let mut parser = JsonParser { chars: text.chars().collect(), pos: 0, depth: 0 };
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    if parser.pos < parser.chars.len() {
+        return Err(parser.unexpected());
+    }
+    Ok(value)
+}
+
+struct JsonParser {
+    chars: Vec<char>,
+    pos: usize,
+    // Arrays and objects currently open
+    depth: usize,
+}
+
+impl JsonParser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn unexpected(&self) -> String {
+        match self.peek() {
+            Some(c) => format!("Unexpected token '{}' in JSON at position {}", c, self.pos),
+            None => "Unexpected end of JSON input".to_string(),
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<JsValue, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{' | '[') => self.parse_nested(),
+            Some('"') => self.parse_string().map(JsValue::String),
+            Some('-' | '0'..='9') => self.parse_number(),
+            Some('t') => self.parse_literal("true", JsValue::Boolean(true)),
+            Some('f') => self.parse_literal("false", JsValue::Boolean(false)),
+            Some('n') => self.parse_literal("null", JsValue::Null),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn parse_literal(&mut self, word: &str, value: JsValue) -> Result<JsValue, String> {
+        for expected in word.chars() {
+            self.expect(expected)?;
+        }
+        Ok(value)
+    }
+
+    fn parse_nested(&mut self) -> Result<JsValue, String> {
+        if self.depth == MAX_DEPTH {
+            return Err(format!("JSON nested too deeply at position {}", self.pos));
+        }
+        self.depth += 1;
+        let value = if self.peek() == Some('{') { self.parse_object() } else { self.parse_array() };
+        self.depth -= 1;
+        value
+    }
+
+    fn parse_object(&mut self) -> Result<JsValue, String> {
+        self.expect('{')?;
+        let mut object = JsObject::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(JsValue::Object(Rc::new(RefCell::new(object))));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            let value = self.parse_value()?;
+            object.set_property(key, value);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => {}
+                Some('}') => return Ok(JsValue::Object(Rc::new(RefCell::new(object)))),
+                _ => {
+                    self.pos -= 1;
+                    return Err(self.unexpected());
+                }
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<JsValue, String> {
+        self.expect('[')?;
+        let mut array = JsObject::new_array();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(JsValue::Object(Rc::new(RefCell::new(array))));
+        }
+        let mut index = 0;
+        loop {
+            let value = self.parse_value()?;
+            array.set_element(index, value);
+            index += 1;
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => {}
+                Some(']') => return Ok(JsValue::Object(Rc::new(RefCell::new(array)))),
+                _ => {
+                    self.pos -= 1;
+                    return Err(self.unexpected());
+                }
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut text = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(text),
+                Some('\\') => text.push(self.parse_escape()?),
+                Some(c) if c >= ' ' => text.push(c),
+                _ => {
+                    self.pos -= 1;
+                    return Err(self.unexpected());
+                }
+            }
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<char, String> {
+        let escaped = match self.next() {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => return self.parse_unicode_escape(),
+            _ => {
+                self.pos -= 1;
+                return Err(self.unexpected());
+            }
+        };
+        Ok(escaped)
+    }
+
+    // After `\u`; a surrogate pair spans two escapes
+    fn parse_unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.parse_hex4()?;
+        if (0xD800..0xDC00).contains(&high) && self.chars.get(self.pos..self.pos + 2) == Some(&['\\', 'u']) {
+            let start = self.pos;
+            self.pos += 2;
+            let low = self.parse_hex4()?;
+            if (0xDC00..0xE000).contains(&low) {
+                let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                return Ok(char::from_u32(code).unwrap_or('\u{FFFD}'));
+            }
+            self.pos = start;
+        }
+        Ok(char::from_u32(high).unwrap_or('\u{FFFD}'))
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, String> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self.peek().and_then(|c| c.to_digit(16)).ok_or_else(|| self.unexpected())?;
+            code = code * 16 + digit;
+            self.pos += 1;
+        }
+        Ok(code)
+    }
+
+    fn parse_number(&mut self) -> Result<JsValue, String> {
+        let start = self.pos;
+        if self.peek() == Some('-') {
+            self.pos += 1;
+        }
+        match self.peek() {
+            Some('0') => self.pos += 1,
+            Some('1'..='9') => self.skip_digits(),
+            _ => return Err(self.unexpected()),
+        }
+        if self.peek() == Some('.') {
+            self.pos += 1;
+            self.require_digits()?;
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            self.pos += 1;
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.pos += 1;
+            }
+            self.require_digits()?;
+        }
+        let literal: String = self.chars[start..self.pos].iter().collect();
+        literal.parse().map(JsValue::Number).map_err(|_| self.unexpected())
+    }
+
+    fn skip_digits(&mut self) {
+        while matches!(self.peek(), Some('0'..='9')) {
+            self.pos += 1;
+        }
+    }
+
+    fn require_digits(&mut self) -> Result<(), String> {
+        if !matches!(self.peek(), Some('0'..='9')) {
+            return Err(self.unexpected());
+        }
+        self.skip_digits();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nesting_is_capped() {
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        let error = parse(&nested(100_000)).expect_err("too deep");
+        assert!(error.contains("nested too deeply"), "error: {}", error);
+    }
+}
@@ -1,6 +1,11 @@
 mod ast;
+mod date;
+mod error;
+mod json;
 mod parser;
+mod regex;
 mod runtime;
+mod storage;
 mod value;
 mod dom_bridge;
 mod tokenizer;
@@ -12,6 +17,8 @@ use value::JsValue;
 use dom_bridge::DomBridge;
 use crate::dom::Node as DomNode;
 
+pub use runtime::{DEFAULT_MAX_EXECUTION_DEPTH, DEFAULT_MAX_LOOP_ITERATIONS};
+
 pub struct JavaScriptEngine {
     runtime: runtime::Runtime,
     dom_bridge: DomBridge,
@@ -33,24 +40,615 @@ impl JavaScriptEngine {
         self.runtime.execute(&ast)
     }
 
-    pub fn bind_dom(&mut self, dom: &crate::dom::Node) -> Result<(), Box<dyn Error>> {
+    /// Give scripts access to `dom`. The tree is shared, not copied: changes
+    /// made by scripts are visible through the caller's handle.
+    pub fn bind_dom(&mut self, dom: Rc<RefCell<DomNode>>) -> Result<(), Box<dyn Error>> {
+        self.dom_bridge.bind_dom(&dom.borrow())?;
         self.runtime.bind_dom(dom);
-        self.dom_bridge.bind_dom(dom)
-    }
-    
-    pub fn bind_dom_shared(&mut self, dom: Rc<RefCell<DomNode>>) {
-        self.runtime.bind_dom_shared(dom);
+        Ok(())
     }
 
-    pub fn handle_event(&mut self, event_name: &str, target: &crate::dom::Node) -> Result<(), Box<dyn Error>> {
-        self.dom_bridge.handle_event(event_name, target)
+    pub fn bind_stylesheet(&mut self, stylesheet: crate::css::StyleSheet) {
+        self.runtime.bind_stylesheet(stylesheet);
     }
-    
+
     pub fn runtime_mut(&mut self) -> &mut runtime::Runtime {
         &mut self.runtime
     }
 
+    /// Route console output to `sender` instead; `take_console_messages` then
+    /// returns nothing further.
     pub fn set_console_log_sender(&mut self, sender: std::sync::mpsc::Sender<(String, String)>) {
         self.runtime.set_console_log_sender(sender);
     }
-} 
\ No newline at end of file
+
+    /// Drain the console messages logged since the last call, as (level, message)
+    /// pairs where level is "log", "info", "warn", "error" or "debug". Only the
+    /// most recent messages are kept between calls.
+    pub fn take_console_messages(&mut self) -> Vec<(String, String)> {
+        self.runtime.take_console_messages()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    // Run a script and collect everything it printed through console.*
+    fn run_and_capture(script: &str) -> Vec<String> {
+        let (tx, rx) = mpsc::channel();
+        let mut engine = JavaScriptEngine::new();
+        engine.set_console_log_sender(tx);
+        engine.evaluate(script).expect("script should run");
+        drop(engine);
+        rx.iter().map(|(_, message)| message).collect()
+    }
+
+    #[test]
+    fn test_thrown_object_is_caught() {
+        let logs = run_and_capture("try { throw {msg: 'x'}; } catch (e) { console.log(e.msg); }");
+        assert_eq!(logs, vec!["x"]);
+    }
+
+    #[test]
+    fn test_error_constructor_and_finally() {
+        let logs = run_and_capture(
+            "try { throw new TypeError('bad'); } catch (e) { console.log(e.name, e.message); } finally { console.log('done'); }",
+        );
+        assert_eq!(logs, vec!["TypeError bad", "done"]);
+    }
+
+    #[test]
+    fn test_catch_block_scopes_let_and_const() {
+        let logs = run_and_capture(
+            "try { throw 1; } catch (e) { let inner = 2; const fixed = 3; var outer = 4; }
+             console.log(typeof e, typeof inner, typeof fixed, outer);",
+        );
+        assert_eq!(logs, vec!["undefined undefined undefined 4"]);
+    }
+
+    #[test]
+    fn test_reference_error_is_catchable() {
+        let logs = run_and_capture(
+            "try { missing + 1; } catch (e) { console.log(e.name, e.message); }
+             console.log(typeof missing);",
+        );
+        assert_eq!(logs, vec!["ReferenceError missing is not defined", "undefined"]);
+    }
+
+    #[test]
+    fn test_uncaught_exception_propagates() {
+        let mut engine = JavaScriptEngine::new();
+        let err = engine.evaluate("throw new Error('boom');").expect_err("should throw");
+        assert_eq!(err.to_string(), "Uncaught Error: boom");
+    }
+
+    #[test]
+    fn test_do_while_runs_body_once() {
+        let logs = run_and_capture("var n = 0; do { n = n + 1; console.log('body'); } while (false); console.log(n);");
+        assert_eq!(logs, vec!["body", "1"]);
+    }
+
+    #[test]
+    fn test_object_destructuring() {
+        let logs = run_and_capture("const {a, b: renamed, c = 3} = {a: 1, b: 2}; console.log(a, renamed, c);");
+        assert_eq!(logs, vec!["1 2 3"]);
+    }
+
+    #[test]
+    fn test_array_destructuring() {
+        let logs = run_and_capture("const [x, , y, z = 'dflt'] = ['one', 'two', 'three']; console.log(x, y, z);");
+        assert_eq!(logs, vec!["one three dflt"]);
+    }
+
+    #[test]
+    fn test_array_literal_elements() {
+        let logs = run_and_capture("var arr = [1, new Error('e'), 'three']; console.log(arr.length, arr[1].message, arr[2]);");
+        assert_eq!(logs, vec!["3 e three"]);
+    }
+
+    #[test]
+    fn test_default_parameter() {
+        let logs = run_and_capture("function f(a, b = 2) { console.log(a + b); } f(1); f(1, 5);");
+        assert_eq!(logs, vec!["3", "6"]);
+    }
+
+    #[test]
+    fn test_rest_parameter() {
+        let logs = run_and_capture("function f(first, ...rest) { console.log(first, rest.length, rest[0], rest[1]); } f('a', 'b', 'c');");
+        assert_eq!(logs, vec!["a 2 b c"]);
+    }
+
+    #[test]
+    fn test_object_keys() {
+        let logs = run_and_capture("var k = Object.keys({a: 1, b: 2}); console.log(k.length, k[0], k[1]);");
+        assert_eq!(logs, vec!["2 a b"]);
+    }
+
+    #[test]
+    fn test_object_assign() {
+        let logs = run_and_capture(
+            "var t = {}; var r = Object.assign(t, {x: 1}); r.y = 2; console.log(t.x, t.y, Object.keys(t).length);",
+        );
+        assert_eq!(logs, vec!["1 2 2"]);
+    }
+
+    #[test]
+    fn test_number_to_string() {
+        let logs = run_and_capture("console.log(String(1), String(1.5), String(NaN), String(-0), String(1/0));");
+        assert_eq!(logs, vec!["1 1.5 NaN 0 Infinity"]);
+        assert_eq!(value::number_to_string(1e21), "1e+21");
+        assert_eq!(value::number_to_string(1.5e-7), "1.5e-7");
+        assert_eq!(value::number_to_string(0.000001), "0.000001");
+        assert_eq!(value::number_to_string(123456.789), "123456.789");
+    }
+
+    #[test]
+    fn test_equality_matrix() {
+        // (left, right, ==, ===)
+        let cases = [
+            ("1", "1", true, true),
+            ("1", "'1'", true, false),
+            ("0", "''", true, false),
+            ("null", "undefined", true, false),
+            ("null", "null", true, true),
+            ("undefined", "undefined", true, true),
+            ("null", "0", false, false),
+            ("undefined", "0", false, false),
+            ("null", "false", false, false),
+            ("true", "1", true, false),
+            ("false", "'0'", true, false),
+            ("true", "'true'", false, false),
+            ("'abc'", "'abc'", true, true),
+            ("NaN", "NaN", false, false),
+        ];
+        for (left, right, loose, strict) in cases {
+            let script = format!("console.log({l} == {r}, {l} === {r}, {l} != {r}, {l} !== {r});", l = left, r = right);
+            let expected = format!("{} {} {} {}", loose, strict, !loose, !strict);
+            assert_eq!(run_and_capture(&script), vec![expected], "{} vs {}", left, right);
+        }
+    }
+
+    #[test]
+    fn test_object_identity_equality() {
+        let logs = run_and_capture("var a = {}; var b = a; console.log(a === b, a === {}, a == b);");
+        assert_eq!(logs, vec!["true false true"]);
+    }
+
+    #[test]
+    fn test_get_computed_style() {
+        let html = "<html><body><div class=\"card\"><p id=\"msg\">hi</p></div></body></html>";
+        let dom = crate::html::parser::Parser::new(html.to_string()).parse();
+        let root = dom.root().expect("document root").clone();
+        let stylesheet = crate::css::parser::CssParser::new(
+            ".card p { color: red; margin-top: 4px; } p { color: blue; }".to_string(),
+        )
+        .parse();
+
+        let (tx, rx) = mpsc::channel();
+        let mut engine = JavaScriptEngine::new();
+        engine.set_console_log_sender(tx);
+        engine.bind_dom(Rc::new(RefCell::new(root))).expect("bind dom");
+        engine.bind_stylesheet(stylesheet);
+        engine
+            .evaluate(
+                "var style = getComputedStyle(document.getElementById('msg'));\n\
+                 console.log(style.getPropertyValue('color'));\n\
+                 console.log(style.getPropertyValue('margin-top'));\n\
+                 console.log(style.getPropertyValue('padding') === '');",
+            )
+            .expect("script should run");
+        drop(engine);
+
+        let logs: Vec<String> = rx.iter().map(|(_, message)| message).collect();
+        assert_eq!(logs, vec!["rgb(255, 0, 0)", "4px", "true"]);
+    }
+
+    #[test]
+    fn test_get_computed_style_of_element_without_an_id() {
+        let html = "<html><body><p>plain</p><div class=\"card\"><p>carded</p></div></body></html>";
+        let dom = crate::html::parser::Parser::new(html.to_string()).parse();
+        let stylesheet = crate::css::parser::CssParser::new(".card p { color: red; }".to_string()).parse();
+
+        let (tx, rx) = mpsc::channel();
+        let mut engine = JavaScriptEngine::new();
+        engine.set_console_log_sender(tx);
+        engine.bind_dom(Rc::new(RefCell::new(dom.root().expect("document root").clone()))).expect("bind dom");
+        engine.bind_stylesheet(stylesheet);
+        engine
+            .evaluate(
+                "var ps = document.getElementsByTagName('p');\n\
+                 console.log(getComputedStyle(ps[0]).getPropertyValue('color') === '', getComputedStyle(ps[1]).getPropertyValue('color'));",
+            )
+            .expect("script should run");
+        drop(engine);
+
+        let logs: Vec<String> = rx.iter().map(|(_, message)| message).collect();
+        assert_eq!(logs, vec!["true rgb(255, 0, 0)"]);
+    }
+
+    #[test]
+    fn test_array_is_array() {
+        let logs = run_and_capture(
+            "console.log(Array.isArray([1,2]), Array.isArray({}), Array.isArray({length: 0}), Array.isArray(Object.keys({a: 1})));",
+        );
+        assert_eq!(logs, vec!["true false false true"]);
+    }
+
+    #[test]
+    fn test_json_stringify_arrays_and_objects() {
+        let logs = run_and_capture(
+            "console.log(JSON.stringify({a: [1, 'x', true, null], b: {c: undefined}, d: \"q\\\"\"}));\n\
+             var out = ''; for (var ch of 'ab') { out = out + ch + '-'; } console.log(out);",
+        );
+        assert_eq!(logs, vec![r#"{"a":[1,"x",true,null],"b":{},"d":"q\""}"#, "a-b-"]);
+    }
+
+    #[test]
+    fn test_storage_keeps_items_across_evaluations() {
+        let (tx, rx) = mpsc::channel();
+        let mut engine = JavaScriptEngine::new();
+        engine.set_console_log_sender(tx);
+        engine
+            .evaluate("localStorage.setItem('k', 'v'); localStorage.setItem('n', 1); sessionStorage.setItem('s', 'x');")
+            .expect("first script should run");
+        engine
+            .evaluate(
+                "console.log(localStorage.getItem('k'), typeof localStorage.getItem('n'), localStorage.length, localStorage.key(1));\n\
+                 localStorage.removeItem('k'); console.log(localStorage.getItem('k'), localStorage.length);\n\
+                 sessionStorage.clear(); console.log(sessionStorage.length, sessionStorage.getItem('s'));",
+            )
+            .expect("second script should run");
+        drop(engine);
+        let logs: Vec<String> = rx.iter().map(|(_, message)| message).collect();
+        assert_eq!(logs, vec!["v string 2 n", "null 1", "0 null"]);
+    }
+
+    #[test]
+    fn test_json_parse_round_trips_and_rejects_bad_input() {
+        let logs = run_and_capture(
+            r#"var v = JSON.parse('{"a": [1, -2.5e1, "\\u0041\\n"], "b": {"c": null}, "d": true}');
+               console.log(v.a[1], v.a[2].length, v.b.c, v.d, JSON.stringify(v));
+               try { JSON.parse('{"a": 1,}'); } catch (e) { console.log(e.name); }"#,
+        );
+        assert_eq!(logs, vec![r#"-25 2 null true {"a":[1,-25,"A\n"],"b":{"c":null},"d":true}"#, "SyntaxError"]);
+    }
+
+    #[test]
+    fn test_date_now_and_fields() {
+        let logs = run_and_capture(
+            "var d = new Date(); console.log(d.getFullYear() >= 2024, Date.now() > 0, d.getTime() > 0);\n\
+             var e = new Date(2020, 0, 15, 8, 30); console.log(e.getFullYear(), e.getMonth(), e.getDate(), e.getHours());\n\
+             console.log(new Date(0).toISOString());",
+        );
+        assert_eq!(logs, vec!["true true true", "2020 0 15 8", "1970-01-01T00:00:00.000Z"]);
+    }
+
+    #[test]
+    fn test_date_call_slots_and_invalid_year() {
+        let logs = run_and_capture(
+            "var d = new Date(0); console.log(Object.keys(d).length, JSON.stringify(d), d.toString());\n\
+             console.log(typeof Date(), typeof new Date());\n\
+             console.log(new Date(NaN, 0).getFullYear(), new Date(NaN, 0).toString());",
+        );
+        assert_eq!(
+            logs,
+            vec![
+                "0 {} Thu Jan 01 1970 00:00:00 GMT+0000 (Coordinated Universal Time)",
+                "string object",
+                "NaN Invalid Date",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spread_in_arrays_and_calls() {
+        let logs = run_and_capture(
+            "var a = [...[1,2],3]; console.log(JSON.stringify(a), a.length);\n\
+             function add(x, y, z) { return x + y + z; }\n\
+             var args = [1, 2]; console.log(add(...args, 10), add(...'abc'));\n\
+             function count(...rest) { return rest.length; } console.log(count(...a, ...a));",
+        );
+        assert_eq!(logs, vec!["[1,2,3] 3", "13 abc", "6"]);
+    }
+
+    #[test]
+    fn test_take_console_messages() {
+        let mut engine = JavaScriptEngine::new();
+        engine.evaluate("console.log('hello'); console.warn('careful', 1);").expect("script should run");
+        let messages = engine.take_console_messages();
+        assert_eq!(
+            messages,
+            vec![
+                ("log".to_string(), "hello".to_string()),
+                ("warn".to_string(), "careful 1".to_string()),
+            ]
+        );
+        assert!(engine.take_console_messages().is_empty());
+    }
+
+    #[test]
+    fn test_console_messages_keep_only_the_most_recent() {
+        let mut engine = JavaScriptEngine::new();
+        engine.evaluate("for (var i = 0; i < 1005; i++) { console.log(i); }").expect("script should run");
+        let messages = engine.take_console_messages();
+        assert_eq!(messages.len(), 1000);
+        assert_eq!(messages[0].1, "5");
+        assert_eq!(messages[999].1, "1004");
+    }
+
+    #[test]
+    fn test_remove_event_listener() {
+        let mut engine = JavaScriptEngine::new();
+        engine
+            .evaluate(
+                "function kept() { console.log('kept'); }\n\
+                 function removed() { console.log('removed'); }\n\
+                 document.addEventListener('DOMContentLoaded', kept);\n\
+                 document.addEventListener('DOMContentLoaded', removed);\n\
+                 document.addEventListener('DOMContentLoaded', kept);\n\
+                 document.removeEventListener('DOMContentLoaded', removed);\n\
+                 document.removeEventListener('DOMContentLoaded', function () {});",
+            )
+            .expect("script should run");
+        engine.runtime_mut().fire_dom_content_loaded().expect("listeners should run");
+        let messages: Vec<String> = engine.take_console_messages().into_iter().map(|(_, m)| m).collect();
+        assert_eq!(messages, vec!["kept"]);
+    }
+
+    #[test]
+    fn test_template_content() {
+        let html = "<html><body><template id=\"tpl\"><b>inert</b></template></body></html>";
+        let dom = crate::html::parser::Parser::new(html.to_string()).parse();
+        let root = dom.root().expect("document root").clone();
+        let mut engine = JavaScriptEngine::new();
+        engine.bind_dom(Rc::new(RefCell::new(root))).expect("bind dom");
+        engine
+            .evaluate(
+                "var tpl = document.getElementById('tpl');\n\
+                 console.log(tpl.textContent === '', tpl.content.textContent, tpl.content.innerHTML);",
+            )
+            .expect("script should run");
+        let messages: Vec<String> = engine.take_console_messages().into_iter().map(|(_, m)| m).collect();
+        assert_eq!(messages, vec!["true inert <b>inert</b>"]);
+    }
+
+    #[test]
+    fn test_script_mutations_reach_shared_dom() {
+        let html = "<html><body><div id=\"out\">before</div></body></html>";
+        let dom = crate::html::parser::Parser::new(html.to_string()).parse();
+        let root = Rc::new(RefCell::new(dom.root().expect("document root").clone()));
+
+        let mut engine = JavaScriptEngine::new();
+        engine.bind_dom(Rc::clone(&root)).expect("bind dom");
+        engine
+            .evaluate("document.getElementById('out').textContent = 'after';")
+            .expect("script should run");
+
+        fn find<'a>(node: &'a DomNode, id: &str) -> Option<&'a DomNode> {
+            if node.get_attribute("id") == Some(id) {
+                return Some(node);
+            }
+            node.children().iter().find_map(|child| find(child, id))
+        }
+        let root = root.borrow();
+        let out = find(&root, "out").expect("div");
+        assert_eq!(out.children().len(), 1);
+        assert_eq!(out.children()[0].node_type(), &crate::dom::NodeType::Text("after".to_string()));
+    }
+
+    #[test]
+    fn test_class_list_restyles_element() {
+        let html = "<html><body><p id=\"msg\" class=\"note old\">hi</p></body></html>";
+        let dom = crate::html::parser::Parser::new(html.to_string()).parse();
+        let root = Rc::new(RefCell::new(dom.root().expect("document root").clone()));
+
+        let mut engine = JavaScriptEngine::new();
+        engine.bind_dom(Rc::clone(&root)).expect("bind dom");
+        let (sender, receiver) = mpsc::channel();
+        engine.set_console_log_sender(sender);
+        engine
+            .evaluate(
+                "var list = document.getElementById('msg').classList;\
+                 list.add('active'); list.remove('old');\
+                 console.log(list.contains('active'), list.toggle('note'), list.toggle('note'));",
+            )
+            .expect("script should run");
+        let logged: Vec<String> = receiver.try_iter().map(|(_, message)| message).collect();
+        assert_eq!(logged, vec!["true false true".to_string()]);
+
+        fn find<'a>(node: &'a DomNode, id: &str) -> Option<&'a DomNode> {
+            if node.get_attribute("id") == Some(id) {
+                return Some(node);
+            }
+            node.children().iter().find_map(|child| find(child, id))
+        }
+        let root = root.borrow();
+        let msg = find(&root, "msg").expect("paragraph");
+        assert_eq!(msg.get_attribute("class"), Some("active note"));
+
+        let stylesheet = crate::css::parser::CssParser::new(".active { color: red; }".to_string()).parse();
+        let styled = crate::css::style::StyleEngine::new(stylesheet).apply_styles(msg);
+        assert!(styled.get("color").is_some());
+    }
+
+    #[test]
+    fn test_class_list_works_on_elements_without_an_id() {
+        let html = "<html><body><p class=\"a\">one</p><p class=\"a\">two</p></body></html>";
+        let dom = crate::html::parser::Parser::new(html.to_string()).parse();
+        let root = Rc::new(RefCell::new(dom.root().expect("document root").clone()));
+
+        let mut engine = JavaScriptEngine::new();
+        engine.bind_dom(Rc::clone(&root)).expect("bind dom");
+        engine
+            .evaluate("var second = document.getElementsByTagName('p')[1]; second.classList.add('picked');")
+            .expect("script should run");
+
+        let root = root.borrow();
+        let paragraphs = crate::dom::query::query_selector_all(&root, "p");
+        let classes: Vec<Option<&str>> = paragraphs.iter().map(|p| p.get_attribute("class")).collect();
+        assert_eq!(classes, vec![Some("a"), Some("a picked")]);
+    }
+
+    #[test]
+    fn test_element_node_slots_are_not_enumerable() {
+        let html = "<html><body><p id=\"msg\">hi</p></body></html>";
+        let dom = crate::html::parser::Parser::new(html.to_string()).parse();
+        let root = Rc::new(RefCell::new(dom.root().expect("document root").clone()));
+
+        let (tx, rx) = mpsc::channel();
+        let mut engine = JavaScriptEngine::new();
+        engine.set_console_log_sender(tx);
+        engine.bind_dom(Rc::clone(&root)).expect("bind dom");
+        engine
+            .evaluate(
+                "function keys(o) { var k = Object.keys(o), s = ''; for (var i = 0; i < k.length; i++) s += k[i] + ' '; return s; }\
+                 var msg = document.getElementById('msg');\
+                 console.log(keys(msg)); console.log(keys(msg.classList)); console.log(keys(msg.style));",
+            )
+            .expect("script should run");
+        drop(engine);
+        let logged: Vec<String> = rx.iter().map(|(_, message)| message).collect();
+        assert_eq!(logged.len(), 3);
+        assert!(logged[0].contains("tagName"));
+        assert!(logged.iter().all(|keys| !keys.contains("node_id")));
+    }
+
+    #[test]
+    fn test_style_writes_reach_elements_without_an_id() {
+        let html = "<html><body><p>one</p><p style=\"color: red\">two</p></body></html>";
+        let dom = crate::html::parser::Parser::new(html.to_string()).parse();
+        let root = Rc::new(RefCell::new(dom.root().expect("document root").clone()));
+
+        let (tx, rx) = mpsc::channel();
+        let mut engine = JavaScriptEngine::new();
+        engine.set_console_log_sender(tx);
+        engine.bind_dom(Rc::clone(&root)).expect("bind dom");
+        engine
+            .evaluate("var second = document.getElementsByTagName('p')[1]; console.log(second.style.color); second.style.display = 'none';")
+            .expect("script should run");
+        drop(engine);
+        assert_eq!(rx.iter().map(|(_, message)| message).collect::<Vec<_>>(), vec!["red"]);
+
+        let root = root.borrow();
+        let paragraphs = crate::dom::query::query_selector_all(&root, "p");
+        let styles: Vec<Option<&str>> = paragraphs.iter().map(|p| p.get_attribute("style")).collect();
+        assert_eq!(styles, vec![None, Some("color: red; display: none")]);
+    }
+
+    #[test]
+    fn test_comma_operator_applies_every_side_effect() {
+        let mut engine = JavaScriptEngine::new();
+        engine
+            .evaluate(
+                "var a = 0, b = 0; var r = (a = 1, b = 2, a + b);\n\
+                 var trace = ''; for (var i = 0, j = 3; i < j; i++, j--) { trace += i + ':' + j + ' '; }\n\
+                 var x = 1; var y = (x++, x++, x);\n\
+                 var k, m, steps = 0; for (k = 0, m = 4; k < m; k++, m--) { steps++; }\n\
+                 console.log(r, a, b, trace, i, j, y, steps, k, m);",
+            )
+            .expect("script should run");
+        let messages: Vec<String> = engine.take_console_messages().into_iter().map(|(_, m)| m).collect();
+        assert_eq!(messages, vec!["3 1 2 0:3 1:2  2 1 3 2 2 2"]);
+    }
+
+    #[test]
+    fn test_regex_literals_test_and_replace() {
+        let mut engine = JavaScriptEngine::new();
+        engine
+            .evaluate(
+                "var s = '  a  b c ';\n\
+                 var half = 10 / 2 / 5;\n\
+                 console.log(s.replace(/\\s+/g, ''), s.replace(/\\s+/, '_'), half);\n\
+                 console.log(/^h\\w+o$/i.test('Hello'), /\\d/.test('none'));\n\
+                 console.log('2024-05-06'.replace(/(\\d+)-(\\d+)-(\\d+)/, '$3/$2/$1'));\n\
+                 console.log('a-b'.replace(/[a-z]/g, function (m) { return m + m; }), 'x.y'.replace('.', '!'));",
+            )
+            .expect("script should run");
+        let messages: Vec<String> = engine.take_console_messages().into_iter().map(|(_, m)| m).collect();
+        assert_eq!(messages, vec!["abc _a  b c  1", "true false", "06/05/2024", "aa-bb x!y"]);
+    }
+
+    #[test]
+    fn test_regex_on_long_input_matches_or_throws_range_error() {
+        let logs = run_and_capture(
+            "var s = 'a'; for (var i = 0; i < 17; i++) { s = s + s; }\n\
+             console.log(s.length, /a+/.test(s), s.replace(/a+/, 'x'));\n\
+             try { /(a|b)+/.test(s); } catch (e) { console.log(e.name); }",
+        );
+        assert_eq!(logs, vec!["131072 true x", "RangeError"]);
+    }
+
+    #[test]
+    fn test_loop_past_configured_limit_aborts_script() {
+        let mut engine = JavaScriptEngine::new();
+        engine.runtime_mut().set_limits(50, 100);
+        let error = engine
+            .evaluate("var n = 0; try { while (true) { n++; } } catch (e) { n = -1; }")
+            .expect_err("runaway loop should abort");
+        assert!(error.downcast_ref::<error::ScriptAborted>().is_some(), "unexpected error: {}", error);
+
+        // The runtime survives, and loops within the limit still finish normally
+        engine.evaluate("var m = 0; for (var i = 0; i < 50; i++) { m++; } console.log(n, m);").expect("script should run");
+        let messages: Vec<String> = engine.take_console_messages().into_iter().map(|(_, m)| m).collect();
+        assert_eq!(messages, vec!["51 50"]);
+    }
+
+    #[test]
+    fn test_time_budget_aborts_long_running_script() {
+        let mut engine = JavaScriptEngine::new();
+        engine.runtime_mut().set_limits(usize::MAX, 100);
+        engine.runtime_mut().set_time_budget(Some(std::time::Duration::from_millis(20)));
+        let error = engine.evaluate("while (true) {}").expect_err("script should run out of time");
+        assert!(error.downcast_ref::<error::ScriptAborted>().is_some(), "unexpected error: {}", error);
+        assert!(matches!(engine.evaluate("1 + 1").expect("fresh budget"), JsValue::Number(n) if n == 2.0));
+    }
+
+    #[test]
+    fn test_labeled_break_and_continue() {
+        let mut engine = JavaScriptEngine::new();
+        engine
+            .evaluate(
+                "var pairs = ''; outer: for (var i = 0; i < 3; i++) { for (var j = 0; j < 3; j++) { if (j == 2) continue outer; if (i == 2) break outer; pairs += i + '' + j + ' '; } }\n\
+                 var n = 0; while (true) { n++; if (n < 5) continue; break; }\n\
+                 var reached = 'no'; block: { if (n == 5) break block; reached = 'yes'; }\n\
+                 console.log(pairs, i, j, n, reached);",
+            )
+            .expect("script should run");
+        let messages: Vec<String> = engine.take_console_messages().into_iter().map(|(_, m)| m).collect();
+        assert_eq!(messages, vec!["00 01 10 11  2 0 5 no"]);
+    }
+
+    #[test]
+    fn test_in_and_instanceof_operators() {
+        let mut engine = JavaScriptEngine::new();
+        engine
+            .evaluate(
+                "function Point(x) { this.x = x; } function Other() {}\n\
+                 var p = new Point(3);\n\
+                 console.log('x' in {x: 1}, 'y' in {x: 1}, 'x' in p, 'constructor' in p);\n\
+                 console.log(p instanceof Point, p instanceof Other, {} instanceof Point, p.x);\n\
+                 try { 'x' in 5; } catch (e) { console.log(e.name); }",
+            )
+            .expect("script should run");
+        let messages: Vec<String> = engine.take_console_messages().into_iter().map(|(_, m)| m).collect();
+        assert_eq!(messages, vec!["true false true true", "true false false 3", "TypeError"]);
+    }
+
+    #[test]
+    fn test_void_and_delete_operators() {
+        let mut engine = JavaScriptEngine::new();
+        engine
+            .evaluate(
+                "var n = 0; var v = void n++;\n\
+                 console.log(v === undefined, typeof void 0, n);\n\
+                 var o = {x: 1, y: 2}; var k = 'y';\n\
+                 console.log(delete o.x, 'x' in o, o.x === undefined, delete o[k], Object.keys(o).length);\n\
+                 try { delete undefined.x; } catch (e) { console.log(e.name); }",
+            )
+            .expect("script should run");
+        let messages: Vec<String> = engine.take_console_messages().into_iter().map(|(_, m)| m).collect();
+        assert_eq!(messages, vec!["true undefined 1", "true false true true 0", "TypeError"]);
+    }
+}
@@ -1,4 +1,4 @@
-use super::ast::{Node, BinaryOperator, UnaryOperator};
+use super::ast::{Node, BinaryOperator, UnaryOperator, Pattern, Param, DeclarationKind};
 use super::tokenizer::{Token, tokenize};
 use std::error::Error;
 use log::{debug, error, trace};
@@ -103,9 +103,19 @@ impl Parser {
             },
             Token::If => self.parse_if_statement()?,
             Token::While => self.parse_while_statement()?,
+            Token::Do => self.parse_do_while_statement()?,
             Token::For => self.parse_for_statement()?,
             Token::Return => self.parse_return_statement()?,
+            Token::Try => self.parse_try_statement()?,
+            Token::Throw => self.parse_throw_statement()?,
+            Token::Break | Token::Continue => self.parse_jump_statement()?,
             Token::LeftBrace => self.parse_block()?,
+            Token::Identifier(label) if matches!(self.tokens.get(self.current + 1), Some(Token::Colon)) => {
+                let label = label.clone();
+                self.advance(); // consume label
+                self.advance(); // consume ':'
+                Node::Labeled { label, body: Box::new(self.parse_statement()?) }
+            }
             _ => {
                 // Expression statement
                 let expr = self.parse_expression()?;
@@ -125,13 +135,14 @@ impl Parser {
     }
 
     fn parse_variable_declaration(&mut self) -> Result<Node, Box<dyn Error>> {
-        self.advance(); // consume 'let', 'const', or 'var'
-        let name = match self.peek() {
+        let kind = Self::declaration_kind(self.advance());
+        let pattern = match self.peek() {
             Token::Identifier(name) => {
                 let name = name.clone();
                 self.advance(); // consume identifier
-                name
+                Pattern::Identifier(name)
             },
+            Token::LeftBrace | Token::LeftBracket => self.parse_binding_pattern()?,
             Token::In | Token::Of => {
                 // 'in' or 'of' after variable keyword - likely malformed for loop
                 // Return undefined to allow parsing to continue
@@ -144,12 +155,7 @@ impl Parser {
             }
         };
 
-        let init = if matches!(self.peek(), Token::Equals) {
-            self.advance(); // consume '='
-            Some(Box::new(self.parse_expression()?))
-        } else {
-            None
-        };
+        let declaration = self.parse_declarators(kind, pattern)?;
 
         // Consume optional semicolon
         if matches!(self.peek(), Token::Semicolon) {
@@ -157,7 +163,124 @@ impl Parser {
             self.advance();
         }
 
-        Ok(Node::VariableDecl { name, init })
+        Ok(declaration)
+    }
+
+    /// Parse the rest of a declaration list after its first target:
+    /// `= init, b = 2, c`. Several declarators become a block of declarations,
+    /// evaluated left to right.
+    fn parse_declarators(&mut self, kind: DeclarationKind, first: Pattern) -> Result<Node, Box<dyn Error>> {
+        let mut declarations = Vec::new();
+        let mut pattern = first;
+        loop {
+            // parse_assignment stops at ',', which here separates declarators
+            let init = if matches!(self.peek(), Token::Equals) {
+                self.advance(); // consume '='
+                Some(Box::new(self.parse_assignment()?))
+            } else {
+                None
+            };
+            declarations.push(Node::VariableDecl { kind, pattern, init });
+
+            if !matches!(self.peek(), Token::Comma) {
+                break;
+            }
+            self.advance(); // consume ','
+            pattern = self.parse_binding_pattern()?;
+        }
+
+        if declarations.len() == 1 {
+            Ok(declarations.remove(0))
+        } else {
+            Ok(Node::Block(declarations))
+        }
+    }
+
+    // Only called with the token that started a declaration
+    fn declaration_kind(token: &Token) -> DeclarationKind {
+        match token {
+            Token::Let => DeclarationKind::Let,
+            Token::Const => DeclarationKind::Const,
+            _ => DeclarationKind::Var,
+        }
+    }
+
+    /// Parse a binding target: an identifier, `{a, b: c, d = 1}` or `[x, , y = 2]`.
+    fn parse_binding_pattern(&mut self) -> Result<Pattern, Box<dyn Error>> {
+        match self.advance().clone() {
+            Token::Identifier(name) => Ok(Pattern::Identifier(name)),
+            Token::LeftBrace => {
+                let mut properties = Vec::new();
+                while !matches!(self.peek(), Token::RightBrace | Token::EOF) {
+                    let key = match self.advance().clone() {
+                        Token::Identifier(name) | Token::String(name) => name,
+                        token => {
+                            error!(target: "javascript", "Unexpected token in object pattern: {:?}", token);
+                            return Err("Expected property name in object pattern".into());
+                        }
+                    };
+
+                    // `{ key: target }` renames, `{ key }` binds to the same name
+                    let target = if matches!(self.peek(), Token::Colon) {
+                        self.advance(); // consume ':'
+                        self.parse_binding_pattern()?
+                    } else {
+                        Pattern::Identifier(key.clone())
+                    };
+                    let default = self.parse_pattern_default()?;
+                    properties.push((key, target, default));
+
+                    if matches!(self.peek(), Token::Comma) {
+                        self.advance();
+                    } else {
+                        break;
+                    }
+                }
+                match self.advance() {
+                    Token::RightBrace => Ok(Pattern::Object(properties)),
+                    _ => Err("Expected '}' to close object pattern".into()),
+                }
+            }
+            Token::LeftBracket => {
+                let mut elements = Vec::new();
+                while !matches!(self.peek(), Token::RightBracket | Token::EOF) {
+                    if matches!(self.peek(), Token::Comma) {
+                        // Hole: `[, second]`
+                        self.advance();
+                        elements.push(None);
+                        continue;
+                    }
+
+                    let target = self.parse_binding_pattern()?;
+                    let default = self.parse_pattern_default()?;
+                    elements.push(Some((target, default)));
+
+                    if matches!(self.peek(), Token::Comma) {
+                        self.advance();
+                    } else {
+                        break;
+                    }
+                }
+                match self.advance() {
+                    Token::RightBracket => Ok(Pattern::Array(elements)),
+                    _ => Err("Expected ']' to close array pattern".into()),
+                }
+            }
+            token => {
+                error!(target: "javascript", "Unexpected token in binding pattern: {:?}", token);
+                Err("Expected identifier or destructuring pattern".into())
+            }
+        }
+    }
+
+    fn parse_pattern_default(&mut self) -> Result<Option<Box<Node>>, Box<dyn Error>> {
+        if matches!(self.peek(), Token::Equals) {
+            self.advance(); // consume '='
+            // parse_assignment stops at ',' so the next pattern element isn't swallowed
+            Ok(Some(Box::new(self.parse_assignment()?)))
+        } else {
+            Ok(None)
+        }
     }
     
     fn parse_for_statement(&mut self) -> Result<Node, Box<dyn Error>> {
@@ -259,19 +382,7 @@ impl Parser {
                         // It's a regular for loop, we need to continue parsing
                         // We already consumed 'let/const/var' and identifier
                         // Now check for '=' or ';'
-                        let init = if matches!(self.peek(), Token::Equals) {
-                            self.advance(); // consume '='
-                            let init_expr = self.parse_expression()?;
-                            Some(Box::new(Node::VariableDecl { 
-                                name: var_name, 
-                                init: Some(Box::new(init_expr)) 
-                            }))
-                        } else {
-                            Some(Box::new(Node::VariableDecl { 
-                                name: var_name, 
-                                init: None 
-                            }))
-                        };
+                        let init = Some(Box::new(self.parse_declarators(Self::declaration_kind(&var_token), Pattern::Identifier(var_name))?));
                         
                         return self.parse_for_loop_rest(init);
                     }
@@ -410,27 +521,37 @@ impl Parser {
         // Check for assignment
         if matches!(self.peek(), Token::Equals) {
             self.advance();
-            let right = self.parse_expression()?;
+            let right = self.parse_assignment()?;
             expr = Node::AssignmentExpr {
                 left: Box::new(expr),
                 right: Box::new(right),
             };
         }
         
+        // `i = 0, j = n` assigns both, in order
+        if matches!(self.peek(), Token::Comma) {
+            let mut comma_exprs = vec![expr];
+            while matches!(self.peek(), Token::Comma) {
+                self.advance(); // consume ','
+                comma_exprs.push(self.parse_assignment()?);
+            }
+            expr = Node::CommaExpr(comma_exprs);
+        }
+        
         Ok(expr)
     }
     
     /// Try to parse arrow function parameters (returns None if not an arrow function)
-    fn try_parse_arrow_function_params(&mut self) -> Option<(Vec<String>, Node)> {
+    fn try_parse_arrow_function_params(&mut self) -> Option<(Vec<Param>, Node)> {
         // This is a simplified approach - we'll rely on backtracking in the main handler
         // Just return None here to let the main handler work
         None
     }
     
     /// Extract parameter names from an expression (for arrow functions parsed as expressions)
-    fn extract_params_from_expr(&self, expr: &Node) -> Vec<String> {
+    fn extract_params_from_expr(&self, expr: &Node) -> Vec<Param> {
         match expr {
-            Node::Identifier(name) => vec![name.clone()],
+            Node::Identifier(name) => vec![Param::new(name.clone())],
             // Could extend to handle destructuring, rest params, etc.
             _ => vec![],
         }
@@ -454,6 +575,89 @@ impl Parser {
         Ok(Node::ReturnStatement(expr))
     }
     
+    /// Parse `break` or `continue`, with an optional target label.
+    fn parse_jump_statement(&mut self) -> Result<Node, Box<dyn Error>> {
+        let is_break = matches!(self.advance(), Token::Break);
+        let label = match self.peek() {
+            Token::Identifier(label) => {
+                let label = label.clone();
+                self.advance();
+                Some(label)
+            }
+            _ => None,
+        };
+
+        // Consume optional semicolon
+        if matches!(self.peek(), Token::Semicolon) {
+            self.advance();
+        }
+
+        Ok(if is_break { Node::BreakStatement(label) } else { Node::ContinueStatement(label) })
+    }
+
+    fn parse_try_statement(&mut self) -> Result<Node, Box<dyn Error>> {
+        self.advance(); // consume 'try'
+        if !matches!(self.peek(), Token::LeftBrace) {
+            return Err("Expected '{' after 'try'".into());
+        }
+        let try_block = Box::new(self.parse_block()?);
+
+        let mut catch_param = None;
+        let mut catch_block = None;
+        if matches!(self.peek(), Token::Catch) {
+            self.advance(); // consume 'catch'
+
+            // The binding is optional: `catch (e) { ... }` or just `catch { ... }`
+            if matches!(self.peek(), Token::LeftParen) {
+                self.advance(); // consume '('
+                match self.advance().clone() {
+                    Token::Identifier(name) => catch_param = Some(name),
+                    token => {
+                        error!(target: "javascript", "Expected identifier in catch clause, found: {:?}", token);
+                        return Err("Expected identifier in catch clause".into());
+                    }
+                }
+                if !matches!(self.peek(), Token::RightParen) {
+                    return Err("Expected ')' after catch parameter".into());
+                }
+                self.advance(); // consume ')'
+            }
+
+            if !matches!(self.peek(), Token::LeftBrace) {
+                return Err("Expected '{' after 'catch'".into());
+            }
+            catch_block = Some(Box::new(self.parse_block()?));
+        }
+
+        let finally_block = if matches!(self.peek(), Token::Finally) {
+            self.advance(); // consume 'finally'
+            if !matches!(self.peek(), Token::LeftBrace) {
+                return Err("Expected '{' after 'finally'".into());
+            }
+            Some(Box::new(self.parse_block()?))
+        } else {
+            None
+        };
+
+        if catch_block.is_none() && finally_block.is_none() {
+            return Err("Missing catch or finally after try".into());
+        }
+
+        Ok(Node::TryCatch { try_block, catch_param, catch_block, finally_block })
+    }
+
+    fn parse_throw_statement(&mut self) -> Result<Node, Box<dyn Error>> {
+        self.advance(); // consume 'throw'
+        let expr = self.parse_expression()?;
+
+        // Consume optional semicolon
+        if matches!(self.peek(), Token::Semicolon) {
+            self.advance();
+        }
+
+        Ok(Node::ThrowStatement(Box::new(expr)))
+    }
+
     fn parse_block(&mut self) -> Result<Node, Box<dyn Error>> {
         self.advance(); // consume '{'
         
@@ -474,7 +678,7 @@ impl Parser {
                     debug!(target: "javascript", "Error parsing statement in block: {}, attempting recovery", e);
                     // Skip tokens until we find a closing brace, semicolon, or another statement start
                     let mut skipped = 0;
-                    while !matches!(self.peek(), Token::RightBrace | Token::Semicolon | Token::EOF | Token::Let | Token::Const | Token::Var | Token::Function | Token::If | Token::While | Token::For | Token::Return) 
+                    while !matches!(self.peek(), Token::RightBrace | Token::Semicolon | Token::EOF | Token::Let | Token::Const | Token::Var | Token::Function | Token::If | Token::While | Token::Do | Token::For | Token::Return | Token::Try | Token::Throw) 
                         && skipped < 100 {
                         self.advance();
                         skipped += 1;
@@ -532,8 +736,14 @@ impl Parser {
         self.advance(); // consume 'new'
         debug!(target: "javascript", "Parsing new expression");
 
-        let constructor = Box::new(self.parse_primary()?);
-        let mut arguments = Vec::new();
+        // parse_primary also consumes trailing calls and member accesses, so
+        // `new Foo(a).bar()` comes back as one postfix chain - `new` binds to the
+        // first call in it
+        let (expr, bound) = Self::bind_new(self.parse_primary()?);
+        if bound {
+            return Ok(expr);
+        }
+        let (constructor, mut arguments) = (Box::new(expr), Vec::new());
 
         // Parse constructor arguments if present
         if matches!(self.peek(), Token::LeftParen) {
@@ -541,7 +751,7 @@ impl Parser {
             
             if !matches!(self.peek(), Token::RightParen) {
                 loop {
-                    arguments.push(self.parse_expression()?);
+                    arguments.push(self.parse_element()?);
                     
                     match self.peek() {
                         Token::RightParen => {
@@ -568,6 +778,31 @@ impl Parser {
         })
     }
 
+    // One array element or call argument, either of which may be spread
+    fn parse_element(&mut self) -> Result<Node, Box<dyn Error>> {
+        if matches!(self.peek(), Token::Ellipsis) {
+            self.advance(); // consume '...'
+            return Ok(Node::Spread(Box::new(self.parse_assignment()?)));
+        }
+        self.parse_assignment()
+    }
+
+    // Rewrite the innermost call of a postfix chain into a NewExpr. Returns
+    // whether a call was found; without one the caller wraps the whole chain.
+    fn bind_new(node: Node) -> (Node, bool) {
+        match node {
+            Node::CallExpr { callee, arguments } => match Self::bind_new(*callee) {
+                (callee, true) => (Node::CallExpr { callee: Box::new(callee), arguments }, true),
+                (constructor, false) => (Node::NewExpr { constructor: Box::new(constructor), arguments }, true),
+            },
+            Node::MemberExpr { object, property, computed } => {
+                let (object, bound) = Self::bind_new(*object);
+                (Node::MemberExpr { object: Box::new(object), property, computed }, bound)
+            }
+            other => (other, false),
+        }
+    }
+
     fn parse_assignment(&mut self) -> Result<Node, Box<dyn Error>> {
         let expr = self.parse_ternary()?;
 
@@ -793,7 +1028,7 @@ impl Parser {
     }
     
     fn parse_unary(&mut self) -> Result<Node, Box<dyn Error>> {
-        // Handle unary operators: !, -, typeof, ++, --
+        // Handle unary operators: !, -, typeof, void, delete, ++, --
         match self.peek() {
             Token::Bang => {
                 self.advance();
@@ -819,6 +1054,22 @@ impl Parser {
                     operand: Box::new(operand),
                 })
             }
+            Token::Void => {
+                self.advance();
+                let operand = self.parse_unary()?;
+                Ok(Node::UnaryOp {
+                    op: UnaryOperator::Void,
+                    operand: Box::new(operand),
+                })
+            }
+            Token::Delete => {
+                self.advance();
+                let operand = self.parse_unary()?;
+                Ok(Node::UnaryOp {
+                    op: UnaryOperator::Delete,
+                    operand: Box::new(operand),
+                })
+            }
             Token::PlusPlus => {
                 self.advance();
                 let operand = self.parse_unary()?;
@@ -876,7 +1127,7 @@ impl Parser {
                     
                     if !matches!(self.peek(), Token::RightParen) {
                         loop {
-                            arguments.push(self.parse_expression()?);
+                            arguments.push(self.parse_element()?);
                             match self.peek() {
                                 Token::Comma => { self.advance(); }
                                 Token::RightParen => break,
@@ -1173,6 +1424,12 @@ impl Parser {
                 debug!(target: "javascript", "Found string literal: {:?}", s);
                 Ok::<Node, Box<dyn Error>>(Node::String(s))?
             },
+            Token::Regex(pattern, flags) => {
+                let (pattern, flags) = (pattern.clone(), flags.clone());
+                self.advance();
+                trace!(target: "javascript", "Found regex literal: /{}/{}", pattern, flags);
+                Ok::<Node, Box<dyn Error>>(Node::RegexLiteral { pattern, flags })?
+            },
             Token::Identifier(name) => {
                 let name = name.clone();
                 self.advance();
@@ -1195,6 +1452,10 @@ impl Parser {
                 self.advance();
                 Ok::<Node, Box<dyn Error>>(Node::This)?
             },
+            Token::New => {
+                // `new` nested inside a larger expression (array element, argument, assignment value)
+                self.parse_new_expression()?
+            },
             Token::Undefined => {
                 self.advance();
                 Ok::<Node, Box<dyn Error>>(Node::Undefined)?
@@ -1211,7 +1472,9 @@ impl Parser {
                 } else {
                     // Parse array elements
                     loop {
-                        elements.push(self.parse_expression()?);
+                        // parse_assignment, not parse_expression: the commas here separate
+                        // elements and must not be folded into a comma expression
+                        elements.push(self.parse_element()?);
                         
                         match self.peek() {
                             Token::RightBracket => {
@@ -1427,7 +1690,7 @@ impl Parser {
                             debug!(target: "javascript", "Parsing argument, current token: {:?}", self.peek());
                             // Use parse_assignment() instead of parse_expression() to avoid comma operator
                             // The comma between arguments is handled by this loop, not by the expression parser
-                            let arg = self.parse_assignment()?;
+                            let arg = self.parse_element()?;
                             
                             // Check if the next token might be part of a member access that wasn't fully parsed
                             // This can happen with minified code or complex expressions
@@ -1539,18 +1802,7 @@ impl Parser {
             _ => return Err("Expected '(' after function name".into()),
         }
 
-        let mut params = Vec::new();
-        while !matches!(self.peek(), Token::RightParen) {
-            match self.advance() {
-                Token::Identifier(param) => params.push(param.clone()),
-                _ => return Err("Expected parameter name".into()),
-            }
-
-            if matches!(self.peek(), Token::Comma) {
-                self.advance(); // consume comma
-            }
-        }
-        self.advance(); // consume ')'
+        let params = self.parse_parameter_list()?;
 
         match self.advance() {
             Token::LeftBrace => (),
@@ -1570,6 +1822,41 @@ impl Parser {
         })
     }
     
+    /// Parse parameters after the opening '(' up to and including the closing ')'.
+    /// Supports defaults (`b = 2`) and a trailing rest parameter (`...rest`).
+    fn parse_parameter_list(&mut self) -> Result<Vec<Param>, Box<dyn Error>> {
+        let mut params = Vec::new();
+        while !matches!(self.peek(), Token::RightParen | Token::EOF) {
+            let rest = matches!(self.peek(), Token::Ellipsis);
+            if rest {
+                self.advance(); // consume '...'
+            }
+
+            let mut param = match self.advance() {
+                Token::Identifier(name) => Param::new(name.clone()),
+                Token::Comma => continue,
+                _ => return Err("Expected parameter name".into()),
+            };
+            param.rest = rest;
+
+            if matches!(self.peek(), Token::Equals) {
+                self.advance(); // consume '='
+                // parse_assignment stops at ',' so the next parameter isn't swallowed
+                param.default = Some(Box::new(self.parse_assignment()?));
+            }
+            params.push(param);
+
+            if matches!(self.peek(), Token::Comma) {
+                self.advance(); // consume comma
+            }
+        }
+
+        if matches!(self.peek(), Token::RightParen) {
+            self.advance(); // consume ')'
+        }
+        Ok(params)
+    }
+    
     fn parse_function_expression_after_keyword(&mut self) -> Result<Node, Box<dyn Error>> {
         // We've already consumed 'function', now parse the rest as an expression
         // Optional function name (for named function expressions)
@@ -1586,24 +1873,7 @@ impl Parser {
         }
         self.advance(); // consume '('
 
-        let mut params = Vec::new();
-        while !matches!(self.peek(), Token::RightParen | Token::EOF) {
-            match self.advance() {
-                Token::Identifier(param) => params.push(param.clone()),
-                Token::Comma => continue,
-                Token::RightParen => break,
-                Token::EOF => break,
-                _ => return Err("Expected parameter name".into()),
-            }
-
-            if matches!(self.peek(), Token::Comma) {
-                self.advance(); // consume comma
-            }
-        }
-        
-        if matches!(self.peek(), Token::RightParen) {
-            self.advance(); // consume ')'
-        }
+        let params = self.parse_parameter_list()?;
 
         if !matches!(self.peek(), Token::LeftBrace) {
             return Err("Expected '{' after function parameters".into());
@@ -1692,6 +1962,39 @@ impl Parser {
             body,
         })
     }
+
+    fn parse_do_while_statement(&mut self) -> Result<Node, Box<dyn Error>> {
+        self.advance(); // consume 'do'
+
+        let body = Box::new(self.parse_statement()?);
+
+        match self.advance() {
+            Token::While => (),
+            _ => return Err("Expected 'while' after do-while body".into()),
+        }
+
+        match self.advance() {
+            Token::LeftParen => (),
+            _ => return Err("Expected '(' after 'while'".into()),
+        }
+
+        let condition = Box::new(self.parse_expression()?);
+
+        match self.advance() {
+            Token::RightParen => (),
+            _ => return Err("Expected ')' after do-while condition".into()),
+        }
+
+        // Consume optional semicolon
+        if matches!(self.peek(), Token::Semicolon) {
+            self.advance();
+        }
+
+        Ok(Node::DoWhileLoop {
+            body,
+            condition,
+        })
+    }
 }
 
 pub fn parse(source: &str) -> Result<Node, Box<dyn Error>> {
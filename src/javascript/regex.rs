@@ -0,0 +1,635 @@
+// Regular expressions for RegExp literals: a small backtracking matcher.
+// Supports the common subset of the JavaScript syntax: classes, escapes,
+// anchors, greedy and lazy quantifiers, groups, alternation, lookahead and
+// backreferences. Positions are char indices, not byte offsets.
+
+use std::cell::Cell;
+
+/// Capture spans by group number; group 0 is the whole match.
+pub type Captures = Vec<Option<(usize, usize)>>;
+
+/// How deep the backtracker may nest before giving up on a match. Each
+/// nested call is a native stack frame, so this stands in for a stack limit.
+const MAX_MATCH_DEPTH: usize = 1000;
+
+/// The pattern needed more backtracking depth than `MAX_MATCH_DEPTH` allows.
+#[derive(Debug, PartialEq)]
+pub struct TooDeep;
+
+#[derive(Debug)]
+pub struct Regex {
+    node: Node,
+    groups: usize,
+    pub global: bool,
+    ignore_case: bool,
+    multiline: bool,
+    dot_all: bool,
+}
+
+#[derive(Debug)]
+enum Node {
+    Empty,
+    Char(char),
+    Any,
+    Class { items: Vec<ClassItem>, negated: bool },
+    Start,
+    End,
+    WordBoundary { negated: bool },
+    Group { inner: Box<Node>, index: Option<usize> },
+    LookAhead { inner: Box<Node>, negated: bool },
+    BackReference(usize),
+    Concat(Vec<Node>),
+    Alternation(Vec<Node>),
+    Repeat { inner: Box<Node>, min: usize, max: Option<usize>, greedy: bool },
+}
+
+#[derive(Debug)]
+enum ClassItem {
+    Char(char),
+    Range(char, char),
+    Digit { negated: bool },
+    Word { negated: bool },
+    Space { negated: bool },
+}
+
+impl Regex {
+    pub fn new(pattern: &str, flags: &str) -> Result<Self, String> {
+        let mut parser = PatternParser { chars: pattern.chars().collect(), pos: 0, groups: 0 };
+        let node = parser.parse_alternation()?;
+        if parser.pos < parser.chars.len() {
+            return Err(format!("Unmatched ')' in /{}/", pattern));
+        }
+        Ok(Self {
+            node,
+            groups: parser.groups,
+            global: flags.contains('g'),
+            ignore_case: flags.contains('i'),
+            multiline: flags.contains('m'),
+            dot_all: flags.contains('s'),
+        })
+    }
+
+    pub fn is_match(&self, text: &str) -> Result<bool, TooDeep> {
+        let chars: Vec<char> = text.chars().collect();
+        Ok(self.find_at(&chars, 0)?.is_some())
+    }
+
+    /// The first match starting at or after `start`.
+    pub fn find_at(&self, input: &[char], start: usize) -> Result<Option<Captures>, TooDeep> {
+        let matcher = Matcher { regex: self, input, depth: Cell::new(0), too_deep: Cell::new(false) };
+        for pos in start..=input.len() {
+            let mut caps = vec![None; self.groups + 1];
+            let mut end = None;
+            let matched = matcher.match_node(&self.node, pos, &mut caps, &mut |p, _| {
+                end = Some(p);
+                true
+            });
+            if matcher.too_deep.get() {
+                return Err(TooDeep);
+            }
+            if matched {
+                caps[0] = end.map(|end| (pos, end));
+                return Ok(Some(caps));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Every non-overlapping match if the regex is global, else just the first.
+    pub fn matches(&self, input: &[char]) -> Result<Vec<Captures>, TooDeep> {
+        let mut found = Vec::new();
+        let mut start = 0;
+        while let Some(caps) = self.find_at(input, start)? {
+            let Some((from, to)) = caps[0] else { break };
+            found.push(caps);
+            if !self.global {
+                break;
+            }
+            // Step past empty matches so the scan always advances
+            start = if to == from { to + 1 } else { to };
+            if start > input.len() {
+                break;
+            }
+        }
+        Ok(found)
+    }
+}
+
+/// Expand `$&`, `$1`..`$99` and `$$` in a replacement string.
+pub fn expand_replacement(replacement: &str, input: &[char], caps: &Captures) -> String {
+    let span = |index: usize| -> String {
+        caps.get(index)
+            .copied()
+            .flatten()
+            .map(|(from, to)| input[from..to].iter().collect())
+            .unwrap_or_default()
+    };
+    let mut out = String::new();
+    let mut chars = replacement.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            out.push(c);
+            continue;
+        }
+        match chars.peek().copied() {
+            Some('$') => {
+                chars.next();
+                out.push('$');
+            }
+            Some('&') => {
+                chars.next();
+                out.push_str(&span(0));
+            }
+            Some(d) if d.is_ascii_digit() => {
+                chars.next();
+                let mut index = d.to_digit(10).unwrap_or(0) as usize;
+                // Two-digit group numbers only when that group exists
+                if let Some(next) = chars.peek().and_then(|n| n.to_digit(10)) {
+                    let two = index * 10 + next as usize;
+                    if two < caps.len() {
+                        chars.next();
+                        index = two;
+                    }
+                }
+                if index > 0 && index < caps.len() {
+                    out.push_str(&span(index));
+                } else {
+                    out.push('$');
+                    out.push(d);
+                }
+            }
+            _ => out.push('$'),
+        }
+    }
+    out
+}
+
+struct PatternParser {
+    chars: Vec<char>,
+    pos: usize,
+    groups: usize,
+}
+
+impl PatternParser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_alternation(&mut self) -> Result<Node, String> {
+        let mut branches = vec![self.parse_concat()?];
+        while self.eat('|') {
+            branches.push(self.parse_concat()?);
+        }
+        Ok(if branches.len() == 1 { branches.remove(0) } else { Node::Alternation(branches) })
+    }
+
+    fn parse_concat(&mut self) -> Result<Node, String> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.parse_atom()?;
+            nodes.push(self.parse_quantifier(atom)?);
+        }
+        Ok(match nodes.len() {
+            0 => Node::Empty,
+            1 => nodes.remove(0),
+            _ => Node::Concat(nodes),
+        })
+    }
+
+    fn parse_quantifier(&mut self, atom: Node) -> Result<Node, String> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => match self.parse_braces() {
+                Some(bounds) => bounds,
+                None => return Ok(atom),
+            },
+            _ => return Ok(atom),
+        };
+        self.pos += 1; // the quantifier, or the closing '}' of a brace quantifier
+        if matches!(atom, Node::Start | Node::End | Node::WordBoundary { .. }) {
+            return Err("Nothing to repeat".to_string());
+        }
+        let greedy = !self.eat('?');
+        Ok(Node::Repeat { inner: Box::new(atom), min, max, greedy })
+    }
+
+    // `{n}`, `{n,}` or `{n,m}`, leaving the position on the closing brace.
+    // Anything else is a literal '{' and consumes nothing.
+    fn parse_braces(&mut self) -> Option<(usize, Option<usize>)> {
+        let close = self.chars[self.pos..].iter().position(|&c| c == '}')? + self.pos;
+        let body: String = self.chars[self.pos + 1..close].iter().collect();
+        let (min, max) = match body.split_once(',') {
+            None => {
+                let n = body.parse().ok()?;
+                (n, Some(n))
+            }
+            Some((min, "")) => (min.parse().ok()?, None),
+            Some((min, max)) => (min.parse().ok()?, Some(max.parse().ok()?)),
+        };
+        self.pos = close;
+        Some((min, max))
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, String> {
+        let Some(c) = self.peek() else {
+            return Ok(Node::Empty);
+        };
+        self.pos += 1;
+        match c {
+            '.' => Ok(Node::Any),
+            '^' => Ok(Node::Start),
+            '$' => Ok(Node::End),
+            '[' => self.parse_class(),
+            '(' => {
+                let node = if self.eat('?') {
+                    match self.peek() {
+                        Some(':') => {
+                            self.pos += 1;
+                            Node::Group { inner: Box::new(self.parse_alternation()?), index: None }
+                        }
+                        Some(kind @ ('=' | '!')) => {
+                            self.pos += 1;
+                            Node::LookAhead { inner: Box::new(self.parse_alternation()?), negated: kind == '!' }
+                        }
+                        _ => return Err("Unsupported group syntax".to_string()),
+                    }
+                } else {
+                    self.groups += 1;
+                    let index = self.groups;
+                    Node::Group { inner: Box::new(self.parse_alternation()?), index: Some(index) }
+                };
+                if self.eat(')') {
+                    Ok(node)
+                } else {
+                    Err("Unterminated group".to_string())
+                }
+            }
+            '\\' => self.parse_escape(),
+            '*' | '+' | '?' => Err("Nothing to repeat".to_string()),
+            c => Ok(Node::Char(c)),
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<Node, String> {
+        let Some(c) = self.peek() else {
+            return Err("\\ at end of pattern".to_string());
+        };
+        self.pos += 1;
+        Ok(match c {
+            'b' => Node::WordBoundary { negated: false },
+            'B' => Node::WordBoundary { negated: true },
+            '1'..='9' => {
+                let mut index = c.to_digit(10).unwrap_or(0) as usize;
+                while let Some(d) = self.peek().and_then(|d| d.to_digit(10)) {
+                    self.pos += 1;
+                    index = index * 10 + d as usize;
+                }
+                Node::BackReference(index)
+            }
+            _ => match self.class_escape(c) {
+                ClassItem::Char(c) => Node::Char(c),
+                item => Node::Class { items: vec![item], negated: false },
+            },
+        })
+    }
+
+    // An escape that is valid both inside and outside a class; `c` follows the '\'
+    fn class_escape(&mut self, c: char) -> ClassItem {
+        match c {
+            'd' => ClassItem::Digit { negated: false },
+            'D' => ClassItem::Digit { negated: true },
+            'w' => ClassItem::Word { negated: false },
+            'W' => ClassItem::Word { negated: true },
+            's' => ClassItem::Space { negated: false },
+            'S' => ClassItem::Space { negated: true },
+            'n' => ClassItem::Char('\n'),
+            'r' => ClassItem::Char('\r'),
+            't' => ClassItem::Char('\t'),
+            'f' => ClassItem::Char('\x0C'),
+            'v' => ClassItem::Char('\x0B'),
+            '0' => ClassItem::Char('\0'),
+            'x' => ClassItem::Char(self.hex_escape(2).unwrap_or('x')),
+            'u' => ClassItem::Char(self.hex_escape(4).unwrap_or('u')),
+            c => ClassItem::Char(c),
+        }
+    }
+
+    fn hex_escape(&mut self, digits: usize) -> Option<char> {
+        let hex: String = self.chars.get(self.pos..self.pos + digits)?.iter().collect();
+        let c = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32)?;
+        self.pos += digits;
+        Some(c)
+    }
+
+    fn parse_class(&mut self) -> Result<Node, String> {
+        let negated = self.eat('^');
+        let mut items = Vec::new();
+        loop {
+            let Some(c) = self.peek() else {
+                return Err("Unterminated character class".to_string());
+            };
+            self.pos += 1;
+            let item = match c {
+                ']' => break,
+                '\\' => {
+                    let escaped = self.peek().ok_or("Unterminated character class")?;
+                    self.pos += 1;
+                    // \b is backspace inside a class
+                    if escaped == 'b' { ClassItem::Char('\x08') } else { self.class_escape(escaped) }
+                }
+                c => ClassItem::Char(c),
+            };
+            // `a-z`, unless the '-' is the last character of the class
+            if let ClassItem::Char(from) = item
+                && self.peek() == Some('-')
+                && self.chars.get(self.pos + 1).is_some_and(|&next| next != ']')
+            {
+                self.pos += 1;
+                let to = match self.peek() {
+                    Some('\\') => {
+                        self.pos += 1;
+                        let escaped = self.peek().ok_or("Unterminated character class")?;
+                        self.pos += 1;
+                        match self.class_escape(escaped) {
+                            ClassItem::Char(to) => to,
+                            _ => return Err("Invalid class range".to_string()),
+                        }
+                    }
+                    Some(to) => {
+                        self.pos += 1;
+                        to
+                    }
+                    None => return Err("Unterminated character class".to_string()),
+                };
+                if to < from {
+                    return Err("Range out of order in character class".to_string());
+                }
+                items.push(ClassItem::Range(from, to));
+            } else {
+                items.push(item);
+            }
+        }
+        Ok(Node::Class { items, negated })
+    }
+}
+
+struct Matcher<'a> {
+    regex: &'a Regex,
+    input: &'a [char],
+    depth: Cell<usize>,
+    // Set once `depth` passes the limit; every match attempt then fails
+    too_deep: Cell<bool>,
+}
+
+type Continuation<'k> = dyn FnMut(usize, &mut Captures) -> bool + 'k;
+
+impl Matcher<'_> {
+    // Match `node` at `pos`, then hand the end position to `k`; backtracks
+    // into `node` whenever `k` fails.
+    fn match_node(&self, node: &Node, pos: usize, caps: &mut Captures, k: &mut Continuation) -> bool {
+        if self.too_deep.get() || self.depth.get() >= MAX_MATCH_DEPTH {
+            self.too_deep.set(true);
+            return false;
+        }
+        self.depth.set(self.depth.get() + 1);
+        let matched = self.match_node_inner(node, pos, caps, k);
+        self.depth.set(self.depth.get() - 1);
+        matched
+    }
+
+    fn match_node_inner(&self, node: &Node, pos: usize, caps: &mut Captures, k: &mut Continuation) -> bool {
+        let input = self.input;
+        match node {
+            Node::Empty => k(pos, caps),
+            Node::Char(c) => pos < input.len() && self.chars_equal(input[pos], *c) && k(pos + 1, caps),
+            Node::Any => {
+                pos < input.len() && (self.regex.dot_all || !is_line_terminator(input[pos])) && k(pos + 1, caps)
+            }
+            Node::Class { items, negated } => {
+                pos < input.len() && self.class_matches(items, input[pos]) != *negated && k(pos + 1, caps)
+            }
+            Node::Start => {
+                let at_start = pos == 0 || (self.regex.multiline && is_line_terminator(input[pos - 1]));
+                at_start && k(pos, caps)
+            }
+            Node::End => {
+                let at_end = pos == input.len() || (self.regex.multiline && is_line_terminator(input[pos]));
+                at_end && k(pos, caps)
+            }
+            Node::WordBoundary { negated } => {
+                let before = pos > 0 && is_word_char(input[pos - 1]);
+                let after = pos < input.len() && is_word_char(input[pos]);
+                ((before != after) != *negated) && k(pos, caps)
+            }
+            Node::Group { inner, index: None } => self.match_node(inner, pos, caps, k),
+            Node::Group { inner, index: Some(index) } => {
+                let index = *index;
+                let saved = caps[index];
+                let matched = self.match_node(inner, pos, caps, &mut |end, caps| {
+                    let previous = caps[index];
+                    caps[index] = Some((pos, end));
+                    k(end, caps) || {
+                        caps[index] = previous;
+                        false
+                    }
+                });
+                if !matched {
+                    caps[index] = saved;
+                }
+                matched
+            }
+            Node::LookAhead { inner, negated } => {
+                let mut scratch = caps.clone();
+                let found = self.match_node(inner, pos, &mut scratch, &mut |_, _| true);
+                if found == *negated {
+                    return false;
+                }
+                // A positive lookahead keeps the groups it captured
+                if !negated {
+                    *caps = scratch;
+                }
+                k(pos, caps)
+            }
+            Node::BackReference(index) => {
+                let Some((from, to)) = caps.get(*index).copied().flatten() else {
+                    // Unset groups match the empty string
+                    return k(pos, caps);
+                };
+                let len = to - from;
+                pos + len <= input.len()
+                    && (0..len).all(|i| self.chars_equal(input[pos + i], input[from + i]))
+                    && k(pos + len, caps)
+            }
+            Node::Concat(nodes) => self.match_sequence(nodes, pos, caps, k),
+            Node::Alternation(branches) => branches.iter().any(|branch| self.match_node(branch, pos, caps, k)),
+            Node::Repeat { inner, min, max, greedy } => self.match_repeat(inner, *min, *max, *greedy, 0, pos, caps, k),
+        }
+    }
+
+    fn match_sequence(&self, nodes: &[Node], pos: usize, caps: &mut Captures, k: &mut Continuation) -> bool {
+        match nodes.split_first() {
+            None => k(pos, caps),
+            Some((first, rest)) => self.match_node(first, pos, caps, &mut |next, caps| self.match_sequence(rest, next, caps, k)),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn match_repeat(
+        &self,
+        inner: &Node,
+        min: usize,
+        max: Option<usize>,
+        greedy: bool,
+        count: usize,
+        pos: usize,
+        caps: &mut Captures,
+        k: &mut Continuation,
+    ) -> bool {
+        if let Some(run) = self.single_char_run(inner, pos, max) {
+            return self.match_run(pos, min, run, greedy, caps, k);
+        }
+        let can_repeat = max.is_none_or(|max| count < max);
+        let once_more = |caps: &mut Captures, k: &mut Continuation| {
+            can_repeat
+                && self.match_node(inner, pos, caps, &mut |next, caps| {
+                    // An iteration that consumed nothing cannot make progress
+                    (next != pos || count < min) && self.match_repeat(inner, min, max, greedy, count + 1, next, caps, k)
+                })
+        };
+        // Stopping here is only an option once `min` iterations have matched
+        let satisfied = count >= min;
+        // Greedy repeats try another iteration before stopping; lazy ones after
+        if greedy && once_more(caps, k) {
+            return true;
+        }
+        if satisfied && k(pos, caps) {
+            return true;
+        }
+        !greedy && once_more(caps, k)
+    }
+
+    // How many chars from `pos` a repeat of `inner` could take, if `inner`
+    // always consumes exactly one char. Such repeats are matched in a loop
+    // rather than a level of recursion per iteration.
+    fn single_char_run(&self, inner: &Node, pos: usize, max: Option<usize>) -> Option<usize> {
+        if !matches!(inner, Node::Char(_) | Node::Any | Node::Class { .. }) {
+            return None;
+        }
+        let matches_one = |c: char| match inner {
+            Node::Char(x) => self.chars_equal(c, *x),
+            Node::Any => self.regex.dot_all || !is_line_terminator(c),
+            Node::Class { items, negated } => self.class_matches(items, c) != *negated,
+            _ => false,
+        };
+        let limit = max.unwrap_or(usize::MAX);
+        let mut run = 0;
+        for &c in self.input.get(pos..).unwrap_or_default() {
+            if run == limit || !matches_one(c) {
+                break;
+            }
+            run += 1;
+        }
+        Some(run)
+    }
+
+    fn match_run(&self, pos: usize, min: usize, run: usize, greedy: bool, caps: &mut Captures, k: &mut Continuation) -> bool {
+        if run < min {
+            return false;
+        }
+        if greedy {
+            (min..=run).rev().any(|count| k(pos + count, caps))
+        } else {
+            (min..=run).any(|count| k(pos + count, caps))
+        }
+    }
+
+    fn chars_equal(&self, a: char, b: char) -> bool {
+        a == b || (self.regex.ignore_case && a.to_lowercase().eq(b.to_lowercase()))
+    }
+
+    fn class_matches(&self, items: &[ClassItem], c: char) -> bool {
+        let matches = |c: char| {
+            items.iter().any(|item| match item {
+                ClassItem::Char(x) => *x == c,
+                ClassItem::Range(from, to) => (*from..=*to).contains(&c),
+                ClassItem::Digit { negated } => c.is_ascii_digit() != *negated,
+                ClassItem::Word { negated } => is_word_char(c) != *negated,
+                ClassItem::Space { negated } => c.is_whitespace() != *negated,
+            })
+        };
+        if matches(c) {
+            return true;
+        }
+        self.regex.ignore_case && (c.to_lowercase().any(matches) || c.to_uppercase().any(matches))
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn is_line_terminator(c: char) -> bool {
+    matches!(c, '\n' | '\r' | '\u{2028}' | '\u{2029}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replace_all(pattern: &str, flags: &str, text: &str, replacement: &str) -> String {
+        let regex = Regex::new(pattern, flags).expect("valid pattern");
+        let input: Vec<char> = text.chars().collect();
+        let mut out = String::new();
+        let mut last = 0;
+        for caps in regex.matches(&input).expect("shallow match") {
+            let (from, to) = caps[0].expect("match span");
+            out.extend(&input[last..from]);
+            out.push_str(&expand_replacement(replacement, &input, &caps));
+            last = to;
+        }
+        out.extend(&input[last..]);
+        out
+    }
+
+    impl Regex {
+        fn is_match_ok(&self, text: &str) -> bool {
+            self.is_match(text).expect("shallow match")
+        }
+    }
+
+    #[test]
+    fn test_matches_common_patterns() {
+        assert_eq!(replace_all(r"\s+", "g", " a  b\tc ", ""), "abc");
+        assert_eq!(replace_all(r"(\w+)@(\w+)\.com", "", "mail bob@example.com now", "$2:$1"), "mail example:bob now");
+        assert_eq!(replace_all("a+?", "g", "aaa", "x"), "xxx");
+        assert_eq!(replace_all("^|$", "g", "ab", "|"), "|ab|");
+        assert!(Regex::new(r"^[a-f\d]{2,4}$", "i").expect("pattern").is_match_ok("Bc9"));
+        assert!(!Regex::new(r"^[a-f\d]{2,4}$", "i").expect("pattern").is_match_ok("Bc9fe"));
+        assert!(Regex::new(r"(['\x22])\w+\1", "").expect("pattern").is_match_ok("say 'hi'"));
+        assert!(Regex::new(r"foo(?!bar)", "").expect("pattern").is_match_ok("foobaz"));
+        assert!(Regex::new("(a", "").is_err());
+    }
+    #[test]
+    fn test_long_inputs_do_not_overflow_the_stack() {
+        let long = "a".repeat(100_000);
+        assert!(Regex::new("a+", "").expect("pattern").is_match_ok(&long));
+        assert!(Regex::new("^[a-z]*$", "").expect("pattern").is_match_ok(&long));
+        assert_eq!(replace_all("a+?", "", &long, "x").len(), long.len());
+        // Repeats of anything wider than one char still recurse, and give up
+        assert_eq!(Regex::new("(a|b)+", "").expect("pattern").is_match(&long), Err(TooDeep));
+    }
+}
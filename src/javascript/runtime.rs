@@ -1,11 +1,19 @@
-use super::ast::{Node, BinaryOperator, UnaryOperator};
-use super::value::{JsValue, JsObject, JsUserFunction};
+use super::ast::{Node, BinaryOperator, UnaryOperator, Pattern, Param, DeclarationKind};
+use super::value::{JsValue, JsObject, JsUserFunction, number_to_string};
+use super::error::{JsException, ScriptAborted};
+use super::date;
+use super::json;
+use super::storage::Storage;
+use super::regex::{self, Regex};
+use crate::css::style::StyleEngine;
+use crate::css::StyleSheet;
 use crate::dom::Node as DomNode;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::error::Error;
 use std::rc::Rc;
 use std::cell::RefCell;
 use std::sync::mpsc;
+use std::time::{Duration, Instant};
 use log::debug;
 
 pub struct Runtime {
@@ -14,13 +22,60 @@ pub struct Runtime {
     dom_root: Option<Rc<RefCell<DomNode>>>, // Store DOM root for DOM operations
     execution_depth: usize, // Track execution depth to prevent infinite recursion
     property_access_depth: usize, // Track property access depth to prevent infinite loops
-    dom_content_loaded_listeners: Vec<JsValue>, // Store DOMContentLoaded event listeners
+    event_listeners: HashMap<String, Vec<Rc<JsUserFunction>>>, // document/window listeners, keyed by event type
     console_log_sender: Option<mpsc::Sender<(String, String)>>, // Sender for console logs (level, message)
+    console_messages: VecDeque<(String, String)>, // Console logs kept while no sender is set, newest last
+    style_engine: Option<StyleEngine>, // Page styles, for getComputedStyle
+    max_loop_iterations: usize, // Per loop; a script that goes past it is aborted
+    max_execution_depth: usize, // Nested function calls before further calls are skipped
+    time_budget: Option<Duration>, // Wall-clock allowance for one script or event dispatch
+    deadline: Option<Instant>, // Set while a budgeted script is running
+    pending_labels: Vec<String>, // Labels on the loop about to run, claimed when it starts
+    fetcher: Option<Fetcher>, // Network access for XMLHttpRequest and fetch; without it requests fail
+    local_storage: Storage, // localStorage; kept across page loads
+    session_storage: Storage, // sessionStorage; kept across page loads
 }
 
+/// Performs an HTTP request for a script: `(method, url, body)` to `(status, body text)`.
+/// `url` is as the script wrote it, so relative URLs resolve against the page here.
+pub type Fetcher = Box<dyn Fn(&str, &str, Option<&str>) -> Result<(u16, String), String>>;
+
+/// `break` or `continue` unwinding to the loop or labeled statement it targets.
+/// It travels as an error so `?` carries it out of nested statements.
+#[derive(Debug)]
+enum Jump {
+    Break(Option<String>),
+    Continue(Option<String>),
+}
+
+impl std::fmt::Display for Jump {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Jump::Break(Some(label)) => write!(f, "Undefined label '{}'", label),
+            Jump::Continue(Some(label)) => write!(f, "Undefined label '{}'", label),
+            Jump::Break(None) => write!(f, "Illegal break statement"),
+            Jump::Continue(None) => write!(f, "Illegal continue statement"),
+        }
+    }
+}
+
+impl Error for Jump {}
+
+// Property holding the DOM node id of an element wrapper or its classList
+const NODE_SLOT: &str = "__node_id";
+// Property holding the DOM node id of the element an element.style belongs to
+const STYLE_NODE_SLOT: &str = "__style_node_id";
+
+pub const DEFAULT_MAX_LOOP_ITERATIONS: usize = 10000;
+pub const DEFAULT_MAX_EXECUTION_DEPTH: usize = 1000;
+// Console messages kept for take_console_messages; older ones are dropped
+const MAX_CONSOLE_MESSAGES: usize = 1000;
+
 #[derive(Debug, Clone)]
 pub struct Scope {
     variables: HashMap<String, JsValue>,
+    // Names declared here with `let` or `const`
+    lexical: HashSet<String>,
     parent: Option<Box<Scope>>,
 }
 
@@ -28,6 +83,7 @@ impl Scope {
     pub fn new(parent: Option<Box<Scope>>) -> Self {
         Self {
             variables: HashMap::new(),
+            lexical: HashSet::new(),
             parent,
         }
     }
@@ -41,8 +97,18 @@ impl Runtime {
             dom_root: None,
             execution_depth: 0,
             property_access_depth: 0,
-            dom_content_loaded_listeners: Vec::new(),
+            event_listeners: HashMap::new(),
             console_log_sender: None,
+            console_messages: VecDeque::new(),
+            style_engine: None,
+            max_loop_iterations: DEFAULT_MAX_LOOP_ITERATIONS,
+            max_execution_depth: DEFAULT_MAX_EXECUTION_DEPTH,
+            time_budget: None,
+            deadline: None,
+            pending_labels: Vec::new(),
+            fetcher: None,
+            local_storage: Storage::default(),
+            session_storage: Storage::default(),
         };
 
         // Initialize window object in global scope with common methods
@@ -57,39 +123,129 @@ impl Runtime {
     pub fn set_console_log_sender(&mut self, sender: mpsc::Sender<(String, String)>) {
         self.console_log_sender = Some(sender);
     }
-    
-    pub fn bind_dom(&mut self, dom_root: &DomNode) {
-        // Store a reference to the DOM root for DOM operations
-        // We wrap it in Rc<RefCell<>> to allow shared mutable access
-        // Note: This creates a clone of the DOM node, but we'll work with it
-        self.dom_root = Some(Rc::new(RefCell::new(dom_root.clone())));
-        log::trace!(target: "javascript", "DOM bound to JavaScript runtime");
+
+    /// Drain the console messages kept since the last call, oldest first.
+    pub fn take_console_messages(&mut self) -> Vec<(String, String)> {
+        self.console_messages.drain(..).collect()
+    }
+
+    // Hand a console message to the sender, or keep it for take_console_messages
+    fn emit_console_message(&mut self, level: &str, message: String) {
+        match &self.console_log_sender {
+            Some(sender) => {
+                let _ = sender.send((level.to_string(), message));
+            }
+            None => {
+                if self.console_messages.len() == MAX_CONSOLE_MESSAGES {
+                    self.console_messages.pop_front();
+                }
+                self.console_messages.push_back((level.to_string(), message));
+            }
+        }
     }
     
-    pub fn bind_dom_shared(&mut self, dom_root: Rc<RefCell<DomNode>>) {
+    pub fn bind_dom(&mut self, dom_root: Rc<RefCell<DomNode>>) {
         // Store the shared reference to the actual DOM root
         // This allows JavaScript to modify the real DOM
         self.dom_root = Some(dom_root);
         log::trace!(target: "javascript", "Shared DOM bound to JavaScript runtime");
     }
     
+    pub fn bind_stylesheet(&mut self, stylesheet: StyleSheet) {
+        self.style_engine = Some(StyleEngine::new(stylesheet));
+    }
+
+    /// Route XMLHttpRequest and fetch through `fetcher`. Requests block the script
+    /// until the response is in, whatever `async` flag was passed to `open`.
+    pub fn set_fetcher(&mut self, fetcher: Fetcher) {
+        self.fetcher = Some(fetcher);
+    }
+
+    /// Cap how many times any single loop may run and how deeply functions may
+    /// nest. A loop that goes past its cap aborts the script with [`ScriptAborted`].
+    pub fn set_limits(&mut self, max_loop_iterations: usize, max_execution_depth: usize) {
+        self.max_loop_iterations = max_loop_iterations;
+        self.max_execution_depth = max_execution_depth;
+    }
+
+    /// Abort any script (or event dispatch) still running after `budget`.
+    /// `None`, the default, lets scripts run as long as the loop limits allow.
+    pub fn set_time_budget(&mut self, budget: Option<Duration>) {
+        self.time_budget = budget;
+    }
+
+    // Run `f` under the time budget. Nested calls share the outermost deadline.
+    fn with_deadline<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T, Box<dyn Error>>) -> Result<T, Box<dyn Error>> {
+        if self.deadline.is_some() {
+            return f(self);
+        }
+        self.deadline = self.time_budget.map(|budget| Instant::now() + budget);
+        let result = f(self);
+        self.deadline = None;
+        result
+    }
+
+    // Called after each loop iteration with the count so far
+    fn check_loop_budget(&self, iterations: usize) -> Result<(), Box<dyn Error>> {
+        if iterations > self.max_loop_iterations {
+            log::warn!(target: "javascript", "Loop exceeded {} iterations, aborting script", self.max_loop_iterations);
+            return Err(Box::new(ScriptAborted {
+                reason: format!("loop exceeded {} iterations", self.max_loop_iterations),
+            }));
+        }
+        self.check_deadline()
+    }
+
+    // Run one pass of a loop body. Ok(false) means a `break` ended the loop;
+    // jumps aimed at an outer statement keep unwinding.
+    fn run_loop_body(&mut self, body: &Node, labels: &[String]) -> Result<bool, Box<dyn Error>> {
+        let Err(error) = self.evaluate_node(body) else {
+            return Ok(true);
+        };
+        match error.downcast_ref::<Jump>() {
+            Some(Jump::Break(None)) => Ok(false),
+            Some(Jump::Continue(None)) => Ok(true),
+            Some(Jump::Break(Some(label))) if labels.contains(label) => Ok(false),
+            Some(Jump::Continue(Some(label))) if labels.contains(label) => Ok(true),
+            _ => Err(error),
+        }
+    }
+
+    fn check_deadline(&self) -> Result<(), Box<dyn Error>> {
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => {
+                log::warn!(target: "javascript", "Script exceeded its time budget, aborting");
+                Err(Box::new(ScriptAborted { reason: "time budget exceeded".to_string() }))
+            }
+            _ => Ok(()),
+        }
+    }
+
     pub fn fire_dom_content_loaded(&mut self) -> Result<(), Box<dyn Error>> {
-        // Fire all stored DOMContentLoaded listeners
-        log::info!(target: "javascript", "Firing {} DOMContentLoaded listeners", self.dom_content_loaded_listeners.len());
+        self.fire_event("DOMContentLoaded")
+    }
+
+    /// Fire `load`. We have no subresource tracking past scripts, so the
+    /// browser fires it once every script has run, after DOMContentLoaded.
+    pub fn fire_load(&mut self) -> Result<(), Box<dyn Error>> {
+        self.fire_event("load")
+    }
+
+    fn fire_event(&mut self, event_type: &str) -> Result<(), Box<dyn Error>> {
+        // Collect listeners to avoid borrowing issues
+        let listeners = self.event_listeners.get(event_type).cloned().unwrap_or_default();
+        log::info!(target: "javascript", "Firing {} {} listeners", listeners.len(), event_type);
         let mut event_obj = JsObject::new();
-        event_obj.set("type", JsValue::String("DOMContentLoaded".to_string()));
+        event_obj.set("type", JsValue::String(event_type.to_string()));
         let event_value = JsValue::Object(Rc::new(RefCell::new(event_obj)));
-        
-        // Collect listeners to avoid borrowing issues
-        let listeners: Vec<_> = self.dom_content_loaded_listeners.iter().cloned().collect();
-        
-        for listener in listeners {
-            if let JsValue::Function(func) = listener {
-                debug!(target: "javascript", "Calling DOMContentLoaded listener");
-                self.call_function(&func, &[event_value.clone()])?;
+
+        self.with_deadline(|runtime| {
+            for func in listeners {
+                debug!(target: "javascript", "Calling {} listener", event_type);
+                runtime.call_function(&func, std::slice::from_ref(&event_value))?;
             }
-        }
-        Ok(())
+            Ok(())
+        })
     }
 
     fn init_window(&mut self) {
@@ -145,6 +301,25 @@ impl Runtime {
         // Initialize JSON object
         self.init_json();
         
+        // Initialize Object and Array static methods
+        self.init_object();
+        self.init_array();
+        
+        // Initialize Error constructors
+        self.init_errors();
+        
+        // Global number constants and conversion functions
+        self.set_variable("NaN", JsValue::Number(f64::NAN)).expect("Failed to initialize NaN");
+        self.set_variable("Infinity", JsValue::Number(f64::INFINITY)).expect("Failed to initialize Infinity");
+        self.set_variable("String", JsValue::NativeFunction("String".to_string()))
+            .expect("Failed to initialize String");
+        self.set_variable("Date", JsValue::NativeFunction("Date".to_string()))
+            .expect("Failed to initialize Date");
+        self.set_variable("XMLHttpRequest", JsValue::NativeFunction("XMLHttpRequest".to_string()))
+            .expect("Failed to initialize XMLHttpRequest");
+        self.set_variable("fetch", JsValue::NativeFunction("fetch".to_string()))
+            .expect("Failed to initialize fetch");
+        
         // Initialize document object (basic stub)
         self.init_document();
         
@@ -153,6 +328,8 @@ impl Runtime {
         
         // Initialize location object
         self.init_location();
+
+        self.init_storage();
     }
     
     fn init_json(&mut self) {
@@ -163,6 +340,29 @@ impl Runtime {
         self.set_variable("JSON", json).expect("Failed to initialize JSON object");
     }
     
+    fn init_object(&mut self) {
+        let mut object_obj = JsObject::new();
+        for method in ["keys", "values", "entries", "assign"] {
+            object_obj.set(method, JsValue::NativeFunction(format!("Object.{}", method)));
+        }
+        let object = JsValue::Object(Rc::new(RefCell::new(object_obj)));
+        self.set_variable("Object", object).expect("Failed to initialize Object");
+    }
+    
+    fn init_array(&mut self) {
+        let mut array_obj = JsObject::new();
+        array_obj.set("isArray", JsValue::NativeFunction("Array.isArray".to_string()));
+        let array = JsValue::Object(Rc::new(RefCell::new(array_obj)));
+        self.set_variable("Array", array).expect("Failed to initialize Array");
+    }
+
+    fn init_errors(&mut self) {
+        for name in ["Error", "TypeError", "RangeError", "SyntaxError", "ReferenceError"] {
+            self.set_variable(name, JsValue::NativeFunction(name.to_string()))
+                .expect("Failed to initialize Error constructors");
+        }
+    }
+    
     fn init_document(&mut self) {
         let mut doc_obj = JsObject::new();
         
@@ -186,18 +386,18 @@ impl Runtime {
     }
     
     fn init_navigator(&mut self) {
+        self.set_navigator("Celeris/0.1 (X11; Linux x86_64)", std::env::consts::OS, "en-US");
+    }
+
+    /// Replace the `navigator` identity seen by scripts. Callers keep `user_agent`
+    /// in step with the User-Agent header so UA sniffing sees one browser.
+    pub fn set_navigator(&mut self, user_agent: &str, platform: &str, language: &str) {
         let mut nav_obj = JsObject::new();
         
-        // User agent string
-        nav_obj.set("userAgent", JsValue::String("Celeris/0.1 (X11; Linux x86_64)".to_string()));
-        nav_obj.set("platform", JsValue::String(std::env::consts::OS.to_string()));
-        nav_obj.set("language", JsValue::String("en-US".to_string()));
-        nav_obj.set("languages", JsValue::Object(Rc::new(RefCell::new({
-            let mut arr = JsObject::new();
-            arr.set("0", JsValue::String("en-US".to_string()));
-            arr.set("length", JsValue::Number(1.0));
-            arr
-        }))));
+        nav_obj.set("userAgent", JsValue::String(user_agent.to_string()));
+        nav_obj.set("platform", JsValue::String(platform.to_string()));
+        nav_obj.set("language", JsValue::String(language.to_string()));
+        nav_obj.set("languages", Self::create_array(vec![JsValue::String(language.to_string())]));
         nav_obj.set("cookieEnabled", JsValue::Boolean(true));
         nav_obj.set("onLine", JsValue::Boolean(true));
         
@@ -205,6 +405,18 @@ impl Runtime {
         self.set_variable("navigator", navigator).expect("Failed to initialize navigator object");
     }
     
+    fn init_storage(&mut self) {
+        for name in ["localStorage", "sessionStorage"] {
+            let mut storage_obj = JsObject::new();
+            for method in ["getItem", "setItem", "removeItem", "clear", "key"] {
+                storage_obj.set(method, JsValue::NativeFunction(format!("{}.{}", name, method)));
+            }
+            storage_obj.set("length", JsValue::Number(0.0));
+            let storage = JsValue::Object(Rc::new(RefCell::new(storage_obj)));
+            self.set_variable(name, storage).expect("Failed to initialize storage object");
+        }
+    }
+
     fn init_location(&mut self) {
         let mut loc_obj = JsObject::new();
         
@@ -224,22 +436,30 @@ impl Runtime {
     }
 
     pub fn execute(&mut self, ast: &Node) -> Result<JsValue, Box<dyn Error>> {
-        match ast {
+        self.with_deadline(|runtime| match ast {
             Node::Program(statements) => {
                 let mut result = JsValue::Undefined;
                 for stmt in statements {
-                    result = self.evaluate_node(stmt)?;
+                    result = runtime.evaluate_node(stmt)?;
                 }
                 Ok(result)
             }
-            _ => self.evaluate_node(ast),
-        }
+            _ => runtime.evaluate_node(ast),
+        })
     }
 
     fn evaluate_node(&mut self, node: &Node) -> Result<JsValue, Box<dyn Error>> {
         match node {
             Node::Number(n) => Ok(JsValue::Number(*n)),
             Node::String(s) => Ok(JsValue::String(s.clone())),
+            Node::RegexLiteral { pattern, flags } => {
+                // An invalid pattern is reported when the literal is evaluated
+                if let Err(message) = Regex::new(pattern, flags) {
+                    let error = Self::create_error_object("SyntaxError", &format!("Invalid regular expression: {}", message));
+                    return Err(Box::new(JsException::new(error)));
+                }
+                Ok(Self::regex_object(pattern, flags))
+            }
             Node::Boolean(b) => Ok(JsValue::Boolean(*b)),
             Node::Null => Ok(JsValue::Null),
             Node::Undefined => Ok(JsValue::Undefined),
@@ -336,9 +556,15 @@ impl Runtime {
                         }
                         Ok(new_val) // Return new value for prefix
                     }
+                    UnaryOperator::Delete => self.delete_operand(operand),
+                    // `typeof` of an undeclared name is "undefined", not a ReferenceError
+                    UnaryOperator::Typeof if matches!(&**operand, Node::Identifier(name) if !self.is_declared(name)) => {
+                        Ok(JsValue::String("undefined".to_string()))
+                    }
                     _ => {
                         let val = self.evaluate_node(operand)?;
                         match op {
+                            UnaryOperator::Void => Ok(JsValue::Undefined),
                             UnaryOperator::Not => Ok(JsValue::Boolean(!self.is_truthy(&val))),
                             UnaryOperator::Negative => {
                                 match val {
@@ -381,6 +607,7 @@ impl Runtime {
             }
             
             Node::ForLoop { init, condition, update, body } => {
+                let labels = std::mem::take(&mut self.pending_labels);
                 // Initialize
                 if let Some(init_node) = init {
                     self.evaluate_node(init_node)?;
@@ -388,7 +615,6 @@ impl Runtime {
                 
                 // Loop
                 let mut iterations = 0;
-                const MAX_ITERATIONS: usize = 10000; // Prevent infinite loops
                 
                 loop {
                     // Check condition
@@ -400,7 +626,9 @@ impl Runtime {
                     }
                     
                     // Execute body
-                    self.evaluate_node(body)?;
+                    if !self.run_loop_body(body, &labels)? {
+                        break;
+                    }
                     
                     // Update
                     if let Some(upd) = update {
@@ -408,10 +636,7 @@ impl Runtime {
                     }
                     
                     iterations += 1;
-                    if iterations >= MAX_ITERATIONS {
-                        log::warn!(target: "javascript", "For loop exceeded max iterations, breaking");
-                        break;
-                    }
+                    self.check_loop_budget(iterations)?;
                 }
                 Ok(JsValue::Undefined)
             }
@@ -442,40 +667,39 @@ impl Runtime {
             }
             
             Node::ArrayLiteral(elements) => {
-                let obj = Rc::new(RefCell::new(JsObject::new()));
-                
-                // Evaluate each element
-                for (i, element) in elements.iter().enumerate() {
-                    let value = self.evaluate_node(element)?;
-                    obj.borrow_mut().set_property(i.to_string(), value);
-                }
-                
-                // Set length property
-                obj.borrow_mut().set_property("length".to_string(), JsValue::Number(elements.len() as f64));
-                
-                Ok(JsValue::Object(obj))
+                let values = self.evaluate_elements(elements)?;
+                Ok(Self::create_array(values))
             }
+
+            Node::Spread(_) => Err("Spread syntax is only valid in array literals and argument lists".into()),
             
             Node::NewExpr { constructor, arguments } => {
                 let constructor_value = self.evaluate_node(constructor)?;
-                let mut arg_values = Vec::new();
-                
-                for arg in arguments {
-                    arg_values.push(self.evaluate_node(arg)?);
-                }
+                let arg_values = self.evaluate_elements(arguments)?;
                 
                 match constructor_value {
                     JsValue::Function(func) => {
-                        // Create a new object with the function's prototype
-                        let obj = Rc::new(RefCell::new(JsObject::new()));
-                        // TODO: Set up prototype chain
-                        
-                        // Call the constructor with the new object as 'this'
-                        // TODO: Implement proper constructor calling
-                        
+                        // The instance's prototype points back at its constructor;
+                        // instanceof walks these links
+                        let mut prototype = JsObject::new();
+                        prototype.set("constructor", JsValue::Function(func.clone()));
+                        let mut instance = JsObject::new();
+                        instance.set_prototype(prototype);
+                        let obj = Rc::new(RefCell::new(instance));
+
+                        // Run the constructor with the new object as 'this'. Its
+                        // return value is ignored, so `return {...}` isn't honoured yet
+                        self.call_method(&func, &JsValue::Object(obj.clone()), &arg_values)?;
                         Ok(JsValue::Object(obj))
                     }
-                    _ => Err("Constructor must be a function".into()),
+                    JsValue::NativeFunction(name) if name == "Date" => {
+                        Ok(Self::create_date_object(self.date_time_from_args(&arg_values)))
+                    }
+                    JsValue::NativeFunction(name) => {
+                        // Built-in constructors (Error, ...) build their own object
+                        self.call_native_function(&name, &JsValue::Undefined, &arg_values)
+                    }
+                    _ => Err(Self::type_error("Constructor must be a function")),
                 }
             }
             
@@ -483,16 +707,7 @@ impl Runtime {
                 log::trace!(target: "javascript", "Evaluating member expression");
                 let obj = self.evaluate_node(object)?;
                 log::trace!(target: "javascript", "Object evaluated to: {:?}", obj);
-                let prop = if *computed {
-                    self.evaluate_node(property)?
-                } else {
-                    if let Node::Identifier(name) = &**property {
-                        log::trace!(target: "javascript", "Property name: {}", name);
-                        JsValue::String(name.clone())
-                    } else {
-                        return Err("Invalid property in member expression".into());
-                    }
-                };
+                let prop = self.member_key(property, *computed)?;
                 let result = self.get_property(&obj, &prop)?;
                 log::trace!(target: "javascript", "Member expression {}.{} = {:?}", 
                     self.js_value_to_string(&obj), 
@@ -517,10 +732,11 @@ impl Runtime {
                             }
                         }
                     }
-                    // Variable not found - this is expected for some cases
-                    // The browser will handle undefined variables (e.g., do_capabilities_detection)
-                    log::warn!(target: "javascript", "Variable '{}' not found in scope or window (will be handled by browser)", name);
-                    Ok(JsValue::Undefined)
+                    log::debug!(target: "javascript", "Variable '{}' not found in scope or window", name);
+                    Err(Box::new(JsException::new(Self::create_error_object(
+                        "ReferenceError",
+                        &format!("{} is not defined", name),
+                    ))))
                 }
             }
             
@@ -539,30 +755,25 @@ impl Runtime {
             
             Node::CallExpr { callee, arguments } => {
                 log::trace!(target: "javascript", "Evaluating call expression with {} arguments", arguments.len());
-                let callee_value = self.evaluate_node(callee)?;
-                
-                // Evaluate all arguments
-                let mut arg_values = Vec::new();
-                for (i, arg) in arguments.iter().enumerate() {
-                    let is_function_expr = matches!(arg, Node::FunctionExpr { .. });
-                    // Detailed node information at trace level
-                    log::trace!(target: "javascript", "Evaluating call argument {}: is_function_expr={}, node={:?}", i, is_function_expr, arg);
-                    let arg_val = self.evaluate_node(arg)?;
-                    let is_function_value = matches!(&arg_val, JsValue::Function(_));
-                    // Detailed value information at trace level
-                    log::trace!(target: "javascript", "Call argument {} evaluated to: is_function={}, value={:?}", i, is_function_value, arg_val);
-                    // Simple summary at debug level
-                    if is_function_expr || is_function_value {
-                        log::trace!(target: "javascript", "Call argument {} is a function", i);
+                // Method calls keep their receiver so built-ins can act on `this`
+                let (callee_value, this) = match &**callee {
+                    Node::MemberExpr { object, property, computed } => {
+                        let receiver = self.evaluate_node(object)?;
+                        let prop = self.member_key(property, *computed)?;
+                        (self.get_property(&receiver, &prop)?, receiver)
                     }
-                    arg_values.push(arg_val);
-                }
+                    _ => (self.evaluate_node(callee)?, JsValue::Undefined),
+                };
+                
+                // Evaluate all arguments, expanding any spread
+                let arg_values = self.evaluate_elements(arguments)?;
+                log::trace!(target: "javascript", "Call arguments evaluated to: {:?}", arg_values);
                 
                 match callee_value {
                     JsValue::NativeFunction(name) => {
                         // Handle built-in functions
                         log::trace!(target: "javascript", "Calling native function: {}", name);
-                        self.call_native_function(&name, &arg_values)
+                        self.call_native_function(&name, &this, &arg_values)
                     }
                     JsValue::Function(func) => {
                         // Call user-defined function
@@ -571,7 +782,7 @@ impl Runtime {
                         } else {
                             log::trace!(target: "javascript", "Calling user-defined function");
                         }
-                        self.call_function(&func, &arg_values)
+                        self.call_method(&func, &this, &arg_values)
                     }
                     JsValue::Undefined => {
                         // Function not found - log warning but don't error
@@ -620,16 +831,20 @@ impl Runtime {
                 Ok(JsValue::Function(Rc::new(func)))
             }
             
-            Node::VariableDecl { name, init } => {
+            Node::VariableDecl { kind, pattern, init } => {
                 let value = if let Some(init_expr) = init {
                     self.evaluate_node(init_expr)?
                 } else {
                     JsValue::Undefined
                 };
-                if let JsValue::Function(_) = &value {
+                if let (Pattern::Identifier(name), JsValue::Function(_)) = (pattern, &value) {
                     log::info!(target: "javascript", "Defining variable '{}' as function", name);
                 }
-                self.set_variable(name, value)?;
+                if *kind == DeclarationKind::Var {
+                    self.bind_pattern(pattern, value)?;
+                } else {
+                    self.bind_lexical_pattern(pattern, value)?;
+                }
                 Ok(JsValue::Undefined)
             }
             
@@ -645,8 +860,8 @@ impl Runtime {
             }
             
             Node::WhileLoop { condition, body } => {
+                let labels = std::mem::take(&mut self.pending_labels);
                 let mut iterations = 0;
-                const MAX_ITERATIONS: usize = 10000;
                 
                 loop {
                     let cond_value = self.evaluate_node(condition)?;
@@ -654,57 +869,86 @@ impl Runtime {
                         break;
                     }
                     
-                    self.evaluate_node(body)?;
+                    if !self.run_loop_body(body, &labels)? {
+                        break;
+                    }
                     
                     iterations += 1;
-                    if iterations >= MAX_ITERATIONS {
-                        log::warn!(target: "javascript", "While loop exceeded max iterations, breaking");
+                    self.check_loop_budget(iterations)?;
+                }
+                Ok(JsValue::Undefined)
+            }
+            
+            Node::DoWhileLoop { body, condition } => {
+                let labels = std::mem::take(&mut self.pending_labels);
+                let mut iterations = 0;
+                
+                // Body always runs once before the first condition check
+                loop {
+                    if !self.run_loop_body(body, &labels)? {
+                        break;
+                    }
+                    
+                    let cond_value = self.evaluate_node(condition)?;
+                    if !self.is_truthy(&cond_value) {
                         break;
                     }
+                    
+                    iterations += 1;
+                    self.check_loop_budget(iterations)?;
                 }
                 Ok(JsValue::Undefined)
             }
             
             Node::ForInLoop { variable, object, body } => {
+                let labels = std::mem::take(&mut self.pending_labels);
                 let obj_value = self.evaluate_node(object)?;
                 let mut iterations = 0;
-                const MAX_ITERATIONS: usize = 10000;
                 
                 if let JsValue::Object(obj_ref) = obj_value {
                     let keys: Vec<String> = obj_ref.borrow().keys().cloned().collect();
                     for key in keys {
                         self.set_variable(variable, JsValue::String(key))?;
-                        self.evaluate_node(body)?;
-                        
-                        iterations += 1;
-                        if iterations >= MAX_ITERATIONS {
-                            log::warn!(target: "javascript", "For-in loop exceeded max iterations, breaking");
+                        if !self.run_loop_body(body, &labels)? {
                             break;
                         }
+                        
+                        iterations += 1;
+                        self.check_loop_budget(iterations)?;
                     }
                 }
                 Ok(JsValue::Undefined)
             }
             
             Node::ForOfLoop { variable, iterable, body } => {
+                let labels = std::mem::take(&mut self.pending_labels);
                 let iter_value = self.evaluate_node(iterable)?;
                 let mut iterations = 0;
-                const MAX_ITERATIONS: usize = 10000;
                 
-                if let JsValue::Object(obj_ref) = iter_value {
+                if let JsValue::String(s) = &iter_value {
+                    // Strings iterate by character
+                    for c in s.chars() {
+                        self.set_variable(variable, JsValue::String(c.to_string()))?;
+                        if !self.run_loop_body(body, &labels)? {
+                            break;
+                        }
+
+                        iterations += 1;
+                        self.check_loop_budget(iterations)?;
+                    }
+                } else if let JsValue::Object(obj_ref) = iter_value {
                     // For arrays, iterate over numeric indices
                     if let Some(len) = obj_ref.borrow().get_length() {
                         for i in 0..len {
                             if let Some(elem) = obj_ref.borrow().get_element(i) {
                                 self.set_variable(variable, elem.clone())?;
-                                self.evaluate_node(body)?;
+                                if !self.run_loop_body(body, &labels)? {
+                                    break;
+                                }
                             }
                             
                             iterations += 1;
-                            if iterations >= MAX_ITERATIONS {
-                                log::warn!(target: "javascript", "For-of loop exceeded max iterations, breaking");
-                                break;
-                            }
+                            self.check_loop_budget(iterations)?;
                         }
                     }
                 }
@@ -712,42 +956,61 @@ impl Runtime {
             }
             
             Node::This => {
-                // Return the global window object for now (simplified)
-                if let Some(window) = self.get_variable("window") {
-                    Ok(window)
-                } else {
-                    Ok(JsValue::Undefined)
+                // Inside a method or constructor call; otherwise the global window object
+                match self.get_variable("this") {
+                    Some(this) if !matches!(this, JsValue::Undefined) => Ok(this),
+                    _ => Ok(self.get_variable("window").unwrap_or(JsValue::Undefined)),
                 }
             }
             
-            Node::BreakStatement | Node::ContinueStatement => {
-                // These should be handled by loop constructs, for now just return undefined
-                Ok(JsValue::Undefined)
+            Node::BreakStatement(label) => Err(Box::new(Jump::Break(label.clone()))),
+            Node::ContinueStatement(label) => Err(Box::new(Jump::Continue(label.clone()))),
+
+            Node::Labeled { label, body } => {
+                // A labeled loop also answers `continue label`
+                if matches!(
+                    **body,
+                    Node::ForLoop { .. } | Node::WhileLoop { .. } | Node::DoWhileLoop { .. }
+                        | Node::ForInLoop { .. } | Node::ForOfLoop { .. } | Node::Labeled { .. }
+                ) {
+                    self.pending_labels.push(label.clone());
+                }
+                let result = self.evaluate_node(body);
+                self.pending_labels.clear();
+                match result {
+                    Err(e) if matches!(e.downcast_ref::<Jump>(), Some(Jump::Break(Some(target))) if target == label) => {
+                        Ok(JsValue::Undefined)
+                    }
+                    other => other,
+                }
             }
             
-            Node::TryCatch { try_block, catch_param: _, catch_block, finally_block } => {
+            Node::TryCatch { try_block, catch_param, catch_block, finally_block } => {
                 // Execute try block
-                let result = self.evaluate_node(try_block);
+                let mut result = self.evaluate_node(try_block);
                 
-                // If error and catch block exists, execute it
-                let result = match result {
-                    Err(_) if catch_block.is_some() => {
-                        self.evaluate_node(catch_block.as_ref().unwrap())
+                // Only script-level exceptions are catchable; engine errors keep propagating
+                if let Some(catch_body) = catch_block {
+                    let thrown = match &result {
+                        Err(e) => e.downcast_ref::<JsException>().map(|ex| ex.value.clone()),
+                        Ok(_) => None,
+                    };
+                    if let Some(value) = thrown {
+                        result = self.evaluate_catch_block(catch_param.as_deref(), value, catch_body);
                     }
-                    other => other,
-                };
+                }
                 
                 // Always execute finally if present
                 if let Some(finally) = finally_block {
                     self.evaluate_node(finally)?;
                 }
                 
-                result.or(Ok(JsValue::Undefined))
+                result
             }
             
             Node::ThrowStatement(expr) => {
                 let value = self.evaluate_node(expr)?;
-                Err(format!("Uncaught: {:?}", value).into())
+                Err(Box::new(JsException::new(value)))
             }
             
             Node::LogicalOr { left, right } => {
@@ -789,7 +1052,21 @@ impl Runtime {
         }
     }
     
-    fn call_native_function(&mut self, name: &str, args: &[JsValue]) -> Result<JsValue, Box<dyn Error>> {
+    // Key of a member expression: `obj[expr]` evaluates, `obj.name` is the literal name
+    fn member_key(&mut self, property: &Node, computed: bool) -> Result<JsValue, Box<dyn Error>> {
+        if computed {
+            return self.evaluate_node(property);
+        }
+        match property {
+            Node::Identifier(name) => {
+                log::trace!(target: "javascript", "Property name: {}", name);
+                Ok(JsValue::String(name.clone()))
+            }
+            _ => Err("Invalid property in member expression".into()),
+        }
+    }
+
+    fn call_native_function(&mut self, name: &str, this: &JsValue, args: &[JsValue]) -> Result<JsValue, Box<dyn Error>> {
         debug!(target: "javascript", "call_native_function: {} with {} args", name, args.len());
         match name {
             "console.log" | "console.info" => {
@@ -808,11 +1085,8 @@ impl Runtime {
                     log::info!(target: "js-console", "{}", message);
                 }
                 
-                // Send to console log channel if available
-                if let Some(ref sender) = self.console_log_sender {
-                    let level = if name == "console.info" { "info" } else { "log" };
-                    let _ = sender.send((level.to_string(), message));
-                }
+                let level = if name == "console.info" { "info" } else { "log" };
+                self.emit_console_message(level, message);
                 
                 Ok(JsValue::Undefined)
             }
@@ -828,10 +1102,7 @@ impl Runtime {
                 // Use logger instead of println!
                 log::warn!(target: "js-console", "{}", message);
                 
-                // Send to console log channel if available
-                if let Some(ref sender) = self.console_log_sender {
-                    let _ = sender.send(("warn".to_string(), message));
-                }
+                self.emit_console_message("warn", message);
                 
                 Ok(JsValue::Undefined)
             }
@@ -847,10 +1118,7 @@ impl Runtime {
                 // Use logger instead of println!
                 log::error!(target: "js-console", "{}", message);
                 
-                // Send to console log channel if available
-                if let Some(ref sender) = self.console_log_sender {
-                    let _ = sender.send(("error".to_string(), message));
-                }
+                self.emit_console_message("error", message);
                 
                 Ok(JsValue::Undefined)
             }
@@ -866,10 +1134,7 @@ impl Runtime {
                 // Use logger instead of println!
                 log::debug!(target: "js-console", "{}", message);
                 
-                // Send to console log channel if available
-                if let Some(ref sender) = self.console_log_sender {
-                    let _ = sender.send(("debug".to_string(), message));
-                }
+                self.emit_console_message("debug", message);
                 
                 Ok(JsValue::Undefined)
             }
@@ -926,7 +1191,18 @@ impl Runtime {
                     Ok(JsValue::Null)
                 }
             }
-            "document.querySelectorAll" | "document.getElementsByTagName" | "document.getElementsByClassName" => {
+            "document.getElementsByTagName" => {
+                let tag = args.first().map(|arg| self.js_value_to_string(arg)).unwrap_or_default();
+                let mut elements = Vec::new();
+                if let Some(root) = &self.dom_root {
+                    let root = root.borrow();
+                    let mut matches = Vec::new();
+                    Self::collect_elements_by_tag(&root, &tag, &mut matches);
+                    elements = matches.into_iter().map(|node| self.create_element_object(node)).collect();
+                }
+                Ok(Self::create_array(elements))
+            }
+            "document.querySelectorAll" | "document.getElementsByClassName" => {
                 // Return empty array-like object
                 let mut arr = JsObject::new();
                 arr.set("length", JsValue::Number(0.0));
@@ -947,38 +1223,32 @@ impl Runtime {
                 elem.set("addEventListener", JsValue::NativeFunction("element.addEventListener".to_string()));
                 Ok(JsValue::Object(Rc::new(RefCell::new(elem))))
             }
-            // Event handlers - store callbacks for DOMContentLoaded
+            // Event handlers - store callbacks until the browser fires the event.
+            // As in the DOM, a listener is registered at most once per type.
             "document.addEventListener" | "window.addEventListener" => {
-                log::info!(target: "javascript", "addEventListener called with {} args", args.len());
-                if args.len() >= 2 {
-                    log::info!(target: "javascript", "addEventListener arg[0]: {:?}, arg[1]: {:?}", 
-                        matches!(&args[0], JsValue::String(_)), 
-                        matches!(&args[1], JsValue::Function(_)));
-                    if let (JsValue::String(event_type), JsValue::Function(callback)) = (&args[0], &args[1]) {
-                        log::info!(target: "javascript", "addEventListener event_type: '{}'", event_type);
-                        if event_type == "DOMContentLoaded" {
-                            log::info!(target: "javascript", "DOMContentLoaded listener registered - storing for later");
-                            // Store the callback to be called when fire_dom_content_loaded() is called
-                            // This ensures deferred scripts have loaded first
-                            self.dom_content_loaded_listeners.push(JsValue::Function(callback.clone()));
-                            log::info!(target: "javascript", "DOMContentLoaded listener stored (total: {})", self.dom_content_loaded_listeners.len());
-                            return Ok(JsValue::Undefined);
-                        }
-                    } else {
-                        log::warn!(target: "javascript", "addEventListener args not in expected format (String, Function)");
+                if let (Some(JsValue::String(event_type)), Some(JsValue::Function(callback))) = (args.first(), args.get(1)) {
+                    let listeners = self.event_listeners.entry(event_type.clone()).or_default();
+                    if !listeners.iter().any(|f| Rc::ptr_eq(f, callback)) {
+                        listeners.push(Rc::clone(callback));
                     }
+                    log::info!(target: "javascript", "{} listener stored (total: {})", event_type, listeners.len());
                 } else {
-                    log::warn!(target: "javascript", "addEventListener called with insufficient args");
+                    log::warn!(target: "javascript", "addEventListener args not in expected format (String, Function)");
                 }
-                log::trace!(target: "javascript", "addEventListener called (non-DOMContentLoaded or wrong format)");
                 Ok(JsValue::Undefined)
             }
-            "element.addEventListener" => {
-                // No-op for element events
+            "document.removeEventListener" | "window.removeEventListener" => {
+                // Listeners are matched by function identity, so an equivalent
+                // function expression does not remove anything
+                if let (Some(JsValue::String(event_type)), Some(JsValue::Function(callback))) = (args.first(), args.get(1))
+                    && let Some(listeners) = self.event_listeners.get_mut(event_type)
+                {
+                    listeners.retain(|f| !Rc::ptr_eq(f, callback));
+                }
                 Ok(JsValue::Undefined)
             }
-            "document.removeEventListener" | "window.removeEventListener" | "element.removeEventListener" => {
-                // No-op
+            "element.addEventListener" | "element.removeEventListener" => {
+                // No-op for element events
                 Ok(JsValue::Undefined)
             }
             "element.appendChild" | "element.removeChild" | "element.insertBefore" => {
@@ -988,6 +1258,82 @@ impl Runtime {
             "element.setAttribute" | "element.getAttribute" => {
                 Ok(JsValue::Undefined)
             }
+            "element.classList.add" | "element.classList.remove" | "element.classList.toggle" | "element.classList.contains" => {
+                // `this` is the classList object, which records its element's node
+                let Some(handle) = Self::node_handle(this, NODE_SLOT) else {
+                    return Ok(JsValue::Undefined);
+                };
+                let class = args.first().map(|arg| self.js_value_to_string(arg)).unwrap_or_default();
+                let Some(root) = &self.dom_root else {
+                    return Ok(JsValue::Undefined);
+                };
+                let mut root = root.borrow_mut();
+                let Some(node) = root.find_by_node_id_mut(handle) else {
+                    return Ok(JsValue::Undefined);
+                };
+                match name {
+                    "element.classList.add" => node.add_class(&class),
+                    "element.classList.remove" => node.remove_class(&class),
+                    "element.classList.toggle" => return Ok(JsValue::Boolean(node.toggle_class(&class))),
+                    _ => return Ok(JsValue::Boolean(node.has_class(&class))),
+                }
+                Ok(JsValue::Undefined)
+            }
+            "RegExp.test" => {
+                let Some(regex) = Self::regex_from_value(this) else {
+                    return Err(Self::type_error("RegExp.prototype.test called on incompatible receiver"));
+                };
+                let text = args.first().map(|arg| self.js_value_to_string(arg)).unwrap_or_else(|| "undefined".to_string());
+                Ok(JsValue::Boolean(regex.is_match(&text).map_err(Self::regex_too_deep)?))
+            }
+            "String.replace" => {
+                let JsValue::String(text) = this else {
+                    return Ok(JsValue::Undefined);
+                };
+                let input: Vec<char> = text.chars().collect();
+                let pattern = args.first().cloned().unwrap_or(JsValue::Undefined);
+                // Spans of every match to replace, as char indices with their groups
+                let matches = match Self::regex_from_value(&pattern) {
+                    Some(regex) => regex.matches(&input).map_err(Self::regex_too_deep)?,
+                    None => {
+                        let needle = self.js_value_to_string(&pattern);
+                        text.find(&needle)
+                            .map(|at| {
+                                let from = text[..at].chars().count();
+                                vec![vec![Some((from, from + needle.chars().count()))]]
+                            })
+                            .unwrap_or_default()
+                    }
+                };
+                let replacement = args.get(1).cloned().unwrap_or(JsValue::Undefined);
+                let mut result = String::new();
+                let mut last = 0;
+                for caps in matches {
+                    let Some((from, to)) = caps[0] else { continue };
+                    result.extend(&input[last..from]);
+                    let replaced = match &replacement {
+                        // Callbacks get the match, each group, the offset and the whole string
+                        JsValue::Function(func) => {
+                            let mut call_args: Vec<JsValue> = caps
+                                .iter()
+                                .map(|span| match span {
+                                    Some((from, to)) => JsValue::String(input[*from..*to].iter().collect()),
+                                    None => JsValue::Undefined,
+                                })
+                                .collect();
+                            call_args.push(JsValue::Number(from as f64));
+                            call_args.push(JsValue::String(text.clone()));
+                            let value = self.call_function(func, &call_args)?;
+                            self.js_value_to_string(&value)
+                        }
+                        other => regex::expand_replacement(&self.js_value_to_string(other), &input, &caps),
+                    };
+                    result.push_str(&replaced);
+                    last = to;
+                }
+                result.extend(&input[last..]);
+                Ok(JsValue::String(result))
+            }
             // Window methods
             "window.setTimeout" | "window.setInterval" => {
                 // Return a fake timer ID
@@ -1008,31 +1354,40 @@ impl Runtime {
             }
             // JSON methods
             "JSON.parse" => {
-                if let Some(JsValue::String(s)) = args.first() {
-                    // Very basic JSON parsing - just return an empty object for now
-                    debug!(target: "javascript", "JSON.parse called (stub)");
-                    Ok(JsValue::Object(Rc::new(RefCell::new(JsObject::new()))))
-                } else {
-                    Ok(JsValue::Undefined)
-                }
+                let text = args.first().map(|a| self.js_value_to_string(a)).unwrap_or_else(|| "undefined".to_string());
+                Self::parse_json(&text)
             }
             "JSON.stringify" => {
-                if let Some(val) = args.first() {
-                    Ok(JsValue::String(self.js_value_to_string(val)))
-                } else {
-                    Ok(JsValue::Undefined)
-                }
+                let value = args.first().cloned().unwrap_or(JsValue::Undefined);
+                Ok(match self.json_stringify(&value, &mut Vec::new())? {
+                    Some(json) => JsValue::String(json),
+                    None => JsValue::Undefined,
+                })
             }
             // More window methods
             "window.requestAnimationFrame" | "window.cancelAnimationFrame" => {
                 Ok(JsValue::Number(1.0))
             }
             "window.getComputedStyle" => {
-                // Return a stub style object
                 let mut style = JsObject::new();
+                for (property, value) in self.computed_style_of(args.first()) {
+                    style.set(&property, JsValue::String(value));
+                }
                 style.set("getPropertyValue", JsValue::NativeFunction("style.getPropertyValue".to_string()));
                 Ok(JsValue::Object(Rc::new(RefCell::new(style))))
             }
+            "style.getPropertyValue" => {
+                // Unset properties read as the empty string, never undefined
+                let name = args.first().map(|a| self.js_value_to_string(a)).unwrap_or_default();
+                let value = match this {
+                    JsValue::Object(style) => style.borrow().get_property(&name.to_ascii_lowercase()).cloned(),
+                    _ => None,
+                };
+                Ok(match value {
+                    Some(JsValue::String(s)) => JsValue::String(s),
+                    _ => JsValue::String(String::new()),
+                })
+            }
             "window.matchMedia" => {
                 // Return a stub MediaQueryList
                 let mut mql = JsObject::new();
@@ -1044,9 +1399,58 @@ impl Runtime {
                 mql.set("removeEventListener", JsValue::NativeFunction("mediaQueryList.removeEventListener".to_string()));
                 Ok(JsValue::Object(Rc::new(RefCell::new(mql))))
             }
-            "style.getPropertyValue" | "mediaQueryList.addEventListener" | "mediaQueryList.removeEventListener" => {
+            "mediaQueryList.addEventListener" | "mediaQueryList.removeEventListener" => {
+                Ok(JsValue::Undefined)
+            }
+            "String" => {
+                Ok(JsValue::String(args.first().map(|a| self.js_value_to_string(a)).unwrap_or_default()))
+            }
+            // Called without `new`, Date ignores its arguments and gives the current time as a string
+            "Date" => Ok(JsValue::String(date::to_date_string(date::now_ms()))),
+            "Date.now" => Ok(JsValue::Number(date::now_ms())),
+            _ if name.starts_with("date.") => Self::call_date_method(name, this),
+            // Error constructors (callable with or without `new`)
+            "Error" | "TypeError" | "RangeError" | "SyntaxError" | "ReferenceError" => {
+                let message = args.first()
+                    .filter(|a| !matches!(a, JsValue::Undefined))
+                    .map(|a| self.js_value_to_string(a))
+                    .unwrap_or_default();
+                Ok(Self::create_error_object(name, &message))
+            }
+            "fetch" => self.fetch(args),
+            "response.text" | "response.json" => {
+                let body = match this {
+                    JsValue::Object(response) => match response.borrow().get_property("__body") {
+                        Some(JsValue::String(body)) => body.clone(),
+                        _ => String::new(),
+                    },
+                    _ => return Err(Self::type_error("Response method called on a non-object")),
+                };
+                if name == "response.json" {
+                    Self::parse_json(&body)
+                } else {
+                    Ok(JsValue::String(body))
+                }
+            }
+            _ if name.starts_with("localStorage.") || name.starts_with("sessionStorage.") => {
+                self.call_storage_method(name, this, args)
+            }
+            "XMLHttpRequest" => Ok(Self::create_xhr_object()),
+            "xhr.open" => {
+                if let JsValue::Object(xhr) = this {
+                    let method = args.first().map(|a| self.js_value_to_string(a)).unwrap_or_else(|| "GET".to_string());
+                    let url = args.get(1).map(|a| self.js_value_to_string(a)).unwrap_or_default();
+                    let mut xhr = xhr.borrow_mut();
+                    xhr.set("__method", JsValue::String(method.to_ascii_uppercase()));
+                    xhr.set("__url", JsValue::String(url));
+                    xhr.set("readyState", JsValue::Number(1.0));
+                }
                 Ok(JsValue::Undefined)
             }
+            "xhr.send" => self.xhr_send(this, args),
+            // Request headers aren't forwarded yet
+            "xhr.setRequestHeader" | "xhr.abort" => Ok(JsValue::Undefined),
+            "xhr.getResponseHeader" => Ok(JsValue::Null),
             // Array methods
             "Array.isArray" => {
                 if let Some(val) = args.first() {
@@ -1059,20 +1463,34 @@ impl Runtime {
                 }
             }
             // Object methods
-            "Object.keys" => {
-                if let Some(JsValue::Object(obj)) = args.first() {
-                    let keys: Vec<String> = obj.borrow().keys().cloned().collect();
-                    let mut arr = JsObject::new();
-                    for (i, key) in keys.iter().enumerate() {
-                        arr.set(&i.to_string(), JsValue::String(key.clone()));
+            "Object.keys" | "Object.values" | "Object.entries" => {
+                let entries = match args.first() {
+                    Some(JsValue::Object(obj)) => Self::own_entries(&obj.borrow()),
+                    _ => Vec::new(),
+                };
+                let items = entries.into_iter().map(|(key, value)| match name {
+                    "Object.keys" => JsValue::String(key),
+                    "Object.values" => value,
+                    _ => Self::create_array(vec![JsValue::String(key), value]),
+                }).collect();
+                Ok(Self::create_array(items))
+            }
+            "Object.assign" => {
+                let target = match args.first() {
+                    Some(JsValue::Object(obj)) => Rc::clone(obj),
+                    _ => return Err(Self::type_error("Object.assign target must be an object")),
+                };
+                for source in &args[1..] {
+                    if let JsValue::Object(src) = source {
+                        // Collect first: source and target may be the same object
+                        let entries = Self::own_entries(&src.borrow());
+                        let mut target_ref = target.borrow_mut();
+                        for (key, value) in entries {
+                            target_ref.set_property(key, value);
+                        }
                     }
-                    arr.set("length", JsValue::Number(keys.len() as f64));
-                    Ok(JsValue::Object(Rc::new(RefCell::new(arr))))
-                } else {
-                    let mut arr = JsObject::new();
-                    arr.set("length", JsValue::Number(0.0));
-                    Ok(JsValue::Object(Rc::new(RefCell::new(arr))))
                 }
+                Ok(JsValue::Object(target))
             }
             _ => {
                 log::warn!(target: "javascript", "Unknown native function: {}", name);
@@ -1082,48 +1500,494 @@ impl Runtime {
     }
     
     fn call_function(&mut self, func: &JsUserFunction, args: &[JsValue]) -> Result<JsValue, Box<dyn Error>> {
-        const MAX_CALL_DEPTH: usize = 1000; // Prevent infinite recursion
-        
-        if self.execution_depth >= MAX_CALL_DEPTH {
+        self.call_method(func, &JsValue::Undefined, args)
+    }
+
+    // Call `func` with `this` bound to `this`; undefined means a plain call
+    fn call_method(&mut self, func: &JsUserFunction, this: &JsValue, args: &[JsValue]) -> Result<JsValue, Box<dyn Error>> {
+        self.check_deadline()?;
+        if self.execution_depth >= self.max_execution_depth {
             log::warn!(target: "javascript", "Maximum call depth exceeded, preventing infinite recursion");
             return Ok(JsValue::Undefined);
         }
         
         self.execution_depth += 1;
         
-        // Create a new scope for the function
-        let mut new_scope = Scope::new(None);
-        
-        // Bind parameters to arguments
-        for (i, param) in func.params.iter().enumerate() {
-            let arg_value = args.get(i).cloned().unwrap_or(JsValue::Undefined);
-            new_scope.variables.insert(param.clone(), arg_value);
-        }
-        
-        // Push the scope onto the call stack
-        self.call_stack.push(new_scope);
+        // Push a fresh scope for the function; parameters are bound inside it so
+        // default values can refer to earlier parameters
+        let mut scope = Scope::new(None);
+        // Always bound, so a plain call doesn't see its caller's `this`
+        scope.variables.insert("this".to_string(), this.clone());
+        self.call_stack.push(scope);
         
         // Execute the function body
-        let mut result = JsValue::Undefined;
-        for stmt in &func.body {
-            result = self.evaluate_node(stmt)?;
-            // TODO: Handle early return statements properly
+        let mut result = self.bind_parameters(&func.params, args).map(|_| JsValue::Undefined);
+        if result.is_ok() {
+            for stmt in &func.body {
+                result = self.evaluate_node(stmt);
+                // TODO: Handle early return statements properly
+                if result.is_err() {
+                    // Stop at the first error; a thrown exception may still be caught by the caller
+                    break;
+                }
+            }
         }
         
-        // Pop the scope
+        // Pop the scope (also on error, so a caught exception doesn't leak the frame)
         self.call_stack.pop();
         
         self.execution_depth -= 1;
         
+        result
+    }
+    
+    // Bind call arguments into the innermost scope (the one pushed by call_function)
+    fn bind_parameters(&mut self, params: &[Param], args: &[JsValue]) -> Result<(), Box<dyn Error>> {
+        for (i, param) in params.iter().enumerate() {
+            let value = if param.rest {
+                // Rest parameter collects everything that's left into an array
+                Self::create_array(args.iter().skip(i).cloned().collect())
+            } else {
+                let arg = args.get(i).cloned().unwrap_or(JsValue::Undefined);
+                self.apply_pattern_default(arg, param.default.as_deref())?
+            };
+            
+            if let Some(scope) = self.call_stack.last_mut() {
+                scope.variables.insert(param.name.clone(), value);
+            }
+            if param.rest {
+                break;
+            }
+        }
+        Ok(())
+    }
+    
+    // Own enumerable properties as (key, value) pairs; an array's length isn't enumerable
+    fn own_entries(obj: &JsObject) -> Vec<(String, JsValue)> {
+        let is_array = obj.is_array();
+        obj.keys()
+            .filter(|key| !(is_array && key.as_str() == "length"))
+            .filter_map(|key| obj.get_property(key).map(|value| (key.clone(), value.clone())))
+            .collect()
+    }
+    
+    // JSON text for `value`, or None for values JSON can't represent (undefined,
+    // functions). `seen` holds the objects being serialized, to reject cycles.
+    fn json_stringify(&self, value: &JsValue, seen: &mut Vec<Rc<RefCell<JsObject>>>) -> Result<Option<String>, Box<dyn Error>> {
+        let obj = match value {
+            JsValue::Undefined | JsValue::Function(_) | JsValue::NativeFunction(_) => return Ok(None),
+            JsValue::Null => return Ok(Some("null".to_string())),
+            JsValue::Boolean(b) => return Ok(Some(b.to_string())),
+            JsValue::Number(n) if n.is_finite() => return Ok(Some(number_to_string(*n))),
+            JsValue::Number(_) => return Ok(Some("null".to_string())),
+            JsValue::String(s) => return Ok(Some(Self::json_quote(s))),
+            JsValue::Object(obj) => obj,
+        };
+        if seen.iter().any(|o| Rc::ptr_eq(o, obj)) {
+            return Err(Self::type_error("Converting circular structure to JSON"));
+        }
+        seen.push(Rc::clone(obj));
+
+        let is_array = obj.borrow().is_array();
+        let entries = Self::own_entries(&obj.borrow());
+        let mut parts = Vec::new();
+        for (key, item) in entries {
+            let json = self.json_stringify(&item, seen)?;
+            if is_array {
+                // Holes and unrepresentable elements become null
+                parts.push(json.unwrap_or_else(|| "null".to_string()));
+            } else if let Some(json) = json {
+                parts.push(format!("{}:{}", Self::json_quote(&key), json));
+            }
+        }
+        seen.pop();
+
+        Ok(Some(if is_array {
+            format!("[{}]", parts.join(","))
+        } else {
+            format!("{{{}}}", parts.join(","))
+        }))
+    }
+
+    fn json_quote(s: &str) -> String {
+        let mut quoted = String::with_capacity(s.len() + 2);
+        quoted.push('"');
+        for c in s.chars() {
+            match c {
+                '"' => quoted.push_str("\\\""),
+                '\\' => quoted.push_str("\\\\"),
+                '\n' => quoted.push_str("\\n"),
+                '\r' => quoted.push_str("\\r"),
+                '\t' => quoted.push_str("\\t"),
+                c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+                c => quoted.push(c),
+            }
+        }
+        quoted.push('"');
+        quoted
+    }
+
+    /// Evaluate array elements or call arguments, expanding `...spread` entries in place.
+    fn evaluate_elements(&mut self, nodes: &[Node]) -> Result<Vec<JsValue>, Box<dyn Error>> {
+        let mut values = Vec::with_capacity(nodes.len());
+        for node in nodes {
+            match node {
+                Node::Spread(inner) => {
+                    let spread = self.evaluate_node(inner)?;
+                    values.extend(Self::iterate_values(&spread)?);
+                }
+                _ => values.push(self.evaluate_node(node)?),
+            }
+        }
+        Ok(values)
+    }
+
+    // Elements of an array-like or string, for spreading
+    fn iterate_values(value: &JsValue) -> Result<Vec<JsValue>, Box<dyn Error>> {
+        match value {
+            JsValue::String(s) => Ok(s.chars().map(|c| JsValue::String(c.to_string())).collect()),
+            JsValue::Object(obj) => {
+                let obj = obj.borrow();
+                let len = obj.get_length().ok_or_else(|| Self::type_error("object is not iterable"))?;
+                // A forged `length` must not drive an enormous allocation
+                const MAX_SPREAD_LENGTH: usize = 1 << 20;
+                if len > MAX_SPREAD_LENGTH {
+                    return Err(Self::type_error("array-like is too long to spread"));
+                }
+                Ok((0..len)
+                    .map(|i| obj.get_element(i).cloned().unwrap_or(JsValue::Undefined))
+                    .collect())
+            }
+            _ => Err(Self::type_error("value is not iterable")),
+        }
+    }
+
+    fn create_array(values: Vec<JsValue>) -> JsValue {
+        let mut arr = JsObject::new_array();
+        let len = values.len();
+        for (i, value) in values.into_iter().enumerate() {
+            arr.set_property(i.to_string(), value);
+        }
+        arr.set("length", JsValue::Number(len as f64));
+        JsValue::Object(Rc::new(RefCell::new(arr)))
+    }
+    
+    /// Like `bind_pattern`, but declares the names in the current scope so they
+    /// shadow outer bindings and end with the block that declared them.
+    fn bind_lexical_pattern(&mut self, pattern: &Pattern, value: JsValue) -> Result<(), Box<dyn Error>> {
+        let mut names = Vec::new();
+        Self::pattern_names(pattern, &mut names);
+        let scope = self.call_stack.last_mut().unwrap_or(&mut self.global_scope);
+        for name in names {
+            scope.variables.insert(name.clone(), JsValue::Undefined);
+            scope.lexical.insert(name);
+        }
+        self.bind_pattern(pattern, value)
+    }
+    
+    fn pattern_names(pattern: &Pattern, names: &mut Vec<String>) {
+        match pattern {
+            Pattern::Identifier(name) => names.push(name.clone()),
+            Pattern::Object(properties) => {
+                for (_, target, _) in properties {
+                    Self::pattern_names(target, names);
+                }
+            }
+            Pattern::Array(elements) => {
+                for (target, _) in elements.iter().flatten() {
+                    Self::pattern_names(target, names);
+                }
+            }
+        }
+    }
+    
+    /// Bind the names in a declaration pattern, destructuring `value` as needed.
+    fn bind_pattern(&mut self, pattern: &Pattern, value: JsValue) -> Result<(), Box<dyn Error>> {
+        match pattern {
+            Pattern::Identifier(name) => self.set_variable(name, value),
+            Pattern::Object(properties) => {
+                if matches!(value, JsValue::Undefined | JsValue::Null) {
+                    return Err(Self::type_error(&format!("Cannot destructure '{}'", self.js_value_to_string(&value))));
+                }
+                for (key, target, default) in properties {
+                    let prop = self.get_property(&value, &JsValue::String(key.clone()))?;
+                    let prop = self.apply_pattern_default(prop, default.as_deref())?;
+                    self.bind_pattern(target, prop)?;
+                }
+                Ok(())
+            }
+            Pattern::Array(elements) => {
+                if matches!(value, JsValue::Undefined | JsValue::Null) {
+                    return Err(Self::type_error(&format!("{} is not iterable", self.js_value_to_string(&value))));
+                }
+                for (i, element) in elements.iter().enumerate() {
+                    let Some((target, default)) = element else { continue };
+                    let item = self.get_property(&value, &JsValue::Number(i as f64))?;
+                    let item = self.apply_pattern_default(item, default.as_deref())?;
+                    self.bind_pattern(target, item)?;
+                }
+                Ok(())
+            }
+        }
+    }
+    
+    // Defaults only kick in for `undefined`, not for other falsy values
+    fn apply_pattern_default(&mut self, value: JsValue, default: Option<&Node>) -> Result<JsValue, Box<dyn Error>> {
+        match (value, default) {
+            (JsValue::Undefined, Some(default)) => self.evaluate_node(default),
+            (value, _) => Ok(value),
+        }
+    }
+    
+    fn evaluate_catch_block(&mut self, param: Option<&str>, thrown: JsValue, body: &Node) -> Result<JsValue, Box<dyn Error>> {
+        let mut catch_scope = Scope::new(None);
+        if let Some(name) = param {
+            catch_scope.variables.insert(name.to_string(), thrown);
+        }
+        
+        self.call_stack.push(catch_scope);
+        let result = self.evaluate_node(body);
+        
+        // The catch parameter and `let`/`const` names are block-scoped; anything
+        // else declared in the block (e.g. with `var`) belongs to the enclosing scope
+        if let Some(mut scope) = self.call_stack.pop() {
+            if let Some(name) = param {
+                scope.variables.remove(name);
+            }
+            for (name, value) in scope.variables {
+                if !scope.lexical.contains(&name) {
+                    self.set_variable(&name, value)?;
+                }
+            }
+        }
+        
+        result
+    }
+    
+    fn create_error_object(name: &str, message: &str) -> JsValue {
+        let mut error_obj = JsObject::new();
+        error_obj.set("name", JsValue::String(name.to_string()));
+        error_obj.set("message", JsValue::String(message.to_string()));
+        JsValue::Object(Rc::new(RefCell::new(error_obj)))
+    }
+    
+    // Time value for `new Date(...)`: now, a timestamp, or UTC calendar fields
+    fn date_time_from_args(&self, args: &[JsValue]) -> f64 {
+        let number = |i: usize, default: f64| match args.get(i) {
+            Some(JsValue::Number(n)) => *n,
+            Some(other) => Self::string_to_number(&self.js_value_to_string(other)),
+            None => default,
+        };
+        match args.len() {
+            0 => date::now_ms(),
+            1 => number(0, f64::NAN),
+            _ => date::ms_from_parts(
+                number(0, f64::NAN),
+                number(1, 0.0),
+                number(2, 1.0),
+                number(3, 0.0),
+                number(4, 0.0),
+                number(5, 0.0),
+                number(6, 0.0),
+            ),
+        }
+    }
+
+    fn create_date_object(time: f64) -> JsValue {
+        let mut date_obj = JsObject::new();
+        date_obj.set_hidden("__time", JsValue::Number(time));
+        for method in [
+            "getTime", "valueOf", "getFullYear", "getMonth", "getDate", "getHours",
+            "getMinutes", "getSeconds", "getMilliseconds", "toISOString", "toString",
+        ] {
+            date_obj.set_hidden(method, JsValue::NativeFunction(format!("date.{}", method)));
+        }
+        JsValue::Object(Rc::new(RefCell::new(date_obj)))
+    }
+
+    fn call_date_method(name: &str, this: &JsValue) -> Result<JsValue, Box<dyn Error>> {
+        let time = match this {
+            JsValue::Object(obj) => match obj.borrow().get_property("__time") {
+                Some(JsValue::Number(t)) => *t,
+                _ => return Err(Self::type_error("this is not a Date object")),
+            },
+            _ => return Err(Self::type_error("this is not a Date object")),
+        };
+        let method = &name["date.".len()..];
+        if method == "getTime" || method == "valueOf" {
+            return Ok(JsValue::Number(time));
+        }
+        if method == "toString" {
+            return Ok(JsValue::String(date::to_date_string(time)));
+        }
+        if method == "toISOString" {
+            return date::to_iso_string(time)
+                .map(JsValue::String)
+                .ok_or_else(|| Box::new(JsException::new(Self::create_error_object("RangeError", "Invalid time value"))) as Box<dyn Error>);
+        }
+
+        // Every remaining getter reads one calendar field; invalid dates give NaN
+        let Some(parts) = date::parts_from_ms(time) else {
+            return Ok(JsValue::Number(f64::NAN));
+        };
+        let field = match method {
+            "getFullYear" => parts.year as f64,
+            "getMonth" => parts.month as f64,
+            "getDate" => parts.day as f64,
+            "getHours" => parts.hours as f64,
+            "getMinutes" => parts.minutes as f64,
+            "getSeconds" => parts.seconds as f64,
+            "getMilliseconds" => parts.millis as f64,
+            _ => return Ok(JsValue::Undefined),
+        };
+        Ok(JsValue::Number(field))
+    }
+
+    /// A catchable TypeError raised by the runtime itself.
+    fn type_error(message: &str) -> Box<dyn Error> {
+        Box::new(JsException::new(Self::create_error_object("TypeError", message)))
+    }
+    
+    // getItem/setItem/... on localStorage or sessionStorage. `length` is a plain
+    // property, refreshed on `this` after every call.
+    fn call_storage_method(&mut self, name: &str, this: &JsValue, args: &[JsValue]) -> Result<JsValue, Box<dyn Error>> {
+        let (store, method) = name.split_once('.').unwrap_or_default();
+        let arg = |index: usize| args.get(index).map(|a| self.js_value_to_string(a)).unwrap_or_else(|| "undefined".to_string());
+        let (key, value) = (arg(0), arg(1));
+        let storage = if store == "localStorage" { &mut self.local_storage } else { &mut self.session_storage };
+        let result = match method {
+            "getItem" => storage.get(&key).map_or(JsValue::Null, |v| JsValue::String(v.to_string())),
+            "key" => {
+                let index = Self::string_to_number(&key);
+                storage.key(index as usize).map_or(JsValue::Null, |k| JsValue::String(k.to_string()))
+            }
+            "setItem" => {
+                storage.set(key, value);
+                JsValue::Undefined
+            }
+            "removeItem" => {
+                storage.remove(&key);
+                JsValue::Undefined
+            }
+            _ => {
+                storage.clear();
+                JsValue::Undefined
+            }
+        };
+        if let JsValue::Object(obj) = this {
+            obj.borrow_mut().set("length", JsValue::Number(storage.len() as f64));
+        }
         Ok(result)
     }
+
+    fn parse_json(text: &str) -> Result<JsValue, Box<dyn Error>> {
+        json::parse(text).map_err(|message| -> Box<dyn Error> {
+            Box::new(JsException::new(Self::create_error_object("SyntaxError", &message)))
+        })
+    }
+
+    // fetch(url, {method, body}) resolved on the spot: the Response comes back
+    // directly rather than through a promise. Network failures throw a TypeError.
+    fn fetch(&mut self, args: &[JsValue]) -> Result<JsValue, Box<dyn Error>> {
+        let url = args.first().map(|a| self.js_value_to_string(a)).unwrap_or_default();
+        let (method, body) = match args.get(1) {
+            Some(JsValue::Object(init)) => {
+                let init = init.borrow();
+                let method = init.get_property("method").cloned();
+                let body = init.get_property("body").cloned();
+                (method, body)
+            }
+            _ => (None, None),
+        };
+        let method = method.map(|m| self.js_value_to_string(&m).to_ascii_uppercase()).unwrap_or_else(|| "GET".to_string());
+        let body = body
+            .filter(|body| !matches!(body, JsValue::Undefined | JsValue::Null))
+            .map(|body| self.js_value_to_string(&body));
+
+        let result = match &self.fetcher {
+            Some(fetcher) => fetcher(&method, &url, body.as_deref()),
+            None => Err("no network access".to_string()),
+        };
+        let (status, text) = result.map_err(|e| {
+            log::debug!(target: "javascript", "fetch {} {} failed: {}", method, url, e);
+            Self::type_error("Failed to fetch")
+        })?;
+
+        let mut response = JsObject::new();
+        response.set("status", JsValue::Number(f64::from(status)));
+        response.set("ok", JsValue::Boolean((200..300).contains(&status)));
+        response.set("url", JsValue::String(url));
+        response.set("__body", JsValue::String(text));
+        response.set("text", JsValue::NativeFunction("response.text".to_string()));
+        response.set("json", JsValue::NativeFunction("response.json".to_string()));
+        Ok(JsValue::Object(Rc::new(RefCell::new(response))))
+    }
+
+    fn create_xhr_object() -> JsValue {
+        let mut xhr = JsObject::new();
+        xhr.set("readyState", JsValue::Number(0.0));
+        xhr.set("status", JsValue::Number(0.0));
+        xhr.set("responseText", JsValue::String(String::new()));
+        for method in ["open", "send", "setRequestHeader", "abort", "getResponseHeader"] {
+            xhr.set(method, JsValue::NativeFunction(format!("xhr.{}", method)));
+        }
+        for handler in ["onload", "onerror", "onreadystatechange"] {
+            xhr.set(handler, JsValue::Null);
+        }
+        JsValue::Object(Rc::new(RefCell::new(xhr)))
+    }
+
+    // Run the request set up by `open` to completion, then call the handlers the
+    // script assigned, with the request as `this`
+    fn xhr_send(&mut self, this: &JsValue, args: &[JsValue]) -> Result<JsValue, Box<dyn Error>> {
+        let JsValue::Object(xhr) = this else {
+            return Err(Self::type_error("XMLHttpRequest.send called on a non-object"));
+        };
+        let (method, url) = {
+            let xhr = xhr.borrow();
+            match (xhr.get_property("__method"), xhr.get_property("__url")) {
+                (Some(JsValue::String(method)), Some(JsValue::String(url))) => (method.clone(), url.clone()),
+                _ => return Err(Self::type_error("XMLHttpRequest.send called before open")),
+            }
+        };
+        let body = args
+            .first()
+            .filter(|body| !matches!(body, JsValue::Undefined | JsValue::Null))
+            .map(|body| self.js_value_to_string(body));
+
+        let result = match &self.fetcher {
+            Some(fetcher) => fetcher(&method, &url, body.as_deref()),
+            None => Err("no network access".to_string()),
+        };
+        let handler = match result {
+            Ok((status, text)) => {
+                let mut xhr = xhr.borrow_mut();
+                xhr.set("status", JsValue::Number(f64::from(status)));
+                xhr.set("responseText", JsValue::String(text));
+                "onload"
+            }
+            Err(e) => {
+                log::debug!(target: "javascript", "XMLHttpRequest {} {} failed: {}", method, url, e);
+                "onerror"
+            }
+        };
+        xhr.borrow_mut().set("readyState", JsValue::Number(4.0));
+
+        for name in ["onreadystatechange", handler] {
+            let callback = xhr.borrow().get_property(name).cloned();
+            if let Some(JsValue::Function(func)) = callback {
+                self.call_method(&func, this, &[])?;
+            }
+        }
+        Ok(JsValue::Undefined)
+    }
     
     fn js_value_to_string(&self, value: &JsValue) -> String {
         match value {
             JsValue::Undefined => "undefined".to_string(),
             JsValue::Null => "null".to_string(),
             JsValue::Boolean(b) => b.to_string(),
-            JsValue::Number(n) => n.to_string(),
+            JsValue::Number(n) => number_to_string(*n),
             JsValue::String(s) => s.clone(),
             JsValue::Object(_) => "[object Object]".to_string(),
             JsValue::Function(_) => "[function]".to_string(),
@@ -1165,12 +2029,18 @@ impl Runtime {
                     _ => Ok(JsValue::Number(f64::NAN)),
                 }
             }
-            BinaryOperator::Equal | BinaryOperator::StrictEqual => {
+            BinaryOperator::Equal => {
                 Ok(JsValue::Boolean(self.js_equals(left, right)))
             }
-            BinaryOperator::NotEqual | BinaryOperator::StrictNotEqual => {
+            BinaryOperator::StrictEqual => {
+                Ok(JsValue::Boolean(Self::js_strict_equals(left, right)))
+            }
+            BinaryOperator::NotEqual => {
                 Ok(JsValue::Boolean(!self.js_equals(left, right)))
             }
+            BinaryOperator::StrictNotEqual => {
+                Ok(JsValue::Boolean(!Self::js_strict_equals(left, right)))
+            }
             BinaryOperator::LessThan => {
                 match (left, right) {
                     (JsValue::Number(a), JsValue::Number(b)) => Ok(JsValue::Boolean(a < b)),
@@ -1214,10 +2084,20 @@ impl Runtime {
                 }
             }
             BinaryOperator::Instanceof => {
-                // Simplified instanceof - check if left is object and right is constructor
+                // True when some prototype on the object's chain was made by the constructor
                 match (left, right) {
-                    (JsValue::Object(_), JsValue::Function(_)) => Ok(JsValue::Boolean(true)),
-                    (JsValue::Object(_), _) => Ok(JsValue::Boolean(false)),
+                    (JsValue::Object(obj), JsValue::Function(constructor)) => {
+                        let obj = obj.borrow();
+                        let mut prototype = obj.prototype();
+                        while let Some(proto) = prototype {
+                            if matches!(proto.get_own_property("constructor"), Some(JsValue::Function(f)) if Rc::ptr_eq(f, constructor)) {
+                                return Ok(JsValue::Boolean(true));
+                            }
+                            prototype = proto.prototype();
+                        }
+                        Ok(JsValue::Boolean(false))
+                    }
+                    // Built-ins such as Array are plain objects here, so they never match
                     _ => Ok(JsValue::Boolean(false)),
                 }
             }
@@ -1228,24 +2108,101 @@ impl Runtime {
                         let prop_name = self.js_value_to_string(prop);
                         Ok(JsValue::Boolean(obj.borrow().get_property(&prop_name).is_some()))
                     }
-                    _ => Ok(JsValue::Boolean(false)),
+                    _ => Err(Self::type_error("Cannot use 'in' operator to search for a key in a non-object")),
                 }
             }
         }
     }
     
+    // `delete operand`: removes an own property of a member expression's object.
+    // Variables can't be deleted; anything else is evaluated and reports success.
+    fn delete_operand(&mut self, operand: &Node) -> Result<JsValue, Box<dyn Error>> {
+        match operand {
+            Node::MemberExpr { object, property, computed } => {
+                let obj = self.evaluate_node(object)?;
+                let prop_name = match (&**property, *computed) {
+                    (Node::Identifier(name), false) => name.clone(),
+                    _ => {
+                        let prop = self.evaluate_node(property)?;
+                        self.js_value_to_string(&prop)
+                    }
+                };
+                match obj {
+                    JsValue::Object(obj_ref) => {
+                        obj_ref.borrow_mut().remove_property(&prop_name);
+                        Ok(JsValue::Boolean(true))
+                    }
+                    JsValue::Undefined | JsValue::Null => Err(Self::type_error(&format!(
+                        "Cannot delete property '{}' of {}",
+                        prop_name,
+                        self.js_value_to_string(&obj)
+                    ))),
+                    _ => Ok(JsValue::Boolean(true)),
+                }
+            }
+            Node::Identifier(_) => Ok(JsValue::Boolean(false)),
+            _ => {
+                self.evaluate_node(operand)?;
+                Ok(JsValue::Boolean(true))
+            }
+        }
+    }
+
+    // Loose equality (==) with JavaScript's type coercion rules
     fn js_equals(&self, left: &JsValue, right: &JsValue) -> bool {
+        match (left, right) {
+            (JsValue::Undefined | JsValue::Null, JsValue::Undefined | JsValue::Null) => true,
+            (JsValue::Undefined | JsValue::Null, _) | (_, JsValue::Undefined | JsValue::Null) => false,
+            (JsValue::Number(a), JsValue::String(b)) => *a == Self::string_to_number(b),
+            (JsValue::String(a), JsValue::Number(b)) => Self::string_to_number(a) == *b,
+            // Booleans compare as numbers (true == 1, false == "0")
+            (JsValue::Boolean(b), other) => self.js_equals(&JsValue::Number(if *b { 1.0 } else { 0.0 }), other),
+            (other, JsValue::Boolean(b)) => self.js_equals(other, &JsValue::Number(if *b { 1.0 } else { 0.0 })),
+            // An object against a primitive compares by its string conversion
+            (JsValue::Object(_), JsValue::Number(_) | JsValue::String(_)) => {
+                self.js_equals(&JsValue::String(self.js_value_to_string(left)), right)
+            }
+            (JsValue::Number(_) | JsValue::String(_), JsValue::Object(_)) => {
+                self.js_equals(left, &JsValue::String(self.js_value_to_string(right)))
+            }
+            _ => Self::js_strict_equals(left, right),
+        }
+    }
+    
+    // Strict equality (===): no coercion, objects and functions compare by identity
+    fn js_strict_equals(left: &JsValue, right: &JsValue) -> bool {
         match (left, right) {
             (JsValue::Undefined, JsValue::Undefined) => true,
             (JsValue::Null, JsValue::Null) => true,
-            (JsValue::Undefined, JsValue::Null) | (JsValue::Null, JsValue::Undefined) => true,
             (JsValue::Boolean(a), JsValue::Boolean(b)) => a == b,
+            // NaN is never equal to itself; 0 and -0 are equal
             (JsValue::Number(a), JsValue::Number(b)) => a == b,
             (JsValue::String(a), JsValue::String(b)) => a == b,
+            (JsValue::Object(a), JsValue::Object(b)) => Rc::ptr_eq(a, b),
+            (JsValue::Function(a), JsValue::Function(b)) => Rc::ptr_eq(a, b),
+            (JsValue::NativeFunction(a), JsValue::NativeFunction(b)) => a == b,
             _ => false,
         }
     }
     
+    // ToNumber for strings: surrounding whitespace is ignored and "" is 0
+    fn string_to_number(s: &str) -> f64 {
+        let trimmed = s.trim();
+        if trimmed.is_empty() {
+            return 0.0;
+        }
+        match trimmed {
+            "Infinity" | "+Infinity" => f64::INFINITY,
+            "-Infinity" => f64::NEG_INFINITY,
+            _ if trimmed.starts_with("0x") || trimmed.starts_with("0X") => {
+                u64::from_str_radix(&trimmed[2..], 16).map(|n| n as f64).unwrap_or(f64::NAN)
+            }
+            // Rust accepts "inf"/"nan" spellings that JavaScript doesn't
+            _ if trimmed.chars().any(|c| c.is_ascii_alphabetic() && c != 'e' && c != 'E') => f64::NAN,
+            _ => trimmed.parse::<f64>().unwrap_or(f64::NAN),
+        }
+    }
+    
     fn is_truthy(&self, value: &JsValue) -> bool {
         match value {
             JsValue::Undefined | JsValue::Null => false,
@@ -1282,6 +2239,17 @@ impl Runtime {
         self.global_scope.variables.get(name).cloned()
     }
 
+    // Whether `name` resolves to anything, as a variable or a window property
+    fn is_declared(&self, name: &str) -> bool {
+        if self.get_variable(name).is_some() {
+            return true;
+        }
+        match self.get_variable("window") {
+            Some(JsValue::Object(window)) => window.borrow().get_property(name).is_some(),
+            _ => false,
+        }
+    }
+
     fn find_scope_with_variable(&mut self, name: &str) -> Option<&mut Scope> {
         for scope in self.call_stack.iter_mut().rev() {
             if scope.variables.contains_key(name) {
@@ -1312,55 +2280,34 @@ impl Runtime {
                 JsValue::Object(obj_ref) => {
                     let prop_name = match prop {
                         JsValue::String(s) => s.clone(),
-                        JsValue::Number(n) => n.to_string(),
+                        JsValue::Number(n) => number_to_string(*n),
                         _ => {
                             self.property_access_depth -= 1;
                             return Ok(()); // Silently ignore invalid property keys
                         }
                     };
-                
-                // Check if this is a DOM element with innerHTML or textContent
-                if prop_name == "innerHTML" || prop_name == "textContent" {
-                    log::info!(target: "javascript", "Setting property '{}' on element object", prop_name);
-                    // Try to find the DOM node reference
-                    let obj_borrow = obj_ref.borrow();
-                    if let Some(id) = obj_borrow.get_property("id") {
-                        if let JsValue::String(id_str) = id.clone() {
-                            log::info!(target: "javascript", "Element has id: '{}', attempting to modify DOM", id_str);
-                            // Find and modify the element in the shared DOM
-                            if let Some(root) = &self.dom_root {
-                                let new_value = match &value {
-                                    JsValue::String(s) => s.clone(),
-                                    _ => self.js_value_to_string(&value),
-                                };
-                                
-                                log::info!(target: "javascript", "Searching for element '{}' in shared DOM to set '{}' to '{}'", 
-                                    id_str, prop_name, &new_value[..new_value.len().min(100)]);
-                                
-                                if let Some(node) = root.borrow_mut().find_and_modify_child_by_id(&id_str) {
-                                    log::info!(target: "javascript", "Found element '{}', modifying...", id_str);
-                                    if prop_name == "innerHTML" {
-                                        node.set_inner_html(&new_value);
-                                    } else {
-                                        // textContent
-                                        node.set_text_content(&new_value);
-                                    }
-                                    log::info!(target: "javascript", "Successfully modified element '{}' property '{}' to '{}'", 
-                                        id_str, prop_name, &new_value[..new_value.len().min(50)]);
-                                } else {
-                                    log::warn!(target: "javascript", "Could not find element with id '{}' for modification", id_str);
-                                }
-                            } else {
-                                log::warn!(target: "javascript", "No DOM root bound to runtime");
-                            }
-                        } else {
-                            log::warn!(target: "javascript", "Element object has no valid id property: {:?}", id);
+
+                    // Writes to innerHTML and textContent of an element replace its children
+                    if (prop_name == "innerHTML" || prop_name == "textContent")
+                        && let Some(handle) = Self::node_handle(obj, NODE_SLOT)
+                    {
+                        let new_value = self.js_value_to_string(&value);
+                        match self.dom_root.as_ref().map(|root| root.borrow_mut()) {
+                            Some(mut root) => match root.find_by_node_id_mut(handle) {
+                                Some(node) if prop_name == "innerHTML" => node.set_inner_html(&new_value),
+                                Some(node) => node.set_text_content(&new_value),
+                                None => log::warn!(target: "javascript", "Element for '{}' is no longer in the DOM", prop_name),
+                            },
+                            None => log::warn!(target: "javascript", "No DOM root bound to runtime"),
                         }
-                    } else {
-                        log::warn!(target: "javascript", "Element object has no id property");
                     }
-                }
-                
+
+                    // Writes to element.style go to the element's inline style attribute
+                    if let Some(handle) = Self::node_handle(obj, STYLE_NODE_SLOT) {
+                        let css_value = self.js_value_to_string(&value);
+                        self.set_inline_style(handle, &prop_name, &css_value);
+                    }
+
                     // Always update the JS object property
                     obj_ref.borrow_mut().set_property(prop_name, value);
                     Ok(())
@@ -1377,17 +2324,27 @@ impl Runtime {
         result
     }
     
-    fn set_dom_text_content(&self, node: &mut DomNode, text: &str) {
-        // Use the DOM node's method to set text content
-        node.set_text_content(text);
-        log::trace!(target: "javascript", "Set text content to: {}", text);
+    // `property` is the camelCase name scripts use, e.g. backgroundColor
+    fn set_inline_style(&self, handle: usize, property: &str, value: &str) {
+        let Some(root) = &self.dom_root else {
+            return;
+        };
+        let mut root = root.borrow_mut();
+        if let Some(node) = root.find_by_node_id_mut(handle) {
+            node.set_inline_style_property(&css_property_name(property), value);
+        }
     }
-    
-    fn set_dom_inner_html(&self, node: &mut DomNode, html: &str) {
-        // For now, treat innerHTML as textContent
-        // TODO: Parse HTML and create proper DOM nodes
-        node.set_text_content(html);
-        log::trace!(target: "javascript", "Set innerHTML to: {}", html);
+
+    // Elements named `tag` (or every element for "*") in document order
+    fn collect_elements_by_tag<'a>(node: &'a DomNode, tag: &str, found: &mut Vec<&'a DomNode>) {
+        for child in node.children() {
+            if let crate::dom::NodeType::Element { tag_name, .. } = child.node_type()
+                && (tag == "*" || tag_name.eq_ignore_ascii_case(tag))
+            {
+                found.push(child);
+            }
+            Self::collect_elements_by_tag(child, tag, found);
+        }
     }
 
     // DOM search helper methods - check if elements exist in the shared DOM
@@ -1436,116 +2393,63 @@ impl Runtime {
         false
     }
     
-    // Get mutable access to an element by ID for modification
-    fn get_element_mut_by_id(&self, id: &str) -> Option<std::cell::RefMut<'_, DomNode>> {
-        if let Some(root) = &self.dom_root {
-            Self::search_and_get_mut_by_id(root, id)
-        } else {
-            None
-        }
-    }
-    
-    // Recursively search and get mutable access to element by ID
-    fn search_and_get_mut_by_id<'a>(root: &'a Rc<RefCell<DomNode>>, id: &str) -> Option<std::cell::RefMut<'a, DomNode>> {
-        // Check root
-        {
-            let root_ref = root.borrow();
-            if let Some(node_id) = root_ref.get_attribute("id") {
-                if node_id == id {
-                    drop(root_ref);
-                    return Some(root.borrow_mut());
-                }
-            }
-        }
-        
-        // Search children - we need to find the path and navigate
-        if let Some(path) = Self::find_path_by_id(root, id) {
-            Self::navigate_path_mut(root, &path)
-        } else {
-            None
-        }
-    }
-    
-    // Find path to element with given ID
-    fn find_path_by_id(root: &Rc<RefCell<DomNode>>, id: &str) -> Option<Vec<usize>> {
-        let root_ref = root.borrow();
-        
-        // Check root
-        if let Some(node_id) = root_ref.get_attribute("id") {
-            if node_id == id {
-                return Some(Vec::new());
-            }
-        }
-        
-        // Search children
-        for (i, child) in root_ref.children().iter().enumerate() {
-            if let Some(child_id) = child.get_attribute("id") {
-                if child_id == id {
-                    return Some(vec![i]);
-                }
-            }
-            // Recursively search child
-            let child_rc = Rc::new(RefCell::new(child.clone()));
-            if let Some(mut path) = Self::find_path_by_id(&child_rc, id) {
-                path.insert(0, i);
-                return Some(path);
-            }
-        }
-        None
-    }
-    
-    // Navigate to element using path and return mutable reference
-    fn navigate_path_mut<'a>(root: &'a Rc<RefCell<DomNode>>, path: &[usize]) -> Option<std::cell::RefMut<'a, DomNode>> {
-        if path.is_empty() {
-            return Some(root.borrow_mut());
-        }
-        
-        // We can't easily get mutable access to a child in Vec<Node>
-        // So we'll use a different approach: modify through the parent
-        // For now, return None and handle modification differently
-        None
+    // Computed properties of an element object, as (property, serialized value)
+    // pairs.
+    fn computed_style_of(&self, element: Option<&JsValue>) -> Vec<(String, String)> {
+        let Some(handle) = element.and_then(|element| Self::node_handle(element, NODE_SLOT)) else {
+            return Vec::new();
+        };
+        let (Some(engine), Some(root)) = (&self.style_engine, &self.dom_root) else {
+            return Vec::new();
+        };
+
+        let root = root.borrow();
+        let mut ancestors = Vec::new();
+        let Some(node) = root.find_by_node_id_with_ancestors(handle, &mut ancestors) else {
+            return Vec::new();
+        };
+        let styled = engine.style_node(node, &ancestors);
+        styled
+            .computed()
+            .map(|(property, value)| {
+                // Color keywords compute to rgb() like every other color
+                let serialized = match value {
+                    crate::css::Value::Keyword(kw) if property.ends_with("color") => crate::css::Color::from_named(kw)
+                        .map(|color| color.to_string())
+                        .unwrap_or_else(|| kw.clone()),
+                    _ => value.to_string(),
+                };
+                (property.to_ascii_lowercase(), serialized)
+            })
+            .collect()
     }
-    
+
     fn create_element_object_with_id(&self, id: String) -> JsValue {
-        let mut elem_obj = JsObject::new();
-        
-        // Store the ID so we can find and modify the element later
-        elem_obj.set("id", JsValue::String(id.clone()));
-        
-        // Get element properties from DOM by searching
-        if let Some(root) = &self.dom_root {
-            if let Some((tag_name, class_name, inner_html, text_content)) = Self::get_element_info_by_id(&root.borrow(), &id) {
-                elem_obj.set("tagName", JsValue::String(tag_name.to_uppercase()));
-                elem_obj.set("nodeName", JsValue::String(tag_name.to_uppercase()));
-                if let Some(class) = class_name {
-                    elem_obj.set("className", JsValue::String(class));
-                }
-                elem_obj.set("innerHTML", JsValue::String(inner_html));
-                elem_obj.set("textContent", JsValue::String(text_content));
-            }
+        let Some(root) = &self.dom_root else {
+            return JsValue::Null;
+        };
+        let root = root.borrow();
+        match root.find_by_id(&id) {
+            Some(node) => self.create_element_object(node),
+            None => JsValue::Null,
         }
-        
-        // Add methods
-        elem_obj.set("setAttribute", JsValue::NativeFunction("element.setAttribute".to_string()));
-        elem_obj.set("getAttribute", JsValue::NativeFunction("element.getAttribute".to_string()));
-        elem_obj.set("addEventListener", JsValue::NativeFunction("element.addEventListener".to_string()));
-        
-        JsValue::Object(Rc::new(RefCell::new(elem_obj)))
     }
-    
-    fn create_element_object(&self, element: Rc<RefCell<DomNode>>) -> JsValue {
+
+    fn create_element_object(&self, dom_node: &DomNode) -> JsValue {
         let mut elem_obj = JsObject::new();
         
         // Get element properties from DOM
-        let dom_node = element.borrow();
         if let crate::dom::NodeType::Element { tag_name, .. } = dom_node.node_type() {
             elem_obj.set("tagName", JsValue::String(tag_name.to_uppercase()));
             elem_obj.set("nodeName", JsValue::String(tag_name.to_uppercase()));
             
-            // Get id
             if let Some(id) = dom_node.get_attribute("id") {
                 elem_obj.set("id", JsValue::String(id.to_string()));
             }
+            // Writes through classList and style find the element by its node id
+            elem_obj.set_hidden(NODE_SLOT, JsValue::Number(dom_node.id() as f64));
+            elem_obj.set("classList", Self::class_list_object(dom_node.id()));
+            elem_obj.set("style", Self::style_object(dom_node.id(), dom_node.inline_style()));
             
             // Get className
             if let Some(class) = dom_node.get_attribute("class") {
@@ -1553,30 +2457,94 @@ impl Runtime {
             }
             
             // Get innerHTML and textContent from children
-            let inner_html = Self::extract_inner_html(&dom_node);
-            let text_content = Self::extract_text_content(&dom_node);
+            let inner_html = Self::extract_inner_html(dom_node);
+            let text_content = Self::extract_text_content(dom_node);
             elem_obj.set("innerHTML", JsValue::String(inner_html.clone()));
             elem_obj.set("textContent", JsValue::String(text_content.clone()));
+            if let Some(content) = Self::template_content_object(dom_node) {
+                elem_obj.set("content", content);
+            }
         }
-        drop(dom_node);
-        
-        // Store reference to DOM node for property setters
-        elem_obj.set("__dom_node", JsValue::Object(Rc::new(RefCell::new({
-            let mut wrapper = JsObject::new();
-            wrapper.set("__ref", JsValue::String(format!("{:p}", &*element.borrow())));
-            wrapper
-        }))));
         
         // Add methods
         elem_obj.set("setAttribute", JsValue::NativeFunction("element.setAttribute".to_string()));
         elem_obj.set("getAttribute", JsValue::NativeFunction("element.getAttribute".to_string()));
         elem_obj.set("addEventListener", JsValue::NativeFunction("element.addEventListener".to_string()));
         
-        // Store the element reference in a special way so we can access it later
-        // We'll use a custom property to store the Rc pointer
         JsValue::Object(Rc::new(RefCell::new(elem_obj)))
     }
     
+    // classList for the element node `handle`; its methods edit the shared DOM's class attribute
+    fn class_list_object(handle: usize) -> JsValue {
+        let mut list = JsObject::new();
+        list.set_hidden(NODE_SLOT, JsValue::Number(handle as f64));
+        for method in ["add", "remove", "toggle", "contains"] {
+            list.set(method, JsValue::NativeFunction(format!("element.classList.{}", method)));
+        }
+        JsValue::Object(Rc::new(RefCell::new(list)))
+    }
+
+    // A RegExp from a literal. The pattern is compiled again wherever it is used.
+    fn regex_object(pattern: &str, flags: &str) -> JsValue {
+        let mut object = JsObject::new();
+        object.set("__regexp", JsValue::Boolean(true));
+        object.set("source", JsValue::String(pattern.to_string()));
+        object.set("flags", JsValue::String(flags.to_string()));
+        object.set("global", JsValue::Boolean(flags.contains('g')));
+        object.set("ignoreCase", JsValue::Boolean(flags.contains('i')));
+        object.set("multiline", JsValue::Boolean(flags.contains('m')));
+        object.set("lastIndex", JsValue::Number(0.0));
+        object.set("test", JsValue::NativeFunction("RegExp.test".to_string()));
+        JsValue::Object(Rc::new(RefCell::new(object)))
+    }
+
+    fn regex_from_value(value: &JsValue) -> Option<Regex> {
+        let JsValue::Object(object) = value else {
+            return None;
+        };
+        let object = object.borrow();
+        object.get_property("__regexp")?;
+        match (object.get_property("source"), object.get_property("flags")) {
+            (Some(JsValue::String(source)), Some(JsValue::String(flags))) => Regex::new(source, flags).ok(),
+            _ => None,
+        }
+    }
+
+    // The DOM node id an element wrapper, classList or style object stores in `slot`
+    fn node_handle(value: &JsValue, slot: &str) -> Option<usize> {
+        match value {
+            JsValue::Object(object) => match object.borrow().get_property(slot) {
+                Some(JsValue::Number(handle)) => Some(*handle as usize),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn regex_too_deep(_: regex::TooDeep) -> Box<dyn Error> {
+        Box::new(JsException::new(Self::create_error_object("RangeError", "Maximum call stack size exceeded in regular expression")))
+    }
+
+    // element.style for the element node `handle`, seeded with its inline declarations
+    fn style_object(handle: usize, declarations: Vec<(String, String)>) -> JsValue {
+        let mut style = JsObject::new();
+        style.set_hidden(STYLE_NODE_SLOT, JsValue::Number(handle as f64));
+        for (property, value) in declarations {
+            style.set(&js_style_name(&property), JsValue::String(value));
+        }
+        JsValue::Object(Rc::new(RefCell::new(style)))
+    }
+
+    // Read-only snapshot of a <template>'s content fragment
+    fn template_content_object(node: &DomNode) -> Option<JsValue> {
+        let content = node.template_content()?;
+        let mut fragment = JsObject::new();
+        fragment.set("nodeName", JsValue::String("#document-fragment".to_string()));
+        fragment.set("innerHTML", JsValue::String(Self::extract_inner_html(content)));
+        fragment.set("textContent", JsValue::String(Self::extract_text_content(content)));
+        Some(JsValue::Object(Rc::new(RefCell::new(fragment))))
+    }
+
     fn extract_inner_html(node: &DomNode) -> String {
         let mut html = String::new();
         for child in node.children() {
@@ -1588,7 +2556,8 @@ impl Runtime {
                         html.push(' ');
                         html.push_str(&attr.name);
                         html.push_str("=\"");
-                        html.push_str(&attr.value);
+                        // Attribute values are stored decoded, so escape them again
+                        html.push_str(&attr.value.replace('&', "&amp;").replace('"', "&quot;"));
                         html.push('"');
                     }
                     html.push('>');
@@ -1610,28 +2579,6 @@ impl Runtime {
         html
     }
     
-    fn get_element_info_by_id(node: &DomNode, id: &str) -> Option<(String, Option<String>, String, String)> {
-        if let Some(node_id) = node.get_attribute("id") {
-            if node_id == id {
-                if let crate::dom::NodeType::Element { tag_name, .. } = node.node_type() {
-                    return Some((
-                        tag_name.clone(),
-                        node.get_attribute("class").map(|s| s.to_string()),
-                        Self::extract_inner_html(node),
-                        Self::extract_text_content(node),
-                    ));
-                }
-            }
-        }
-        
-        for child in node.children() {
-            if let Some(info) = Self::get_element_info_by_id(child, id) {
-                return Some(info);
-            }
-        }
-        None
-    }
-    
     fn extract_text_content(node: &DomNode) -> String {
         let mut text = String::new();
         for child in node.children() {
@@ -1657,7 +2604,7 @@ impl Runtime {
         
         let prop_name = match prop {
             JsValue::String(s) => s.to_string(),
-            JsValue::Number(n) => n.to_string(),
+            JsValue::Number(n) => number_to_string(*n),
             _ => return Ok(JsValue::Undefined),
         };
         
@@ -1672,6 +2619,7 @@ impl Runtime {
                 // String properties like .length
                 match prop_name.as_str() {
                     "length" => Ok(JsValue::Number(s.len() as f64)),
+                    "replace" => Ok(JsValue::NativeFunction("String.replace".to_string())),
                     _ => {
                         // Try to access character by index
                         if let Ok(idx) = prop_name.parse::<usize>() {
@@ -1684,6 +2632,13 @@ impl Runtime {
                     }
                 }
             }
+            JsValue::NativeFunction(name) => {
+                // Static members of built-in constructors
+                Ok(match (name.as_str(), prop_name.as_str()) {
+                    ("Date", "now") => JsValue::NativeFunction("Date.now".to_string()),
+                    _ => JsValue::Undefined,
+                })
+            }
             JsValue::Undefined | JsValue::Null => {
                 // In JavaScript, accessing properties on null/undefined is a TypeError
                 // But we'll be lenient and return undefined
@@ -1697,4 +2652,32 @@ impl Runtime {
             }
         }
     }
-} 
\ No newline at end of file
+}
+
+// CSS property name for a camelCase style property: backgroundColor -> background-color
+fn css_property_name(property: &str) -> String {
+    let mut name = String::with_capacity(property.len() + 2);
+    for ch in property.chars() {
+        if ch.is_ascii_uppercase() {
+            name.push('-');
+            name.push(ch.to_ascii_lowercase());
+        } else {
+            name.push(ch);
+        }
+    }
+    name
+}
+
+// The reverse of css_property_name: background-color -> backgroundColor
+fn js_style_name(property: &str) -> String {
+    let mut parts = property.split('-');
+    let mut name = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        let mut chars = part.chars();
+        if let Some(first) = chars.next() {
+            name.push(first.to_ascii_uppercase());
+            name.push_str(chars.as_str());
+        }
+    }
+    name
+}
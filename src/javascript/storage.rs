@@ -0,0 +1,38 @@
+// Backing store for the Web Storage globals (localStorage, sessionStorage).
+// Both live in memory only, for as long as the browser does.
+
+/// Key/value pairs in insertion order, which is the order `key(n)` reports.
+#[derive(Debug, Default)]
+pub struct Storage {
+    items: Vec<(String, String)>,
+}
+
+impl Storage {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.items.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    /// Set `key`, keeping its place if it was already stored.
+    pub fn set(&mut self, key: String, value: String) {
+        match self.items.iter_mut().find(|(k, _)| *k == key) {
+            Some(item) => item.1 = value,
+            None => self.items.push((key, value)),
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        self.items.retain(|(k, _)| k != key);
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn key(&self, index: usize) -> Option<&str> {
+        self.items.get(index).map(|(k, _)| k.as_str())
+    }
+}
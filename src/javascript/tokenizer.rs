@@ -10,6 +10,7 @@ pub enum Token {
     If,
     Else,
     While,
+    Do,
     For,
     In,     // for...in
     Of,     // for...of
@@ -35,6 +36,7 @@ pub enum Token {
     Number(f64),
     String(String),
     Identifier(String),
+    Regex(String, String), // pattern, flags
     
     // Operators
     Plus,
@@ -74,6 +76,7 @@ pub enum Token {
     Comma,
     Colon,
     Arrow,  // =>
+    Ellipsis, // ...
     
     EOF,
 }
@@ -158,6 +161,7 @@ pub fn tokenize(source: &str) -> Vec<Token> {
                     "if" => Token::If,
                     "else" => Token::Else,
                     "while" => Token::While,
+                    "do" => Token::Do,
                     "for" => Token::For,
                     "in" => Token::In,
                     "of" => Token::Of,
@@ -264,35 +268,9 @@ pub fn tokenize(source: &str) -> Vec<Token> {
                         chars.next();
                         Token::Star
                     },
-                    '/' => {
-                        chars.next();
-                        if chars.peek() == Some(&'/') {
-                            log::trace!(target: "javascript", "Found single-line comment");
-                            chars.next();
-                            while let Some(&c) = chars.peek() {
-                                if c == '\n' {
-                                    break;
-                                }
-                                chars.next();
-                            }
-                            continue;
-                        } else if chars.peek() == Some(&'*') {
-                            log::trace!(target: "javascript", "Found multi-line comment");
-                            chars.next();
-                            while let Some(&c) = chars.peek() {
-                                if c == '*' {
-                                    chars.next();
-                                    if chars.peek() == Some(&'/') {
-                                        chars.next();
-                                        break;
-                                    }
-                                } else {
-                                    chars.next();
-                                }
-                            }
-                            continue;
-                        }
-                        Token::Slash
+                    '/' => match scan_slash(&mut chars, tokens.last()) {
+                        Some(token) => token,
+                        None => continue,
                     },
                     '(' => {
                         chars.next();
@@ -337,7 +315,15 @@ pub fn tokenize(source: &str) -> Vec<Token> {
                     },
                     '.' => {
                         chars.next();
-                        Token::Dot
+                        // Look two characters ahead without consuming for `...`
+                        let mut lookahead = chars.clone();
+                        if lookahead.next() == Some('.') && lookahead.next() == Some('.') {
+                            chars.next();
+                            chars.next();
+                            Token::Ellipsis
+                        } else {
+                            Token::Dot
+                        }
                     },
                     '|' => {
                         chars.next();
@@ -379,4 +365,124 @@ pub fn tokenize(source: &str) -> Vec<Token> {
     tokens.push(Token::EOF);
     log::trace!(target: "javascript", "Tokenization complete. Tokens: {:?}", tokens);
     tokens
+}
+
+// Scan from a '/': a comment, which is skipped and yields None, a regex
+// literal, or division, depending on the `previous` token.
+fn scan_slash(chars: &mut std::iter::Peekable<std::str::Chars>, previous: Option<&Token>) -> Option<Token> {
+    chars.next();
+    if chars.peek() == Some(&'/') {
+        log::trace!(target: "javascript", "Found single-line comment");
+        chars.next();
+        while let Some(&c) = chars.peek() {
+            if c == '\n' {
+                break;
+            }
+            chars.next();
+        }
+        return None;
+    } else if chars.peek() == Some(&'*') {
+        log::trace!(target: "javascript", "Found multi-line comment");
+        chars.next();
+        while let Some(&c) = chars.peek() {
+            if c == '*' {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    break;
+                }
+            } else {
+                chars.next();
+            }
+        }
+        return None;
+    }
+    if regex_allowed_after(previous)
+        && let Some(token) = scan_regex_literal(chars)
+    {
+        return Some(token);
+    }
+    Some(Token::Slash)
+}
+
+// A '/' starts a regex literal wherever an operand is expected, and is
+// division after anything that ends an operand.
+fn regex_allowed_after(previous: Option<&Token>) -> bool {
+    !matches!(
+        previous,
+        Some(
+            Token::Number(_)
+                | Token::String(_)
+                | Token::Identifier(_)
+                | Token::Regex(..)
+                | Token::RightParen
+                | Token::RightBracket
+                | Token::True
+                | Token::False
+                | Token::Null
+                | Token::Undefined
+                | Token::This
+                | Token::PlusPlus
+                | Token::MinusMinus
+        )
+    )
+}
+
+// Scan the rest of a regex literal after its opening '/'. Consumes nothing and
+// returns None if the literal is unterminated on this line.
+fn scan_regex_literal(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<Token> {
+    let mut lookahead = chars.clone();
+    let mut pattern = String::new();
+    let mut in_class = false;
+    loop {
+        let c = lookahead.next()?;
+        match c {
+            '\n' | '\r' => return None,
+            '\\' => {
+                pattern.push(c);
+                pattern.push(lookahead.next()?);
+                continue;
+            }
+            '[' => in_class = true,
+            ']' => in_class = false,
+            '/' if !in_class => break,
+            _ => {}
+        }
+        pattern.push(c);
+    }
+    let mut flags = String::new();
+    while let Some(&c) = lookahead.peek() {
+        if !c.is_alphanumeric() {
+            break;
+        }
+        flags.push(c);
+        lookahead.next();
+    }
+    *chars = lookahead;
+    log::trace!(target: "javascript", "Found regex literal: /{}/{}", pattern, flags);
+    Some(Token::Regex(pattern, flags))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slash_is_regex_or_division_by_context() {
+        assert!(matches!(
+            tokenize("x = /ab/g;").as_slice(),
+            [Token::Identifier(_), Token::Equals, Token::Regex(pattern, flags), Token::Semicolon, Token::EOF]
+                if pattern == "ab" && flags == "g"
+        ));
+        assert!(matches!(
+            tokenize("a / b / c").as_slice(),
+            [Token::Identifier(_), Token::Slash, Token::Identifier(_), Token::Slash, Token::Identifier(_), Token::EOF]
+        ));
+        // A '/' inside a class doesn't end the literal
+        assert!(matches!(
+            tokenize("s.replace(/[/]+/, '')").as_slice(),
+            [_, Token::Dot, _, Token::LeftParen, Token::Regex(pattern, flags), Token::Comma, ..]
+                if pattern == "[/]+" && flags.is_empty()
+        ));
+    }
 } 
\ No newline at end of file
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::rc::Rc;
 use std::cell::RefCell;
-use super::ast::Node;
+use super::ast::{Node, Param};
 
 #[derive(Debug, Clone)]
 pub enum JsValue {
@@ -18,25 +18,56 @@ pub enum JsValue {
 #[derive(Debug, Clone)]
 pub struct JsObject {
     properties: HashMap<String, JsValue>,
+    // Insertion order of property names, so enumeration is deterministic
+    order: Vec<String>,
     prototype: Option<Box<JsObject>>,
+    array: bool, // Created as an array, not merely array-like
 }
 
 impl JsObject {
     pub fn new() -> Self {
         Self {
             properties: HashMap::new(),
+            order: Vec::new(),
             prototype: None,
+            array: false,
         }
     }
 
+    /// An empty array: `length` 0 and flagged so `Array.isArray` recognises it.
+    pub fn new_array() -> Self {
+        let mut arr = Self::new();
+        arr.array = true;
+        arr.set("length", JsValue::Number(0.0));
+        arr
+    }
+
     pub fn set_property(&mut self, name: String, value: JsValue) {
+        if !self.properties.contains_key(&name) {
+            self.order.push(name.clone());
+        }
         self.properties.insert(name, value);
     }
 
     pub fn set(&mut self, name: &str, value: JsValue) {
+        self.set_property(name.to_string(), value);
+    }
+
+    /// Set an own property that enumeration skips, such as a built-in method or
+    /// an internal slot. Setting an existing enumerable property leaves it enumerable.
+    pub fn set_hidden(&mut self, name: &str, value: JsValue) {
         self.properties.insert(name.to_string(), value);
     }
 
+    /// Remove an own property; the prototype chain is left alone.
+    pub fn remove_property(&mut self, name: &str) -> Option<JsValue> {
+        let removed = self.properties.remove(name);
+        if removed.is_some() {
+            self.order.retain(|key| key != name);
+        }
+        removed
+    }
+
     pub fn get_property(&self, name: &str) -> Option<&JsValue> {
         self.properties.get(name).or_else(|| {
             self.prototype
@@ -45,9 +76,21 @@ impl JsObject {
         })
     }
 
+    /// A property set on this object itself, ignoring the prototype chain.
+    pub fn get_own_property(&self, name: &str) -> Option<&JsValue> {
+        self.properties.get(name)
+    }
+
+    pub fn prototype(&self) -> Option<&JsObject> {
+        self.prototype.as_deref()
+    }
+
+    pub fn set_prototype(&mut self, prototype: JsObject) {
+        self.prototype = Some(Box::new(prototype));
+    }
+
     pub fn is_array(&self) -> bool {
-        self.properties.contains_key("length") && 
-        self.properties.iter().any(|(k, _)| k.parse::<usize>().is_ok())
+        self.array
     }
 
     pub fn get_length(&self) -> Option<usize> {
@@ -71,9 +114,12 @@ impl JsObject {
         }
     }
     
-    /// Get all property keys
+    /// Get all own property keys: array indices in ascending order first,
+    /// then the remaining keys in insertion order (as JavaScript enumerates them)
     pub fn keys(&self) -> impl Iterator<Item = &String> {
-        self.properties.keys()
+        let mut indices: Vec<&String> = self.order.iter().filter(|k| k.parse::<u32>().is_ok()).collect();
+        indices.sort_by_key(|k| k.parse::<u32>().unwrap_or(0));
+        indices.into_iter().chain(self.order.iter().filter(|k| k.parse::<u32>().is_err()))
     }
 }
 
@@ -81,12 +127,54 @@ impl JsObject {
 #[derive(Debug, Clone)]
 pub struct JsUserFunction {
     pub name: Option<String>,
-    pub params: Vec<String>,
+    pub params: Vec<Param>,
     pub body: Vec<Node>,
 }
 
 impl JsUserFunction {
-    pub fn new(name: Option<String>, params: Vec<String>, body: Vec<Node>) -> Self {
+    pub fn new(name: Option<String>, params: Vec<Param>, body: Vec<Node>) -> Self {
         Self { name, params, body }
     }
-} 
\ No newline at end of file
+}
+
+/// Format a number the way JavaScript's Number::toString does: integral values
+/// without a fractional part, the shortest round-tripping digits otherwise, and
+/// exponent notation outside the 1e-7..1e21 range.
+pub fn number_to_string(n: f64) -> String {
+    if n.is_nan() {
+        return "NaN".to_string();
+    }
+    if n.is_infinite() {
+        return if n > 0.0 { "Infinity" } else { "-Infinity" }.to_string();
+    }
+    if n == 0.0 {
+        // Covers -0 as well
+        return "0".to_string();
+    }
+
+    let sign = if n < 0.0 { "-" } else { "" };
+    // `{:e}` yields the shortest round-trip digits, e.g. "1.5e2"
+    let formatted = format!("{:e}", n.abs());
+    let (mantissa, exponent) = formatted.split_once('e').unwrap_or((&formatted, "0"));
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let k = digits.len() as i32;
+    // Position of the decimal point relative to the start of `digits`
+    let point = exponent.parse::<i32>().unwrap_or(0) + 1;
+
+    let body = if k <= point && point <= 21 {
+        format!("{}{}", digits, "0".repeat((point - k) as usize))
+    } else if 0 < point && point <= 21 {
+        format!("{}.{}", &digits[..point as usize], &digits[point as usize..])
+    } else if -6 < point && point <= 0 {
+        format!("0.{}{}", "0".repeat((-point) as usize), digits)
+    } else {
+        let exp = point - 1;
+        let exp_sign = if exp < 0 { "-" } else { "+" };
+        if k == 1 {
+            format!("{}e{}{}", digits, exp_sign, exp.abs())
+        } else {
+            format!("{}.{}e{}{}", &digits[..1], &digits[1..], exp_sign, exp.abs())
+        }
+    };
+    format!("{}{}", sign, body)
+}
@@ -1,19 +1,37 @@
 mod css;
 mod dom;
+mod history;
 mod html;
 pub mod logger;
 mod networking;
 pub mod rendering;
 mod javascript;
 
+pub use css::rules::Origin as StyleOrigin;
+pub use dom::query::ElementSummary;
+pub use dom::{DomTree, Node as DomNode, NodeType, Patch as DomPatch, apply as apply_dom_patches, diff as diff_dom};
+pub use html::forms::{FormInfo, FormMethod};
+pub use html::HtmlDocument;
+pub use html::parser::Parser as HtmlParser;
+pub use networking::{Headers, Method, NetworkError, NetworkManager, Request, RequestInterceptor, RequestLogEntry, Response, SchemeHandler, Uri};
 use log::{debug, info, trace};
 use std::error::Error;
 
+// Subresources (stylesheets, scripts) fetched in parallel during a page load
+const MAX_CONCURRENT_FETCHES: usize = 6;
+
 pub struct Browser {
     config: BrowserConfig,
-    networking: networking::NetworkManager,
+    networking: std::sync::Arc<networking::NetworkManager>,
     renderer: rendering::Renderer,
     js_engine: javascript::JavaScriptEngine,
+    // Styled tree of the last loaded page, kept so it can be re-laid out for screenshots
+    styled_dom: Option<css::style::StyledNode>,
+    // Sheets from `add_stylesheet`, applied to every page alongside its own
+    extra_stylesheets: Vec<(css::StyleSheet, StyleOrigin)>,
+    // URL of the page currently loaded (after redirects)
+    current_url: Option<String>,
+    history: history::History,
 }
 
 #[derive(Clone)]
@@ -21,25 +39,87 @@ pub struct BrowserConfig {
     pub headless: bool,
     pub debug: bool,
     pub enable_javascript: bool,
+    /// Initial viewport size used for layout (media queries, vw/vh units, wrapping).
+    pub viewport_width: u32,
+    pub viewport_height: u32,
+    /// Fetch `<img>` resources. When false, images are laid out as zero-size
+    /// placeholders and never requested, which speeds up text scraping.
+    pub load_images: bool,
+    /// Send a Referer header: the page URL for subresources and the previous page
+    /// for navigations (never from https to http).
+    pub send_referer: bool,
+    /// Identity reported to sites: sent as the User-Agent header and exposed to
+    /// scripts through `navigator`, so UA sniffing sees consistent values.
+    pub user_agent: String,
+    pub platform: String,
+    pub language: String,
+    /// Scheme given to typed addresses that have none (see `load_user_input`).
+    /// Loopback hosts always get `http`.
+    pub default_scheme: String,
+    /// When a typed address's host doesn't resolve, try it again under `www.`.
+    pub www_fallback: bool,
+    /// Follow HTTP redirects. When false, a 3xx response is loaded as the page
+    /// itself, so its status and Location header can be inspected.
+    pub follow_redirects: bool,
+    /// Times any one script loop may run before the whole script is aborted.
+    pub script_max_loop_iterations: usize,
+    /// Nested script function calls allowed before further calls are skipped.
+    pub script_max_call_depth: usize,
+    /// Wall-clock allowance for one script or event dispatch. `None` lets
+    /// scripts run as long as the loop limit allows.
+    pub script_time_budget: Option<std::time::Duration>,
+}
+
+impl Default for BrowserConfig {
+    fn default() -> Self {
+        Self {
+            headless: false,
+            debug: false,
+            enable_javascript: true,
+            // A standard desktop viewport
+            viewport_width: 1920,
+            viewport_height: 1080,
+            load_images: true,
+            send_referer: true,
+            user_agent: networking::user_agent(),
+            platform: std::env::consts::OS.to_string(),
+            language: "en-US".to_string(),
+            default_scheme: "https".to_string(),
+            www_fallback: true,
+            follow_redirects: true,
+            script_max_loop_iterations: javascript::DEFAULT_MAX_LOOP_ITERATIONS,
+            script_max_call_depth: javascript::DEFAULT_MAX_EXECUTION_DEPTH,
+            script_time_budget: None,
+        }
+    }
 }
 
 impl Browser {
     pub fn new(config: BrowserConfig) -> Result<Self, Box<dyn Error>> {
         let config_clone = config.clone();
+        let mut networking = networking::NetworkManager::new()?;
+        networking.set_user_agent(config.user_agent.clone());
+        networking.set_follow_redirects(config.follow_redirects);
+        let mut js_engine = javascript::JavaScriptEngine::new();
+        let runtime = js_engine.runtime_mut();
+        runtime.set_navigator(&config.user_agent, &config.platform, &config.language);
+        runtime.set_limits(config.script_max_loop_iterations, config.script_max_call_depth);
+        runtime.set_time_budget(config.script_time_budget);
         let mut browser = Self {
             config,
-            networking: networking::NetworkManager::new()?,
+            networking: std::sync::Arc::new(networking),
             renderer: rendering::Renderer::new(config_clone.headless)?,
-            js_engine: javascript::JavaScriptEngine::new(),
+            js_engine,
+            styled_dom: None,
+            extra_stylesheets: Vec::new(),
+            current_url: None,
+            history: history::History::new(),
         };
         
-        // In headless mode, use a reasonable default viewport size for layout calculations
-        // This is needed for proper text extraction and layout, even without visual rendering
-        if config_clone.headless {
-            // Use a standard desktop viewport size for headless mode
-            browser.set_viewport_size(1920, 1080);
-        }
-        // In GUI mode, viewport will be set by the GUI when window size is known
+        // Layout needs a viewport even without visual rendering (text extraction, vw/vh units).
+        // The GUI may still resize it later once the window size is known.
+        browser.set_viewport_size(config_clone.viewport_width, config_clone.viewport_height);
+        browser.renderer.set_scripting_enabled(config_clone.enable_javascript);
         
         Ok(browser)
     }
@@ -48,35 +128,192 @@ impl Browser {
         self.renderer.set_viewport_size(width, height);
     }
     
+    /// Navigate to `url`, adding it to the session history.
     pub async fn load_url(&mut self, url: &str) -> Result<(crate::rendering::DisplayList, String), Box<dyn Error>> {
-        println!("\n[*] Loading: {}", url);
-        info!(target: "browser", "Starting request for URL: {}", url);
+        self.navigate(url, true, false).await
+    }
 
-        let response = self.networking.fetch(url).await?;
-        println!("[+] Status: {}", response.status.code);
+    /// Navigate to an address as a user typed it: the configured default scheme is
+    /// added when it has none, and with `www_fallback` a host that doesn't resolve
+    /// is tried once more under `www.`.
+    pub async fn load_user_input(&mut self, input: &str) -> Result<(crate::rendering::DisplayList, String), Box<dyn Error>> {
+        let url = networking::Uri::normalize_user_input_with_scheme(input, &self.config.default_scheme);
+        let result = self.load_url(&url).await;
+        let dns_failed = matches!(
+            result.as_ref().map_err(|e| e.downcast_ref::<networking::NetworkError>()),
+            Err(Some(networking::NetworkError::DnsLookupFailed(_)))
+        );
+        if dns_failed
+            && self.config.www_fallback
+            && let Some(fallback) = networking::Uri::parse(&url).ok().and_then(|uri| uri.with_www_host())
+        {
+            info!(target: "browser", "{} did not resolve, trying {}", url, fallback);
+            return self.load_url(&fallback).await;
+        }
+        result
+    }
 
-        let raw_content = String::from_utf8_lossy(&response.body);
+    /// Load the current page again. With `bypass_cache` the document is fetched
+    /// from the network even when a cached copy exists.
+    pub async fn reload(&mut self, bypass_cache: bool) -> Result<(crate::rendering::DisplayList, String), Box<dyn Error>> {
+        let url = self.current_url.clone().ok_or("No page has been loaded")?;
+        self.navigate(&url, false, bypass_cache).await
+    }
 
-        // Print raw content only in headless mode (for debugging)
-        if self.config.headless {
-            println!("\n[+] Raw HTML Content:");
-            println!("{}", "=".repeat(80));
-            println!("{}", raw_content);
-            println!("{}", "=".repeat(80));
+    /// Reload the previous history entry. Returns `None` when there is nothing to go back to.
+    pub async fn go_back(&mut self) -> Result<Option<(crate::rendering::DisplayList, String)>, Box<dyn Error>> {
+        self.traverse_history(-1).await
+    }
+
+    /// Reload the next history entry. Returns `None` when there is nothing to go forward to.
+    pub async fn go_forward(&mut self) -> Result<Option<(crate::rendering::DisplayList, String)>, Box<dyn Error>> {
+        self.traverse_history(1).await
+    }
+
+    /// Forms on the current page, with the values they would submit right now.
+    pub fn forms(&self) -> Vec<FormInfo> {
+        let (Some(styled_dom), Some(url)) = (&self.styled_dom, &self.current_url) else {
+            return Vec::new();
+        };
+        match networking::Uri::parse(url) {
+            Ok(base) => html::forms::extract_forms(&styled_dom.node, &base),
+            Err(_) => Vec::new(),
         }
+    }
 
-        // Use the full HTML content as-is - the parser should handle DOCTYPE, comments, etc.
-        let html_content = raw_content.to_string();
+    /// The first element of the current page matching the CSS `selector`.
+    pub fn query_selector(&self, selector: &str) -> Option<ElementSummary> {
+        let styled_dom = self.styled_dom.as_ref()?;
+        dom::query::query_selector(&styled_dom.node, selector).and_then(ElementSummary::from_node)
+    }
 
-        log::trace!(target: "browser", "Parsed HTML content (first 500 chars): {}",
-            html_content.chars().take(500).collect::<String>());
-        if html_content.len() > 500 {
-            log::trace!(target: "browser", "HTML content truncated (total length: {} chars)", html_content.len());
+    /// Every element of the current page matching the CSS `selector`, in document order.
+    pub fn query_selector_all(&self, selector: &str) -> Vec<ElementSummary> {
+        let Some(styled_dom) = &self.styled_dom else {
+            return Vec::new();
+        };
+        dom::query::query_selector_all(&styled_dom.node, selector)
+            .into_iter()
+            .filter_map(ElementSummary::from_node)
+            .collect()
+    }
+
+    /// Submit `form`, with `overrides` replacing (or adding) field values, and
+    /// load the response as a new page. GET forms put the fields in the query
+    /// string; POST forms send them as an urlencoded body.
+    pub async fn submit_form(
+        &mut self,
+        form: &FormInfo,
+        overrides: &[(String, String)],
+    ) -> Result<(crate::rendering::DisplayList, String), Box<dyn Error>> {
+        let encoded = html::forms::urlencoded(&form.fields_with(overrides));
+        match form.method {
+            FormMethod::Get => {
+                // The form data replaces any query the action already had
+                let action = form.action.split('#').next().unwrap_or(&form.action);
+                let action = action.split('?').next().unwrap_or(action);
+                self.navigate(&format!("{}?{}", action, encoded), true, false).await
+            }
+            FormMethod::Post => self.navigate_with_body(&form.action, Some(encoded.into_bytes()), true, false).await,
         }
+    }
 
-        debug!(target: "browser", "Starting HTML parsing");
-        let mut parser = html::parser::Parser::new(html_content);
-        let dom = parser.parse();
+    /// Apply `css` to every page loaded from now on. Rules cascade by `origin`:
+    /// user-agent and user rules lose to the page's own rules.
+    pub fn add_stylesheet(&mut self, css: &str, origin: StyleOrigin) {
+        let sheet = css::parser::CssParser::new(css.to_string()).parse();
+        self.extra_stylesheets.push((sheet, origin));
+    }
+
+    /// Console output from page scripts since the last call, as (level, message) pairs.
+    pub fn take_console_messages(&mut self) -> Vec<(String, String)> {
+        self.js_engine.take_console_messages()
+    }
+
+    /// Send console output from page scripts to `sender` as it happens,
+    /// instead of keeping it for `take_console_messages`.
+    pub fn set_console_log_sender(&mut self, sender: std::sync::mpsc::Sender<(String, String)>) {
+        self.js_engine.set_console_log_sender(sender);
+    }
+
+    /// Store a cookie for `url`'s host. It is sent with later requests there,
+    /// alongside cookies set by the sites themselves.
+    pub async fn set_cookie(&self, url: &str, name: &str, value: &str) -> Result<(), Box<dyn Error>> {
+        Ok(self.networking.set_cookie(url, name, value).await?)
+    }
+
+    /// Cookies a request to `url` would carry, as (name, value) pairs sorted by name.
+    pub async fn get_cookies(&self, url: &str) -> Vec<(String, String)> {
+        self.networking.cookies(url).await
+    }
+
+    /// Drop all cached responses, so the next loads fetch everything again.
+    pub async fn clear_cache(&self) {
+        self.networking.clear_cache().await;
+    }
+
+    /// Drop all cookies, both site-set and those added with `set_cookie`.
+    pub async fn clear_cookies(&self) {
+        self.networking.clear_cookies().await;
+    }
+
+    /// DOM of the current page as its scripts left it. Snapshot it and compare
+    /// with a later one through `diff_dom` to see what changed.
+    pub fn document(&self) -> Option<&DomNode> {
+        self.styled_dom.as_ref().map(|styled_dom| &styled_dom.node)
+    }
+
+    /// The network layer pages load through, sharing its cache and cookies,
+    /// for requests of the embedder's own.
+    pub fn network(&self) -> &std::sync::Arc<NetworkManager> {
+        &self.networking
+    }
+
+    /// The network layer, for changing its settings. `None` while requests the
+    /// browser started (or `fetch_all` batches) still hold a reference to it.
+    pub fn network_mut(&mut self) -> Option<&mut NetworkManager> {
+        std::sync::Arc::get_mut(&mut self.networking)
+    }
+
+    /// URL of the page currently loaded, after redirects.
+    pub fn current_url(&self) -> Option<&str> {
+        self.current_url.as_deref()
+    }
+
+    async fn traverse_history(&mut self, delta: isize) -> Result<Option<(crate::rendering::DisplayList, String)>, Box<dyn Error>> {
+        let Some(url) = self.history.peek(delta).map(str::to_string) else {
+            return Ok(None);
+        };
+        let page = self.navigate(&url, false, false).await?;
+        // Only move once the entry has actually loaded
+        self.history.go(delta);
+        Ok(Some(page))
+    }
+
+    async fn navigate(
+        &mut self,
+        url: &str,
+        record_history: bool,
+        bypass_cache: bool,
+    ) -> Result<(crate::rendering::DisplayList, String), Box<dyn Error>> {
+        self.navigate_with_body(url, None, record_history, bypass_cache).await
+    }
+
+    // Navigation that POSTs `form_body` (urlencoded) when given, as a form submission does
+    async fn navigate_with_body(
+        &mut self,
+        url: &str,
+        form_body: Option<Vec<u8>>,
+        record_history: bool,
+        bypass_cache: bool,
+    ) -> Result<(crate::rendering::DisplayList, String), Box<dyn Error>> {
+        // A meta refresh may have moved us; everything below resolves against the final URL
+        let (final_url, dom) = self.fetch_document(url, form_body, bypass_cache).await?;
+        self.current_url = Some(final_url.clone());
+        if record_history {
+            self.history.push(final_url.clone());
+        }
+        let url = final_url.as_str();
 
         let dom_root = dom.root().ok_or("No root node found")?;
         let root_node = self
@@ -88,10 +325,23 @@ impl Browser {
         use std::cell::RefCell;
         let shared_dom_root = Rc::new(RefCell::new(root_node.clone()));
 
+        // Stylesheets load before scripts run, as scripts may query computed styles
+        let base_uri = crate::networking::Uri::parse(url).ok();
+        let stylesheet = self.load_stylesheets(root_node, base_uri.as_ref()).await;
+        log::info!(target: "browser", "Loaded stylesheet with {} rules", stylesheet.rules().len());
+
         if self.config.enable_javascript {
             // Bind DOM to JavaScript engine before executing scripts
             // Pass the shared reference so JS can modify the actual DOM
-            self.js_engine.bind_dom_shared(Rc::clone(&shared_dom_root));
+            if let Err(e) = self.js_engine.bind_dom(Rc::clone(&shared_dom_root)) {
+                log::warn!(target: "browser", "Failed to bind DOM to JavaScript: {}", e);
+            }
+            self.js_engine.bind_stylesheet(stylesheet.clone());
+            let networking = std::sync::Arc::clone(&self.networking);
+            let page_url = url.to_string();
+            self.js_engine.runtime_mut().set_fetcher(Box::new(move |method: &str, target: &str, body: Option<&str>| {
+                script_request(&networking, &page_url, method, target, body)
+            }));
             
             // Create javascript-detection element if it doesn't exist
             {
@@ -131,9 +381,9 @@ impl Browser {
                     log::info!(target: "browser", "javascript-detection element not found, creating it");
                     // Create the element
                     let detection_elem = dom::Node::new(dom::NodeType::Element {
-                        tag_name: "div".to_string(),
+                        tag_name: "div".into(),
                         attributes: vec![dom::Attribute {
-                            name: "id".to_string(),
+                            name: "id".into(),
                             value: "javascript-detection".to_string(),
                         }],
                         events: Vec::new(),
@@ -152,17 +402,17 @@ impl Browser {
             }
             
             // Execute inline scripts first (non-defer)
-            self.execute_inline_scripts(&*shared_dom_root.borrow());
+            self.execute_inline_scripts(&shared_dom_root);
             
             // Execute external scripts (non-defer)
             if let Ok(base_uri) = crate::networking::Uri::parse(url) {
-                self.execute_external_scripts(&*shared_dom_root.borrow(), &base_uri, false).await;
+                self.execute_external_scripts(&shared_dom_root, &base_uri, false).await;
             }
             
             // Execute deferred scripts BEFORE firing DOMContentLoaded
             // This ensures functions like do_capabilities_detection() are defined
             if let Ok(base_uri) = crate::networking::Uri::parse(url) {
-                self.execute_external_scripts(&*shared_dom_root.borrow(), &base_uri, true).await;
+                self.execute_external_scripts(&shared_dom_root, &base_uri, true).await;
             }
             
             // Check if do_capabilities_detection is defined, and define stub if not
@@ -213,6 +463,11 @@ impl Browser {
                     log::debug!(target: "browser", "Could not check do_capabilities_detection type: {}", e);
                 }
             }
+
+            // Subresources have all been fetched by now, so the page counts as loaded
+            if let Err(e) = self.js_engine.runtime_mut().fire_load() {
+                log::warn!(target: "browser", "Error firing load: {}", e);
+            }
         }
         
         // Use the shared DOM root for rendering (may have been modified by JS)
@@ -264,72 +519,263 @@ impl Browser {
 
         debug!(target: "browser", "Found root node with {} children", root.children().len());
 
-        // Parse CSS
-        let base_uri = crate::networking::Uri::parse(url).ok();
-        let stylesheet = self.load_stylesheets(&*root, base_uri.as_ref()).await;
-        log::info!(target: "browser", "Loaded stylesheet with {} rules", stylesheet.rules().len());
         let style_engine = css::style::StyleEngine::new(stylesheet);
         let styled_dom = style_engine.apply_styles(&*root);
 
         // Create display list and render using RenderTree
         // Log viewport size before layout
         log::info!(target: "browser", "About to compute layout, viewport should be set");
-        let render_tree = self.renderer.build_render_tree(&styled_dom);
-        let display_list = render_tree.build_display_list();
+        let display_list = self.build_display_list(&styled_dom);
         self.renderer.paint(&display_list)?;
+        let text = self.extract_styled_text(&styled_dom);
+        self.styled_dom = Some(styled_dom);
 
         // Print text content (trace level)
         log::trace!(target: "browser", "Page Content:");
         self.extract_content(&*root);
 
-        Ok((display_list, self.extract_text_content(&*root)))
+        Ok((display_list, text))
     }
     
-    pub fn extract_text_content(&self, node: &dom::Node) -> String {
-        let mut text = String::new();
-        self.extract_text_content_recursive(node, &mut text);
-        text
+    /// Lay out the most recently loaded page at the current viewport size and
+    /// rasterize it to PNG bytes.
+    pub fn screenshot(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let styled_dom = self.styled_dom.take().ok_or("No page has been loaded")?;
+        let display_list = self.build_display_list(&styled_dom);
+        self.styled_dom = Some(styled_dom);
+        self.renderer.screenshot(&display_list)
     }
-    
-    fn extract_text_content_recursive(&self, node: &dom::Node, text: &mut String) {
-        match node.node_type() {
-            dom::NodeType::Element { tag_name, .. } => {
-                // Skip non-content elements
-                if matches!(tag_name.as_str(), "script" | "style" | "meta" | "link" | "head") {
-                    return;
+
+    // Referrer for requests made on behalf of the current page
+    fn referer(&self) -> Option<&str> {
+        if self.config.send_referer {
+            self.current_url.as_deref()
+        } else {
+            None
+        }
+    }
+
+    fn build_display_list(&mut self, styled_dom: &css::style::StyledNode) -> rendering::DisplayList {
+        let mut display_list = self.renderer.build_render_tree(styled_dom).build_display_list();
+        if !self.config.load_images {
+            display_list.collapse_images();
+        }
+        // Links navigate to absolute URLs
+        let base = self.current_url.as_deref().and_then(|url| networking::Uri::parse(url).ok());
+        display_list.resolve_links(|href| base.as_ref()?.resolve_reference(href).ok());
+        display_list
+    }
+
+    /// Fetch the images referenced by a display list, resolving relative URLs
+    /// against `base_url`. Returns nothing when image loading is disabled.
+    pub async fn fetch_images(&self, display_list: &rendering::DisplayList, base_url: &str) -> Vec<(String, Vec<u8>)> {
+        let mut images = Vec::new();
+        if !self.config.load_images {
+            debug!(target: "browser", "Image loading disabled, skipping image fetches");
+            return images;
+        }
+        let Ok(base) = networking::Uri::parse(base_url) else {
+            return images;
+        };
+
+        for item in display_list.items() {
+            if let rendering::DisplayItem::Image { url, .. } = item {
+                if url.is_empty() {
+                    continue;
                 }
-                
-                // Process children
-                for child in node.children() {
-                    self.extract_text_content_recursive(child, text);
+                let Ok(image_url) = base.resolve_reference(url) else {
+                    continue;
+                };
+                match self.networking.fetch_with_referer(&image_url, self.referer()).await {
+                    Ok(response) => {
+                        info!(target: "browser", "Fetched image: {} ({} bytes)", image_url, response.body.len());
+                        images.push((image_url, response.body));
+                    }
+                    Err(e) => {
+                        log::warn!(target: "browser", "Failed to fetch image {}: {}", image_url, e);
+                    }
                 }
-                
-                // Add newlines after block elements
-                if matches!(
-                    tag_name.as_str(),
-                    "div" | "p" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" |
-                    "article" | "section" | "header" | "footer" | "br" |
-                    "ul" | "ol" | "li" | "table" | "tr" | "form"
-                ) {
-                    text.push('\n');
+            }
+        }
+        images
+    }
+
+    /// Fetch and parse a document, following zero-delay `<meta http-equiv="refresh">`
+    /// redirects up to the network redirect cap. Returns the final URL and its DOM.
+    async fn fetch_document(
+        &mut self,
+        url: &str,
+        form_body: Option<Vec<u8>>,
+        bypass_cache: bool,
+    ) -> Result<(String, dom::DomTree), Box<dyn Error>> {
+        let mut current = url.to_string();
+        // Only the first request carries the form; refresh targets are plain GETs
+        let mut form_body = form_body;
+
+        for _ in 0..=networking::MAX_REDIRECTS {
+            let dom = self.fetch_and_parse(&current, form_body.take().as_deref(), bypass_cache).await?;
+
+            match dom.root().and_then(meta_refresh_target) {
+                Some((0, target)) => {
+                    let resolved = networking::Uri::parse(&current)?.resolve_reference(&target)?;
+                    info!(target: "browser", "Following meta refresh from {} to {}", current, resolved);
+                    current = resolved;
+                }
+                Some((delay, target)) => {
+                    debug!(target: "browser", "Ignoring meta refresh to {} after {}s", target, delay);
+                    return Ok((current, dom));
                 }
+                None => return Ok((current, dom)),
+            }
+        }
+
+        Err(networking::NetworkError::TooManyRedirects.into())
+    }
+
+    async fn fetch_and_parse(
+        &mut self,
+        url: &str,
+        form_body: Option<&[u8]>,
+        bypass_cache: bool,
+    ) -> Result<dom::DomTree, Box<dyn Error>> {
+        println!("\n[*] Loading: {}", url);
+        info!(target: "browser", "Starting request for URL: {}", url);
+
+        // Navigation: the referrer is the page we're leaving, if any
+        let response = match form_body {
+            Some(body) => {
+                self.networking
+                    .post(url, "application/x-www-form-urlencoded", body, self.referer())
+                    .await?
             }
+            None => self.networking.fetch_with_options(url, self.referer(), bypass_cache).await?,
+        };
+        println!("[+] Status: {}", response.status.code);
+
+        // Pick the decoder from the Content-Type charset, falling back to a <meta> pre-scan
+        let content_type = response.headers.get("content-type").map(|s| s.as_str());
+        let raw_content = html::charset::decode_document(&response.body, content_type);
+
+        // Print raw content only in headless mode (for debugging)
+        if self.config.headless {
+            println!("\n[+] Raw HTML Content:");
+            println!("{}", "=".repeat(80));
+            println!("{}", raw_content);
+            println!("{}", "=".repeat(80));
+        }
+
+        // Use the full HTML content as-is - the parser should handle DOCTYPE, comments, etc.
+        let html_content = raw_content;
+
+        log::trace!(target: "browser", "Parsed HTML content (first 500 chars): {}",
+            html_content.chars().take(500).collect::<String>());
+        if html_content.len() > 500 {
+            log::trace!(target: "browser", "HTML content truncated (total length: {} chars)", html_content.len());
+        }
+
+        debug!(target: "browser", "Starting HTML parsing");
+        let mut parser = html::parser::Parser::new(html_content);
+        Ok(parser.parse())
+    }
+
+    pub fn extract_text_content(&self, node: &dom::Node) -> String {
+        self.extract_styled_text(&css::style::StyledNode::new(node.clone()))
+    }
+
+    // The text of a styled tree, so stylesheet rules can hide or reflow it
+    fn extract_styled_text(&self, node: &css::style::StyledNode) -> String {
+        let mut segments = TextSegments::default();
+        self.extract_text_content_recursive(node, TextContext::default(), &mut segments);
+        segments.0.into_iter().map(|(_, text)| text).collect()
+    }
+
+    /// The text of `node` like `extract_text_content`, split into runs by the
+    /// language in effect: the `lang` attribute of the nearest element that has
+    /// one, or None when no element does or it is empty.
+    pub fn extract_text_with_lang(&self, node: &dom::Node) -> Vec<(Option<String>, String)> {
+        let mut segments = TextSegments::default();
+        let styled = css::style::StyledNode::new(node.clone());
+        self.extract_text_content_recursive(&styled, TextContext::default(), &mut segments);
+        segments
+            .0
+            .into_iter()
+            .map(|(lang, text)| (lang, text.trim().to_string()))
+            .filter(|(_, text)| !text.is_empty())
+            .collect()
+    }
+    
+    fn extract_text_content_recursive(&self, node: &css::style::StyledNode, context: TextContext<'_>, segments: &mut TextSegments) {
+        match node.node.node_type() {
+            dom::NodeType::Element { tag_name, .. } => self.extract_element_text(node, tag_name, context, segments),
             dom::NodeType::Text(content) => {
-                let trimmed = content.trim();
-                if !trimmed.is_empty() {
-                    let decoded = html::entities::decode_html_entities(trimmed);
-                    if !decoded.trim().is_empty() {
-                        if !text.is_empty() && !text.ends_with('\n') && !text.ends_with(' ') {
-                            text.push(' ');
-                        }
-                        text.push_str(&decoded);
-                    }
-                }
+                let decoded = html::entities::decode_html_entities(content);
+                segments.push_text(context.lang, &decoded, context.white_space);
             }
             _ => {}
         }
     }
 
+    fn extract_element_text(
+        &self,
+        styled: &css::style::StyledNode,
+        tag_name: &str,
+        context: TextContext<'_>,
+        segments: &mut TextSegments,
+    ) {
+        let node = &styled.node;
+        // Skip non-content elements
+        if matches!(tag_name, "script" | "style" | "meta" | "link" | "head") {
+            return;
+        }
+        // <noscript> is fallback content for browsers without scripting
+        if tag_name == "noscript" && self.config.enable_javascript {
+            return;
+        }
+
+        // Styles can change how the element lays out its text
+        let keyword = |property: &str| match styled.get(property) {
+            Some(css::Value::Keyword(kw)) => Some(kw.to_ascii_lowercase()),
+            _ => None,
+        };
+        let is_block = match keyword("display").as_deref() {
+            // Nor is anything under display: none
+            Some("none") => return,
+            Some(display) => !display.starts_with("inline") && !matches!(display, "contents" | "table-cell"),
+            None => is_block_element(tag_name),
+        };
+        let default_white_space = if matches!(tag_name, "pre" | "textarea" | "listing" | "xmp") {
+            rendering::layout::WhiteSpace::Pre
+        } else {
+            context.white_space
+        };
+        let context = TextContext {
+            lang: match node.get_attribute("lang").map(str::trim) {
+                Some("") => None,
+                Some(declared) => Some(declared),
+                None => context.lang,
+            },
+            white_space: keyword("white-space")
+                .and_then(|kw| rendering::layout::WhiteSpace::from_keyword(&kw))
+                .unwrap_or(default_white_space),
+        };
+
+        // Block boxes start and end on their own lines
+        if is_block {
+            segments.push_break();
+        }
+        for child in &styled.children {
+            self.extract_text_content_recursive(child, context, segments);
+        }
+        if tag_name == "br" {
+            segments.push_line_break(context.lang);
+        } else if is_block {
+            segments.push_break();
+        } else if matches!(tag_name, "td" | "th") {
+            // Keep the text of adjacent cells apart
+            segments.push_text(context.lang, " ", rendering::layout::WhiteSpace::Normal);
+        }
+    }
+
     fn find_first_element<'a>(&self, node: &'a dom::Node, tag_name: &str) -> Option<&'a dom::Node> {
         match node.node_type() {
             dom::NodeType::Element { tag_name: t, .. } if t.eq_ignore_ascii_case(tag_name) => {
@@ -415,33 +861,35 @@ impl Browser {
         }
     }
 
+    // Iterative, so logging never recurses as deep as the tree goes
     fn print_dom_structure(&self, node: &dom::Node, indent: usize) {
-        let indent_str = " ".repeat(indent);
+        let mut pending = vec![(node, indent)];
+        while let Some((node, indent)) = pending.pop() {
+            let indent_str = " ".repeat(indent);
 
-        match node.node_type() {
-            dom::NodeType::Element {
-                tag_name,
-                attributes,
-                ..
-            } => {
-                log::trace!(target: "browser", "{}Element: <{}>", indent_str, tag_name);
-                if !attributes.is_empty() {
-                    log::trace!(target: "browser", "{}Attributes: {:?}", indent_str + "  ", attributes);
-                }
+            match node.node_type() {
+                dom::NodeType::Element {
+                    tag_name,
+                    attributes,
+                    ..
+                } => {
+                    log::trace!(target: "browser", "{}Element: <{}>", indent_str, tag_name);
+                    if !attributes.is_empty() {
+                        log::trace!(target: "browser", "{}Attributes: {:?}", indent_str + "  ", attributes);
+                    }
 
-                // Process all children
-                for child in node.children().iter() {
-                    self.print_dom_structure(child, indent + 2);
+                    // Process all children, first child on top
+                    pending.extend(node.children().iter().rev().map(|child| (child, indent + 2)));
                 }
-            }
-            dom::NodeType::Text(text) => {
-                let text = text.trim();
-                if !text.is_empty() {
-                    log::trace!(target: "browser", "{}Text: \"{}\"", indent_str, text);
+                dom::NodeType::Text(text) => {
+                    let text = text.trim();
+                    if !text.is_empty() {
+                        log::trace!(target: "browser", "{}Text: \"{}\"", indent_str, text);
+                    }
+                }
+                dom::NodeType::Comment(comment) => {
+                    log::trace!(target: "browser", "{}Comment: \"{}\"", indent_str, comment);
                 }
-            }
-            dom::NodeType::Comment(comment) => {
-                log::trace!(target: "browser", "{}Comment: \"{}\"", indent_str, comment);
             }
         }
     }
@@ -455,147 +903,255 @@ impl Browser {
         let _ = self.js_engine.evaluate("if(typeof do_capabilities_detection === 'function') { do_capabilities_detection(); }");
     }
 
-    fn execute_inline_scripts(&mut self, node: &dom::Node) {
-        match node.node_type() {
-            dom::NodeType::Element { tag_name, attributes, .. } => {
-                if tag_name == "script" {
-                    if !is_javascript_script_tag(attributes) {
-                        // e.g. application/ld+json, module, etc.
-                        return;
-                    }
+    // Scripts are collected before any of them runs: a borrow of the DOM held
+    // while they run would make any DOM mutation from script panic.
+    fn execute_inline_scripts(&mut self, dom: &std::rc::Rc<std::cell::RefCell<dom::Node>>) {
+        let mut scripts = Vec::new();
+        collect_inline_scripts(&dom.borrow(), &mut scripts);
+        for script in scripts {
+            debug!(target: "browser", "Executing inline JavaScript");
+            if let Err(e) = self.js_engine.evaluate(&script) {
+                log::warn!(target: "javascript", "Inline script error: {}", e);
+            }
+        }
+    }
 
-                    // Check if it's an inline script (no src attribute)
-                    if !attributes.iter().any(|attr| attr.name == "src") {
-                        // Get the script content from children
-                        if let Some(text_node) = node.children().first() {
-                            if let dom::NodeType::Text(script) = text_node.node_type() {
-                                debug!(target: "browser", "Executing inline JavaScript");
-                                if let Err(e) = self.js_engine.evaluate(script) {
-                                    log::warn!(target: "javascript", "Inline script error: {}", e);
-                                }
-                            }
-                        }
-                    }
-                }
+    async fn execute_external_scripts(
+        &mut self,
+        dom: &std::rc::Rc<std::cell::RefCell<dom::Node>>,
+        base_uri: &crate::networking::Uri,
+        defer_only: bool,
+    ) {
+        // Same as the network layer's own request timeout
+        const SCRIPT_FETCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
 
-                // Recursively process children
-                for child in node.children() {
-                    self.execute_inline_scripts(child);
+        // Fetch every script up front, then run them in document order
+        let mut urls = Vec::new();
+        collect_external_scripts(&dom.borrow(), base_uri, defer_only, &mut urls);
+        let responses = self
+            .networking
+            .fetch_concurrently(&urls, self.referer(), MAX_CONCURRENT_FETCHES, SCRIPT_FETCH_TIMEOUT)
+            .await;
+
+        for (resolved, response) in urls.iter().zip(responses) {
+            match response {
+                Ok(response) => self.run_external_script(resolved, &response.body),
+                Err(e) => {
+                    log::warn!(
+                        target: "browser",
+                        "Failed to load external script {}: {}",
+                        resolved,
+                        e
+                    );
                 }
             }
-            _ => {}
         }
     }
 
-    async fn execute_external_scripts(&mut self, node: &dom::Node, base_uri: &crate::networking::Uri, defer_only: bool) {
+    fn run_external_script(&mut self, resolved: &str, body: &[u8]) {
         const MAX_EXTERNAL_SCRIPT_BYTES: usize = 256 * 1024; // Keep initial JS support lightweight.
 
-        match node.node_type() {
-            dom::NodeType::Element { tag_name, attributes, .. } => {
-                if tag_name == "script" {
-                    if !is_javascript_script_tag(attributes) {
-                        return;
-                    }
+        if body.len() > MAX_EXTERNAL_SCRIPT_BYTES {
+            log::warn!(
+                target: "javascript",
+                "Skipping large external script ({} bytes): {}",
+                body.len(),
+                resolved
+            );
+            return;
+        }
 
-                    // Check for defer attribute
-                    let has_defer = attributes.iter().any(|attr| attr.name == "defer");
-                    
-                    // Skip if we're only processing defer scripts and this doesn't have defer
-                    // Or if we're processing non-defer scripts and this has defer
-                    if defer_only != has_defer {
-                        // Recursively process children
-                        for child in node.children() {
-                            Box::pin(self.execute_external_scripts(child, base_uri, defer_only)).await;
+        let script = String::from_utf8_lossy(body);
+        log::info!(target: "browser", "Executing external script from {} ({} bytes)", resolved, script.len());
+        if let Err(e) = self.js_engine.evaluate(&script) {
+            log::warn!(
+                target: "javascript",
+                "External script error ({}): {}",
+                resolved,
+                e
+            );
+            return;
+        }
+        log::info!(target: "browser", "External script from {} executed successfully", resolved);
+        // Check if do_capabilities_detection is now defined
+        if resolved.contains("site.min.js") {
+            // Try to manually define it for testing if it's not found
+            let check_str = self.js_engine.evaluate("String(typeof do_capabilities_detection)");
+            if let Ok(ref val) = check_str {
+                // Use debug format to check the value
+                let val_str = format!("{:?}", val);
+                if val_str.contains("undefined") {
+                    log::warn!(target: "browser", "do_capabilities_detection not defined after site.min.js, defining stub");
+                    // Define a stub function that modifies the DOM
+                    let stub_code = r#"
+                        function do_capabilities_detection() {
+                            var elem = document.getElementById('javascript-detection');
+                            if (elem) {
+                                elem.innerHTML = '<span class="detection-message">Yes - JavaScript is enabled</span>';
+                            }
                         }
-                        return;
+                    "#;
+                    if let Err(e) = self.js_engine.evaluate(stub_code) {
+                        log::warn!(target: "browser", "Failed to define stub do_capabilities_detection: {}", e);
+                    } else {
+                        log::info!(target: "browser", "Successfully defined stub do_capabilities_detection");
                     }
+                }
+            }
+        }
+    }
+}
 
-                    if let Some(src) = attributes.iter().find(|attr| attr.name == "src") {
-                        let resolved = match base_uri.resolve_reference(&src.value) {
-                            Ok(u) => u,
-                            Err(e) => {
-                                log::warn!(
-                                    target: "browser",
-                                    "Failed to resolve script src '{}' against '{}': {}",
-                                    src.value,
-                                    base_uri,
-                                    e
-                                );
-                                return;
-                            }
-                        };
+// What text extraction inherits from the enclosing elements
+#[derive(Clone, Copy)]
+struct TextContext<'a> {
+    lang: Option<&'a str>,
+    white_space: rendering::layout::WhiteSpace,
+}
+
+impl Default for TextContext<'_> {
+    fn default() -> Self {
+        Self { lang: None, white_space: rendering::layout::WhiteSpace::Normal }
+    }
+}
+
+// Elements laid out as blocks unless styled otherwise
+fn is_block_element(tag_name: &str) -> bool {
+    matches!(
+        tag_name,
+        "html" | "body" | "div" | "p" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" |
+        "article" | "section" | "header" | "footer" | "main" | "nav" | "aside" |
+        "ul" | "ol" | "li" | "dl" | "dt" | "dd" | "table" | "tr" | "form" | "fieldset" |
+        "blockquote" | "pre" | "figure" | "figcaption" | "address" | "hr" | "details" | "summary"
+    )
+}
+
+// Extracted text as (language, text) runs, a new run starting wherever the
+// language changes. Concatenated, the runs are the plain extracted text.
+#[derive(Default)]
+struct TextSegments(Vec<(Option<String>, String)>);
 
+impl TextSegments {
+    // Append a text node's contents, with whitespace handled per `white_space`
+    fn push_text(&mut self, lang: Option<&str>, text: &str, white_space: rendering::layout::WhiteSpace) {
+        use rendering::layout::WhiteSpace;
+        let text = match white_space {
+            WhiteSpace::Pre | WhiteSpace::PreWrap => text.to_string(),
+            WhiteSpace::PreLine => collapse_whitespace(text, true),
+            WhiteSpace::Normal | WhiteSpace::NoWrap => collapse_whitespace(text, false),
+        };
+        // A collapsible space never starts a line or doubles another one
+        let after_space = self.0.last().is_none_or(|(_, last)| last.ends_with(['\n', ' ']));
+        let text = match white_space {
+            WhiteSpace::Pre | WhiteSpace::PreWrap => text,
+            _ if after_space => text.trim_start_matches(' ').to_string(),
+            // The parser drops whitespace-only text between tags, so runs of
+            // text from separate nodes are taken to be separate words
+            _ if !text.starts_with(' ') && !text.is_empty() => format!(" {}", text),
+            _ => text,
+        };
+        if text.is_empty() {
+            return;
+        }
+        let text = text.as_str();
+        match self.0.last_mut() {
+            // Whitespace between runs stays with the run before it
+            Some((last_lang, last)) if last_lang.as_deref() == lang || text.trim().is_empty() => last.push_str(text),
+            _ => self.0.push((lang.map(str::to_string), text.to_string())),
+        }
+    }
+
+    // Edge of a block box: end the current line, if one is open
+    fn push_break(&mut self) {
+        if let Some((_, last)) = self.0.last_mut() {
+            last.truncate(last.trim_end_matches(' ').len());
+            if !last.is_empty() && !last.ends_with('\n') {
+                last.push('\n');
+            }
+        }
+    }
+
+    // A `<br>`: always a new line, even an empty one
+    fn push_line_break(&mut self, lang: Option<&str>) {
+        match self.0.last_mut() {
+            Some((_, last)) => {
+                last.truncate(last.trim_end_matches(' ').len());
+                last.push('\n');
+            }
+            None => self.0.push((lang.map(str::to_string), "\n".to_string())),
+        }
+    }
+}
+
+// Collapse each run of whitespace to one space. With `keep_newlines`, newlines
+// survive and swallow the spaces around them.
+fn collapse_whitespace(text: &str, keep_newlines: bool) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    for c in text.chars() {
+        if keep_newlines && c == '\n' {
+            collapsed.truncate(collapsed.trim_end_matches(' ').len());
+            collapsed.push('\n');
+        } else if c.is_ascii_whitespace() {
+            let separated = collapsed.ends_with(' ') || (keep_newlines && collapsed.ends_with('\n'));
+            if !separated {
+                collapsed.push(' ');
+            }
+        } else {
+            collapsed.push(c);
+        }
+    }
+    collapsed
+}
+
+// Source of every inline classic script under `node`, in document order
+fn collect_inline_scripts(node: &dom::Node, scripts: &mut Vec<String>) {
+    let dom::NodeType::Element { tag_name, attributes, .. } = node.node_type() else {
+        return;
+    };
+    // Scripts with a src are external; other types are e.g. application/ld+json or module
+    if tag_name == "script"
+        && is_javascript_script_tag(attributes)
+        && !attributes.iter().any(|attr| attr.name == "src")
+        && let Some(dom::NodeType::Text(script)) = node.children().first().map(|child| child.node_type())
+    {
+        scripts.push(script.clone());
+    }
+    for child in node.children() {
+        collect_inline_scripts(child, scripts);
+    }
+}
+
+// Resolved URLs of the external scripts under `node`, in document order, taking
+// only deferred or only non-deferred ones
+fn collect_external_scripts(node: &dom::Node, base_uri: &networking::Uri, defer_only: bool, urls: &mut Vec<String>) {
+    if let dom::NodeType::Element { tag_name, attributes, .. } = node.node_type() {
+        if tag_name == "script" {
+            if !is_javascript_script_tag(attributes) {
+                return;
+            }
+            let has_defer = attributes.iter().any(|attr| attr.name == "defer");
+            let src = attributes.iter().find(|attr| attr.name == "src");
+            if let Some(src) = src.filter(|_| defer_only == has_defer) {
+                match base_uri.resolve_reference(&src.value) {
+                    Ok(resolved) => {
                         debug!(target: "browser", "Loading external JavaScript from {}", resolved);
-                        
-                        match self.networking.fetch(&resolved).await {
-                            Ok(response) => {
-                                if response.body.len() > MAX_EXTERNAL_SCRIPT_BYTES {
-                                    log::warn!(
-                                        target: "javascript",
-                                        "Skipping large external script ({} bytes): {}",
-                                        response.body.len(),
-                                        resolved
-                                    );
-                                    return;
-                                }
-
-                                let script = String::from_utf8_lossy(&response.body);
-                                log::info!(target: "browser", "Executing external script from {} ({} bytes)", resolved, script.len());
-                                if let Err(e) = self.js_engine.evaluate(&script) {
-                                    log::warn!(
-                                        target: "javascript",
-                                        "External script error ({}): {}",
-                                        resolved,
-                                        e
-                                    );
-                                } else {
-                                    log::info!(target: "browser", "External script from {} executed successfully", resolved);
-                                    // Check if do_capabilities_detection is now defined
-                                    if resolved.contains("site.min.js") {
-                                        // Try to manually define it for testing if it's not found
-                                        let check_str = self.js_engine.evaluate("String(typeof do_capabilities_detection)");
-                                        if let Ok(ref val) = check_str {
-                                            // Use debug format to check the value
-                                            let val_str = format!("{:?}", val);
-                                            if val_str.contains("undefined") {
-                                                log::warn!(target: "browser", "do_capabilities_detection not defined after site.min.js, defining stub");
-                                                // Define a stub function that modifies the DOM
-                                                let stub_code = r#"
-                                                    function do_capabilities_detection() {
-                                                        var elem = document.getElementById('javascript-detection');
-                                                        if (elem) {
-                                                            elem.innerHTML = '<span class="detection-message">Yes - JavaScript is enabled</span>';
-                                                        }
-                                                    }
-                                                "#;
-                                                if let Err(e) = self.js_engine.evaluate(stub_code) {
-                                                    log::warn!(target: "browser", "Failed to define stub do_capabilities_detection: {}", e);
-                                                } else {
-                                                    log::info!(target: "browser", "Successfully defined stub do_capabilities_detection");
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                log::warn!(
-                                    target: "browser",
-                                    "Failed to load external script {}: {}",
-                                    resolved,
-                                    e
-                                );
-                            }
-                        }
+                        urls.push(resolved);
+                    }
+                    Err(e) => {
+                        log::warn!(
+                            target: "browser",
+                            "Failed to resolve script src '{}' against '{}': {}",
+                            src.value,
+                            base_uri,
+                            e
+                        );
+                        return;
                     }
-                }
-
-                // Use Box::pin for recursive async calls
-                for child in node.children() {
-                    Box::pin(self.execute_external_scripts(child, base_uri, defer_only)).await;
                 }
             }
-            _ => {}
+        }
+        for child in node.children() {
+            collect_external_scripts(child, base_uri, defer_only, urls);
         }
     }
 }
@@ -621,6 +1177,83 @@ fn is_javascript_script_tag(attributes: &[dom::Attribute]) -> bool {
     )
 }
 
+/// Run an XMLHttpRequest or fetch from a script on `page_url` to completion. The script
+/// engine is synchronous, so this blocks its worker thread, which needs the
+/// multi-threaded runtime.
+fn script_request(
+    networking: &networking::NetworkManager,
+    page_url: &str,
+    method: &str,
+    target: &str,
+    body: Option<&str>,
+) -> Result<(u16, String), String> {
+    let url = networking::Uri::parse(page_url)
+        .and_then(|base| base.resolve_reference(target))
+        .map_err(|e| e.to_string())?;
+    let handle = tokio::runtime::Handle::try_current().map_err(|e| e.to_string())?;
+    if handle.runtime_flavor() != tokio::runtime::RuntimeFlavor::MultiThread {
+        return Err("synchronous requests need the multi-threaded runtime".to_string());
+    }
+    if method != "GET" && method != "POST" {
+        return Err(format!("unsupported method {}", method));
+    }
+    let request = async {
+        if method == "POST" {
+            let body = body.unwrap_or_default().as_bytes();
+            networking.post(&url, "text/plain;charset=UTF-8", body, Some(page_url)).await
+        } else {
+            networking.fetch_with_referer(&url, Some(page_url)).await
+        }
+    };
+    let response = tokio::task::block_in_place(|| handle.block_on(request)).map_err(|e| e.to_string())?;
+    Ok((response.status.code, String::from_utf8_lossy(&response.body).into_owned()))
+}
+
+/// Find a `<meta http-equiv="refresh">` in the document head and return its delay
+/// (whole seconds) and target URL. Refreshes without a URL (plain reloads) are ignored.
+fn meta_refresh_target(root: &dom::Node) -> Option<(u64, String)> {
+    fn find_head(node: &dom::Node) -> Option<&dom::Node> {
+        match node.node_type() {
+            dom::NodeType::Element { tag_name, .. } if tag_name.eq_ignore_ascii_case("head") => Some(node),
+            _ => node.children().iter().find_map(find_head),
+        }
+    }
+
+    let head = find_head(root)?;
+    head.children().iter().find_map(|child| {
+        let is_refresh = child.is_element("meta")
+            && child.get_attribute("http-equiv").is_some_and(|v| v.eq_ignore_ascii_case("refresh"));
+        if !is_refresh {
+            return None;
+        }
+        parse_meta_refresh(child.get_attribute("content")?)
+    })
+}
+
+// Parse a refresh value like `0;url=/next`, `5, URL='page.html'` or `0; /next`.
+fn parse_meta_refresh(content: &str) -> Option<(u64, String)> {
+    let content = content.trim();
+    let split_at = content.find([';', ',']).unwrap_or(content.len());
+    let (delay, rest) = content.split_at(split_at);
+    let delay = delay.trim().parse::<f64>().ok().filter(|d| *d >= 0.0)?.floor() as u64;
+
+    let rest = rest.trim_start_matches([';', ',']).trim_start();
+    let target = match rest.get(..3) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("url") => {
+            let after = rest[3..].trim_start();
+            after.strip_prefix('=').map(str::trim_start).unwrap_or(rest)
+        }
+        _ => rest,
+    };
+    let target = target.trim().trim_matches(|c| c == '"' || c == '\'').trim();
+
+    if target.is_empty() {
+        None
+    } else {
+        Some((delay, target.to_string()))
+    }
+}
+
 impl Browser {
     /// Load all stylesheets from inline <style> tags and external <link rel="stylesheet"> tags.
     async fn load_stylesheets(
@@ -631,70 +1264,54 @@ impl Browser {
         const CSS_FETCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
 
         let mut stylesheet = css::StyleSheet::new();
+        for (sheet, origin) in &self.extra_stylesheets {
+            stylesheet.merge(sheet.clone(), *origin);
+        }
         let mut css_sources = Vec::new();
 
         // Collect inline styles and external stylesheet URLs
         self.collect_css_sources(root, &mut css_sources);
         log::info!(target: "browser", "Found {} CSS sources", css_sources.len());
 
-        for source in css_sources {
-            match source {
-                CssSource::Inline(css) => {
-                    log::debug!(target: "browser", "Parsing inline CSS ({} chars)", css.len());
-                    let mut parser = css::parser::CssParser::new(css.clone());
-                    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| parser.parse())) {
-                        Ok(parsed) => {
-                            log::debug!(target: "browser", "Parsed {} CSS rules from inline styles", parsed.rules().len());
-                            for rule in parsed.rules() {
-                                stylesheet.add_rule(rule.clone());
-                            }
-                        }
-                        Err(e) => {
-                            log::error!(target: "browser", "CSS parsing panic in inline styles: {:?}", e);
-                            log::error!(target: "browser", "CSS content preview: {}", &css[..css.len().min(200)]);
-                        }
+        // External stylesheets are fetched together, then applied in document order
+        let resolved: Vec<Option<String>> = css_sources
+            .iter()
+            .map(|source| match source {
+                CssSource::External(href) => base_uri.and_then(|base| base.resolve_reference(href).ok()),
+                CssSource::Inline(_) => None,
+            })
+            .collect();
+        let urls: Vec<String> = resolved.iter().flatten().cloned().collect();
+        let mut responses = self
+            .networking
+            .fetch_concurrently(&urls, self.referer(), MAX_CONCURRENT_FETCHES, CSS_FETCH_TIMEOUT)
+            .await
+            .into_iter();
+
+        for (source, url) in css_sources.into_iter().zip(resolved) {
+            let (css, origin) = match (source, url) {
+                (CssSource::Inline(css), _) => (css, "inline styles".to_string()),
+                (CssSource::External(_), None) => continue,
+                (CssSource::External(_), Some(url)) => match responses.next() {
+                    Some(Ok(response)) => (String::from_utf8_lossy(&response.body).to_string(), url),
+                    Some(Err(e)) => {
+                        log::warn!(target: "browser", "Failed to fetch CSS {}: {}", url, e);
+                        continue;
                     }
+                    None => continue,
+                },
+            };
+
+            log::debug!(target: "browser", "Parsing CSS from {} ({} chars)", origin, css.len());
+            let mut parser = css::parser::CssParser::new(css.clone());
+            match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| parser.parse())) {
+                Ok(parsed) => {
+                    log::debug!(target: "browser", "Parsed {} CSS rules from {}", parsed.rules().len(), origin);
+                    stylesheet.merge(parsed, css::rules::Origin::Author);
                 }
-                CssSource::External(href) => {
-                    let Some(base) = base_uri else { continue };
-                    let resolved = match base.resolve_reference(&href) {
-                        Ok(r) => r,
-                        Err(_) => continue,
-                    };
-                    
-                    // Fetch with timeout to avoid hanging on slow/broken CSS resources
-                    let fetch_result = tokio::time::timeout(
-                        CSS_FETCH_TIMEOUT,
-                        self.networking.fetch(&resolved)
-                    ).await;
-                    
-                    let response = match fetch_result {
-                        Ok(Ok(r)) => r,
-                        Ok(Err(e)) => {
-                            log::warn!(target: "browser", "Failed to fetch CSS {}: {}", resolved, e);
-                            continue;
-                        }
-                        Err(_) => {
-                            log::warn!(target: "browser", "CSS fetch timed out: {}", resolved);
-                            continue;
-                        }
-                    };
-                    
-                    let css = String::from_utf8_lossy(&response.body).to_string();
-                    log::debug!(target: "browser", "Parsing external CSS from {} ({} chars)", resolved, css.len());
-                    let mut parser = css::parser::CssParser::new(css.clone());
-                    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| parser.parse())) {
-                        Ok(parsed) => {
-                            log::debug!(target: "browser", "Parsed {} CSS rules from {}", parsed.rules().len(), resolved);
-                            for rule in parsed.rules() {
-                                stylesheet.add_rule(rule.clone());
-                            }
-                        }
-                        Err(e) => {
-                            log::error!(target: "browser", "CSS parsing panic in {}: {:?}", resolved, e);
-                            log::error!(target: "browser", "CSS content preview: {}", &css[..css.len().min(200)]);
-                        }
-                    }
+                Err(e) => {
+                    log::error!(target: "browser", "CSS parsing panic in {}: {:?}", origin, e);
+                    log::error!(target: "browser", "CSS content preview: {}", &css[..css.len().min(200)]);
                 }
             }
         }
@@ -705,8 +1322,14 @@ impl Browser {
     fn collect_css_sources(&self, node: &dom::Node, sources: &mut Vec<CssSource>) {
         match node.node_type() {
             dom::NodeType::Element { tag_name, attributes, .. } => {
+                // Sheets for other media (print, speech) never apply to the screen
+                if let Some(media) = node.get_attribute("media")
+                    && !media_applies_to_screen(media)
+                {
+                    debug!(target: "browser", "Skipping <{}> for media \"{}\"", tag_name, media);
+                }
                 // Inline <style> tags
-                if tag_name.eq_ignore_ascii_case("style") {
+                else if tag_name.eq_ignore_ascii_case("style") {
                     if let Some(text_node) = node.children().first() {
                         if let dom::NodeType::Text(css) = text_node.node_type() {
                             sources.push(CssSource::Inline(css.clone()));
@@ -715,13 +1338,10 @@ impl Browser {
                 }
                 // External <link rel="stylesheet" href="...">
                 else if tag_name.eq_ignore_ascii_case("link") {
-                    let is_stylesheet = attributes
-                        .iter()
-                        .any(|a| a.name.eq_ignore_ascii_case("rel") && a.value.eq_ignore_ascii_case("stylesheet"));
-                    if is_stylesheet {
-                        if let Some(href) = attributes.iter().find(|a| a.name.eq_ignore_ascii_case("href")) {
-                            sources.push(CssSource::External(href.value.clone()));
-                        }
+                    if link_loads_stylesheet(node)
+                        && let Some(href) = attributes.iter().find(|a| a.name.eq_ignore_ascii_case("href"))
+                    {
+                        sources.push(CssSource::External(href.value.clone()));
                     }
                 }
 
@@ -739,3 +1359,883 @@ enum CssSource {
     Inline(String),
     External(String),
 }
+
+// Whether a media query list (the `media` attribute) can match a screen. Only the
+// media types are checked; feature tests like `(min-width: ...)` are assumed to pass.
+fn media_applies_to_screen(media: &str) -> bool {
+    media.split(',').any(|query| {
+        let query = query.trim().to_ascii_lowercase();
+        let (negated, rest) = match query.strip_prefix("not ") {
+            Some(rest) => (true, rest.trim_start()),
+            None => (false, query.strip_prefix("only ").unwrap_or(&query).trim_start()),
+        };
+        let media_type = rest.split(|c: char| c.is_whitespace() || c == '(').next().unwrap_or_default();
+        let matches = matches!(media_type, "" | "all" | "screen");
+        matches != negated
+    })
+}
+
+// Whether a <link> brings in a stylesheet to apply now. rel is a set of
+// space-separated tokens: alternate stylesheets stay disabled until the user picks
+// one, and `rel="preload" as="style"` is fetched and applied like a stylesheet.
+fn link_loads_stylesheet(link: &dom::Node) -> bool {
+    let rel = link.get_attribute("rel").unwrap_or_default().to_ascii_lowercase();
+    let has = |token: &str| rel.split_ascii_whitespace().any(|t| t == token);
+    if has("stylesheet") {
+        return !has("alternate");
+    }
+    has("preload") && link.get_attribute("as").is_some_and(|kind| kind.trim().eq_ignore_ascii_case("style"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networking::test_server::{TestResponse, TestServer};
+    use std::io::Read;
+
+    fn test_browser() -> Browser {
+        Browser::new(BrowserConfig {
+            enable_javascript: false,
+            ..BrowserConfig::default()
+        })
+        .expect("browser")
+    }
+
+    #[test]
+    fn test_parse_meta_refresh() {
+        assert_eq!(parse_meta_refresh("0;url=/next"), Some((0, "/next".to_string())));
+        assert_eq!(parse_meta_refresh("5, URL='page.html'"), Some((5, "page.html".to_string())));
+        assert_eq!(parse_meta_refresh("0; /next"), Some((0, "/next".to_string())));
+        assert_eq!(parse_meta_refresh("30"), None);
+    }
+
+    #[tokio::test]
+    async fn test_meta_refresh_redirect() {
+        let server = TestServer::start(vec![
+            (
+                "/start",
+                TestResponse::html(
+                    r#"<html><head><meta http-equiv="refresh" content="0;url=/target"></head><body>Redirecting</body></html>"#,
+                ),
+            ),
+            ("/target", TestResponse::html("<html><body><p>Target page</p></body></html>")),
+        ])
+        .await;
+
+        let mut browser = test_browser();
+        let (_, text) = browser.load_url(&server.url("/start")).await.expect("load");
+        assert!(text.contains("Target page"));
+        assert!(!text.contains("Redirecting"));
+    }
+
+    #[tokio::test]
+    async fn test_screenshot_matches_viewport() {
+        let server = TestServer::start(vec![(
+            "/",
+            TestResponse::html("<html><body><h1>Hello</h1><div style=\"background-color: red; height: 20px\"></div></body></html>"),
+        )])
+        .await;
+
+        let mut browser = test_browser();
+        assert!(browser.screenshot().is_err(), "no page loaded yet");
+        browser.set_viewport_size(640, 480);
+        browser.load_url(&server.url("/")).await.expect("load page");
+
+        let png = browser.screenshot().expect("screenshot");
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        let width = u32::from_be_bytes([png[16], png[17], png[18], png[19]]);
+        let height = u32::from_be_bytes([png[20], png[21], png[22], png[23]]);
+        assert_eq!((width, height), (640, 480));
+
+        // The single IDAT chunk inflates to one filter byte plus RGBA per row
+        let idat_len = u32::from_be_bytes([png[33], png[34], png[35], png[36]]) as usize;
+        assert_eq!(&png[37..41], b"IDAT");
+        let mut raw = Vec::new();
+        flate2::read::ZlibDecoder::new(&png[41..41 + idat_len])
+            .read_to_end(&mut raw)
+            .expect("inflate IDAT");
+        assert_eq!(raw.len(), 480 * (1 + 640 * 4));
+    }
+
+    #[test]
+    fn test_configured_viewport_drives_layout() {
+        let browser = Browser::new(BrowserConfig {
+            enable_javascript: false,
+            viewport_width: 1200,
+            viewport_height: 900,
+            ..BrowserConfig::default()
+        })
+        .expect("browser");
+        assert_eq!(browser.renderer.viewport_size(), (1200, 900));
+
+        let mut browser = browser;
+        let stylesheet = css::parser::CssParser::new("div { width: 50vw; height: 10vh }".to_string()).parse();
+        let div = dom::Node::new(dom::NodeType::Element {
+            tag_name: "div".into(),
+            attributes: Vec::new(),
+            events: Vec::new(),
+        });
+        let styled = css::style::StyleEngine::new(stylesheet).apply_styles(&div);
+        let tree = browser.renderer.build_render_tree(&styled);
+        assert_eq!(tree.root().bounds().width, 600.0);
+        assert_eq!(tree.root().bounds().height, 90.0);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_images_are_not_fetched() {
+        let page = "<html><body><p>Caption text</p><img src=\"/photo.png\" width=\"50\" height=\"40\"></body></html>";
+        let server = TestServer::start(vec![
+            ("/", TestResponse::html(page)),
+            ("/photo.png", TestResponse::ok("image/png", vec![0u8; 16])),
+        ])
+        .await;
+
+        let mut browser = Browser::new(BrowserConfig {
+            enable_javascript: false,
+            load_images: false,
+            ..BrowserConfig::default()
+        })
+        .expect("browser");
+        let (display_list, text) = browser.load_url(&server.url("/")).await.expect("load page");
+        assert!(text.contains("Caption text"));
+
+        let images: Vec<_> = display_list.items().iter().filter_map(|item| match item {
+            rendering::DisplayItem::Image { url, width, height, .. } => Some((url.clone(), *width, *height)),
+            _ => None,
+        }).collect();
+        assert_eq!(images, vec![("/photo.png".to_string(), 0.0, 0.0)]);
+
+        assert!(browser.fetch_images(&display_list, &server.url("/")).await.is_empty());
+        assert!(server.requests().iter().all(|r| !r.contains("/photo.png")));
+
+        // With images enabled the same page fetches the image
+        let mut browser = test_browser();
+        let (display_list, _) = browser.load_url(&server.url("/")).await.expect("load page");
+        let fetched = browser.fetch_images(&display_list, &server.url("/")).await;
+        assert_eq!(fetched.len(), 1);
+        assert!(server.requests().iter().any(|r| r.contains("/photo.png")));
+    }
+
+    fn gzip(data: &str) -> Vec<u8> {
+        use std::io::Write;
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data.as_bytes()).expect("gzip write");
+        encoder.finish().expect("gzip finish")
+    }
+
+    #[tokio::test]
+    async fn test_gzipped_script_and_stylesheet_are_decoded() {
+        let page = "<html><head><link rel=\"stylesheet\" href=\"/site.css\"></head>\
+            <body><div id=\"out\">pending</div><script src=\"/app.js\"></script></body></html>";
+        let server = TestServer::start(vec![
+            ("/", TestResponse::html(page)),
+            (
+                "/app.js",
+                TestResponse::ok("application/javascript", gzip("document.getElementById('out').textContent = 'script ran';"))
+                    .header("Content-Encoding", "gzip"),
+            ),
+            (
+                "/site.css",
+                TestResponse::ok("text/css", gzip("p { color: red; } div { margin: 4px; }"))
+                    .header("Content-Encoding", "gzip"),
+            ),
+        ])
+        .await;
+
+        let mut browser = Browser::new(BrowserConfig::default()).expect("browser");
+        let (_, text) = browser.load_url(&server.url("/")).await.expect("load page");
+        assert!(text.contains("script ran"), "script effect missing from: {}", text);
+
+        let dom = browser.fetch_and_parse(&server.url("/"), None, false).await.expect("parse page");
+        let base = networking::Uri::parse(&server.url("/")).expect("base uri");
+        let root = dom.root().expect("root");
+        let stylesheet = browser.load_stylesheets(root, Some(&base)).await;
+        assert!(stylesheet.rules().len() >= 2);
+    }
+
+    fn referer_header(request: &str) -> Option<String> {
+        request.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.eq_ignore_ascii_case("referer").then(|| value.trim().to_string())
+        })
+    }
+
+    #[tokio::test]
+    async fn test_referer_sent_for_subresources_and_navigation() {
+        let server = TestServer::start(vec![
+            ("/", TestResponse::html("<html><head><link rel=\"stylesheet\" href=\"/site.css\"></head><body>hi</body></html>")),
+            ("/site.css", TestResponse::ok("text/css", "body { margin: 0; }")),
+            ("/next", TestResponse::html("<html><body>next</body></html>")),
+        ])
+        .await;
+
+        let mut browser = test_browser();
+        browser.load_url(&server.url("/")).await.expect("load page");
+        browser.load_url(&server.url("/next")).await.expect("navigate");
+
+        let requests = server.requests();
+        let referer_of = |path: &str| {
+            requests
+                .iter()
+                .find(|r| r.starts_with(&format!("GET {} ", path)))
+                .and_then(|r| referer_header(r))
+        };
+        assert_eq!(referer_of("/"), None);
+        assert_eq!(referer_of("/site.css"), Some(server.url("/")));
+        assert_eq!(referer_of("/next"), Some(server.url("/")));
+    }
+
+    #[tokio::test]
+    async fn test_referer_disabled() {
+        let server = TestServer::start(vec![
+            ("/", TestResponse::html("<html><head><link rel=\"stylesheet\" href=\"/site.css\"></head><body>hi</body></html>")),
+            ("/site.css", TestResponse::ok("text/css", "body { margin: 0; }")),
+        ])
+        .await;
+
+        let mut browser = Browser::new(BrowserConfig {
+            enable_javascript: false,
+            send_referer: false,
+            ..BrowserConfig::default()
+        })
+        .expect("browser");
+        browser.load_url(&server.url("/")).await.expect("load page");
+        assert!(server.requests().iter().all(|r| referer_header(r).is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_history_back_and_forward() {
+        let server = TestServer::start(vec![
+            ("/one", TestResponse::html("<html><body>one</body></html>")),
+            ("/two", TestResponse::html("<html><body>two</body></html>")),
+            ("/three", TestResponse::html("<html><body>three</body></html>")),
+            ("/four", TestResponse::html("<html><body>four</body></html>")),
+        ])
+        .await;
+        let mut browser = test_browser();
+
+        for path in ["/one", "/two", "/three"] {
+            browser.load_url(&server.url(path)).await.expect("load");
+        }
+        browser.go_back().await.expect("back").expect("has previous entry");
+        let (_, text) = browser.go_back().await.expect("back").expect("has previous entry");
+        assert_eq!(browser.current_url(), Some(server.url("/one").as_str()));
+        assert!(text.contains("one"));
+        assert!(browser.go_back().await.expect("back").is_none());
+
+        browser.go_forward().await.expect("forward").expect("has next entry");
+        assert_eq!(browser.current_url(), Some(server.url("/two").as_str()));
+
+        // A new navigation drops the forward entries
+        browser.load_url(&server.url("/four")).await.expect("load");
+        assert!(browser.go_forward().await.expect("forward").is_none());
+        browser.go_back().await.expect("back").expect("has previous entry");
+        assert_eq!(browser.current_url(), Some(server.url("/two").as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_reload_bypassing_cache() {
+        let server = TestServer::start(vec![("/", TestResponse::html("<html><body>page</body></html>"))]).await;
+        let mut browser = test_browser();
+        let page_requests = || server.requests().iter().filter(|r| r.starts_with("GET / ")).count();
+
+        assert!(browser.reload(false).await.is_err());
+        browser.load_url(&server.url("/")).await.expect("load");
+        assert_eq!(page_requests(), 1);
+
+        // A plain reload is served from the response cache
+        browser.reload(false).await.expect("reload");
+        assert_eq!(page_requests(), 1);
+
+        let (_, text) = browser.reload(true).await.expect("hard reload");
+        assert!(text.contains("page"));
+        let requests = server.requests();
+        let last = requests.iter().rev().find(|r| r.starts_with("GET / ")).expect("reload request");
+        assert_eq!(page_requests(), 2);
+        assert!(last.lines().any(|l| l.eq_ignore_ascii_case("cache-control: no-cache")));
+    }
+
+    #[test]
+    fn test_extract_text_with_lang_segments_by_nearest_lang() {
+        let html = "<html lang=\"en\"><body><p>Hello there</p>\
+            <section lang=\"fr\"><p>Bonjour</p><p>le <b>monde</b></p></section>\
+            <p>Back in English <span lang=\"fr\">merci</span></p><div lang=\"\">unknown</div></body></html>";
+        let dom = html::parser::Parser::new(html.to_string()).parse();
+        let root = dom.root().expect("root");
+        let browser = test_browser();
+
+        let segments = browser.extract_text_with_lang(root);
+        let expected: Vec<(Option<String>, String)> = [
+            (Some("en"), "Hello there"),
+            (Some("fr"), "Bonjour\nle monde"),
+            (Some("en"), "Back in English"),
+            (Some("fr"), "merci"),
+            (None, "unknown"),
+        ]
+        .into_iter()
+        .map(|(lang, text)| (lang.map(str::to_string), text.to_string()))
+        .collect();
+        assert_eq!(segments, expected);
+
+        // The plain extraction is unchanged
+        assert_eq!(browser.extract_text_content(root), "Hello there\nBonjour\nle monde\nBack in English merci\nunknown\n");
+    }
+
+    #[test]
+    fn test_extraction_follows_block_boundaries_and_white_space() {
+        let browser = test_browser();
+        let extract = |html: &str| {
+            let dom = html::parser::Parser::new(html.to_string()).parse();
+            browser.extract_text_content(dom.root().expect("root"))
+        };
+
+        // Inline boxes stay on one line
+        assert_eq!(extract("<span>a</span><span>b</span>"), "a b");
+        assert_eq!(extract("<p><b>bold</b>  and\n  <i>italic</i></p>"), "bold and italic\n");
+        // Each block sits on its own line, however deeply nested
+        assert_eq!(extract("<p>a</p><p>b</p>"), "a\nb\n");
+        assert_eq!(extract("<div><section><p>a</p></section>\n  <p>b</p></div>"), "a\nb\n");
+        assert_eq!(extract("<div>a<span style=\"display: block\">b</span><p style=\"display: inline\">c</p></div>"), "a\nb\nc\n");
+        // Preserved whitespace is kept as written
+        assert_eq!(extract("<pre>x   y\n  z</pre>"), "x   y\n  z\n");
+        assert_eq!(extract("<p style=\"white-space: pre-line\">one   two\n  three</p>"), "one two\nthree\n");
+        assert_eq!(extract("<p>line<br>next</p>"), "line\nnext\n");
+    }
+
+    #[test]
+    fn test_template_content_is_inert() {
+        let html = "<html><body><p>shown</p><template id=\"row\"><p>hidden</p></template></body></html>";
+        let dom = html::parser::Parser::new(html.to_string()).parse();
+        let root = dom.root().expect("root");
+        let browser = test_browser();
+
+        let text = browser.extract_text_content(root);
+        assert!(text.contains("shown"));
+        assert!(!text.contains("hidden"));
+
+        let template = browser.find_first_element(root, "template").expect("template element");
+        assert!(template.children().is_empty());
+        let content = template.template_content().expect("template content");
+        assert_eq!(browser.extract_text_content(content).trim(), "hidden");
+    }
+
+    #[tokio::test]
+    async fn test_subresources_fetched_concurrently() {
+        let delay = std::time::Duration::from_millis(150);
+        let server = TestServer::start(vec![
+            (
+                "/",
+                TestResponse::html(
+                    "<html><head><link rel=\"stylesheet\" href=\"/a.css\"><link rel=\"stylesheet\" href=\"/b.css\">\
+                     <script src=\"/1.js\"></script><script src=\"/2.js\"></script><script src=\"/3.js\"></script>\
+                     </head><body>page</body></html>",
+                ),
+            ),
+            ("/a.css", TestResponse::ok("text/css", "p { color: red; }").delay(delay)),
+            ("/b.css", TestResponse::ok("text/css", "p { color: blue; }").delay(delay)),
+            // The slowest script still runs first
+            ("/1.js", TestResponse::ok("text/javascript", "var order = '1';").delay(delay * 2)),
+            ("/2.js", TestResponse::ok("text/javascript", "order = order + '2';").delay(delay)),
+            ("/3.js", TestResponse::ok("text/javascript", "order = order + '3'; console.log(order);").delay(delay)),
+        ])
+        .await;
+
+        let mut browser = Browser::new(BrowserConfig {
+            enable_javascript: true,
+            ..BrowserConfig::default()
+        })
+        .expect("browser");
+        browser.load_url(&server.url("/")).await.expect("load page");
+
+        assert!(server.max_in_flight() >= 3, "max in flight: {}", server.max_in_flight());
+        let logged: Vec<String> = browser.take_console_messages().into_iter().map(|(_, m)| m).collect();
+        assert!(logged.contains(&"123".to_string()), "console: {:?}", logged);
+    }
+
+    #[tokio::test]
+    async fn test_navigator_matches_request_user_agent() {
+        let server = TestServer::start(vec![(
+            "/",
+            TestResponse::html("<html><body><script>console.log(navigator.userAgent + '|' + navigator.language);</script></body></html>"),
+        )])
+        .await;
+
+        let mut browser = Browser::new(BrowserConfig {
+            user_agent: "TestAgent/1.0".to_string(),
+            language: "fr-FR".to_string(),
+            ..BrowserConfig::default()
+        })
+        .expect("browser");
+        browser.load_url(&server.url("/")).await.expect("load page");
+
+        let requests = server.requests();
+        let sent = requests[0].lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.eq_ignore_ascii_case("user-agent").then(|| value.trim().to_string())
+        });
+        assert_eq!(sent.as_deref(), Some("TestAgent/1.0"));
+        let logged: Vec<String> = browser.take_console_messages().into_iter().map(|(_, m)| m).collect();
+        assert_eq!(logged, vec!["TestAgent/1.0|fr-FR".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_console_log_sender_receives_page_output() {
+        let server = TestServer::start(vec![(
+            "/",
+            TestResponse::html("<html><body><script>console.warn('careful');</script></body></html>"),
+        )])
+        .await;
+
+        let mut browser = Browser::new(BrowserConfig::default()).expect("browser");
+        let (sender, receiver) = std::sync::mpsc::channel();
+        browser.set_console_log_sender(sender);
+        browser.load_url(&server.url("/")).await.expect("load page");
+
+        let received: Vec<(String, String)> = receiver.try_iter().collect();
+        assert_eq!(received, vec![("warn".to_string(), "careful".to_string())]);
+        assert!(browser.take_console_messages().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_script_style_display_none_hides_element() {
+        let page = "<html><body><p id=\"shown\">visible text</p><p id=\"gone\" style=\"color: blue\">secret text</p>\
+            <script>\
+                var el = document.getElementById('gone');\
+                el.style.display = 'none';\
+                el.style.backgroundColor = 'red';\
+                console.log(el.style.color, el.style.display);\
+            </script></body></html>";
+        let server = TestServer::start(vec![("/", TestResponse::html(page))]).await;
+
+        let mut browser = Browser::new(BrowserConfig::default()).expect("browser");
+        let (display_list, _) = browser.load_url(&server.url("/")).await.expect("load page");
+        let painted: Vec<&str> = display_list.items().iter().filter_map(|item| match item {
+            rendering::DisplayItem::Text { content, .. } => Some(content.as_str()),
+            _ => None,
+        }).collect();
+        assert!(painted.iter().any(|t| t.contains("visible text")), "painted: {:?}", painted);
+        assert!(!painted.iter().any(|t| t.contains("secret text")), "painted: {:?}", painted);
+
+        let logged: Vec<String> = browser.take_console_messages().into_iter().map(|(_, m)| m).collect();
+        assert_eq!(logged, vec!["blue none".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_display_none_subtree_is_not_rendered_or_extracted() {
+        let page = "<html><body><p>shown</p><div style=\"display:none\">hidden<p>nested</p><button>go</button></div></body></html>";
+        let server = TestServer::start(vec![("/", TestResponse::html(page))]).await;
+
+        let mut browser = test_browser();
+        let (display_list, text) = browser.load_url(&server.url("/")).await.expect("load page");
+        assert!(text.contains("shown"));
+        assert!(!text.contains("hidden") && !text.contains("nested"), "text: {:?}", text);
+        for item in display_list.items() {
+            match item {
+                rendering::DisplayItem::Text { content, .. } => assert_eq!(content, "shown"),
+                rendering::DisplayItem::Button { .. } => panic!("hidden button painted: {:?}", item),
+                _ => {}
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stylesheet_generated_content_is_painted() {
+        let page = "<html><head><style>.x::before{content:\"* \"}</style></head><body><p class=x>item</p></body></html>";
+        let server = TestServer::start(vec![("/", TestResponse::html(page))]).await;
+
+        let mut browser = test_browser();
+        let (display_list, _) = browser.load_url(&server.url("/")).await.expect("load page");
+        let painted: Vec<&str> = display_list
+            .items()
+            .iter()
+            .filter_map(|item| match item {
+                rendering::DisplayItem::Text { content, .. } => Some(content.as_str()),
+                _ => None,
+            })
+            .collect();
+        let marker = painted.iter().position(|t| t.trim() == "*");
+        let item = painted.iter().position(|t| *t == "item");
+        assert!(marker.is_some() && marker < item, "painted: {:?}", painted);
+    }
+
+    #[tokio::test]
+    async fn test_stylesheet_inherit_and_initial_apply_on_page_load() {
+        let page = "<html><head><style>\
+            .quiet{visibility:hidden} .back{visibility:initial} .up{visibility:inherit}\
+            </style></head><body><div class=quiet><p>muted</p><p class=back>restored <span class=up>kept</span></p></div></body></html>";
+        let server = TestServer::start(vec![("/", TestResponse::html(page))]).await;
+
+        let mut browser = test_browser();
+        let (display_list, _) = browser.load_url(&server.url("/")).await.expect("load page");
+        let painted: Vec<&str> = display_list
+            .items()
+            .iter()
+            .filter_map(|item| match item {
+                rendering::DisplayItem::Text { content, .. } => Some(content.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(painted, vec!["restored", "kept"]);
+    }
+
+    #[tokio::test]
+    async fn test_added_stylesheets_cascade_below_page_styles() {
+        let page = "<html><head><style>.shown{display:block}</style></head><body>\
+            <p class=shown>page wins</p><p class=ad>hidden by user</p><p>plain</p></body></html>";
+        let server = TestServer::start(vec![("/", TestResponse::html(page))]).await;
+
+        let mut browser = test_browser();
+        browser.add_stylesheet("p { display: none }", StyleOrigin::UserAgent);
+        browser.add_stylesheet("p { display: block } .ad { display: none } .shown { display: none }", StyleOrigin::User);
+        let (_, text) = browser.load_url(&server.url("/")).await.expect("load page");
+        assert!(text.contains("page wins") && text.contains("plain"), "text: {:?}", text);
+        assert!(!text.contains("hidden by user"), "text: {:?}", text);
+    }
+
+    #[tokio::test]
+    async fn test_stylesheet_descendant_and_class_selectors_hide_content() {
+        let page = "<html><head><style>p span{display:none} .gone{display:none}</style></head><body>\
+            <p>para <span>inner</span></p><span>outer</span><div class=gone>classed</div></body></html>";
+        let server = TestServer::start(vec![("/", TestResponse::html(page))]).await;
+
+        let mut browser = test_browser();
+        let (display_list, text) = browser.load_url(&server.url("/")).await.expect("load page");
+        let painted: Vec<&str> = display_list
+            .items()
+            .iter()
+            .filter_map(|item| match item {
+                rendering::DisplayItem::Text { content, .. } => Some(content.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(painted, vec!["para", "outer"]);
+        assert!(!text.contains("inner") && !text.contains("classed"), "text: {:?}", text);
+    }
+
+    #[tokio::test]
+    async fn test_closed_details_shows_only_its_summary() {
+        let page = "<html><body>\
+            <details><summary>Closed summary</summary><p>closed body</p></details>\
+            <details open><summary>Open summary</summary><p>open body</p></details>\
+            </body></html>";
+        let server = TestServer::start(vec![("/", TestResponse::html(page))]).await;
+
+        let mut browser = test_browser();
+        let (display_list, text) = browser.load_url(&server.url("/")).await.expect("load page");
+        assert!(text.contains("Closed summary") && text.contains("Open summary"), "text: {:?}", text);
+        assert!(text.contains("open body"), "text: {:?}", text);
+        assert!(!text.contains("closed body"), "text: {:?}", text);
+        let painted: Vec<&str> = display_list
+            .items()
+            .iter()
+            .filter_map(|item| match item {
+                rendering::DisplayItem::Text { content, .. } => Some(content.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert!(painted.contains(&"open body"), "painted: {:?}", painted);
+        assert!(!painted.contains(&"closed body"), "painted: {:?}", painted);
+    }
+
+    #[tokio::test]
+    async fn test_script_loop_limit_comes_from_config() {
+        let page = "<html><body><script>\
+                var n = 0; for (var i = 0; i < 20000; i++) { n++; } console.log(n);\
+            </script></body></html>";
+        let server = TestServer::start(vec![("/", TestResponse::html(page))]).await;
+
+        let mut roomy = Browser::new(BrowserConfig { script_max_loop_iterations: 50_000, ..BrowserConfig::default() })
+            .expect("browser");
+        roomy.load_url(&server.url("/")).await.expect("load page");
+        let logged: Vec<String> = roomy.take_console_messages().into_iter().map(|(_, m)| m).collect();
+        assert_eq!(logged, vec!["20000".to_string()]);
+
+        // Past the limit the script is abandoned, but the page still loads
+        let mut strict = Browser::new(BrowserConfig { script_max_loop_iterations: 100, ..BrowserConfig::default() })
+            .expect("browser");
+        strict.load_url(&server.url("/")).await.expect("load page");
+        assert!(strict.take_console_messages().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_elements_by_tag_name_counts_paragraphs() {
+        let page = "<html><body><p id=\"first\">one</p><div><P>two</P><p>three</p></div>\
+            <script>\
+                var paragraphs = document.getElementsByTagName('p');\
+                console.log(paragraphs.length, paragraphs[0].id, paragraphs[2].textContent);\
+            </script></body></html>";
+        let server = TestServer::start(vec![("/", TestResponse::html(page))]).await;
+
+        let mut browser = Browser::new(BrowserConfig::default()).expect("browser");
+        browser.load_url(&server.url("/")).await.expect("load page");
+        let logged: Vec<String> = browser.take_console_messages().into_iter().map(|(_, m)| m).collect();
+        assert_eq!(logged, vec!["3 first three".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_dom_content_loaded_fires_before_load() {
+        let page = "<html><body><script>\
+                window.addEventListener('load', function (e) { console.log(e.type); });\
+                document.addEventListener('DOMContentLoaded', function (e) { console.log(e.type); });\
+                console.log('script');\
+            </script></body></html>";
+        let server = TestServer::start(vec![("/", TestResponse::html(page))]).await;
+
+        let mut browser = Browser::new(BrowserConfig::default()).expect("browser");
+        browser.load_url(&server.url("/")).await.expect("load page");
+        let logged: Vec<String> = browser.take_console_messages().into_iter().map(|(_, m)| m).collect();
+        assert_eq!(logged, vec!["script", "DOMContentLoaded", "load"]);
+    }
+
+    // The request blocks the script's thread, so the server needs another worker
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_xhr_reads_response_text() {
+        let page = "<html><body><script>\
+                var xhr = new XMLHttpRequest();\
+                xhr.onload = function () { console.log('loaded', this.readyState); };\
+                xhr.open('GET', '/data.txt', false);\
+                xhr.send();\
+                console.log(xhr.status, xhr.responseText);\
+                var missing = new XMLHttpRequest();\
+                missing.open('GET', 'missing.txt');\
+                missing.send();\
+                console.log(missing.status);\
+            </script></body></html>";
+        let server = TestServer::start(vec![
+            ("/", TestResponse::html(page)),
+            ("/data.txt", TestResponse::ok("text/plain", "hello from the server")),
+        ])
+        .await;
+
+        let mut browser = Browser::new(BrowserConfig::default()).expect("browser");
+        browser.load_url(&server.url("/")).await.expect("load page");
+        let logged: Vec<String> = browser.take_console_messages().into_iter().map(|(_, m)| m).collect();
+        assert_eq!(logged, vec!["loaded 4", "200 hello from the server", "404"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_fetch_json_returns_parsed_object() {
+        let page = "<html><body><script>\
+                var response = fetch('/data.json');\
+                var data = response.json();\
+                console.log(response.status, response.ok, data.name, data.tags[1], data.tags.length);\
+            </script></body></html>";
+        let server = TestServer::start(vec![
+            ("/", TestResponse::html(page)),
+            ("/data.json", TestResponse::ok("application/json", r#"{"name": "celeris", "tags": ["a", "b"]}"#)),
+        ])
+        .await;
+
+        let mut browser = Browser::new(BrowserConfig::default()).expect("browser");
+        browser.load_url(&server.url("/")).await.expect("load page");
+        let logged: Vec<String> = browser.take_console_messages().into_iter().map(|(_, m)| m).collect();
+        assert_eq!(logged, vec!["200 true celeris b 2"]);
+    }
+
+    #[tokio::test]
+    async fn test_link_regions_carry_absolute_href() {
+        let server = TestServer::start(vec![(
+            "/docs/index.html",
+            TestResponse::html("<p><a href=\"../about.html\">About us</a></p>"),
+        )])
+        .await;
+        let mut browser = test_browser();
+        let (display_list, _) = browser.load_url(&server.url("/docs/index.html")).await.expect("load page");
+
+        let (href, x, y) = display_list.items().iter().find_map(|item| match item {
+            rendering::DisplayItem::Link { href, x, y, .. } => Some((href.clone(), *x, *y)),
+            _ => None,
+        }).expect("link region");
+        assert_eq!(href, server.url("/about.html"));
+        assert_eq!(display_list.link_at(x + 1.0, y + 1.0), Some(href.as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_alternate_stylesheets_are_not_loaded() {
+        let page = "<link rel=\"alternate stylesheet\" title=\"Contrast\" href=\"/alt.css\">\
+                    <link rel=\"preload\" as=\"style\" href=\"/preload.css\">\
+                    <link rel=\"Stylesheet  icon\" href=\"/main.css\">\
+                    <link rel=\"preload\" as=\"script\" href=\"/app.js\"><p>text</p>";
+        let server = TestServer::start(vec![
+            ("/", TestResponse::html(page)),
+            ("/alt.css", TestResponse::ok("text/css", "p { color: red; }")),
+            ("/preload.css", TestResponse::ok("text/css", "p { margin: 4px; }")),
+            ("/main.css", TestResponse::ok("text/css", "p { padding: 2px; }")),
+        ])
+        .await;
+        let mut browser = test_browser();
+        browser.load_url(&server.url("/")).await.expect("load page");
+
+        let paths: Vec<String> = server
+            .requests()
+            .iter()
+            .filter_map(|request| request.split_whitespace().nth(1).map(str::to_string))
+            .collect();
+        assert!(paths.contains(&"/preload.css".to_string()), "requests: {:?}", paths);
+        assert!(paths.contains(&"/main.css".to_string()), "requests: {:?}", paths);
+        assert!(!paths.contains(&"/alt.css".to_string()), "requests: {:?}", paths);
+        assert!(!paths.contains(&"/app.js".to_string()), "requests: {:?}", paths);
+    }
+
+    #[tokio::test]
+    async fn test_print_stylesheets_do_not_apply_to_screen() {
+        let page = "<html><head>\
+                    <link rel=\"stylesheet\" media=\"print\" href=\"/print.css\">\
+                    <link rel=\"stylesheet\" media=\"only screen and (min-width: 10px)\" href=\"/screen.css\">\
+                    <style media=\"print\">html { margin: 50px; }</style>\
+                    <style media=\"not print, speech\">html { padding: 3px; }</style>\
+                    </head><body><p>text</p></body></html>";
+        let server = TestServer::start(vec![
+            ("/", TestResponse::html(page)),
+            ("/print.css", TestResponse::ok("text/css", "html { font-size: 40px; }")),
+            ("/screen.css", TestResponse::ok("text/css", "html { color: blue; }")),
+        ])
+        .await;
+        let mut browser = test_browser();
+        let dom = browser.fetch_and_parse(&server.url("/"), None, false).await.expect("parse page");
+        let base = networking::Uri::parse(&server.url("/")).expect("base uri");
+        let root = dom.root().expect("root");
+        let stylesheet = browser.load_stylesheets(root, Some(&base)).await;
+
+        let html = browser.find_first_element(root, "html").expect("html element");
+        let styled = css::style::StyleEngine::new(stylesheet).apply_styles(html);
+        assert!(styled.get("color").is_some());
+        assert!(styled.get("padding").is_some());
+        assert!(styled.get("margin").is_none());
+        assert!(styled.get("font-size").is_none());
+        assert!(!server.requests().iter().any(|request| request.contains("/print.css")));
+    }
+
+    #[tokio::test]
+    async fn test_cookies_set_through_browser_are_sent() {
+        let server = TestServer::start(vec![
+            ("/account", TestResponse::html("<p>Account</p>").header("Set-Cookie", "theme=dark; Path=/")),
+        ])
+        .await;
+        let mut browser = test_browser();
+        browser.set_cookie(&server.url("/"), "session", "abc123").await.expect("set cookie");
+        assert!(browser.set_cookie("not a url", "a", "b").await.is_err());
+
+        browser.load_url(&server.url("/account")).await.expect("load page");
+        let request = server.requests().into_iter().next().expect("request");
+        assert!(request.to_ascii_lowercase().contains("cookie: session=abc123"), "request: {}", request);
+
+        let cookies = browser.get_cookies(&server.url("/account")).await;
+        assert_eq!(
+            cookies,
+            vec![("session".to_string(), "abc123".to_string()), ("theme".to_string(), "dark".to_string())]
+        );
+        assert!(browser.get_cookies("http://other.test/").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_submit_form_sends_fields_by_method() {
+        let page = "<form id=\"search\" action=\"/echo?stale=1\">\
+                        <input name=\"q\" value=\"old\"><input type=\"checkbox\" name=\"safe\" checked>\
+                        <input type=\"checkbox\" name=\"skip\"><input type=\"submit\" name=\"go\" value=\"Go\">\
+                    </form>\
+                    <form method=\"post\" action=\"echo\">\
+                        <textarea name=\"note\">a &amp; b</textarea>\
+                        <select name=\"size\"><option>S</option><option value=\"m\" selected>M</option></select>\
+                    </form>";
+        let server = TestServer::start(vec![("/form.html", TestResponse::html(page)), ("/echo", TestResponse::echo())]).await;
+        let mut browser = test_browser();
+        browser.load_url(&server.url("/form.html")).await.expect("load form page");
+
+        let forms = browser.forms();
+        assert_eq!(forms.len(), 2);
+        assert_eq!(forms[0].id.as_deref(), Some("search"));
+        assert_eq!(forms[0].method, FormMethod::Get);
+        assert_eq!(forms[1].method, FormMethod::Post);
+        assert_eq!(forms[1].action, server.url("/echo"));
+
+        let overrides = vec![("q".to_string(), "rust lang".to_string())];
+        let (_, text) = browser.submit_form(&forms[0], &overrides).await.expect("submit GET form");
+        assert!(text.contains("/echo?q=rust+lang&safe=on"), "text: {:?}", text);
+        assert_eq!(browser.current_url(), Some(server.url("/echo?q=rust+lang&safe=on").as_str()));
+
+        let (_, text) = browser.submit_form(&forms[1], &[]).await.expect("submit POST form");
+        assert!(text.contains("note=a+%26+b&size=m"), "text: {:?}", text);
+        let request = server.requests().pop().expect("POST request");
+        assert!(request.starts_with("POST /echo "), "request: {}", request);
+        assert!(request.to_ascii_lowercase().contains("content-type: application/x-www-form-urlencoded"));
+    }
+
+    #[tokio::test]
+    async fn test_query_selector_extracts_headline() {
+        let page = "<div id=\"news\"><h2 class=\"headline top\" data-id=\"7\">Rust &amp; the web</h2>\
+                    <p class=\"headline\">Second story</p></div><p class=\"headline\">Outside</p>";
+        let server = TestServer::start(vec![("/", TestResponse::html(page))]).await;
+        let mut browser = test_browser();
+        assert!(browser.query_selector(".headline").is_none());
+        browser.load_url(&server.url("/")).await.expect("load page");
+
+        let headline = browser.query_selector(".headline").expect("headline element");
+        assert_eq!(headline.tag_name, "h2");
+        assert_eq!(headline.text, "Rust & the web");
+        assert_eq!(headline.attribute("data-id"), Some("7"));
+
+        let texts: Vec<String> = browser.query_selector_all("#news .headline").into_iter().map(|e| e.text).collect();
+        assert_eq!(texts, vec!["Rust & the web", "Second story"]);
+        assert_eq!(browser.query_selector_all("h2, p.headline").len(), 3);
+        assert!(browser.query_selector_all("p {").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_embedder_requests_share_the_browser_network() {
+        let server = TestServer::start(vec![("/", TestResponse::html("<p>hi</p>"))]).await;
+        let mut browser = test_browser();
+        browser.network_mut().expect("network not shared yet").set_max_body_bytes(1 << 20);
+        browser.load_url(&server.url("/")).await.expect("load page");
+
+        let response = browser.network().head(&server.url("/")).await.expect("HEAD request");
+        assert_eq!(response.status.code, 200);
+        assert!(response.body.is_empty());
+        assert!(server.requests()[1].starts_with("HEAD / "));
+    }
+
+    #[tokio::test]
+    async fn test_document_diff_between_loads() {
+        let server = TestServer::start(vec![
+            ("/a", TestResponse::html("<div><p>Before</p><p>Same</p></div>")),
+            ("/b", TestResponse::html("<div><p>After</p><p>Same</p></div>")),
+        ])
+        .await;
+        let mut browser = test_browser();
+        assert!(browser.document().is_none());
+        browser.load_url(&server.url("/a")).await.expect("load first page");
+        let mut before = browser.document().expect("first document").clone();
+        browser.load_url(&server.url("/b")).await.expect("load second page");
+        let after = browser.document().expect("second document");
+
+        let patches = diff_dom(&before, after);
+        assert_eq!(patches.len(), 1);
+        assert!(matches!(&patches[0], DomPatch::SetText { text, .. } if text == "After"));
+        apply_dom_patches(&mut before, &patches);
+        assert!(diff_dom(&before, after).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_noscript_renders_only_without_javascript() {
+        let page = "<p>Always here</p><noscript><p>Please enable JavaScript</p></noscript>";
+        let server = TestServer::start(vec![("/", TestResponse::html(page))]).await;
+
+        let mut with_js = Browser::new(BrowserConfig::default()).expect("browser");
+        let (display_list, text) = with_js.load_url(&server.url("/")).await.expect("load page");
+        assert!(text.contains("Always here"));
+        assert!(!text.contains("enable JavaScript"), "text: {:?}", text);
+        assert!(!display_list.items().iter().any(|item| matches!(
+            item,
+            rendering::DisplayItem::Text { content, .. } if content.contains("enable JavaScript")
+        )));
+
+        let mut without_js = test_browser();
+        let (display_list, text) = without_js.load_url(&server.url("/")).await.expect("load page");
+        assert!(text.contains("Please enable JavaScript"), "text: {:?}", text);
+        assert!(display_list.items().iter().any(|item| matches!(
+            item,
+            rendering::DisplayItem::Text { content, .. } if content.contains("enable JavaScript")
+        )));
+    }
+}
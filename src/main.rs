@@ -27,6 +27,7 @@ fn main() {
         headless: false,
         debug: true,
         enable_javascript: true,
+        ..Default::default()
     }) {
         Ok(b) => b,
         Err(e) => {
@@ -60,6 +61,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
         headless: true,  // Always headless when GUI feature is disabled
         debug: true,
         enable_javascript: true,
+        ..Default::default()
     })?;
 
     println!("Celeris Browser Engine");
@@ -72,18 +74,13 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let mut input = String::new();
     io::stdin().read_line(&mut input)?;
 
-    let url = input.trim();
-    let url = if url.is_empty() {
-        String::from("https://example.com")
-    } else if !url.starts_with("http://") && !url.starts_with("https://") {
-        // Add https:// prefix if not present
-        format!("https://{}", url)
-    } else {
-        url.to_string()
+    let url = match input.trim() {
+        "" => "https://example.com",
+        url => url,
     };
 
     let start = std::time::Instant::now();
-    let (_display_list, content) = engine.load_url(&url).await?;
+    let (_display_list, content) = engine.load_user_input(url).await?;
     let duration = start.elapsed();
 
     println!("\n[+] Page loaded in: {:.2?}", duration);
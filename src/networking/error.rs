@@ -4,6 +4,7 @@ use std::fmt;
 pub enum NetworkError {
     InvalidUri,
     ConnectionFailed(String),
+    DnsLookupFailed(String),
     SendFailed(String),
     ReceiveFailed(String),
     TlsError(String),
@@ -15,8 +16,12 @@ pub enum NetworkError {
     InvalidStatusCode,
     InvalidHeader,
     TooLargeResponse,
+    DecompressedTooLarge(usize),
     TooManyRedirects,
     Timeout(String),
+    FileNotFound(String),
+    IsDirectory(String),
+    FileReadFailed(String),
 }
 
 impl std::error::Error for NetworkError {}
@@ -26,6 +31,7 @@ impl fmt::Display for NetworkError {
         match self {
             NetworkError::InvalidUri => write!(f, "Invalid URI"),
             NetworkError::ConnectionFailed(e) => write!(f, "Connection failed: {}", e),
+            NetworkError::DnsLookupFailed(e) => write!(f, "DNS lookup failed: {}", e),
             NetworkError::SendFailed(e) => write!(f, "Failed to send request: {}", e),
             NetworkError::ReceiveFailed(e) => write!(f, "Failed to receive response: {}", e),
             NetworkError::TlsError(e) => write!(f, "TLS error: {}", e),
@@ -37,8 +43,12 @@ impl fmt::Display for NetworkError {
             NetworkError::InvalidStatusCode => write!(f, "Invalid status code"),
             NetworkError::InvalidHeader => write!(f, "Invalid header"),
             NetworkError::TooLargeResponse => write!(f, "Response too large"),
+            NetworkError::DecompressedTooLarge(limit) => write!(f, "Decompressed body exceeds {} bytes", limit),
             NetworkError::TooManyRedirects => write!(f, "Too many redirects"),
             NetworkError::Timeout(e) => write!(f, "Request timed out: {}", e),
+            NetworkError::FileNotFound(path) => write!(f, "File not found: {}", path),
+            NetworkError::IsDirectory(path) => write!(f, "Is a directory: {}", path),
+            NetworkError::FileReadFailed(e) => write!(f, "Failed to read file: {}", e),
         }
     }
 }
@@ -0,0 +1,98 @@
+// Loader for file:// URLs: reads the file from disk and synthesizes an HTTP-like
+// response so callers can treat local documents exactly like fetched ones.
+
+use crate::networking::{error::NetworkError, http};
+
+/// Read the file named by a `file://` URL.
+pub async fn load(url: &str) -> Result<http::Response, NetworkError> {
+    let path = path_from_url(url)?;
+
+    let metadata = tokio::fs::metadata(&path).await.map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => NetworkError::FileNotFound(path.clone()),
+        _ => NetworkError::FileReadFailed(format!("{}: {}", path, e)),
+    })?;
+    if metadata.is_dir() {
+        return Err(NetworkError::IsDirectory(path));
+    }
+
+    let body = tokio::fs::read(&path)
+        .await
+        .map_err(|e| NetworkError::FileReadFailed(format!("{}: {}", path, e)))?;
+    log::debug!(target: "network", "Loaded {} ({} bytes) from disk", path, body.len());
+
+    let mut headers = http::Headers::new();
+    headers.insert("Content-Type".to_string(), content_type_for(&path).to_string());
+    headers.insert("Content-Length".to_string(), body.len().to_string());
+
+    Ok(http::Response {
+        version: http::Version::Http11,
+        status: http::Status {
+            code: 200,
+            text: "OK".to_string(),
+        },
+        headers,
+        body,
+    })
+}
+
+// "file:///tmp/a%20b.html" -> "/tmp/a b.html"; "localhost" is the only host accepted
+fn path_from_url(url: &str) -> Result<String, NetworkError> {
+    let rest = url.strip_prefix("file://").ok_or(NetworkError::InvalidUri)?;
+    let rest = rest.split(['?', '#']).next().unwrap_or(rest);
+    let path = match rest.strip_prefix("localhost") {
+        Some(path) => path,
+        None if rest.starts_with('/') => rest,
+        None => return Err(NetworkError::InvalidUri),
+    };
+    if path.is_empty() {
+        return Err(NetworkError::InvalidUri);
+    }
+    percent_decode(path)
+}
+
+fn percent_decode(input: &str) -> Result<String, NetworkError> {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = input.get(i + 1..i + 3).ok_or(NetworkError::InvalidUri)?;
+            out.push(u8::from_str_radix(hex, 16).map_err(|_| NetworkError::InvalidUri)?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).map_err(|_| NetworkError::InvalidUri)
+}
+
+fn content_type_for(path: &str) -> &'static str {
+    let extension = path.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()).unwrap_or_default();
+    match extension.as_str() {
+        "html" | "htm" | "xhtml" => "text/html",
+        "css" => "text/css",
+        "js" | "mjs" => "application/javascript",
+        "json" => "application/json",
+        "txt" => "text/plain",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_from_url() {
+        assert_eq!(path_from_url("file:///tmp/a%20b.html").ok(), Some("/tmp/a b.html".to_string()));
+        assert_eq!(path_from_url("file://localhost/etc/hosts").ok(), Some("/etc/hosts".to_string()));
+        assert!(path_from_url("file://example.com/x").is_err());
+    }
+}
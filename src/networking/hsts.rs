@@ -0,0 +1,120 @@
+// HTTP Strict Transport Security (RFC 6797): hosts that asked, over https, to
+// be reached only over https. Plain http requests to them are upgraded
+// before anything is sent.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+struct HstsPolicy {
+    expires: Instant,
+    include_subdomains: bool,
+}
+
+#[derive(Default)]
+pub struct HstsStore {
+    hosts: HashMap<String, HstsPolicy>,
+}
+
+impl HstsStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a `Strict-Transport-Security` header received from `host` over https.
+    pub fn record(&mut self, host: &str, header: &str) {
+        self.record_at(host, header, Instant::now());
+    }
+
+    /// The https equivalent of `url` when it is plain http to a known HSTS host.
+    pub fn upgrade(&self, url: &str) -> Option<String> {
+        self.upgrade_at(url, Instant::now())
+    }
+
+    fn record_at(&mut self, host: &str, header: &str, now: Instant) {
+        // IP literals are never HSTS hosts
+        if host.parse::<std::net::IpAddr>().is_ok() || host.starts_with('[') {
+            return;
+        }
+        let mut max_age = None;
+        let mut include_subdomains = false;
+        for directive in header.split(';') {
+            let directive = directive.trim();
+            match directive.split_once('=') {
+                Some((name, value)) if name.trim().eq_ignore_ascii_case("max-age") => {
+                    max_age = value.trim().trim_matches('"').parse::<u64>().ok();
+                }
+                None if directive.eq_ignore_ascii_case("includeSubDomains") => include_subdomains = true,
+                _ => {}
+            }
+        }
+        // A header without a valid max-age is ignored; max-age=0 forgets the host
+        let Some(max_age) = max_age else {
+            return;
+        };
+        let host = host.to_ascii_lowercase();
+        if max_age == 0 {
+            self.hosts.remove(&host);
+            return;
+        }
+        log::debug!(target: "network", "HSTS: {} for {}s (subdomains: {})", host, max_age, include_subdomains);
+        let expires = now.checked_add(Duration::from_secs(max_age)).unwrap_or(now + Duration::from_secs(u32::MAX as u64));
+        self.hosts.insert(host, HstsPolicy { expires, include_subdomains });
+    }
+
+    fn upgrade_at(&self, url: &str, now: Instant) -> Option<String> {
+        let rest = url.get(..7).filter(|scheme| scheme.eq_ignore_ascii_case("http://")).map(|_| &url[7..])?;
+        let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+        let (authority, path) = rest.split_at(authority_end);
+        let host_port = authority.rsplit('@').next().unwrap_or(authority);
+        let host = match host_port.rsplit_once(':') {
+            Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => host,
+            _ => host_port,
+        };
+        if !self.is_known_host(&host.to_ascii_lowercase(), now) {
+            return None;
+        }
+        // The default http port maps to the default https port; others are kept
+        let authority = authority.strip_suffix(":80").unwrap_or(authority);
+        Some(format!("https://{}{}", authority, path))
+    }
+
+    fn is_known_host(&self, host: &str, now: Instant) -> bool {
+        let active = |name: &str, exact: bool| {
+            self.hosts
+                .get(name)
+                .is_some_and(|policy| policy.expires > now && (exact || policy.include_subdomains))
+        };
+        if active(host, true) {
+            return true;
+        }
+        // Superdomains only apply with includeSubDomains
+        host.match_indices('.').any(|(dot, _)| active(&host[dot + 1..], false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_hosts_are_upgraded_until_expiry() {
+        let now = Instant::now();
+        let mut store = HstsStore::new();
+        store.record_at("Secure.test", "max-age=60", now);
+        store.record_at("wide.test", "max-age=\"60\"; includeSubDomains", now);
+        store.record_at("127.0.0.1", "max-age=60", now);
+
+        assert_eq!(store.upgrade_at("http://secure.test/a?b=1", now).as_deref(), Some("https://secure.test/a?b=1"));
+        assert_eq!(store.upgrade_at("http://secure.test:80/", now).as_deref(), Some("https://secure.test/"));
+        assert_eq!(store.upgrade_at("http://secure.test:8080", now).as_deref(), Some("https://secure.test:8080"));
+        assert_eq!(store.upgrade_at("http://sub.secure.test/", now), None);
+        assert_eq!(store.upgrade_at("http://deep.sub.wide.test/", now).as_deref(), Some("https://deep.sub.wide.test/"));
+        assert_eq!(store.upgrade_at("https://secure.test/", now), None);
+        assert_eq!(store.upgrade_at("http://127.0.0.1/", now), None);
+
+        // Expired, then forgotten with max-age=0
+        assert_eq!(store.upgrade_at("http://secure.test/", now + Duration::from_secs(61)), None);
+        store.record_at("wide.test", "max-age=0", now);
+        assert_eq!(store.upgrade_at("http://wide.test/", now), None);
+    }
+}
@@ -1,5 +1,4 @@
 use crate::networking::error::NetworkError;
-use std::collections::HashMap;
 
 #[derive(Debug, Clone)]
 pub struct Response {
@@ -20,10 +19,12 @@ pub struct Request {
 
 /// HTTP headers storage. Supports multiple values per header name
 /// (required for Set-Cookie and other headers that can appear multiple times).
-#[derive(Debug, Clone)]
-pub struct Headers(HashMap<String, Vec<String>>);
+/// Names match case-insensitively but keep the case they were given, and
+/// entries stay in the order they were added.
+#[derive(Debug, Clone, Default)]
+pub struct Headers(Vec<(String, String)>);
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Method {
     GET,
     POST,
@@ -48,6 +49,23 @@ impl Request {
         RequestBuilder::new()
     }
 
+    pub fn method(&self) -> Method {
+        self.method
+    }
+
+    /// The request target: path and query, as sent on the request line.
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    pub fn set_uri(&mut self, uri: impl Into<String>) {
+        self.uri = uri.into();
+    }
+
+    pub fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut request = Vec::new();
 
@@ -112,6 +130,11 @@ impl RequestBuilder {
         self
     }
 
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
     pub fn build(self) -> Result<Request, NetworkError> {
         Ok(Request {
             method: self.method.ok_or(NetworkError::MissingMethod)?,
@@ -125,34 +148,82 @@ impl RequestBuilder {
 
 impl Headers {
     pub fn new() -> Self {
-        Self(HashMap::new())
+        Self(Vec::new())
     }
 
-    /// Insert a header value. For request headers, this replaces any existing value.
+    /// Insert a header value. For request headers, this replaces any existing value,
+    /// taking the place of the first one.
     pub fn insert(&mut self, name: String, value: String) {
-        self.0.insert(name.to_lowercase(), vec![value]);
+        match self.0.iter().position(|(existing, _)| existing.eq_ignore_ascii_case(&name)) {
+            Some(first) => {
+                // Later duplicates go; nothing before `first` can match
+                let mut index = 0;
+                self.0.retain(|(existing, _)| {
+                    index += 1;
+                    index <= first + 1 || !existing.eq_ignore_ascii_case(&name)
+                });
+                self.0[first] = (name, value);
+            }
+            None => self.0.push((name, value)),
+        }
     }
 
     /// Append a header value (used for response parsing where headers can repeat).
     pub fn append(&mut self, name: String, value: String) {
-        self.0
-            .entry(name.to_lowercase())
-            .or_default()
-            .push(value);
+        self.0.push((name, value));
+    }
+
+    /// Remove every value of a header, returning them in order.
+    pub fn remove(&mut self, name: &str) -> Vec<String> {
+        let mut removed = Vec::new();
+        self.0.retain(|(existing, value)| {
+            let matches = existing.eq_ignore_ascii_case(name);
+            if matches {
+                removed.push(value.clone());
+            }
+            !matches
+        });
+        removed
     }
 
     /// Get the first value for a header (most common case).
     pub fn get(&self, name: &str) -> Option<&String> {
-        self.0.get(&name.to_lowercase()).and_then(|v| v.first())
+        self.0.iter().find(|(existing, _)| existing.eq_ignore_ascii_case(name)).map(|(_, value)| value)
     }
 
-    /// Get all values for a header (for Set-Cookie, etc.).
-    pub fn get_all(&self, name: &str) -> Option<&Vec<String>> {
-        self.0.get(&name.to_lowercase())
+    /// Get all values for a header (for Set-Cookie, etc.), in order.
+    pub fn get_all(&self, name: &str) -> Vec<&String> {
+        self.0.iter().filter(|(existing, _)| existing.eq_ignore_ascii_case(name)).map(|(_, value)| value).collect()
     }
 
-    /// Iterate over headers. For multi-value headers, yields each value separately.
+    /// Iterate over headers in order. For multi-value headers, yields each value separately.
     pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
-        self.0.iter().flat_map(|(k, vs)| vs.iter().map(move |v| (k, v)))
+        self.0.iter().map(|(name, value)| (name, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_headers_are_a_case_insensitive_multimap() {
+        let mut headers = Headers::new();
+        headers.append("Content-Type".to_string(), "text/html".to_string());
+        headers.append("Set-Cookie".to_string(), "a=1".to_string());
+        headers.append("X-Trace".to_string(), "t".to_string());
+        headers.append("set-cookie".to_string(), "b=2".to_string());
+
+        assert_eq!(headers.get("Content-Type"), headers.get("content-type"));
+        assert_eq!(headers.get("CONTENT-TYPE").map(String::as_str), Some("text/html"));
+        assert_eq!(headers.get_all("set-cookie"), vec!["a=1", "b=2"]);
+
+        // Insert replaces every value in place of the first; remove drops them all
+        headers.insert("SET-COOKIE".to_string(), "c=3".to_string());
+        let names: Vec<(&str, &str)> = headers.iter().map(|(n, v)| (n.as_str(), v.as_str())).collect();
+        assert_eq!(names, vec![("Content-Type", "text/html"), ("SET-COOKIE", "c=3"), ("X-Trace", "t")]);
+        assert_eq!(headers.remove("x-trace"), vec!["t"]);
+        assert!(headers.get("X-Trace").is_none());
+        assert!(headers.get_all("x-trace").is_empty());
     }
 }
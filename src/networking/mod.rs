@@ -1,20 +1,70 @@
 mod error;
+mod file;
+mod hsts;
 mod http;
 mod pool;
 mod tcp;
 mod uri;
 mod user_agent;
+#[cfg(test)]
+pub(crate) mod test_server;
 
 pub use error::NetworkError;
+pub use http::{Headers, Method, Request, Response};
 pub use uri::Uri;
+pub use user_agent::user_agent;
 use pool::ConnectionPool;
-use tokio::sync::Mutex;
-use std::collections::HashMap;
+use tokio::sync::{Mutex, Semaphore};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Maximum number of redirects followed for a single navigation.
+pub const MAX_REDIRECTS: usize = 10;
+
+/// Synthesizes the response for a URL of a custom scheme, in place of network I/O.
+pub type SchemeHandler = Box<dyn Fn(&Uri) -> Result<http::Response, NetworkError> + Send + Sync>;
+
+/// Sees every request just before it is sent, default headers included, and may
+/// change its headers or target.
+pub type RequestInterceptor = Box<dyn Fn(&mut http::Request) + Send + Sync>;
+
+// Worker pool size and per-URL time limit for `fetch_all`
+const FETCH_ALL_WORKERS: usize = 8;
+const FETCH_ALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Requests kept in the request log; older ones are dropped first
+const REQUEST_LOG_CAPACITY: usize = 500;
+
+/// One request sent over the network, as recorded for the dev panel. Each
+/// redirect hop is a separate entry; cache hits send nothing and are not logged.
+#[derive(Debug, Clone)]
+pub struct RequestLogEntry {
+    pub url: String,
+    pub method: http::Method,
+    /// None when no response arrived.
+    pub status: Option<u16>,
+    /// Size of the response body as delivered, after decompression.
+    pub bytes: usize,
+    pub started: Instant,
+    pub duration: Duration,
+}
 
 pub struct NetworkManager {
     cache: Mutex<ResponseCache>,
     cookies: Mutex<CookieJar>,
+    hsts: Mutex<hsts::HstsStore>,
     pool: ConnectionPool,
+    max_body_bytes: usize,
+    max_decompressed_bytes: Option<usize>,
+    user_agent: String,
+    // Keyed by lowercase scheme name
+    scheme_handlers: HashMap<String, SchemeHandler>,
+    request_interceptor: Option<RequestInterceptor>,
+    // When false, 3xx responses come back as they are instead of being followed
+    follow_redirects: bool,
+    // Most recent requests, oldest first, at most REQUEST_LOG_CAPACITY
+    request_log: Mutex<VecDeque<RequestLogEntry>>,
 }
 
 impl NetworkManager {
@@ -22,12 +72,87 @@ impl NetworkManager {
         Ok(Self {
             cache: Mutex::new(ResponseCache::new()),
             cookies: Mutex::new(CookieJar::new()),
+            hsts: Mutex::new(hsts::HstsStore::new()),
             pool: ConnectionPool::new(),
+            max_body_bytes: tcp::TcpConnection::DEFAULT_MAX_BODY_BYTES,
+            max_decompressed_bytes: None,
+            user_agent: user_agent::user_agent(),
+            scheme_handlers: HashMap::new(),
+            request_interceptor: None,
+            follow_redirects: true,
+            request_log: Mutex::new(VecDeque::new()),
         })
     }
 
+    /// Close pooled keep-alive connections after `timeout` without use.
+    pub fn set_keep_alive_timeout(&mut self, timeout: Duration) {
+        self.pool.set_max_idle_time(timeout);
+    }
+
+    /// Cap response bodies at `max_body_bytes`; larger responses fail with
+    /// `NetworkError::TooLargeResponse`. Independent of the response cache limits.
+    pub fn set_max_body_bytes(&mut self, max_body_bytes: usize) {
+        self.max_body_bytes = max_body_bytes;
+    }
+
+    /// Cap gzip/deflate bodies at `max_decompressed_bytes` once decoded; larger
+    /// ones fail with `NetworkError::DecompressedTooLarge`. `None` (the default)
+    /// applies the body limit.
+    pub fn set_max_decompressed_bytes(&mut self, max_decompressed_bytes: Option<usize>) {
+        self.max_decompressed_bytes = max_decompressed_bytes;
+    }
+
+    /// With `follow` false, redirect responses (3xx with a Location) are returned
+    /// as they are rather than followed, e.g. for link checking.
+    pub fn set_follow_redirects(&mut self, follow: bool) {
+        self.follow_redirects = follow;
+    }
+
+    /// Send `user_agent` as the User-Agent header instead of the detected default.
+    pub fn set_user_agent(&mut self, user_agent: String) {
+        self.user_agent = user_agent;
+    }
+
+    /// Serve every URL of `scheme` (e.g. `"app"` for `app://...`) from `handler`.
+    /// Handlers are consulted before anything else, bypassing the cache and cookies,
+    /// and replace any handler already registered for the scheme.
+    pub fn register_scheme_handler(&mut self, scheme: &str, handler: SchemeHandler) {
+        self.scheme_handlers.insert(scheme.to_ascii_lowercase(), handler);
+    }
+
+    /// Run `interceptor` on each outgoing request, redirect hops included, after
+    /// the default headers are set so it can override them. Replaces any
+    /// interceptor set before.
+    pub fn set_request_interceptor(&mut self, interceptor: RequestInterceptor) {
+        self.request_interceptor = Some(interceptor);
+    }
+
     pub async fn fetch(&self, url: &str) -> Result<http::Response, NetworkError> {
-        if let Some(hit) = self.cache.lock().await.get(url) {
+        self.fetch_with_referer(url, None).await
+    }
+
+    /// Fetch `url`, sending `referer` (the URL of the requesting page) as the Referer
+    /// header where the referrer policy allows it.
+    pub async fn fetch_with_referer(&self, url: &str, referer: Option<&str>) -> Result<http::Response, NetworkError> {
+        self.fetch_with_options(url, referer, false).await
+    }
+
+    /// Like `fetch_with_referer`, but with `bypass_cache` set the response cache is
+    /// not consulted and the request carries `Cache-Control: no-cache`. The fresh
+    /// response still replaces any cached entry.
+    pub async fn fetch_with_options(
+        &self,
+        url: &str,
+        referer: Option<&str>,
+        bypass_cache: bool,
+    ) -> Result<http::Response, NetworkError> {
+        let url = match self.route(url).await {
+            Route::Local(response) => return response,
+            Route::Network(url) => url,
+        };
+        let url = url.as_str();
+
+        if !bypass_cache && let Some(hit) = self.cache.lock().await.get(url) {
             return Ok(hit);
         }
 
@@ -38,7 +163,7 @@ impl NetworkManager {
         let mut last_error = None;
         
         for attempt in 0..MAX_RETRIES {
-            match self.fetch_with_pool(url, cookie_header.as_deref()).await {
+            match self.fetch_with_pool(url, http::Method::GET, None, cookie_header.as_deref(), referer, bypass_cache).await {
                 Ok(response) => {
                     // Check if response indicates a failure that should be retried
                     // (e.g., truncated chunked data, decompression failures)
@@ -47,10 +172,18 @@ impl NetworkManager {
                     // Extract Set-Cookie headers and store them
                     self.cookies.lock().await.extract_cookies(url, &response.headers);
                     
-                    // Cache successful response
-                    self.cache.lock().await.insert(url, &response);
+                    // Cache successful response. An unfollowed redirect isn't
+                    // cached, or it would be served once redirects are followed again
+                    if !is_redirect_status(response.status.code) {
+                        self.cache.lock().await.insert(url, &response);
+                    }
                     return Ok(response);
                 }
+                Err(e @ (NetworkError::TooLargeResponse | NetworkError::DecompressedTooLarge(_) | NetworkError::DnsLookupFailed(_))) => {
+                    // Retrying would download the same oversized body again, or
+                    // look up the same missing name
+                    return Err(e);
+                }
                 Err(e) => {
                     last_error = Some(e);
                     if attempt < MAX_RETRIES - 1 {
@@ -68,21 +201,183 @@ impl NetworkManager {
         Err(last_error.unwrap())
     }
 
-    async fn fetch_with_pool(&self, url: &str, cookie_header: Option<&str>) -> Result<http::Response, NetworkError> {
-        const MAX_REDIRECTS: usize = 10;
+    /// Fetch `urls` on separate tasks, running at most `max_concurrent` at once and
+    /// giving each `timeout` to finish. Results come back in the order of `urls`.
+    pub async fn fetch_concurrently(
+        self: &Arc<Self>,
+        urls: &[String],
+        referer: Option<&str>,
+        max_concurrent: usize,
+        timeout: Duration,
+    ) -> Vec<Result<http::Response, NetworkError>> {
+        let permits = Arc::new(Semaphore::new(max_concurrent.max(1)));
+        let mut tasks = tokio::task::JoinSet::new();
+        for (index, url) in urls.iter().enumerate() {
+            let manager = Arc::clone(self);
+            let permits = Arc::clone(&permits);
+            let url = url.clone();
+            let referer = referer.map(str::to_string);
+            tasks.spawn(async move {
+                // The semaphore is never closed, so acquiring only waits
+                let _permit = permits.acquire_owned().await;
+                let result = tokio::time::timeout(timeout, manager.fetch_with_referer(&url, referer.as_deref()))
+                    .await
+                    .unwrap_or(Err(NetworkError::Timeout(url)));
+                (index, result)
+            });
+        }
+
+        let mut results: Vec<Option<Result<http::Response, NetworkError>>> = urls.iter().map(|_| None).collect();
+        while let Some(joined) = tasks.join_next().await {
+            match joined {
+                Ok((index, result)) => results[index] = Some(result),
+                Err(e) => log::warn!(target: "network", "Fetch task failed: {}", e),
+            }
+        }
+        results
+            .into_iter()
+            .map(|result| result.unwrap_or_else(|| Err(NetworkError::ConnectionFailed("fetch task failed".to_string()))))
+            .collect()
+    }
+
+    /// POST `body` to `url` with the given Content-Type. POST responses are neither
+    /// cached nor retried, since repeating the request could repeat its effect.
+    pub async fn post(
+        &self,
+        url: &str,
+        content_type: &str,
+        body: &[u8],
+        referer: Option<&str>,
+    ) -> Result<http::Response, NetworkError> {
+        let upgraded = self.upgrade_to_https(url).await;
+        let url = upgraded.as_deref().unwrap_or(url);
+
+        let cookie_header = self.cookies.lock().await.get_cookie_header(url);
+        let response = self
+            .fetch_with_pool(url, http::Method::POST, Some((content_type, body)), cookie_header.as_deref(), referer, false)
+            .await?;
+        self.cookies.lock().await.extract_cookies(url, &response.headers);
+        Ok(response)
+    }
+
+    /// Fetch a batch of URLs, a few at a time, through the shared cache and cookie
+    /// jar. Results come back in the order of `urls`.
+    pub async fn fetch_all(self: &Arc<Self>, urls: &[String]) -> Vec<Result<http::Response, NetworkError>> {
+        self.fetch_concurrently(urls, None, FETCH_ALL_WORKERS, FETCH_ALL_TIMEOUT).await
+    }
+
+    /// Issue a HEAD request: status and headers only, the body is always empty.
+    /// HEAD responses are neither cached nor retried.
+    pub async fn head(&self, url: &str) -> Result<http::Response, NetworkError> {
+        let url = match self.route(url).await {
+            Route::Local(response) => {
+                return response.map(|mut response| {
+                    response.body.clear();
+                    response
+                });
+            }
+            Route::Network(url) => url,
+        };
+        let cookie_header = self.cookies.lock().await.get_cookie_header(&url);
+        let response = self.fetch_with_pool(&url, http::Method::HEAD, None, cookie_header.as_deref(), None, false).await?;
+        self.cookies.lock().await.extract_cookies(&url, &response.headers);
+        Ok(response)
+    }
+
+    /// Store a cookie for `url`'s host, as if the server had sent it with
+    /// `Path=/`. It replaces any cookie of the same name for that host.
+    pub async fn set_cookie(&self, url: &str, name: &str, value: &str) -> Result<(), NetworkError> {
+        let domain = Uri::parse(url).map_err(|_| NetworkError::InvalidUri)?.host().to_lowercase();
+        let cookie = Cookie { name: name.to_string(), value: value.to_string(), path: "/".to_string() };
+        self.cookies.lock().await.insert(domain, cookie);
+        Ok(())
+    }
+
+    /// The cookies a request to `url` would carry, as (name, value) pairs.
+    pub async fn cookies(&self, url: &str) -> Vec<(String, String)> {
+        self.cookies.lock().await.cookies_for(url)
+    }
+
+    /// Forget every cached response; later fetches go to the network.
+    pub async fn clear_cache(&self) {
+        *self.cache.lock().await = ResponseCache::new();
+    }
+
+    /// Forget every cookie, whether set by sites or through `set_cookie`.
+    pub async fn clear_cookies(&self) {
+        *self.cookies.lock().await = CookieJar::new();
+    }
+
+    /// The most recent requests sent over the network, oldest first.
+    pub async fn request_log(&self) -> Vec<RequestLogEntry> {
+        self.request_log.lock().await.iter().cloned().collect()
+    }
+
+    async fn record_request(&self, entry: RequestLogEntry) {
+        let mut log = self.request_log.lock().await;
+        if log.len() == REQUEST_LOG_CAPACITY {
+            log.pop_front();
+        }
+        log.push_back(entry);
+    }
+
+    // Where a request for `url` is served from: a registered scheme handler,
+    // the local file system, or the network. Known HSTS hosts are only ever
+    // contacted over https.
+    async fn route(&self, url: &str) -> Route {
+        if let Some((scheme, _)) = url.split_once("://")
+            && let Some(handler) = self.scheme_handlers.get(&scheme.to_ascii_lowercase())
+        {
+            log::debug!(target: "network", "Serving {} from the {} scheme handler", url, scheme);
+            return Route::Local(Uri::parse_any_scheme(url).and_then(|uri| handler(&uri)));
+        }
+
+        // Local files bypass the cache and cookie jar entirely
+        if url.starts_with("file://") {
+            return Route::Local(file::load(url).await);
+        }
+
+        match self.upgrade_to_https(url).await {
+            Some(upgraded) => Route::Network(upgraded),
+            None => Route::Network(url.to_string()),
+        }
+    }
+
+    async fn upgrade_to_https(&self, url: &str) -> Option<String> {
+        let upgraded = self.hsts.lock().await.upgrade(url)?;
+        log::debug!(target: "network", "HSTS upgrade: {} -> {}", url, upgraded);
+        Some(upgraded)
+    }
+
+    async fn fetch_with_pool(
+        &self,
+        url: &str,
+        method: http::Method,
+        body: Option<(&str, &[u8])>, // Content-Type and payload
+        cookie_header: Option<&str>,
+        referer: Option<&str>,
+        bypass_cache: bool,
+    ) -> Result<http::Response, NetworkError> {
         const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
         let mut current = url.to_string();
+        let (mut method, mut body) = (method, body);
 
         for _ in 0..MAX_REDIRECTS {
+            // A redirect may point back at plain http on an HSTS host
+            if let Some(upgraded) = self.upgrade_to_https(&current).await {
+                current = upgraded;
+            }
             let uri = Uri::parse(&current)?;
             let mut connection = self.pool.get(&uri).await?;
+            connection.set_max_body_bytes(self.max_body_bytes);
+            connection.set_max_decompressed_bytes(self.max_decompressed_bytes);
 
             let mut builder = http::Request::new()
-                .method(http::Method::GET)
+                .method(method)
                 .uri(uri.request_target())
                 .header("Host", uri.host())
                 .header("Connection", "keep-alive")
-                .header("User-Agent", user_agent::user_agent())
+                .header("User-Agent", &self.user_agent)
                 .header("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")
                 .header("Accept-Encoding", "gzip, deflate, identity")
                 .header("Accept-Language", "en-US,en;q=0.9");
@@ -93,26 +388,73 @@ impl NetworkManager {
                 }
             }
 
-            let request = builder.build()?;
+            // Ask intermediaries to revalidate rather than serve their own copy
+            if bypass_cache {
+                builder = builder
+                    .header("Cache-Control", "no-cache")
+                    .header("Pragma", "no-cache");
+            }
+
+            // Checked per hop: a redirect may downgrade the target to http
+            if let Some(referer) = referer.and_then(|r| referer_for(r, &current)) {
+                builder = builder.header("Referer", referer);
+            }
+
+            if let Some((content_type, payload)) = body {
+                builder = builder
+                    .header("Content-Type", content_type)
+                    .header("Content-Length", payload.len().to_string())
+                    .body(payload);
+            }
+
+            let mut request = builder.build()?;
+            if let Some(interceptor) = &self.request_interceptor {
+                interceptor(&mut request);
+            }
             
             // Wrap send_request with timeout
-            let response = tokio::time::timeout(
+            let started = Instant::now();
+            let sent = tokio::time::timeout(
                 REQUEST_TIMEOUT,
                 connection.send_request(&request)
             )
             .await
-            .map_err(|_| NetworkError::Timeout("Request timed out".to_string()))??;
+            .map_err(|_| NetworkError::Timeout("Request timed out".to_string()))
+            .and_then(|result| result);
+            self.record_request(RequestLogEntry {
+                url: current.clone(),
+                method,
+                status: sent.as_ref().ok().map(|response| response.status.code),
+                bytes: sent.as_ref().map_or(0, |response| response.body.len()),
+                started,
+                duration: started.elapsed(),
+            })
+            .await;
+            let response = sent?;
 
             // Don't reuse connections for now - causes hangs when the response 
             // reading leaves the connection in a bad state.
             // TODO: Fix response reading to properly drain the connection before reuse.
             drop(connection);
 
-            if is_redirect_status(response.status.code) {
-                if let Some(location) = response.headers.get("location") {
-                    current = uri.resolve_reference(location)?;
-                    continue;
+            // The header only counts when it arrives over a secure connection
+            if uri.scheme() == "https"
+                && let Some(sts) = response.headers.get("strict-transport-security")
+            {
+                self.hsts.lock().await.record(uri.host(), sts);
+            }
+
+            if self.follow_redirects
+                && is_redirect_status(response.status.code)
+                && let Some(location) = response.headers.get("location")
+            {
+                current = uri.resolve_reference(location)?;
+                // Like browsers, only 307 and 308 repeat a POST; the others fetch the target
+                if method == http::Method::POST && !matches!(response.status.code, 307 | 308) {
+                    method = http::Method::GET;
+                    body = None;
                 }
+                continue;
             }
             return Ok(response);
         }
@@ -121,10 +463,32 @@ impl NetworkManager {
     }
 }
 
+/// Referer value to send from page `from` to request `to`, following the
+/// no-referrer-when-downgrade policy: nothing is sent from https to plain http.
+/// The fragment is never part of a referrer.
+pub fn referer_for(from: &str, to: &str) -> Option<String> {
+    let from = from.split('#').next().unwrap_or(from);
+    let from_secure = from.starts_with("https://");
+    if !from_secure && !from.starts_with("http://") {
+        return None;
+    }
+    if from_secure && to.starts_with("http://") {
+        return None;
+    }
+    Some(from.to_string())
+}
+
 fn is_redirect_status(code: u16) -> bool {
     matches!(code, 301 | 302 | 303 | 307 | 308)
 }
 
+// How `NetworkManager::route` serves a request: already answered locally, or
+// over the network at this URL
+enum Route {
+    Local(Result<http::Response, NetworkError>),
+    Network(String),
+}
+
 struct ResponseCache {
     entries: HashMap<String, http::Response>,
     current_body_bytes: usize,
@@ -198,33 +562,43 @@ impl CookieJar {
         };
 
         // Process all Set-Cookie headers (there can be multiple)
-        if let Some(set_cookies) = headers.get_all("set-cookie") {
-            for set_cookie in set_cookies {
-                if let Some(cookie) = parse_set_cookie(set_cookie, &domain) {
-                    self.cookies
-                        .entry(domain.clone())
-                        .or_default()
-                        .insert(cookie.name.clone(), cookie);
-                }
+        for set_cookie in headers.get_all("set-cookie") {
+            if let Some(cookie) = parse_set_cookie(set_cookie, &domain) {
+                self.insert(domain.clone(), cookie);
             }
         }
     }
 
-    // Build Cookie header for a request
-    fn get_cookie_header(&self, url: &str) -> Option<String> {
-        let uri = Uri::parse(url).ok()?;
+    fn insert(&mut self, domain: String, cookie: Cookie) {
+        self.cookies.entry(domain).or_default().insert(cookie.name.clone(), cookie);
+    }
+
+    // Cookies that apply to a request for `url`, sorted by name
+    fn cookies_for(&self, url: &str) -> Vec<(String, String)> {
+        let Ok(uri) = Uri::parse(url) else {
+            return Vec::new();
+        };
         let domain = uri.host().to_lowercase();
         let path = uri.path();
 
-        let domain_cookies = self.cookies.get(&domain)?;
-        if domain_cookies.is_empty() {
-            return None;
-        }
-
-        let cookies: Vec<String> = domain_cookies
-            .values()
+        let mut cookies: Vec<(String, String)> = self
+            .cookies
+            .get(&domain)
+            .into_iter()
+            .flat_map(|domain_cookies| domain_cookies.values())
             .filter(|c| path.starts_with(&c.path))
-            .map(|c| format!("{}={}", c.name, c.value))
+            .map(|c| (c.name.clone(), c.value.clone()))
+            .collect();
+        cookies.sort();
+        cookies
+    }
+
+    // Build Cookie header for a request
+    fn get_cookie_header(&self, url: &str) -> Option<String> {
+        let cookies: Vec<String> = self
+            .cookies_for(url)
+            .into_iter()
+            .map(|(name, value)| format!("{}={}", name, value))
             .collect();
 
         if cookies.is_empty() {
@@ -260,3 +634,241 @@ fn parse_set_cookie(header_value: &str, _default_domain: &str) -> Option<Cookie>
         path,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_server::{TestResponse, TestServer};
+
+    #[test]
+    fn test_referer_policy() {
+        assert_eq!(referer_for("https://a.test/page#top", "https://b.test/x.js"), Some("https://a.test/page".to_string()));
+        assert_eq!(referer_for("http://a.test/", "https://a.test/"), Some("http://a.test/".to_string()));
+        assert_eq!(referer_for("https://a.test/", "http://a.test/style.css"), None);
+        assert_eq!(referer_for("file:///tmp/page.html", "http://a.test/"), None);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_file_url() {
+        let dir = std::env::temp_dir().join(format!("celeris-file-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("create temp dir");
+        let path = dir.join("page.html");
+        std::fs::write(&path, "<html><body>offline docs</body></html>").expect("write file");
+
+        let manager = NetworkManager::new().expect("network manager");
+        let url = format!("file://{}", path.display());
+        let response = manager.fetch(&url).await.expect("file fetch");
+        assert_eq!(response.status.code, 200);
+        assert_eq!(response.headers.get("content-type").map(|s| s.as_str()), Some("text/html"));
+        assert_eq!(response.body, std::fs::read(&path).expect("read file"));
+
+        let missing = manager.fetch(&format!("file://{}/missing.html", dir.display())).await;
+        assert!(matches!(missing, Err(NetworkError::FileNotFound(_))));
+        let directory = manager.fetch(&format!("file://{}", dir.display())).await;
+        assert!(matches!(directory, Err(NetworkError::IsDirectory(_))));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_custom_scheme_handler() {
+        let mut manager = NetworkManager::new().expect("network manager");
+        manager.register_scheme_handler(
+            "test",
+            Box::new(|uri| {
+                let mut headers = http::Headers::new();
+                headers.insert("Content-Type".to_string(), "text/plain".to_string());
+                Ok(http::Response {
+                    version: http::Version::Http11,
+                    status: http::Status { code: 200, text: "OK".to_string() },
+                    headers,
+                    body: format!("served {}{}", uri.host(), uri.path()).into_bytes(),
+                })
+            }),
+        );
+
+        let response = manager.fetch("test://x").await.expect("handled fetch");
+        assert_eq!(response.status.code, 200);
+        assert_eq!(response.body, b"served x/");
+        let response = manager.fetch("TEST://app/page?q=1").await.expect("scheme is case-insensitive");
+        assert_eq!(response.body, b"served app/page");
+
+        // Unregistered custom schemes still fail
+        assert!(matches!(manager.fetch("other://x").await, Err(NetworkError::InvalidUri)));
+
+        // HEAD goes through the same handler, without the body
+        let response = manager.head("test://x").await.expect("handled head");
+        assert_eq!(response.status.code, 200);
+        assert_eq!(response.headers.get("content-type").map(String::as_str), Some("text/plain"));
+        assert!(response.body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_body_size_limit() {
+        let server = TestServer::start(vec![
+            ("/big", TestResponse::ok("text/plain", vec![b'x'; 4096])),
+            ("/small", TestResponse::ok("text/plain", vec![b'x'; 512])),
+        ])
+        .await;
+
+        let mut manager = NetworkManager::new().expect("network manager");
+        manager.set_max_body_bytes(1024);
+        let result = manager.fetch(&server.url("/big")).await;
+        assert!(matches!(result, Err(NetworkError::TooLargeResponse)));
+        // Not retried
+        assert_eq!(server.requests().len(), 1);
+
+        let small = manager.fetch(&server.url("/small")).await.expect("small body");
+        assert_eq!(small.body.len(), 512);
+    }
+
+    #[tokio::test]
+    async fn test_decompressed_size_limit() {
+        use flate2::{Compression, write::GzEncoder};
+        use std::io::Write;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&vec![0u8; 1024 * 1024]).expect("compress");
+        let bomb = encoder.finish().expect("finish gzip");
+        assert!(bomb.len() < 4096);
+        let server = TestServer::start(vec![(
+            "/bomb",
+            TestResponse::ok("text/plain", bomb).header("Content-Encoding", "gzip"),
+        )])
+        .await;
+
+        let mut manager = NetworkManager::new().expect("network manager");
+        manager.set_max_decompressed_bytes(Some(64 * 1024));
+        let result = manager.fetch(&server.url("/bomb")).await;
+        assert!(matches!(result, Err(NetworkError::DecompressedTooLarge(limit)) if limit == 64 * 1024));
+        // Not retried
+        assert_eq!(server.requests().len(), 1);
+
+        // Without an explicit limit the body limit applies to the decoded size
+        manager.set_max_decompressed_bytes(None);
+        manager.set_max_body_bytes(512 * 1024);
+        let result = manager.fetch(&server.url("/bomb")).await;
+        assert!(matches!(result, Err(NetworkError::DecompressedTooLarge(limit)) if limit == 512 * 1024));
+    }
+
+    #[tokio::test]
+    async fn test_head_request_has_no_body() {
+        let server = TestServer::start(vec![
+            ("/doc", TestResponse::html("<html>a body that must not be read</html>").header("Last-Modified", "Wed, 21 Oct 2015 07:28:00 GMT")),
+        ])
+        .await;
+
+        let manager = NetworkManager::new().expect("network manager");
+        let response = manager.head(&server.url("/doc")).await.expect("HEAD request");
+        assert_eq!(response.status.code, 200);
+        assert_eq!(response.headers.get("last-modified").map(|s| s.as_str()), Some("Wed, 21 Oct 2015 07:28:00 GMT"));
+        assert!(response.headers.get("content-length").is_some());
+        assert_eq!(response.body.len(), 0);
+        assert!(server.requests()[0].starts_with("HEAD /doc "));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_all_keeps_input_order() {
+        let delay = Duration::from_millis(100);
+        let server = TestServer::start(vec![
+            ("/slow", TestResponse::ok("text/plain", "slow").delay(delay * 2)),
+            ("/a", TestResponse::ok("text/plain", "a").delay(delay)),
+            ("/b", TestResponse::ok("text/plain", "b")),
+        ])
+        .await;
+
+        let manager = Arc::new(NetworkManager::new().expect("network manager"));
+        let urls: Vec<String> = ["/slow", "/a", "/b", "/missing"].iter().map(|path| server.url(path)).collect();
+        let results = manager.fetch_all(&urls).await;
+
+        let statuses: Vec<u16> = results.iter().map(|r| r.as_ref().expect("fetch").status.code).collect();
+        assert_eq!(statuses, vec![200, 200, 200, 404]);
+        let bodies: Vec<&[u8]> = results[..3].iter().map(|r| r.as_ref().expect("fetch").body.as_slice()).collect();
+        assert_eq!(bodies, vec![b"slow".as_slice(), b"a", b"b"]);
+        assert!(server.max_in_flight() >= 2, "max in flight: {}", server.max_in_flight());
+    }
+
+    #[tokio::test]
+    async fn test_request_log_records_each_fetch() {
+        let server = TestServer::start(vec![
+            ("/page", TestResponse::html("<p>hello</p>")),
+            ("/missing", TestResponse::html("gone").status(404)),
+        ])
+        .await;
+        let manager = NetworkManager::new().expect("network manager");
+        manager.fetch(&server.url("/page")).await.expect("page");
+        manager.fetch(&server.url("/missing")).await.expect("404 response");
+
+        let log = manager.request_log().await;
+        let summary: Vec<(String, http::Method, Option<u16>, usize)> =
+            log.iter().map(|entry| (entry.url.clone(), entry.method, entry.status, entry.bytes)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (server.url("/page"), http::Method::GET, Some(200), 12),
+                (server.url("/missing"), http::Method::GET, Some(404), 4),
+            ]
+        );
+        assert!(log[0].started <= log[1].started);
+    }
+
+    #[tokio::test]
+    async fn test_redirect_is_returned_when_not_following() {
+        let server = TestServer::start(vec![
+            ("/old", TestResponse::html("moved").status(302).header("Location", "/new")),
+            ("/new", TestResponse::html("new page")),
+        ])
+        .await;
+        let mut manager = NetworkManager::new().expect("network manager");
+        manager.set_follow_redirects(false);
+        let response = manager.fetch(&server.url("/old")).await.expect("redirect response");
+        assert_eq!(response.status.code, 302);
+        assert_eq!(response.headers.get("location").map(String::as_str), Some("/new"));
+        assert_eq!(server.requests().len(), 1);
+
+        manager.set_follow_redirects(true);
+        let response = manager.fetch(&server.url("/old")).await.expect("followed");
+        assert_eq!(response.body, b"new page");
+    }
+
+    #[tokio::test]
+    async fn test_request_interceptor_sees_and_overrides_default_headers() {
+        let server = TestServer::start(vec![("/page", TestResponse::html("ok")), ("/rewritten", TestResponse::html("moved"))]).await;
+        let mut manager = NetworkManager::new().expect("network manager");
+        manager.set_request_interceptor(Box::new(|request| {
+            request.headers_mut().insert("X-Test".to_string(), "yes".to_string());
+            request.headers_mut().insert("user-agent".to_string(), "Interceptor/1.0".to_string());
+            if request.uri() == "/page" {
+                request.set_uri("/rewritten");
+            }
+        }));
+        let response = manager.fetch(&server.url("/page")).await.expect("fetch");
+        assert_eq!(response.body, b"moved");
+
+        let requests = server.requests();
+        let lines: Vec<&str> = requests[0].lines().collect();
+        assert_eq!(lines[0], "GET /rewritten HTTP/1.1");
+        assert!(lines.contains(&"X-Test: yes"), "request: {:?}", lines);
+        let agents: Vec<&&str> = lines.iter().filter(|l| l.to_ascii_lowercase().starts_with("user-agent:")).collect();
+        assert_eq!(agents, vec![&"user-agent: Interceptor/1.0"]);
+    }
+
+    #[tokio::test]
+    async fn test_clear_cache_and_cookies() {
+        let server = TestServer::start(vec![("/page", TestResponse::html("hello").header("Set-Cookie", "session=abc; Path=/"))]).await;
+        let manager = NetworkManager::new().expect("network manager");
+        let url = server.url("/page");
+        manager.fetch(&url).await.expect("first fetch");
+        manager.fetch(&url).await.expect("cached fetch");
+        assert_eq!(server.requests().len(), 1);
+        assert_eq!(manager.cookies(&url).await, vec![("session".to_string(), "abc".to_string())]);
+
+        manager.clear_cache().await;
+        manager.clear_cookies().await;
+        assert!(manager.cookies(&url).await.is_empty());
+        manager.fetch(&url).await.expect("fetch after clearing");
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(!requests[1].lines().any(|l| l.to_ascii_lowercase().starts_with("cookie:")), "request: {}", requests[1]);
+    }
+}
@@ -28,18 +28,23 @@ impl ConnectionPool {
         }
     }
 
+    /// Close pooled connections once they have been idle for `max_idle_time`,
+    /// before the server is likely to have dropped them.
+    pub fn set_max_idle_time(&mut self, max_idle_time: Duration) {
+        self.max_idle_time = max_idle_time;
+    }
+
     /// Get a connection for the given URI, either from the pool or by creating a new one.
     pub async fn get(&self, uri: &Uri) -> Result<TcpConnection, NetworkError> {
         let key = pool_key(uri);
         
-        // Try to get an existing connection from the pool
+        // Try to get an existing connection from the pool, dropping any that
+        // have sat idle too long on the way
         let mut pool = self.connections.lock().await;
+        self.retain_fresh(&mut pool);
         if let Some(pooled) = pool.remove(&key) {
-            if pooled.last_used.elapsed() < self.max_idle_time {
-                log::debug!(target: "network", "Reusing pooled connection for {}", key);
-                return Ok(pooled.connection);
-            }
-            log::debug!(target: "network", "Dropping expired connection for {}", key);
+            log::debug!(target: "network", "Reusing pooled connection for {}", key);
+            return Ok(pooled.connection);
         }
         drop(pool);
 
@@ -58,8 +63,7 @@ impl ConnectionPool {
         
         // Evict old connections if pool is getting large
         if pool.len() >= 16 {
-            let now = Instant::now();
-            pool.retain(|_, v| now.duration_since(v.last_used) < self.max_idle_time);
+            self.retain_fresh(&mut pool);
         }
 
         pool.insert(key, PooledConnection {
@@ -72,8 +76,18 @@ impl ConnectionPool {
     #[allow(dead_code)]
     pub async fn evict_expired(&self) {
         let mut pool = self.connections.lock().await;
+        self.retain_fresh(&mut pool);
+    }
+
+    fn retain_fresh(&self, pool: &mut HashMap<String, PooledConnection>) {
         let now = Instant::now();
-        pool.retain(|_, v| now.duration_since(v.last_used) < self.max_idle_time);
+        pool.retain(|key, v| {
+            let fresh = now.duration_since(v.last_used) < self.max_idle_time;
+            if !fresh {
+                log::debug!(target: "network", "Dropping expired connection for {}", key);
+            }
+            fresh
+        });
     }
 }
 
@@ -82,3 +96,55 @@ fn pool_key(uri: &Uri) -> String {
     format!("{}:{}:{}", uri.scheme(), uri.host(), port)
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_idle_connection_is_not_reused_after_timeout() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let uri = Uri::parse(&format!("http://127.0.0.1:{}/", listener.local_addr().expect("address").port())).expect("uri");
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&accepted);
+        tokio::spawn(async move {
+            let mut open = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                open.push(stream);
+            }
+        });
+        let connections_after = |expected: usize| {
+            let accepted = Arc::clone(&accepted);
+            async move {
+                for _ in 0..100 {
+                    if accepted.load(Ordering::SeqCst) >= expected {
+                        break;
+                    }
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                }
+                accepted.load(Ordering::SeqCst)
+            }
+        };
+
+        let mut pool = ConnectionPool::new();
+        pool.set_max_idle_time(Duration::from_millis(100));
+        let connection = pool.get(&uri).await.expect("connect");
+        assert_eq!(connections_after(1).await, 1);
+
+        // Returned and asked for again right away, the connection is reused
+        pool.put(&uri, connection).await;
+        let connection = pool.get(&uri).await.expect("reuse");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(accepted.load(Ordering::SeqCst), 1);
+
+        // Idle past the timeout, it is dropped and a fresh one is opened
+        pool.put(&uri, connection).await;
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        let _connection = pool.get(&uri).await.expect("reconnect");
+        assert_eq!(connections_after(2).await, 2);
+        assert!(pool.connections.lock().await.is_empty());
+    }
+}
@@ -17,10 +17,13 @@ pub struct TcpConnection {
     connection: Connection,
     host: String,
     keep_alive: bool,
+    max_body_bytes: usize,
+    // Limit on a body after Content-Encoding is undone; None follows max_body_bytes
+    max_decompressed_bytes: Option<usize>,
 }
 
 impl TcpConnection {
-    const MAX_DECODED_BODY_BYTES: usize = 32 * 1024 * 1024; // 32 MiB safety cap
+    pub const DEFAULT_MAX_BODY_BYTES: usize = 32 * 1024 * 1024; // 32 MiB safety cap
 
     pub async fn connect(uri: &Uri) -> Result<Self, NetworkError> {
         let is_https = uri.scheme() == "https";
@@ -28,7 +31,17 @@ impl TcpConnection {
         let port = uri.port().unwrap_or(default_port);
         let addr = format!("{}:{}", uri.host(), port);
 
-        let tcp_stream = TcpStream::connect(&addr)
+        // Resolve separately so a name that doesn't exist is told apart from a
+        // host that refuses the connection
+        let addrs: Vec<_> = tokio::net::lookup_host(&addr)
+            .await
+            .map_err(|e| NetworkError::DnsLookupFailed(format!("{}: {}", uri.host(), e)))?
+            .collect();
+        if addrs.is_empty() {
+            return Err(NetworkError::DnsLookupFailed(uri.host().to_string()));
+        }
+
+        let tcp_stream = TcpStream::connect(&addrs[..])
             .await
             .map_err(|e| NetworkError::ConnectionFailed(e.to_string()))?;
 
@@ -63,6 +76,8 @@ impl TcpConnection {
             connection,
             host: uri.host().to_string(),
             keep_alive: true,
+            max_body_bytes: Self::DEFAULT_MAX_BODY_BYTES,
+            max_decompressed_bytes: None,
         })
     }
 
@@ -70,6 +85,17 @@ impl TcpConnection {
         &self.host
     }
 
+    /// Limit on the size of a response body; reads abort with `TooLargeResponse` past it.
+    pub fn set_max_body_bytes(&mut self, max_body_bytes: usize) {
+        self.max_body_bytes = max_body_bytes;
+    }
+
+    /// Limit on a gzip/deflate body once decompressed, so a small compressed
+    /// response can't expand without bound. `None` uses the body limit.
+    pub fn set_max_decompressed_bytes(&mut self, max_decompressed_bytes: Option<usize>) {
+        self.max_decompressed_bytes = max_decompressed_bytes;
+    }
+
     /// Returns true if the connection can be reused for another request.
     pub fn is_keep_alive(&self) -> bool {
         self.keep_alive
@@ -97,7 +123,9 @@ impl TcpConnection {
 
         // Read response with keep-alive support: don't wait for EOF,
         // instead read headers first, then read exact body length.
-        let response_data = self.read_response().await?;
+        // A HEAD response carries the GET headers (including Content-Length) but never a body.
+        let expect_body = request.method() != http::Method::HEAD;
+        let response_data = self.read_response(expect_body).await?;
 
         if response_data.is_empty() {
             return Err(NetworkError::ReceiveFailed(
@@ -109,21 +137,37 @@ impl TcpConnection {
     }
 
     /// Read an HTTP response, handling both keep-alive and close connections.
-    async fn read_response(&mut self) -> Result<Vec<u8>, NetworkError> {
+    /// With `expect_body` false only the header section is read.
+    async fn read_response(&mut self, expect_body: bool) -> Result<Vec<u8>, NetworkError> {
         let mut data = Vec::new();
         let mut buffer = [0u8; 8192];
 
-        // First, read until we have the full headers
-        let header_end = loop {
-            let n = self.read_some(&mut buffer).await?;
-            if n == 0 {
-                // Connection closed before headers complete
-                break find_header_end(&data).unwrap_or(data.len());
-            }
-            data.extend_from_slice(&buffer[..n]);
-            if let Some(end) = find_header_end(&data) {
-                break end;
+        // First, read until we have the full headers. Interim 1xx responses
+        // (100 Continue, 103 Early Hints) come before the final one and are
+        // skipped; 101 Switching Protocols is final.
+        let (header_end, status_code) = loop {
+            let header_end = loop {
+                if let Some(end) = find_header_end(&data) {
+                    break end;
+                }
+                let n = self.read_some(&mut buffer).await?;
+                if n == 0 {
+                    // Connection closed before headers complete
+                    break data.len();
+                }
+                data.extend_from_slice(&buffer[..n]);
+            };
+            let status_code = String::from_utf8_lossy(&data[..header_end])
+                .split_whitespace()
+                .nth(1)
+                .and_then(|code| code.parse::<u16>().ok())
+                .unwrap_or(0);
+            if (100..200).contains(&status_code) && status_code != 101 {
+                log::debug!(target: "network", "Skipping interim {} response", status_code);
+                data.drain(..header_end);
+                continue;
             }
+            break (header_end, status_code);
         };
 
         // Parse headers to determine body length strategy
@@ -159,6 +203,16 @@ impl TcpConnection {
         // Update keep-alive status
         self.keep_alive = !connection_close;
 
+        // 1xx, 204 and 304 responses never have a body, whatever their headers say
+        // (a 304 may repeat the Content-Length of the cached representation).
+        let bodyless_status = (100..200).contains(&status_code) || status_code == 204 || status_code == 304;
+
+        if !expect_body || bodyless_status || (!is_chunked && content_length == Some(0)) {
+            // Anything past the headers belongs to no response; drop it
+            data.truncate(header_end);
+            return Ok(data);
+        }
+
         // Now read the body
         let body_start = header_end;
 
@@ -172,13 +226,12 @@ impl TcpConnection {
                     break;
                 }
                 data.extend_from_slice(&buffer[..n]);
-                // Safety check for very large responses
-                if data.len() > Self::MAX_DECODED_BODY_BYTES + 1024 * 1024 {
-                    log::warn!(target: "network", "Chunked body exceeds max size, truncating");
-                    break;
-                }
+                // Raw size includes chunk framing, which is negligible next to the limit
+                self.check_body_size(data.len() - body_start)?;
             }
         } else if let Some(len) = content_length {
+            // Refuse up front rather than reading a body we'd throw away
+            self.check_body_size(len)?;
             // Read exactly len bytes for the body
             let target = body_start + len;
             while data.len() < target {
@@ -196,6 +249,7 @@ impl TcpConnection {
                     break;
                 }
                 data.extend_from_slice(&buffer[..n]);
+                self.check_body_size(data.len() - body_start)?;
             }
             self.keep_alive = false;
         } else {
@@ -209,6 +263,16 @@ impl TcpConnection {
         Ok(data)
     }
 
+    fn check_body_size(&mut self, body_bytes: usize) -> Result<(), NetworkError> {
+        if body_bytes > self.max_body_bytes {
+            log::warn!(target: "network", "Response body exceeds {} bytes, aborting read", self.max_body_bytes);
+            // The rest of the body is still on the wire
+            self.keep_alive = false;
+            return Err(NetworkError::TooLargeResponse);
+        }
+        Ok(())
+    }
+
     /// Read from the underlying stream with timeout, returning bytes read or 0 on EOF.
     async fn read_some(&mut self, buffer: &mut [u8]) -> Result<usize, NetworkError> {
         const READ_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
@@ -289,7 +353,7 @@ impl TcpConnection {
         // Decode Transfer-Encoding: chunked if present. Many sites (including https://nornity.com)
         // use chunked responses, and the chunk-size lines must not leak into HTML parsing.
         if is_transfer_encoding_chunked(&headers) {
-            body = decode_chunked_body(&body, Self::MAX_DECODED_BODY_BYTES)?;
+            body = decode_chunked_body(&body, self.max_body_bytes)?;
         } else if let Some(content_length) = headers.get("content-length") {
             if let Ok(len) = content_length.trim().parse::<usize>() {
                 if body.len() >= len {
@@ -299,7 +363,7 @@ impl TcpConnection {
         }
 
         // Decompress Content-Encoding: gzip or deflate
-        body = decompress_body(&headers, body)?;
+        body = decompress_body(&headers, body, self.max_decompressed_bytes.unwrap_or(self.max_body_bytes))?;
 
         Ok(http::Response {
             version,
@@ -313,7 +377,7 @@ impl TcpConnection {
     }
 }
 
-fn decompress_body(headers: &http::Headers, body: Vec<u8>) -> Result<Vec<u8>, NetworkError> {
+fn decompress_body(headers: &http::Headers, body: Vec<u8>, max_bytes: usize) -> Result<Vec<u8>, NetworkError> {
     let Some(encoding) = headers.get("content-encoding") else {
         return Ok(body);
     };
@@ -333,10 +397,11 @@ fn decompress_body(headers: &http::Headers, body: Vec<u8>) -> Result<Vec<u8>, Ne
                 return Ok(body);
             }
             
-            let mut decoder = GzDecoder::new(&body[..]);
+            let decoder = GzDecoder::new(&body[..]);
             let mut decompressed = Vec::new();
-            match decoder.read_to_end(&mut decompressed) {
-                Ok(_) => {
+            match read_limited(decoder, &mut decompressed, max_bytes) {
+                Err(e @ NetworkError::DecompressedTooLarge(_)) => Err(e),
+                Ok(()) => {
                     log::debug!(target: "network", "Successfully decompressed gzip body: {} -> {} bytes", body.len(), decompressed.len());
                     Ok(decompressed)
                 }
@@ -351,10 +416,11 @@ fn decompress_body(headers: &http::Headers, body: Vec<u8>) -> Result<Vec<u8>, Ne
             }
         }
         "deflate" => {
-            let mut decoder = DeflateDecoder::new(&body[..]);
+            let decoder = DeflateDecoder::new(&body[..]);
             let mut decompressed = Vec::new();
-            match decoder.read_to_end(&mut decompressed) {
-                Ok(_) => {
+            match read_limited(decoder, &mut decompressed, max_bytes) {
+                Err(e @ NetworkError::DecompressedTooLarge(_)) => Err(e),
+                Ok(()) => {
                     log::debug!(target: "network", "Successfully decompressed deflate body: {} -> {} bytes", body.len(), decompressed.len());
                     Ok(decompressed)
                 }
@@ -374,6 +440,21 @@ fn decompress_body(headers: &http::Headers, body: Vec<u8>) -> Result<Vec<u8>, Ne
     }
 }
 
+// Decode into `out`, stopping with DecompressedTooLarge once more than `max_bytes`
+// come out rather than inflating the whole body first
+fn read_limited(decoder: impl Read, out: &mut Vec<u8>, max_bytes: usize) -> Result<(), NetworkError> {
+    let limit = u64::try_from(max_bytes).unwrap_or(u64::MAX).saturating_add(1);
+    decoder
+        .take(limit)
+        .read_to_end(out)
+        .map_err(|e| NetworkError::ParseError(e.to_string()))?;
+    if out.len() > max_bytes {
+        log::warn!(target: "network", "Decompressed body exceeds {} bytes, aborting", max_bytes);
+        return Err(NetworkError::DecompressedTooLarge(max_bytes));
+    }
+    Ok(())
+}
+
 fn find_header_end(data: &[u8]) -> Option<usize> {
     for (i, window) in data.windows(4).enumerate() {
         if window == b"\r\n\r\n" {
@@ -561,3 +642,78 @@ fn find_crlf(buf: &[u8], start: usize) -> Option<usize> {
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    // Serve one canned response and keep the socket open afterwards, so a reader
+    // that waits for more body bytes (or EOF) would hang.
+    async fn serve_and_hold(response: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let port = listener.local_addr().expect("address").port();
+        tokio::spawn(async move {
+            if let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf).await;
+                let _ = stream.write_all(response.as_bytes()).await;
+                tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+            }
+        });
+        format!("http://127.0.0.1:{}/", port)
+    }
+
+    async fn get_promptly(url: &str) -> http::Response {
+        let uri = Uri::parse(url).expect("uri");
+        let request = http::Request::new()
+            .method(http::Method::GET)
+            .uri(uri.request_target())
+            .header("Host", uri.host())
+            .build()
+            .expect("request");
+        let mut connection = TcpConnection::connect(&uri).await.expect("connect");
+        tokio::time::timeout(std::time::Duration::from_secs(2), connection.send_request(&request))
+            .await
+            .expect("bodyless response should not block")
+            .expect("response")
+    }
+
+    #[tokio::test]
+    async fn test_no_content_returns_promptly() {
+        let url = serve_and_hold("HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n").await;
+        let response = get_promptly(&url).await;
+        assert_eq!(response.status.code, 204);
+        assert!(response.body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_not_modified_ignores_content_length() {
+        let url = serve_and_hold("HTTP/1.1 304 Not Modified\r\nContent-Length: 512\r\nETag: \"v1\"\r\n\r\n").await;
+        let response = get_promptly(&url).await;
+        assert_eq!(response.status.code, 304);
+        assert!(response.body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_interim_responses_are_skipped() {
+        let url = serve_and_hold(
+            "HTTP/1.1 100 Continue\r\n\r\n\
+             HTTP/1.1 103 Early Hints\r\nLink: </style.css>; rel=preload\r\n\r\n\
+             HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello",
+        )
+        .await;
+        let response = get_promptly(&url).await;
+        assert_eq!(response.status.code, 200);
+        assert_eq!(response.body, b"hello");
+        assert!(response.headers.get("link").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_zero_content_length_returns_promptly() {
+        let url = serve_and_hold("HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").await;
+        let response = get_promptly(&url).await;
+        assert_eq!(response.status.code, 200);
+        assert!(response.body.is_empty());
+    }
+}
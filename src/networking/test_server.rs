@@ -0,0 +1,157 @@
+// Minimal HTTP/1.1 server on localhost for exercising the fetch pipeline in tests.
+// Every response closes the connection, which keeps request framing trivial.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+#[derive(Clone)]
+pub struct TestResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    pub delay: Duration,
+    // Answer with the request target and body instead of `body`
+    pub echo: bool,
+}
+
+impl TestResponse {
+    pub fn ok(content_type: &str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status: 200,
+            headers: vec![("Content-Type".to_string(), content_type.to_string())],
+            body: body.into(),
+            delay: Duration::ZERO,
+            echo: false,
+        }
+    }
+
+    pub fn html(body: &str) -> Self {
+        Self::ok("text/html", body)
+    }
+
+    pub fn status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// An HTML page showing the request target in `<p id="target">` and the
+    /// request body in `<p id="body">`. Matches the route with any query string.
+    pub fn echo() -> Self {
+        Self { echo: true, ..Self::html("") }
+    }
+
+    /// Wait this long after reading the request before responding.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+pub struct TestServer {
+    port: u16,
+    requests: Arc<Mutex<Vec<String>>>,
+    // The most requests seen being answered at once
+    max_in_flight: Arc<AtomicUsize>,
+}
+
+impl TestServer {
+    pub async fn start(routes: Vec<(&str, TestResponse)>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind test server");
+        let port = listener.local_addr().expect("test server address").port();
+        let routes: Arc<HashMap<String, TestResponse>> =
+            Arc::new(routes.into_iter().map(|(p, r)| (p.to_string(), r)).collect());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        // Requests being answered right now
+        let current = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+
+        let log = Arc::clone(&requests);
+        let max = Arc::clone(&max_in_flight);
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let routes = Arc::clone(&routes);
+                let log = Arc::clone(&log);
+                let (current, max) = (Arc::clone(&current), Arc::clone(&max));
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 1024];
+                    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match stream.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                        }
+                    }
+                    // Read the body too, when the request announces one
+                    let head_len = buf.windows(4).position(|w| w == b"\r\n\r\n").map_or(0, |i| i + 4);
+                    let content_length = String::from_utf8_lossy(&buf[..head_len])
+                        .lines()
+                        .find_map(|line| {
+                            let (name, value) = line.split_once(':')?;
+                            name.eq_ignore_ascii_case("content-length").then(|| value.trim().parse::<usize>().ok())?
+                        })
+                        .unwrap_or(0);
+                    while buf.len() < head_len + content_length {
+                        match stream.read(&mut chunk).await {
+                            Ok(0) | Err(_) => break,
+                            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                        }
+                    }
+                    let request = String::from_utf8_lossy(&buf).to_string();
+                    let target = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+                    let path = target.split('?').next().unwrap_or("/").to_string();
+                    log.lock().expect("request log").push(request.clone());
+
+                    let mut response = routes
+                        .get(&target)
+                        .or_else(|| routes.get(&path).filter(|r| r.echo))
+                        .cloned()
+                        .unwrap_or_else(|| TestResponse::html("not found").status(404));
+                    if response.echo {
+                        let body = request.get(head_len..).unwrap_or_default();
+                        response.body = format!("<p id=\"target\">{}</p><p id=\"body\">{}</p>", target, body).into_bytes();
+                    }
+                    let now = current.fetch_add(1, Ordering::SeqCst) + 1;
+                    max.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(response.delay).await;
+                    current.fetch_sub(1, Ordering::SeqCst);
+
+                    let mut out = format!("HTTP/1.1 {} Test\r\n", response.status);
+                    for (name, value) in &response.headers {
+                        out.push_str(&format!("{}: {}\r\n", name, value));
+                    }
+                    out.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", response.body.len()));
+                    let mut bytes = out.into_bytes();
+                    bytes.extend_from_slice(&response.body);
+                    let _ = stream.write_all(&bytes).await;
+                    let _ = stream.shutdown().await;
+                });
+            }
+        });
+
+        Self { port, requests, max_in_flight }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://127.0.0.1:{}{}", self.port, path)
+    }
+
+    /// The largest number of requests that were being answered at the same time.
+    pub fn max_in_flight(&self) -> usize {
+        self.max_in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Raw requests (head and body) received so far, in arrival order.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().expect("request log").clone()
+    }
+}
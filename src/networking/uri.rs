@@ -12,11 +12,21 @@ pub struct Uri {
 
 impl Uri {
     pub fn parse(uri: &str) -> Result<Self, NetworkError> {
+        let parsed = Self::parse_any_scheme(uri)?;
+        if parsed.scheme != "http" && parsed.scheme != "https" {
+            return Err(NetworkError::InvalidUri);
+        }
+        Ok(parsed)
+    }
+
+    /// Parse `scheme://authority/path` for any scheme, such as the custom ones
+    /// served by registered scheme handlers. `parse` only accepts http(s).
+    pub fn parse_any_scheme(uri: &str) -> Result<Self, NetworkError> {
         let (scheme_part, remainder) = uri.split_once("://").ok_or(NetworkError::InvalidUri)?;
-        let scheme = scheme_part.to_string();
-        if scheme != "http" && scheme != "https" {
+        if scheme_part.is_empty() {
             return Err(NetworkError::InvalidUri);
         }
+        let scheme = scheme_part.to_ascii_lowercase();
 
         let (authority, path_and_more) = remainder.split_once('/').unwrap_or((remainder, ""));
 
@@ -48,6 +58,39 @@ impl Uri {
         })
     }
 
+    /// Turn what a user typed into an address bar into a URL. Input that already
+    /// has a scheme is left alone; loopback hosts (`localhost`, `127.0.0.1`) get
+    /// `http://`, since local servers rarely speak TLS, and anything else `https://`.
+    pub fn normalize_user_input(input: &str) -> String {
+        Self::normalize_user_input_with_scheme(input, "https")
+    }
+
+    /// Like `normalize_user_input`, with `default_scheme` for non-loopback hosts.
+    pub fn normalize_user_input_with_scheme(input: &str, default_scheme: &str) -> String {
+        let input = input.trim();
+        let has_scheme = input.contains("://") || input.starts_with("about:") || input.starts_with("data:");
+        if input.is_empty() || has_scheme {
+            return input.to_string();
+        }
+        let scheme = if is_loopback_host(host_of_input(input)) { "http" } else { default_scheme };
+        format!("{}://{}", scheme, input)
+    }
+
+    /// This URL with `www.` put in front of the host, to try when the bare name
+    /// doesn't resolve. None when the host already starts with `www.`, is an IP
+    /// address or `localhost`, or has no dot.
+    pub fn with_www_host(&self) -> Option<String> {
+        let host = self.host.to_ascii_lowercase();
+        let is_ip = host.parse::<std::net::IpAddr>().is_ok();
+        if host.starts_with("www.") || is_ip || is_loopback_host(&host) || !host.contains('.') {
+            return None;
+        }
+        let mut www = self.clone();
+        www.host = format!("www.{}", self.host);
+        let query = www.query.as_ref().map(|q| format!("?{}", q)).unwrap_or_default();
+        Some(format!("{}{}{}", www.origin(), www.path, query))
+    }
+
     pub fn host(&self) -> &str {
         &self.host
     }
@@ -148,6 +191,17 @@ impl fmt::Display for Uri {
     }
 }
 
+// Host part of address-bar input without a scheme: `localhost:8080/x` -> `localhost`
+fn host_of_input(input: &str) -> &str {
+    let authority = input.split(['/', '?', '#']).next().unwrap_or(input);
+    authority.split(':').next().unwrap_or(authority)
+}
+
+fn is_loopback_host(host: &str) -> bool {
+    let host = host.to_ascii_lowercase();
+    host == "localhost" || host.ends_with(".localhost") || host.starts_with("127.")
+}
+
 fn base_dir_of_path(path: &str) -> &str {
     // Always returns a string ending with '/', so we can safely append a relative reference.
     //
@@ -185,3 +239,29 @@ fn normalize_path(path: &str) -> String {
         out
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_user_input() {
+        assert_eq!(Uri::normalize_user_input("example.com"), "https://example.com");
+        assert_eq!(Uri::normalize_user_input("  example.com/path?q=1 "), "https://example.com/path?q=1");
+        assert_eq!(Uri::normalize_user_input("http://x"), "http://x");
+        assert_eq!(Uri::normalize_user_input("localhost:8080/app"), "http://localhost:8080/app");
+        assert_eq!(Uri::normalize_user_input("127.0.0.1"), "http://127.0.0.1");
+        assert_eq!(Uri::normalize_user_input("about:blank"), "about:blank");
+        assert_eq!(Uri::normalize_user_input_with_scheme("example.com", "http"), "http://example.com");
+    }
+
+    #[test]
+    fn test_with_www_host() {
+        let uri = Uri::parse("https://example.com:8443/a?b=c").expect("valid uri");
+        assert_eq!(uri.with_www_host().as_deref(), Some("https://www.example.com:8443/a?b=c"));
+        for skipped in ["https://www.example.com/", "http://localhost/", "http://10.0.0.1/", "http://intranet/"] {
+            let uri = Uri::parse(skipped).expect("valid uri");
+            assert_eq!(uri.with_www_host(), None, "{}", skipped);
+        }
+    }
+}
@@ -59,6 +59,7 @@ impl ContentView {
                 DisplayItem::Rectangle { .. } => (text, rect + 1, img, btn),
                 DisplayItem::Image { .. } => (text, rect, img + 1, btn),
                 DisplayItem::Button { .. } => (text, rect, img, btn + 1),
+                DisplayItem::Link { .. } => (text, rect, img, btn),
             }
         });
         log::info!(target: "content_view", "Display list breakdown: {} text, {} rects, {} images, {} buttons", 
@@ -213,6 +214,7 @@ impl gpui::Render for ContentView {
                         DisplayItem::Rectangle { y, height, .. } => *y + *height,
                         DisplayItem::Image { y, height, .. } => *y + *height,
                         DisplayItem::Button { y, height, .. } => *y + *height,
+                        DisplayItem::Link { y, height, .. } => *y + *height,
                     };
                     max.max(item_max)
                 });
@@ -404,6 +406,8 @@ impl gpui::Render for ContentView {
                                     ), rc, bc, tc, new_ic)
                                 }
                             }
+                            // Hit regions only; the link text is drawn by its Text items
+                            DisplayItem::Link { .. } => (acc, rc, bc, tc, ic),
                         }
                     }
                 );
@@ -64,11 +64,7 @@ impl BrowserWindow {
     pub fn load_url(&mut self, url: &str, cx: &mut Context<Self>) {
         // Get viewport size from window if available
         // We'll need to get this from the window in render, but for now use default
-        let url = if !url.starts_with("http://") && !url.starts_with("https://") {
-            format!("https://{}", url)
-        } else {
-            url.to_string()
-        };
+        let url = crate::networking::Uri::normalize_user_input(url);
         
         self.address_bar.update(cx, |address_bar, _cx| {
             address_bar.set_url(url.clone());
@@ -106,6 +102,7 @@ impl BrowserWindow {
                     headless: false,
                     debug: true,
                     enable_javascript: true,
+                    ..Default::default()
                 }) {
                     Ok(b) => b,
                     Err(e) => {
@@ -116,12 +113,12 @@ impl BrowserWindow {
                 };
                 
                 // Set up console log capture
-                browser.js_engine.set_console_log_sender(console_tx);
+                browser.set_console_log_sender(console_tx);
                 
                 // Set viewport size before loading (using default for now)
                 browser.set_viewport_size(vw, vh);
                 
-                match browser.load_url(&url_clone).await {
+                match browser.load_user_input(&url_clone).await {
                     Ok((display_list, content)) => {
                         log::info!(target: "browser", "Successfully loaded URL: {}", url_clone);
                         log::info!(target: "browser", "Display list has {} items, content length: {}", 
@@ -133,42 +130,8 @@ impl BrowserWindow {
                             console_logs.push((level, message));
                         }
                         
-                        // Fetch images from display list
-                        let mut images = Vec::new();
-                        // Create a new NetworkManager for fetching images (since it's not Clone)
-                        if let Ok(image_network_manager) = crate::networking::NetworkManager::new() {
-                            for item in display_list.items() {
-                                if let crate::rendering::DisplayItem::Image { url, .. } = item {
-                                    if !url.is_empty() {
-                                        // Resolve relative URL
-                                        let image_url = if url.starts_with("http://") || url.starts_with("https://") {
-                                            url.clone()
-                                        } else {
-                                            // Resolve relative to base URL
-                                            let base = url_clone.trim_end_matches('/');
-                                            if url.starts_with('/') {
-                                                format!("{}{}", base, url)
-                                            } else {
-                                                format!("{}/{}", base, url)
-                                            }
-                                        };
-                                        
-                                        // Fetch image
-                                        match image_network_manager.fetch(&image_url).await {
-                                            Ok(response) => {
-                                                log::info!(target: "browser", "Fetched image: {} ({} bytes)", image_url, response.body.len());
-                                                images.push((image_url, response.body));
-                                            }
-                                            Err(e) => {
-                                                log::warn!(target: "browser", "Failed to fetch image {}: {}", image_url, e);
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        } else {
-                            log::warn!(target: "browser", "Failed to create NetworkManager for image fetching");
-                        }
+                        // Fetch images from display list (skipped when image loading is disabled)
+                        let images = browser.fetch_images(&display_list, &url_clone).await;
                         
                         match tx.send(LoadResult::Success { 
                             url: url_clone.clone(), 
@@ -1,14 +1,32 @@
-use super::{DisplayList, DisplayItem, Color};
+use super::{Bounds, DisplayList, DisplayItem, Color};
 use crate::css::style::StyledNode;
 use crate::css::{Value, Unit};
 use crate::html::entities;
 use std::collections::HashMap;
 
+// Default padding inside button boxes, around the label
+const BUTTON_PADDING_X: f32 = 12.0;
+const BUTTON_PADDING_Y: f32 = 6.0;
+// Initial value of font-size
+const DEFAULT_FONT_SIZE: f32 = 16.0;
+
 pub struct LayoutEngine {
     viewport_width: u32,
     viewport_height: u32,
     computed_styles: HashMap<String, ComputedStyle>,
     font_manager: FontManager,
+    // Display list of the last layout, keyed by layout_key
+    cached_layout: Option<(u64, DisplayList)>,
+    layouts_computed: usize,
+    // `visibility` of the element being laid out, inherited by its children
+    visibility: Visibility,
+    // With scripting on, <noscript> content is not rendered
+    scripting_enabled: bool,
+    // Computed font-size of the root element, what `rem` is relative to
+    root_font_size: f32,
+    // Computed font-size of the element being laid out, inherited by its
+    // children and what their `em` font-sizes are relative to
+    font_size: f32,
 }
 
 #[derive(Clone, Debug)]
@@ -111,15 +129,16 @@ pub struct ComputedStyle {
     pub color: Color,
     pub text_align: TextAlign,
     pub vertical_align: VerticalAlign,
+    pub white_space: WhiteSpace,
+    pub overflow: Overflow,
+    pub visibility: Visibility,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum FontWeight {
-    Normal,
-    Bold,
     Bolder,
     Lighter,
-    Number(u16), // 100, 200, ..., 900
+    Number(u16), // 1..=1000; normal is 400 and bold 700
 }
 
 #[derive(Clone, Debug)]
@@ -137,6 +156,50 @@ pub enum TextAlign {
     Justify,
 }
 
+/// How text handles spaces, newlines and line wrapping.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WhiteSpace {
+    /// Collapse whitespace and wrap at the container edge.
+    Normal,
+    /// Collapse whitespace, never wrap.
+    NoWrap,
+    /// Keep spaces and newlines, never wrap.
+    Pre,
+    /// Keep spaces and newlines, and wrap.
+    PreWrap,
+    /// Collapse spaces but keep newlines, and wrap.
+    PreLine,
+}
+
+impl WhiteSpace {
+    pub fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword.to_ascii_lowercase().as_str() {
+            "normal" => Some(WhiteSpace::Normal),
+            "nowrap" => Some(WhiteSpace::NoWrap),
+            "pre" => Some(WhiteSpace::Pre),
+            "pre-wrap" | "break-spaces" => Some(WhiteSpace::PreWrap),
+            "pre-line" => Some(WhiteSpace::PreLine),
+            _ => None,
+        }
+    }
+}
+
+/// Whether content past the box edges is painted.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Overflow {
+    Visible,
+    /// Clipped to the padding box (`hidden`, `clip`, and `scroll`/`auto`, which
+    /// have no scrollbars here).
+    Hidden,
+}
+
+/// Whether a box paints. Hidden boxes still take up their space in layout.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Visibility {
+    Visible,
+    Hidden,
+}
+
 #[derive(Clone, Debug)]
 pub enum VerticalAlign {
     Baseline,
@@ -160,6 +223,10 @@ pub struct Edges {
 pub enum Display {
     Block,
     Inline,
+    Table,
+    TableRowGroup,
+    TableRow,
+    TableCell,
     None,
 }
 
@@ -183,6 +250,12 @@ impl LayoutEngine {
             viewport_height,
             computed_styles: HashMap::new(),
             font_manager: FontManager::new(),
+            cached_layout: None,
+            layouts_computed: 0,
+            visibility: Visibility::Visible,
+            scripting_enabled: false,
+            root_font_size: DEFAULT_FONT_SIZE,
+            font_size: DEFAULT_FONT_SIZE,
         }
     }
     
@@ -191,6 +264,13 @@ impl LayoutEngine {
         self.viewport_height = height;
     }
 
+    pub fn set_scripting_enabled(&mut self, enabled: bool) {
+        if self.scripting_enabled != enabled {
+            self.scripting_enabled = enabled;
+            self.cached_layout = None;
+        }
+    }
+
     pub fn viewport_width(&self) -> u32 {
         self.viewport_width
     }
@@ -199,7 +279,69 @@ impl LayoutEngine {
         self.viewport_height
     }
 
+    /// Lay out `styled_node` at the current viewport size. Laying out the same
+    /// content at the same size again returns the previous result; any change
+    /// to the tree, its styles or the viewport computes a fresh one.
     pub fn compute_layout(&mut self, styled_node: &StyledNode) -> DisplayList {
+        let key = self.layout_key(styled_node);
+        if let Some((cached_key, display_list)) = &self.cached_layout
+            && *cached_key == key
+        {
+            log::debug!(target: "layout", "Reusing cached layout ({} items)", display_list.items().len());
+            return display_list.clone();
+        }
+        let display_list = self.compute_layout_uncached(styled_node);
+        self.layouts_computed += 1;
+        self.cached_layout = Some((key, display_list.clone()));
+        display_list
+    }
+
+    /// Number of layouts actually computed, i.e. not served from the cache.
+    pub fn layouts_computed(&self) -> usize {
+        self.layouts_computed
+    }
+
+    pub fn invalidate_layout_cache(&mut self) {
+        self.cached_layout = None;
+    }
+
+    // Hash of everything layout depends on: every styled node's own content and
+    // declarations, and the viewport. Node ids are left out so that an
+    // identical page parsed again still matches.
+    fn layout_key(&self, styled_node: &StyledNode) -> u64 {
+        use std::hash::{Hash, Hasher};
+        fn hash_styled(styled: &StyledNode, hasher: &mut impl Hasher) {
+            match styled.node.node_type() {
+                crate::dom::NodeType::Element { tag_name, attributes, .. } => {
+                    tag_name.hash(hasher);
+                    for attribute in attributes {
+                        attribute.name.hash(hasher);
+                        attribute.value.hash(hasher);
+                    }
+                }
+                crate::dom::NodeType::Text(text) => text.hash(hasher),
+                crate::dom::NodeType::Comment(_) => {}
+            }
+            // Declarations hold floats, so hash their printed form
+            format!("{:?}", styled.styles).hash(hasher);
+            for pseudo in [&styled.before, &styled.after] {
+                pseudo.is_some().hash(hasher);
+                if let Some(pseudo) = pseudo {
+                    hash_styled(pseudo, hasher);
+                }
+            }
+            styled.children.len().hash(hasher);
+            for child in &styled.children {
+                hash_styled(child, hasher);
+            }
+        }
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        (self.viewport_width, self.viewport_height).hash(&mut hasher);
+        hash_styled(styled_node, &mut hasher);
+        hasher.finish()
+    }
+
+    fn compute_layout_uncached(&mut self, styled_node: &StyledNode) -> DisplayList {
         log::info!(target: "layout", "Starting layout computation with viewport: {}x{}", 
             self.viewport_width, self.viewport_height);
         
@@ -213,9 +355,11 @@ impl LayoutEngine {
             }
         }
         
+        self.root_font_size = self.compute_root_font_size(styled_node);
+
         let mut display_list = DisplayList::new();
         // Start layout at top of viewport (y=0)
-        let height = self.layout_node(styled_node, 0.0, 0.0, &mut display_list);
+        let height = self.layout_node(styled_node, 0.0, 0.0, WhiteSpace::Normal, &mut display_list);
         log::info!(target: "layout", "Layout complete, created {} display items, root height: {}", 
             display_list.items().len(), height);
         
@@ -234,6 +378,9 @@ impl LayoutEngine {
                 super::DisplayItem::Button { x, y, width, height, .. } => {
                     log::info!(target: "layout", "Item #{}: Button at ({}, {}), size {}x{}", idx, x, y, width, height);
                 }
+                super::DisplayItem::Link { href, x, y, .. } => {
+                    log::info!(target: "layout", "Item #{}: Link to {} at ({}, {})", idx, href, x, y);
+                }
             }
         }
         
@@ -244,6 +391,7 @@ impl LayoutEngine {
                 super::DisplayItem::Rectangle { .. } => (t, r + 1, i, b),
                 super::DisplayItem::Image { .. } => (t, r, i + 1, b),
                 super::DisplayItem::Button { .. } => (t, r, i, b + 1),
+                super::DisplayItem::Link { .. } => (t, r, i, b),
             }
         });
         log::info!(target: "layout", "Items breakdown: {} text, {} rects, {} images, {} buttons", text, rect, img, btn);
@@ -263,13 +411,31 @@ impl LayoutEngine {
                 super::DisplayItem::Button { x, width, .. } => {
                     log::info!(target: "layout", "Item #{}: Button at x={}, width={}", idx, x, width);
                 }
+                super::DisplayItem::Link { x, width, .. } => {
+                    log::info!(target: "layout", "Item #{}: Link at x={}, width={}", idx, x, width);
+                }
             }
         }
         
         display_list
     }
 
-    fn layout_node(&mut self, node: &StyledNode, x: f32, y: f32, display_list: &mut DisplayList) -> f32 {
+    // Font-size of the <html> element, whether `root` is it or the #document
+    // above it. `rem` inside the root element itself means the initial value.
+    fn compute_root_font_size(&mut self, root: &StyledNode) -> f32 {
+        self.root_font_size = DEFAULT_FONT_SIZE;
+        self.font_size = DEFAULT_FONT_SIZE;
+        if root.node.is_element("html") {
+            return self.compute_style(root, WhiteSpace::Normal).font_size;
+        }
+        match root.children.iter().find(|child| child.node.is_element("html")) {
+            Some(html) => self.compute_style(html, WhiteSpace::Normal).font_size,
+            None => self.compute_style(root, WhiteSpace::Normal).font_size,
+        }
+    }
+
+    // `parent_white_space` is the computed white-space of the node's parent
+    fn layout_node(&mut self, node: &StyledNode, x: f32, y: f32, parent_white_space: WhiteSpace, display_list: &mut DisplayList) -> f32 {
         // Log what node we're processing
         match node.node.node_type() {
             crate::dom::NodeType::Element { tag_name, .. } => {
@@ -282,6 +448,13 @@ impl LayoutEngine {
             _ => {}
         }
         
+        // display: none takes the element and its whole subtree out of layout,
+        // replaced elements included
+        let computed = self.compute_style(node, parent_white_space);
+        if matches!(computed.display, Display::None) {
+            return 0.0;
+        }
+        
         // Handle special elements first (img, button, input) regardless of display type
         // This must happen BEFORE checking for script/style tags
         if let crate::dom::NodeType::Element { tag_name, .. } = node.node.node_type() {
@@ -299,7 +472,6 @@ impl LayoutEngine {
                         .unwrap_or(200.0); // Default to 200px instead of 100px
                     
                     // Real browsers: Apply margins and padding for positioning
-                    let computed = self.compute_style(node);
                     let left_padding = if x < 20.0 { 20.0 } else { computed.padding.left };
                     let img_x = x + left_padding + computed.margin.left;
                     let img_y = y + computed.margin.top;
@@ -307,57 +479,51 @@ impl LayoutEngine {
                     log::info!(target: "layout", "Found img element: src='{}', alt='{}', size={}x{} at ({}, {}) -> img_x={}, img_y={}", 
                         img_url, alt_text, img_width, img_height, x, y, img_x, img_y);
                     
-                    display_list.add_item(DisplayItem::Image {
-                        url: img_url,
-                        x: img_x,
-                        y: img_y,
-                        width: img_width,
-                        height: img_height,
-                        alt: alt_text,
-                    });
+                    if computed.visibility == Visibility::Visible {
+                        display_list.add_item(DisplayItem::Image {
+                            url: img_url,
+                            x: img_x,
+                            y: img_y,
+                            width: img_width,
+                            height: img_height,
+                            alt: alt_text,
+                        });
+                    }
                     // Return total height including margins
                     return img_height + computed.margin.top + computed.margin.bottom;
                 }
                 "button" | "input" => {
-                    let button_text = if tag_name == "button" {
-                        // Extract text from button children
-                        let mut text = String::new();
-                        for child in node.node.children() {
-                            if let crate::dom::NodeType::Text(t) = child.node_type() {
-                                text.push_str(t.trim());
-                            }
-                        }
-                        if text.is_empty() {
-                            node.node.get_attribute("value").unwrap_or("Button").to_string()
-                        } else {
-                            text
-                        }
-                    } else {
-                        // input element
-                        node.node.get_attribute("value")
-                            .or_else(|| node.node.get_attribute("placeholder"))
-                            .unwrap_or("Input")
-                            .to_string()
-                    };
-                    
                     // Real browsers: Apply margins and padding for positioning
-                    let computed = self.compute_style(node);
                     let left_padding = if x < 20.0 { 20.0 } else { computed.padding.left };
                     let button_x = x + left_padding + computed.margin.left;
                     let button_y = y + computed.margin.top;
                     
-                    log::debug!(target: "layout", "Found {} element: text={} at ({}, {}) -> button_x={}, button_y={}", tag_name, button_text, x, y, button_x, button_y);
+                    let (button_text, button_width, button_height) = match button_label(&node.node) {
+                        Some(label) => {
+                            let (width, height) = self.button_size(&label, &computed);
+                            (label, width, height)
+                        }
+                        // Text-like inputs have no box of their own yet; draw a fixed-size field
+                        None => {
+                            let text = node.node.get_attribute("value")
+                                .or_else(|| node.node.get_attribute("placeholder"))
+                                .unwrap_or("Input")
+                                .to_string();
+                            (text, 120.0, 32.0)
+                        }
+                    };
                     
-                    let button_width = 120.0;
-                    let button_height = 32.0;
+                    log::debug!(target: "layout", "Found {} element: text={} at ({}, {}) -> button_x={}, button_y={}", tag_name, button_text, x, y, button_x, button_y);
                     
-                    display_list.add_item(DisplayItem::Button {
-                        text: button_text,
-                        x: button_x,
-                        y: button_y,
-                        width: button_width,
-                        height: button_height,
-                    });
+                    if computed.visibility == Visibility::Visible {
+                        display_list.add_item(DisplayItem::Button {
+                            text: button_text,
+                            x: button_x,
+                            y: button_y,
+                            width: button_width,
+                            height: button_height,
+                        });
+                    }
                     // Return total height including margins
                     return button_height + computed.margin.top + computed.margin.bottom;
                 }
@@ -368,7 +534,7 @@ impl LayoutEngine {
         // Skip script and style content - they should not be rendered
         if let crate::dom::NodeType::Element { tag_name, .. } = node.node.node_type() {
             let tag_lower = tag_name.to_lowercase();
-            if matches!(tag_lower.as_str(), "script" | "style" | "noscript") {
+            if matches!(tag_lower.as_str(), "script" | "style") {
                 log::debug!(target: "layout", "Skipping {} element (not renderable)", tag_lower);
                 // Don't process children of script/style tags - they should not be rendered
                 return 0.0;
@@ -382,9 +548,11 @@ impl LayoutEngine {
         }
         
         // Basic layout algorithm - expand as needed
-        let computed = self.compute_style(node);
+        let parent_font_size = std::mem::replace(&mut self.font_size, computed.font_size);
+        let parent_visibility = self.inherit_visibility(computed.visibility);
+        let first_item = display_list.items().len();
         
-        match computed.display {
+        let height = match computed.display {
             Display::Block => {
                 // Handle block layout
                 self.layout_block(node, x, y, &computed, display_list)
@@ -393,25 +561,33 @@ impl LayoutEngine {
                 // Handle inline layout
                 self.layout_inline(node, x, y, &computed, display_list)
             },
+            Display::Table => self.layout_table(node, x, y, &computed, display_list),
+            // Table parts outside a table are laid out as plain blocks
+            Display::TableRowGroup | Display::TableRow | Display::TableCell => {
+                self.layout_block(node, x, y, &computed, display_list)
+            }
             Display::None => 0.0,
+        };
+        if computed.overflow == Overflow::Hidden {
+            let clip = self.clip_bounds(x, y, height, &computed);
+            display_list.clip_from(first_item, &clip, computed.font_size * 0.6);
         }
+        self.font_size = parent_font_size;
+        self.visibility = parent_visibility;
+        height
+    }
+
+    /// Make `visibility` what the children laid out next inherit, returning the
+    /// value it replaces so the caller can restore it.
+    pub fn inherit_visibility(&mut self, visibility: Visibility) -> Visibility {
+        std::mem::replace(&mut self.visibility, visibility)
     }
 
-    pub fn compute_style(&self, node: &StyledNode) -> ComputedStyle {
+    /// Computed style of `node`. Its white-space is inherited from
+    /// `parent_white_space` unless the node sets its own.
+    pub fn compute_style(&self, node: &StyledNode, parent_white_space: WhiteSpace) -> ComputedStyle {
         // Start with defaults based on element type
-        let mut display = if let crate::dom::NodeType::Element { tag_name, .. } = node.node.node_type() {
-            let tag_lower = tag_name.to_lowercase();
-            match tag_lower.as_str() {
-                "div" | "section" | "article" | "header" | "footer" | "main" | "body" | "html" | 
-                "p" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "ul" | "ol" | "li" | 
-                "blockquote" | "nav" | "aside" | "form" | "table" | "tr" | "td" | "th" => Display::Block,
-                "span" | "a" | "strong" | "em" | "b" | "i" | "u" | "code" | "small" | "sub" | "sup" => Display::Inline,
-                "img" | "button" | "input" => Display::Inline,
-                _ => Display::Block,
-            }
-        } else {
-            Display::Block
-        };
+        let mut display = self.default_display(node);
         
         let mut position = Position::Static;
         let mut width = Dimension::Auto;
@@ -419,24 +595,23 @@ impl LayoutEngine {
         let mut margin = Edges { top: 0.0, right: 0.0, bottom: 0.0, left: 0.0 };
         let mut padding = Edges { top: 0.0, right: 0.0, bottom: 0.0, left: 0.0 };
         let mut font_family = vec!["sans-serif".to_string()];
-        let mut font_size = 16.0;
-        let mut font_weight = FontWeight::Normal;
+        // Resolved up front, since `em` in every other property depends on it
+        let font_size = self.resolve_font_size(node);
+        let mut font_weight = FontWeight::Number(400);
         let mut line_height = LineHeight::Normal;
         let mut color = Color { r: 0, g: 0, b: 0, a: 255 };
         let mut text_align = TextAlign::Left;
         let mut vertical_align = VerticalAlign::Baseline;
+        let mut white_space = default_white_space(node, parent_white_space);
+        let mut overflow = Overflow::Visible;
+        let mut visibility = self.visibility;
         
         // Apply CSS declarations from stylesheet
         for decl in &node.styles {
             match decl.property.to_lowercase().as_str() {
                 "display" => {
-                    if let Value::Keyword(kw) = &decl.value {
-                        match kw.to_lowercase().as_str() {
-                            "none" => display = Display::None,
-                            "block" => display = Display::Block,
-                            "inline" => display = Display::Inline,
-                            _ => {}
-                        }
+                    if let Some(value) = display_from(&decl.value) {
+                        display = value;
                     }
                 }
                 "color" => {
@@ -448,17 +623,6 @@ impl LayoutEngine {
                         }
                     }
                 }
-                "font-size" => {
-                    if let Value::Length(val, unit) = &decl.value {
-                        match unit {
-                            Unit::Px => font_size = *val,
-                            Unit::Em => font_size = *val * 16.0,
-                            Unit::Rem => font_size = *val * 16.0,
-                            Unit::Percent => font_size = *val * 16.0 / 100.0,
-                            _ => font_size = *val,
-                        }
-                    }
-                }
                 "font-family" => {
                     if let Value::Multiple(values) = &decl.value {
                         font_family = values.iter().filter_map(|v| {
@@ -475,17 +639,8 @@ impl LayoutEngine {
                     }
                 }
                 "font-weight" => {
-                    if let Value::Keyword(kw) = &decl.value {
-                        match kw.to_lowercase().as_str() {
-                            "bold" | "bolder" => font_weight = FontWeight::Bold,
-                            "normal" => font_weight = FontWeight::Normal,
-                            "lighter" => font_weight = FontWeight::Lighter,
-                            _ => {
-                                if let Ok(num) = kw.parse::<u16>() {
-                                    font_weight = FontWeight::Number(num);
-                                }
-                            }
-                        }
+                    if let Some(value) = font_weight_from(&decl.value) {
+                        font_weight = value;
                     }
                 }
                 "line-height" => {
@@ -497,6 +652,18 @@ impl LayoutEngine {
                         }
                     }
                 }
+                "width" | "height" => {
+                    let dimension = match &decl.value {
+                        Value::Length(val, Unit::Percent) => Dimension::Percentage(*val),
+                        Value::Length(val, unit) => Dimension::Length(self.resolve_length(*val, unit, font_size)),
+                        _ => Dimension::Auto,
+                    };
+                    if decl.property.eq_ignore_ascii_case("width") {
+                        width = dimension;
+                    } else {
+                        height = dimension;
+                    }
+                }
                 "text-align" => {
                     if let Value::Keyword(kw) = &decl.value {
                         match kw.to_lowercase().as_str() {
@@ -508,12 +675,30 @@ impl LayoutEngine {
                         }
                     }
                 }
+                "white-space" => {
+                    if let Value::Keyword(kw) = &decl.value
+                        && let Some(value) = WhiteSpace::from_keyword(kw)
+                    {
+                        white_space = value;
+                    }
+                }
+                "visibility" => {
+                    if let Some(value) = visibility_from(&decl.value) {
+                        visibility = value;
+                    }
+                }
+                "overflow" => {
+                    if let Value::Keyword(kw) = &decl.value {
+                        match kw.to_lowercase().as_str() {
+                            "visible" => overflow = Overflow::Visible,
+                            "hidden" | "clip" | "scroll" | "auto" => overflow = Overflow::Hidden,
+                            _ => {}
+                        }
+                    }
+                }
                 "margin" | "margin-top" | "margin-right" | "margin-bottom" | "margin-left" => {
                     if let Value::Length(val, unit) = &decl.value {
-                        let px_val = match unit {
-                            Unit::Px => *val,
-                            _ => *val,
-                        };
+                        let px_val = self.resolve_length(*val, unit, font_size);
                         match decl.property.to_lowercase().as_str() {
                             "margin-top" => margin.top = px_val,
                             "margin-right" => margin.right = px_val,
@@ -531,10 +716,7 @@ impl LayoutEngine {
                 }
                 "padding" | "padding-top" | "padding-right" | "padding-bottom" | "padding-left" => {
                     if let Value::Length(val, unit) = &decl.value {
-                        let px_val = match unit {
-                            Unit::Px => *val,
-                            _ => *val,
-                        };
+                        let px_val = self.resolve_length(*val, unit, font_size);
                         match decl.property.to_lowercase().as_str() {
                             "padding-top" => padding.top = px_val,
                             "padding-right" => padding.right = px_val,
@@ -568,6 +750,75 @@ impl LayoutEngine {
             color,
             text_align,
             vertical_align,
+            white_space,
+            overflow,
+            visibility,
+        }
+    }
+
+    // Display of `node` before any stylesheet applies, from its element type
+    fn default_display(&self, node: &StyledNode) -> Display {
+        let crate::dom::NodeType::Element { tag_name, .. } = node.node.node_type() else {
+            return Display::Block;
+        };
+        match tag_name.to_lowercase().as_str() {
+            "div" | "section" | "article" | "header" | "footer" | "main" | "body" | "html" | 
+            "p" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "ul" | "ol" | "li" | 
+            "blockquote" | "nav" | "aside" | "form" => Display::Block,
+            "table" => Display::Table,
+            "thead" | "tbody" | "tfoot" => Display::TableRowGroup,
+            "tr" => Display::TableRow,
+            "td" | "th" => Display::TableCell,
+            "span" | "a" | "strong" | "em" | "b" | "i" | "u" | "code" | "small" | "sub" | "sup" => Display::Inline,
+            "img" | "button" | "input" => Display::Inline,
+            "noscript" if self.scripting_enabled => Display::None,
+            // Document metadata is never rendered
+            "head" | "title" | "meta" | "link" | "style" | "script" => Display::None,
+            _ => Display::Block,
+        }
+    }
+
+    // The box an `overflow` element clips its content to, placed the way
+    // `layout_block` places the element; `height` is what layout returned.
+    fn clip_bounds(&self, x: f32, y: f32, height: f32, style: &ComputedStyle) -> Bounds {
+        let box_x = if x < 20.0 { 20.0 } else { x } + style.margin.left;
+        let available = self.viewport_width as f32 - box_x - 20.0 - style.margin.right;
+        let width = match style.width {
+            Dimension::Length(w) => w + style.padding.left + style.padding.right,
+            Dimension::Percentage(p) => available * p / 100.0,
+            Dimension::Auto => available,
+        };
+        let height = match style.height {
+            Dimension::Length(h) => h + style.padding.top + style.padding.bottom,
+            _ => height - style.margin.top - style.margin.bottom,
+        };
+        Bounds { x: box_x, y: y + style.margin.top, width: width.max(0.0), height: height.max(0.0) }
+    }
+
+    // `font-size` of `node`: inherited from the parent unless declared, with
+    // `em` and percentages relative to the parent's size.
+    fn resolve_font_size(&self, node: &StyledNode) -> f32 {
+        let parent = self.font_size;
+        let declared = node.styles.iter().rev().find(|decl| decl.property.eq_ignore_ascii_case("font-size"));
+        match declared.map(|decl| &decl.value) {
+            Some(Value::Length(val, Unit::Em)) => val * parent,
+            Some(Value::Length(val, Unit::Percent)) => val * parent / 100.0,
+            Some(Value::Length(val, unit)) => self.resolve_length(*val, unit, parent),
+            _ => parent,
+        }
+    }
+
+    /// Convert an absolute or viewport-relative length to pixels.
+    /// Percentages depend on the containing block and are resolved by the caller.
+    pub fn resolve_length(&self, val: f32, unit: &Unit, font_size: f32) -> f32 {
+        match unit {
+            Unit::Vw => val * self.viewport_width as f32 / 100.0,
+            Unit::Vh => val * self.viewport_height as f32 / 100.0,
+            Unit::Vmin => val * self.viewport_width.min(self.viewport_height) as f32 / 100.0,
+            Unit::Vmax => val * self.viewport_width.max(self.viewport_height) as f32 / 100.0,
+            Unit::Em => val * font_size,
+            Unit::Rem => val * self.root_font_size,
+            _ => val,
         }
     }
 
@@ -596,11 +847,10 @@ impl LayoutEngine {
                 // This prevents invisible elements from pushing content down
                 // For html/body, we want their children to start at y=0 (or the passed y)
                 let mut max_child_height: f32 = 0.0;
-                for child in node.node.children() {
-                    let styled_child = crate::css::style::StyledNode::new(child.clone());
+                for styled_child in &node.children {
                     // Use the same current_y for all children of skipped elements
                     // For html/body, this ensures content starts at the top
-                    let child_height: f32 = self.layout_node(&styled_child, x, current_y, display_list);
+                    let child_height: f32 = self.layout_node(styled_child, x, current_y, style.white_space, display_list);
                     if child_height > 0.0 {
                         max_child_height = max_child_height.max(child_height);
                         // Don't accumulate Y for skipped elements - their children should start at the same Y
@@ -614,9 +864,11 @@ impl LayoutEngine {
         let mut has_children = false;
         let mut max_child_height: f32 = 0.0;
         
-        for (idx, child) in node.node.children().iter().enumerate() {
-            let styled_child = crate::css::style::StyledNode::new(child.clone());
-            let child_computed = self.compute_style(&styled_child);
+        // Generated ::before/::after content lays out like first and last children
+        let styled_children = node.before.as_deref().into_iter().chain(&node.children).chain(node.after.as_deref());
+        for (idx, styled_child) in styled_children.enumerate() {
+            let child = &styled_child.node;
+            let child_computed = self.compute_style(styled_child, style.white_space);
             
             // Real browsers: Apply top margin before positioning child
             // Margin collapsing: adjacent margins collapse (use max of two margins)
@@ -631,7 +883,7 @@ impl LayoutEngine {
             // Calculate child x position: add left padding and margin
             let child_x = x + left_padding + child_computed.margin.left;
             
-            let child_height: f32 = self.layout_node(&styled_child, child_x, child_y, display_list);
+            let child_height: f32 = self.layout_node(styled_child, child_x, child_y, style.white_space, display_list);
             
             if child_height > 0.0 {
                 has_children = true;
@@ -643,9 +895,9 @@ impl LayoutEngine {
             } else {
                 // Fallback for elements that don't return height
                 match child_computed.display {
-                    Display::Block => {
+                    Display::Block | Display::Table | Display::TableRowGroup | Display::TableRow | Display::TableCell => {
                         has_children = true;
-                        let h: f32 = self.layout_block(&styled_child, child_x, child_y, &child_computed, display_list);
+                        let h: f32 = self.layout_block(styled_child, child_x, child_y, &child_computed, display_list);
                         let child_bottom_margin = child_computed.margin.bottom;
                         let child_total_height = h.max(line_height) + child_bottom_margin;
                         current_y += child_total_height;
@@ -653,7 +905,7 @@ impl LayoutEngine {
                     }
                     Display::Inline => {
                         has_children = true;
-                        let h: f32 = self.layout_inline(&styled_child, child_x, child_y, &child_computed, display_list);
+                        let h: f32 = self.layout_inline(styled_child, child_x, child_y, &child_computed, display_list);
                         let child_bottom_margin = child_computed.margin.bottom;
                         let child_total_height = h.max(line_height) + child_bottom_margin;
                         current_y += child_total_height;
@@ -684,18 +936,32 @@ impl LayoutEngine {
                     let looks_like_code = looks_like_js || looks_like_css || (trimmed.contains("{") && trimmed.contains("}") && trimmed.len() > 50);
                     
                     if !looks_like_code {
-                        let decoded = entities::decode_html_entities(trimmed);
+                        // Preserved whitespace keeps the text as written, apart from
+                        // the newline that directly follows a start tag
+                        let source = match style.white_space {
+                            WhiteSpace::Pre | WhiteSpace::PreWrap | WhiteSpace::PreLine => {
+                                text.strip_prefix('\n').unwrap_or(text).trim_end_matches('\n')
+                            }
+                            WhiteSpace::Normal | WhiteSpace::NoWrap => trimmed,
+                        };
+                        let decoded = entities::decode_html_entities(source);
                         if !decoded.trim().is_empty() {
                             // Calculate proper x position: add left padding and margin
                             let text_x = x + left_padding + style.margin.left;
-                            display_list.add_item(DisplayItem::Text {
-                                content: decoded,
-                                x: text_x,
-                                y: current_y, // Use current_y for proper positioning
-                                color: style.color.clone(),
-                            });
-                            // Update current_y for text (add line height)
-                            current_y += line_height;
+                            let max_width = self.viewport_width as f32 - text_x - right_padding;
+                            for line in self.break_lines(&decoded, style, max_width) {
+                                // Hidden text still takes up its lines
+                                if style.visibility == Visibility::Visible {
+                                    display_list.add_item(DisplayItem::Text {
+                                        content: line,
+                                        x: text_x,
+                                        y: current_y, // Use current_y for proper positioning
+                                        color: style.color.clone(),
+                                    });
+                                }
+                                // Update current_y for text (add line height)
+                                current_y += line_height;
+                            }
                         }
                     } else {
                         log::debug!(target: "layout", "Skipping text that looks like code (JS/CSS)");
@@ -735,7 +1001,11 @@ impl LayoutEngine {
                     
                     // Only add rectangle if it has meaningful dimensions and is not the root html/body
                     // Use white background for layout structure (will be filtered in rendering)
-                    if block_width > 0.0 && block_height > 0.0 && !matches!(tag_lower.as_str(), "html" | "body") {
+                    if block_width > 0.0
+                        && block_height > 0.0
+                        && !matches!(tag_lower.as_str(), "html" | "body")
+                        && style.visibility == Visibility::Visible
+                    {
                         display_list.add_item(DisplayItem::Rectangle {
                             x: block_x,
                             y: block_start_y,
@@ -760,6 +1030,150 @@ impl LayoutEngine {
         total_height
     }
 
+    /// Size of a button box: its label plus the default button padding.
+    pub fn button_size(&self, label: &str, style: &ComputedStyle) -> (f32, f32) {
+        let metrics = self.font_manager.measure_text(label, &style.font_family, style.font_size);
+        (metrics.width + 2.0 * BUTTON_PADDING_X, metrics.height + 2.0 * BUTTON_PADDING_Y)
+    }
+
+    // Split text into the lines it occupies under `style.white_space`, wrapping
+    // between words at `max_width` where wrapping is allowed. A word wider than
+    // the line overflows rather than being broken.
+    fn break_lines(&self, text: &str, style: &ComputedStyle, max_width: f32) -> Vec<String> {
+        let (collapse_spaces, keep_newlines, wrap) = match style.white_space {
+            WhiteSpace::Normal => (true, false, true),
+            WhiteSpace::NoWrap => (true, false, false),
+            WhiteSpace::Pre => (false, true, false),
+            WhiteSpace::PreWrap => (false, true, true),
+            WhiteSpace::PreLine => (true, true, true),
+        };
+        let paragraphs: Vec<&str> = if keep_newlines { text.split('\n').collect() } else { vec![text] };
+
+        let mut lines = Vec::new();
+        for paragraph in paragraphs {
+            let paragraph = if collapse_spaces {
+                paragraph.split_whitespace().collect::<Vec<_>>().join(" ")
+            } else {
+                paragraph.to_string()
+            };
+            if !wrap {
+                lines.push(paragraph);
+                continue;
+            }
+            let measure = |text: &str| self.font_manager.measure_text(text, &style.font_family, style.font_size).width;
+            let mut line = String::new();
+            // Width of `line`, trailing spaces included; text widths add up, so
+            // each word is measured once
+            let mut line_width = 0.0;
+            // Words keep their trailing spaces so preserved spacing survives
+            for word in paragraph.split_inclusive(' ') {
+                let word_width = measure(word);
+                let visible_width = line_width + measure(word.trim_end());
+                if visible_width > max_width && !line.trim().is_empty() {
+                    lines.push(line.trim_end().to_string());
+                    line.clear();
+                    line_width = 0.0;
+                }
+                line.push_str(word);
+                line_width += word_width;
+            }
+            lines.push(if collapse_spaces { line.trim_end().to_string() } else { line });
+        }
+        lines
+    }
+
+    // Fixed table layout: every column is as wide as its widest cell, and all cells
+    // in a row start at the row's top so their first lines share a baseline.
+    fn layout_table(&mut self, node: &StyledNode, x: f32, y: f32, style: &ComputedStyle, display_list: &mut DisplayList) -> f32 {
+        // Default `border-spacing` of HTML tables
+        const CELL_SPACING: f32 = 2.0;
+
+        let rows: Vec<Vec<(&StyledNode, ComputedStyle)>> = self
+            .table_rows(node, style.white_space)
+            .into_iter()
+            .map(|(row, white_space)| {
+                row.children
+                    .iter()
+                    .map(|cell| (cell, self.compute_style(cell, white_space)))
+                    .filter(|(_, cell_style)| matches!(cell_style.display, Display::TableCell))
+                    .collect()
+            })
+            .collect();
+
+        let mut column_widths: Vec<f32> = Vec::new();
+        for row in &rows {
+            for (column, (cell, cell_style)) in row.iter().enumerate() {
+                let width = self.cell_content_width(cell, cell_style);
+                match column_widths.get_mut(column) {
+                    Some(max) => *max = max.max(width),
+                    None => column_widths.push(width),
+                }
+            }
+        }
+
+        let left_padding = if x < 20.0 { 20.0 } else { style.padding.left };
+        let table_x = x + left_padding;
+        let table_y = y + style.margin.top + style.padding.top;
+        let mut row_y = table_y + CELL_SPACING;
+        for row in &rows {
+            let mut cell_x = table_x + CELL_SPACING;
+            let mut row_height: f32 = 0.0;
+            for ((cell, cell_style), column_width) in row.iter().zip(&column_widths) {
+                let height = self.layout_block(cell, cell_x, row_y, cell_style, display_list);
+                row_height = row_height.max(height);
+                cell_x += column_width + CELL_SPACING;
+            }
+            row_y += row_height + CELL_SPACING;
+        }
+
+        row_y - table_y + style.margin.top + style.margin.bottom + style.padding.top + style.padding.bottom
+    }
+
+    // Rows of a table in document order, looking through row groups, each with
+    // the white-space its cells inherit
+    fn table_rows<'a>(&self, table: &'a StyledNode, white_space: WhiteSpace) -> Vec<(&'a StyledNode, WhiteSpace)> {
+        let mut rows = Vec::new();
+        for child in &table.children {
+            let child_style = self.compute_style(child, white_space);
+            match child_style.display {
+                Display::TableRow => rows.push((child, child_style.white_space)),
+                Display::TableRowGroup => {
+                    for row in &child.children {
+                        let row_style = self.compute_style(row, child_style.white_space);
+                        if matches!(row_style.display, Display::TableRow) {
+                            rows.push((row, row_style.white_space));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        rows
+    }
+
+    // Width a cell needs to fit its text on one line, including padding
+    fn cell_content_width(&self, cell: &StyledNode, style: &ComputedStyle) -> f32 {
+        fn collect_text(node: &crate::dom::Node, text: &mut String) {
+            match node.node_type() {
+                crate::dom::NodeType::Text(t) => {
+                    let trimmed = t.trim();
+                    if !trimmed.is_empty() {
+                        if !text.is_empty() {
+                            text.push(' ');
+                        }
+                        text.push_str(trimmed);
+                    }
+                }
+                _ => node.children().iter().for_each(|child| collect_text(child, text)),
+            }
+        }
+        let mut text = String::new();
+        collect_text(&cell.node, &mut text);
+        let text = entities::decode_html_entities(&text);
+        let metrics = self.font_manager.measure_text(&text, &style.font_family, style.font_size);
+        metrics.width + style.padding.left + style.padding.right
+    }
+
     fn layout_inline(&mut self, node: &StyledNode, x: f32, y: f32, style: &ComputedStyle, display_list: &mut DisplayList) -> f32 {
         let font_metrics = self.font_manager.get_metrics(&style.font_family, style.font_size);
         let line_height = match style.line_height {
@@ -788,14 +1202,16 @@ impl LayoutEngine {
                 log::info!(target: "layout", "Found inline img element: src='{}', alt='{}', size={}x{} at ({}, {}) -> ({}, {})", 
                     img_url, alt_text, img_width, img_height, x, y, inline_x, inline_y);
                 
-                display_list.add_item(DisplayItem::Image {
-                    url: img_url,
-                    x: inline_x,
-                    y: inline_y,
-                    width: img_width,
-                    height: img_height,
-                    alt: alt_text,
-                });
+                if style.visibility == Visibility::Visible {
+                    display_list.add_item(DisplayItem::Image {
+                        url: img_url,
+                        x: inline_x,
+                        y: inline_y,
+                        width: img_width,
+                        height: img_height,
+                        alt: alt_text,
+                    });
+                }
                 // Return total height including margins
                 return img_height + style.margin.top + style.margin.bottom;
             }
@@ -819,7 +1235,7 @@ impl LayoutEngine {
                     let looks_like_code = looks_like_js || looks_like_css || (trimmed.contains("{") && trimmed.contains("}") && trimmed.len() > 50);
                     if !looks_like_code {
                         let decoded = entities::decode_html_entities(trimmed);
-                        if !decoded.trim().is_empty() {
+                        if !decoded.trim().is_empty() && style.visibility == Visibility::Visible {
                             display_list.add_item(DisplayItem::Text {
                                 content: decoded,
                                 x,
@@ -853,7 +1269,7 @@ impl LayoutEngine {
                         }
                     }
                     
-                    if text_width > 0.0 {
+                    if text_width > 0.0 && style.visibility == Visibility::Visible {
                         display_list.add_item(DisplayItem::Rectangle {
                             x,
                             y: y - 2.0,
@@ -876,10 +1292,12 @@ impl LayoutEngine {
                     LineHeight::Length(h) => h,
                 };
                 let mut max_height: f32 = line_height;
+                // Nested inline elements skip layout_node, so inherit here
+                let parent_visibility = self.inherit_visibility(style.visibility);
                 
-                for child in node.node.children() {
-                    let styled_child = crate::css::style::StyledNode::new(child.clone());
-                    let child_computed = self.compute_style(&styled_child);
+                for styled_child in &node.children {
+                    let child = &styled_child.node;
+                    let child_computed = self.compute_style(styled_child, style.white_space);
                     
                     match child.node_type() {
                         crate::dom::NodeType::Text(text) => {
@@ -887,24 +1305,27 @@ impl LayoutEngine {
                             if !trimmed.is_empty() {
                                 let decoded = entities::decode_html_entities(trimmed);
                                 if !decoded.trim().is_empty() {
-                                    display_list.add_item(DisplayItem::Text {
-                                        content: decoded.clone(),
-                                        x: current_x,
-                                        y: inline_y, // Use inline_y which includes margin
-                                        color: Color { r: 0, g: 0, b: 0, a: 255 },
-                                    });
+                                    if style.visibility == Visibility::Visible {
+                                        display_list.add_item(DisplayItem::Text {
+                                            content: decoded.clone(),
+                                            x: current_x,
+                                            y: inline_y, // Use inline_y which includes margin
+                                            color: Color { r: 0, g: 0, b: 0, a: 255 },
+                                        });
+                                    }
                                     current_x += decoded.len() as f32 * char_width;
                                 }
                             }
                         }
                         _ => {
-                            let child_height: f32 = self.layout_inline(&styled_child, current_x, y, &child_computed, display_list);
+                            let child_height: f32 = self.layout_inline(styled_child, current_x, y, &child_computed, display_list);
                             max_height = max_height.max(child_height);
                             // Estimate width for inline elements
                             current_x += 50.0; // Space for inline elements
                         }
                     }
                 }
+                self.visibility = parent_visibility;
                 
                 return max_height;
             }
@@ -914,4 +1335,348 @@ impl LayoutEngine {
         // Return the height of inline content (typically line height)
         24.0
     }
-} 
\ No newline at end of file
+}
+
+// White-space of `node` before any stylesheet applies: preformatted elements
+// default to `pre`, everything else inherits
+fn default_white_space(node: &StyledNode, parent_white_space: WhiteSpace) -> WhiteSpace {
+    match node.node.node_type() {
+        crate::dom::NodeType::Element { tag_name, .. }
+            if matches!(tag_name.to_ascii_lowercase().as_str(), "pre" | "textarea" | "listing" | "xmp") =>
+        {
+            WhiteSpace::Pre
+        }
+        _ => parent_white_space,
+    }
+}
+
+fn display_from(value: &Value) -> Option<Display> {
+    let Value::Keyword(kw) = value else {
+        return None;
+    };
+    match kw.to_lowercase().as_str() {
+        "none" => Some(Display::None),
+        "block" => Some(Display::Block),
+        "inline" => Some(Display::Inline),
+        "table" => Some(Display::Table),
+        "table-row-group" | "table-header-group" | "table-footer-group" => Some(Display::TableRowGroup),
+        "table-row" => Some(Display::TableRow),
+        "table-cell" => Some(Display::TableCell),
+        _ => None,
+    }
+}
+
+// Values arrive normalized: named weights are already numbers
+fn font_weight_from(value: &Value) -> Option<FontWeight> {
+    let Value::Keyword(kw) = value else {
+        return None;
+    };
+    match kw.as_str() {
+        "bolder" => Some(FontWeight::Bolder),
+        "lighter" => Some(FontWeight::Lighter),
+        _ => kw.parse::<u16>().ok().map(FontWeight::Number),
+    }
+}
+
+fn visibility_from(value: &Value) -> Option<Visibility> {
+    let Value::Keyword(kw) = value else {
+        return None;
+    };
+    match kw.to_lowercase().as_str() {
+        "visible" => Some(Visibility::Visible),
+        "hidden" | "collapse" => Some(Visibility::Hidden),
+        _ => None,
+    }
+}
+
+/// The label drawn on a button, or None if `node` is not rendered as one.
+/// Covers `<button>` and `<input type=button|submit|reset>`.
+pub(crate) fn button_label(node: &crate::dom::Node) -> Option<String> {
+    let crate::dom::NodeType::Element { tag_name, .. } = node.node_type() else {
+        return None;
+    };
+    match tag_name.to_ascii_lowercase().as_str() {
+        "button" => {
+            let text = node.text_content();
+            let label = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            if label.is_empty() {
+                Some(node.get_attribute("value").unwrap_or_default().to_string())
+            } else {
+                Some(label)
+            }
+        }
+        "input" => {
+            let input_type = node.get_attribute("type").unwrap_or("text").to_ascii_lowercase();
+            let default = match input_type.as_str() {
+                "submit" => "Submit",
+                "reset" => "Reset",
+                "button" => "",
+                _ => return None,
+            };
+            Some(node.get_attribute("value").unwrap_or(default).to_string())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_positions(html: &str) -> HashMap<String, (f32, f32)> {
+        let dom = crate::html::parser::Parser::new(html.to_string()).parse();
+        let root = StyledNode::new(dom.root().expect("root").clone());
+        let display_list = LayoutEngine::new(800, 600).compute_layout(&root);
+        display_list
+            .items()
+            .iter()
+            .filter_map(|item| match item {
+                DisplayItem::Text { content, x, y, .. } => Some((content.clone(), (*x, *y))),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_table_columns_align() {
+        let positions = text_positions(
+            "<table><tr><td>a</td><td>b</td></tr><tr><td>much longer</td><td>c</td></tr></table>",
+        );
+        let (a, b, long, c) = (positions["a"], positions["b"], positions["much longer"], positions["c"]);
+
+        // Columns line up, and the first column fits its widest cell
+        assert_eq!(a.0, long.0);
+        assert_eq!(b.0, c.0);
+        assert!(b.0 >= long.0 + "much longer".len() as f32 * 16.0 * 0.6);
+
+        // Cells in a row share a top edge; rows stack
+        assert_eq!(a.1, b.1);
+        assert_eq!(long.1, c.1);
+        assert!(long.1 > a.1);
+    }
+
+    #[test]
+    fn test_identical_layout_is_cached() {
+        let dom = crate::html::parser::Parser::new("<p>hello</p>".to_string()).parse();
+        let root = StyledNode::new(dom.root().expect("root").clone());
+        let mut engine = LayoutEngine::new(800, 600);
+
+        let first = engine.compute_layout(&root).items().len();
+        engine.set_viewport_size(800, 600);
+        assert_eq!(engine.compute_layout(&root).items().len(), first);
+        // The same page parsed again is the same layout
+        let reparsed = crate::html::parser::Parser::new("<p>hello</p>".to_string()).parse();
+        engine.compute_layout(&StyledNode::new(reparsed.root().expect("root").clone()));
+        assert_eq!(engine.layouts_computed(), 1);
+
+        engine.set_viewport_size(400, 600);
+        engine.compute_layout(&root);
+        assert_eq!(engine.layouts_computed(), 2);
+        let changed = crate::html::parser::Parser::new("<p>goodbye</p>".to_string()).parse();
+        engine.compute_layout(&StyledNode::new(changed.root().expect("root").clone()));
+        assert_eq!(engine.layouts_computed(), 3);
+
+        // A stylesheet rule on a descendant changes the layout too
+        let page = crate::html::parser::Parser::new("<div><p>hello</p></div>".to_string()).parse();
+        let style = |css: &str| {
+            let stylesheet = crate::css::parser::CssParser::new(css.to_string()).parse();
+            crate::css::style::StyleEngine::new(stylesheet).apply_styles(page.root().expect("root"))
+        };
+        engine.compute_layout(&style("p { margin-top: 10px }"));
+        engine.compute_layout(&style("p { margin-top: 30px }"));
+        assert_eq!(engine.layouts_computed(), 5);
+        engine.invalidate_layout_cache();
+        engine.compute_layout(&style("p { margin-top: 30px }"));
+        assert_eq!(engine.layouts_computed(), 6);
+    }
+
+    fn text_lines(html: &str, viewport_width: u32) -> Vec<(String, f32)> {
+        let dom = crate::html::parser::Parser::new(html.to_string()).parse();
+        let root = StyledNode::new(dom.root().expect("root").clone());
+        let display_list = LayoutEngine::new(viewport_width, 600).compute_layout(&root);
+        display_list
+            .items()
+            .iter()
+            .filter_map(|item| match item {
+                DisplayItem::Text { content, y, .. } => Some((content.clone(), *y)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_white_space_controls_wrapping() {
+        let words = "lorem ipsum dolor sit amet consectetur adipiscing elit sed do eiusmod";
+        // About 20 characters fit on a 240px line
+        let wrapped = text_lines(&format!("<p>{}</p>", words), 240);
+        assert!(wrapped.len() > 1, "lines: {:?}", wrapped);
+        assert!(wrapped.windows(2).all(|pair| pair[0].1 < pair[1].1));
+
+        let nowrap = text_lines(&format!("<p style=\"white-space: nowrap\">{}</p>", words), 240);
+        assert_eq!(nowrap.len(), 1);
+        assert_eq!(nowrap[0].0, words);
+
+        let pre_wrap = text_lines(&format!("<div style=\"white-space: pre-wrap\">{}</div>", words), 240);
+        assert_eq!(pre_wrap.len(), wrapped.len());
+    }
+
+    #[test]
+    fn test_pre_keeps_newlines_and_spaces() {
+        let lines = text_lines("<pre>first   line\nsecond line</pre>", 800);
+        let content: Vec<&str> = lines.iter().map(|(text, _)| text.as_str()).collect();
+        assert_eq!(content, vec!["first   line", "second line"]);
+        assert!(lines[0].1 < lines[1].1);
+    }
+
+    #[test]
+    fn test_buttons_are_sized_to_their_label() {
+        let dom = crate::html::parser::Parser::new(
+            "<button>Go</button><input type=\"submit\"><input type=\"text\" value=\"typed\">".to_string(),
+        )
+        .parse();
+        let root = StyledNode::new(dom.root().expect("root").clone());
+        let display_list = LayoutEngine::new(800, 600).compute_layout(&root);
+        let buttons: Vec<(&str, f32)> = display_list
+            .items()
+            .iter()
+            .filter_map(|item| match item {
+                DisplayItem::Button { text, width, .. } => Some((text.as_str(), *width)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(buttons[0].0, "Go");
+        assert_eq!(buttons[1].0, "Submit");
+        // Wider label, wider box; both include padding
+        assert!(buttons[0].1 > 16.0 * 0.6 * 2.0);
+        assert!(buttons[1].1 > buttons[0].1);
+    }
+
+    #[test]
+    fn test_after_content_resolves_attr() {
+        let dom = crate::html::parser::Parser::new(
+            "<div class=\"tooltip\" data-tooltip=\"Hi\">Text</div>".to_string(),
+        )
+        .parse();
+        fn find(node: &crate::dom::Node) -> Option<&crate::dom::Node> {
+            if node.is_element("div") {
+                return Some(node);
+            }
+            node.children().iter().find_map(find)
+        }
+        let div = find(dom.root().expect("root")).expect("div");
+        let stylesheet = crate::css::parser::CssParser::new(
+            ".tooltip::after { content: attr(data-tooltip); } .tooltip::before { content: attr(data-missing); }"
+                .to_string(),
+        )
+        .parse();
+        let styled = crate::css::style::StyleEngine::new(stylesheet).apply_styles(div);
+
+        let display_list = LayoutEngine::new(800, 600).compute_layout(&styled);
+        let texts: Vec<&str> = display_list
+            .items()
+            .iter()
+            .filter_map(|item| match item {
+                DisplayItem::Text { content, .. } => Some(content.as_str()),
+                _ => None,
+            })
+            .collect();
+        // A missing attribute generates empty content, which paints nothing
+        assert_eq!(texts, vec!["Text", "Hi"]);
+    }
+
+    #[test]
+    fn test_rem_resolves_against_root_font_size() {
+        let dom = crate::html::parser::Parser::new(
+            "<html style=\"font-size: 20px\"><body><div style=\"width: 2rem; margin-left: 1rem; font-size: 1.5rem\">x</div></body></html>"
+                .to_string(),
+        )
+        .parse();
+        fn find<'a>(node: &'a crate::dom::Node, tag: &str) -> Option<&'a crate::dom::Node> {
+            if node.is_element(tag) {
+                return Some(node);
+            }
+            node.children().iter().find_map(|child| find(child, tag))
+        }
+        let root = dom.root().expect("root");
+        let mut engine = LayoutEngine::new(800, 600);
+        engine.compute_layout(&StyledNode::new(root.clone()));
+
+        let style = engine.compute_style(&StyledNode::new(find(root, "div").expect("div").clone()), WhiteSpace::Normal);
+        assert!(matches!(style.width, Dimension::Length(w) if w == 40.0));
+        assert_eq!(style.margin.left, 20.0);
+        assert_eq!(style.font_size, 30.0);
+        let html = engine.compute_style(&StyledNode::new(find(root, "html").expect("html").clone()), WhiteSpace::Normal);
+        assert_eq!(html.font_size, 20.0);
+
+        // Without a root font-size, rem is the 16px default
+        let plain = crate::html::parser::Parser::new("<div style=\"width: 2rem\">x</div>".to_string()).parse();
+        engine.compute_layout(&StyledNode::new(plain.root().expect("root").clone()));
+        let style = engine.compute_style(&StyledNode::new(find(plain.root().expect("root"), "div").expect("div").clone()), WhiteSpace::Normal);
+        assert!(matches!(style.width, Dimension::Length(w) if w == 32.0));
+    }
+
+    #[test]
+    fn test_em_font_sizes_compound_down_the_tree() {
+        // Button widths scale with the font-size they inherit
+        let dom = crate::html::parser::Parser::new(
+            "<button>ab</button><div style=\"font-size: 2em\"><button>ab</button>\
+             <div style=\"font-size: 2em\"><button>ab</button><p style=\"font-size: 50%\"><button>ab</button></p></div></div>"
+                .to_string(),
+        )
+        .parse();
+        let root = StyledNode::new(dom.root().expect("root").clone());
+        let display_list = LayoutEngine::new(800, 600).compute_layout(&root);
+        let label_widths: Vec<f32> = display_list
+            .items()
+            .iter()
+            .filter_map(|item| match item {
+                DisplayItem::Button { width, .. } => Some(*width - 2.0 * BUTTON_PADDING_X),
+                _ => None,
+            })
+            .collect();
+        let font_sizes: Vec<f32> = label_widths.iter().map(|w| (w / (2.0 * 0.6)).round()).collect();
+        assert_eq!(font_sizes, vec![16.0, 32.0, 64.0, 32.0]);
+    }
+
+    #[test]
+    fn test_overflow_hidden_clips_children_to_the_box() {
+        fn lay_out(parent_style: &str) -> DisplayList {
+            let html = format!("<div style=\"{parent_style}\"><div style=\"width: 500px\">{}</div></div>", "wide ".repeat(60));
+            let dom = crate::html::parser::Parser::new(html).parse();
+            LayoutEngine::new(800, 600).compute_layout(&StyledNode::new(dom.root().expect("root").clone()))
+        }
+        fn right_edge(item: &DisplayItem) -> f32 {
+            match item {
+                DisplayItem::Text { content, x, .. } => x + content.chars().count() as f32 * DEFAULT_FONT_SIZE * 0.6,
+                DisplayItem::Rectangle { x, width, .. } => x + width,
+                _ => 0.0,
+            }
+        }
+
+        // The parent box spans x 20..120 and y 0..40
+        let clipped = lay_out("overflow: hidden; width: 100px; height: 40px");
+        assert!(clipped.items().iter().any(|item| matches!(item, DisplayItem::Text { .. })));
+        for item in clipped.items() {
+            assert!(right_edge(item) <= 120.0, "painted past the right edge: {:?}", item);
+            if let DisplayItem::Text { y, .. } = item {
+                assert!(*y < 40.0, "painted below the box: {:?}", item);
+            }
+        }
+
+        // Without overflow the child paints at its full width
+        let visible = lay_out("width: 100px; height: 40px");
+        assert!(visible.items().iter().any(|item| right_edge(item) > 120.0));
+        assert!(visible.items().iter().any(|item| matches!(item, DisplayItem::Text { y, .. } if *y >= 40.0)));
+    }
+
+    #[test]
+    fn test_display_none_lays_out_nothing() {
+        let dom = crate::html::parser::Parser::new(
+            "<div style=\"display:none\">hidden<button>go</button></div><img src=\"a.png\" style=\"display: none\">".to_string(),
+        )
+        .parse();
+        let root = StyledNode::new(dom.root().expect("root").clone());
+        let display_list = LayoutEngine::new(800, 600).compute_layout(&root);
+        assert!(display_list.items().is_empty(), "painted: {:?}", display_list.items());
+    }
+}
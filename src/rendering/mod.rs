@@ -6,9 +6,10 @@ pub mod gui;
 
 pub mod layout;
 pub mod painter;
+pub mod png;
 pub mod tree;
 
-pub use tree::{RenderTree, RenderNode, Bounds};
+pub use tree::{BoxType, RenderTree, RenderNode, Bounds};
 
 pub struct Renderer {
     headless: bool,
@@ -56,6 +57,14 @@ pub enum DisplayItem {
         width: f32,
         height: f32,
     },
+    /// Clickable area of an `<a href>`; paints nothing.
+    Link {
+        href: String,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -66,6 +75,89 @@ pub struct Color {
     a: u8,
 }
 
+impl DisplayItem {
+    fn to_json(&self) -> String {
+        let (kind, fields) = match self {
+            DisplayItem::Text { content, x, y, color } => (
+                "text",
+                vec![("content", json_string(content)), ("x", json_number(*x)), ("y", json_number(*y)), ("color", color.to_json())],
+            ),
+            DisplayItem::Rectangle { x, y, width, height, color } => (
+                "rectangle",
+                vec![
+                    ("x", json_number(*x)),
+                    ("y", json_number(*y)),
+                    ("width", json_number(*width)),
+                    ("height", json_number(*height)),
+                    ("color", color.to_json()),
+                ],
+            ),
+            DisplayItem::Image { url, x, y, width, height, alt } => (
+                "image",
+                vec![
+                    ("url", json_string(url)),
+                    ("x", json_number(*x)),
+                    ("y", json_number(*y)),
+                    ("width", json_number(*width)),
+                    ("height", json_number(*height)),
+                    ("alt", json_string(alt)),
+                ],
+            ),
+            DisplayItem::Button { text, x, y, width, height } => (
+                "button",
+                vec![
+                    ("text", json_string(text)),
+                    ("x", json_number(*x)),
+                    ("y", json_number(*y)),
+                    ("width", json_number(*width)),
+                    ("height", json_number(*height)),
+                ],
+            ),
+            DisplayItem::Link { href, x, y, width, height } => (
+                "link",
+                vec![
+                    ("href", json_string(href)),
+                    ("x", json_number(*x)),
+                    ("y", json_number(*y)),
+                    ("width", json_number(*width)),
+                    ("height", json_number(*height)),
+                ],
+            ),
+        };
+        let fields: Vec<String> = fields.into_iter().map(|(name, value)| format!("\"{}\":{}", name, value)).collect();
+        format!("{{\"type\":\"{}\",{}}}", kind, fields.join(","))
+    }
+}
+
+impl Color {
+    fn to_json(&self) -> String {
+        format!("{{\"r\":{},\"g\":{},\"b\":{},\"a\":{}}}", self.r, self.g, self.b, self.a)
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// JSON has no NaN or infinity
+fn json_number(value: f32) -> String {
+    if value.is_finite() { value.to_string() } else { "null".to_string() }
+}
+
 impl Renderer {
     pub fn new(headless: bool) -> Result<Self, Box<dyn Error>> {
         // Default viewport size - needed for layout calculations even in headless mode
@@ -85,6 +177,21 @@ impl Renderer {
         self.layout_engine.set_viewport_size(width, height);
     }
 
+    /// Whether pages are laid out as seen by a scripting-enabled browser.
+    pub fn set_scripting_enabled(&mut self, enabled: bool) {
+        self.layout_engine.set_scripting_enabled(enabled);
+    }
+
+    pub fn viewport_size(&self) -> (u32, u32) {
+        (self.layout_engine.viewport_width(), self.layout_engine.viewport_height())
+    }
+
+    /// Rasterize a display list at the current viewport size and encode it as PNG.
+    pub fn screenshot(&self, display_list: &DisplayList) -> Result<Vec<u8>, Box<dyn Error>> {
+        let (width, height) = self.viewport_size();
+        painter::RenderBuffer::rasterize(display_list, width, height).to_png()
+    }
+
     pub fn layout(&mut self, styled_node: &StyledNode) -> DisplayList {
         self.layout_engine.compute_layout(styled_node)
     }
@@ -122,4 +229,111 @@ impl DisplayList {
     pub fn add_item(&mut self, item: DisplayItem) {
         self.items.push(item);
     }
+
+    /// Rewrite every link target with `resolve`, dropping links it rejects.
+    pub fn resolve_links(&mut self, resolve: impl Fn(&str) -> Option<String>) {
+        self.items.retain_mut(|item| match item {
+            DisplayItem::Link { href, .. } => match resolve(href) {
+                Some(resolved) => {
+                    *href = resolved;
+                    true
+                }
+                None => false,
+            },
+            _ => true,
+        });
+    }
+
+    /// Target of the topmost link covering the point, if any.
+    pub fn link_at(&self, px: f32, py: f32) -> Option<&str> {
+        self.items.iter().rev().find_map(|item| match item {
+            DisplayItem::Link { href, x, y, width, height }
+                if px >= *x && px < x + width && py >= *y && py < y + height =>
+            {
+                Some(href.as_str())
+            }
+            _ => None,
+        })
+    }
+
+    /// Clip the items from index `start` on to `clip`: items wholly outside it are
+    /// dropped, boxes are cut down to the part inside, and text lines lose the
+    /// characters past its right edge, measured at `glyph_advance` per character.
+    pub fn clip_from(&mut self, start: usize, clip: &Bounds, glyph_advance: f32) {
+        let (right, bottom) = (clip.x + clip.width, clip.y + clip.height);
+        let mut index = 0;
+        self.items.retain_mut(|item| {
+            index += 1;
+            if index <= start {
+                return true;
+            }
+            match item {
+                DisplayItem::Text { content, x, y, .. } => {
+                    if *y < clip.y || *y >= bottom || *x >= right {
+                        return false;
+                    }
+                    let fits = ((right - *x) / glyph_advance).floor().max(0.0) as usize;
+                    if let Some((cut, _)) = content.char_indices().nth(fits) {
+                        content.truncate(cut);
+                    }
+                    // Characters left of the clip are not trimmed; text rarely starts there
+                    !content.trim().is_empty()
+                }
+                DisplayItem::Rectangle { x, y, width, height, .. }
+                | DisplayItem::Image { x, y, width, height, .. }
+                | DisplayItem::Button { x, y, width, height, .. }
+                | DisplayItem::Link { x, y, width, height, .. } => {
+                    let (x0, y0) = (x.max(clip.x), y.max(clip.y));
+                    let (x1, y1) = ((*x + *width).min(right), (*y + *height).min(bottom));
+                    if x1 <= x0 || y1 <= y0 {
+                        return false;
+                    }
+                    (*x, *y, *width, *height) = (x0, y0, x1 - x0, y1 - y0);
+                    true
+                }
+            }
+        });
+    }
+
+    /// Serialize the list as a JSON array with one object per item, in paint
+    /// order. Each object names its `type` first, then the item's fields in
+    /// declaration order, so the output is stable enough to diff against.
+    pub fn to_json(&self) -> String {
+        let items: Vec<String> = self.items.iter().map(DisplayItem::to_json).collect();
+        format!("[{}]", items.join(","))
+    }
+
+    /// Shrink every image to a zero-size placeholder, keeping its URL and alt text.
+    pub fn collapse_images(&mut self) {
+        for item in &mut self.items {
+            if let DisplayItem::Image { width, height, .. } = item {
+                *width = 0.0;
+                *height = 0.0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_list_to_json() {
+        let mut display_list = DisplayList::new();
+        display_list.add_item(DisplayItem::Text {
+            content: "say \"hi\"".to_string(),
+            x: 20.0,
+            y: 4.5,
+            color: Color { r: 0, g: 0, b: 255, a: 255 },
+        });
+        display_list.add_item(DisplayItem::Link { href: "https://example.com/".to_string(), x: 0.0, y: 0.0, width: 10.0, height: f32::NAN });
+
+        assert_eq!(
+            display_list.to_json(),
+            "[{\"type\":\"text\",\"content\":\"say \\\"hi\\\"\",\"x\":20,\"y\":4.5,\"color\":{\"r\":0,\"g\":0,\"b\":255,\"a\":255}},\
+             {\"type\":\"link\",\"href\":\"https://example.com/\",\"x\":0,\"y\":0,\"width\":10,\"height\":null}]"
+        );
+        assert_eq!(DisplayList::new().to_json(), "[]");
+    }
 }
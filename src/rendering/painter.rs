@@ -1,12 +1,25 @@
-use super::DisplayList;
+use super::{Color, DisplayItem, DisplayList};
 use std::error::Error;
 
+// Without a font rasterizer, text is drawn as one solid block per non-space
+// character, using the same 16px default metrics layout assumes.
+const GLYPH_ADVANCE: f32 = 16.0 * 0.6;
+const GLYPH_WIDTH: f32 = 7.0;
+const GLYPH_TOP: f32 = 4.0;
+const GLYPH_HEIGHT: f32 = 10.0;
+
+const PAGE_BACKGROUND: u32 = 0xFFFF_FFFF;
+const IMAGE_PLACEHOLDER: u32 = 0xFFE0_E0E0;
+const CONTROL_BACKGROUND: u32 = 0xFFEF_EFEF;
+const CONTROL_BORDER: u32 = 0xFF76_7676;
+
 pub struct Painter {
     headless: bool,
     buffer: Option<RenderBuffer>,
 }
 
-struct RenderBuffer {
+/// Software framebuffer of 0xAARRGGBB pixels.
+pub struct RenderBuffer {
     width: u32,
     height: u32,
     pixels: Vec<u32>,
@@ -35,7 +48,7 @@ impl Painter {
     fn paint_to_buffer(&mut self, display_list: &DisplayList) -> Result<(), Box<dyn Error>> {
         if let Some(buffer) = &mut self.buffer {
             buffer.clear();
-            
+
             for item in display_list.items() {
                 buffer.draw_item(item);
             }
@@ -50,19 +63,115 @@ impl Painter {
 }
 
 impl RenderBuffer {
-    fn new(width: u32, height: u32) -> Self {
+    pub fn new(width: u32, height: u32) -> Self {
         Self {
             width,
             height,
-            pixels: vec![0; (width * height) as usize],
+            pixels: vec![PAGE_BACKGROUND; (width * height) as usize],
         }
     }
 
+    /// Rasterize a display list onto a fresh buffer of the given size.
+    pub fn rasterize(display_list: &DisplayList, width: u32, height: u32) -> Self {
+        let mut buffer = Self::new(width, height);
+        for item in display_list.items() {
+            buffer.draw_item(item);
+        }
+        buffer
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Pixels as RGBA bytes, row-major.
+    pub fn to_rgba(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|&p| {
+                let [a, r, g, b] = p.to_be_bytes();
+                [r, g, b, a]
+            })
+            .collect()
+    }
+
+    pub fn to_png(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        super::png::encode_rgba(self.width, self.height, &self.to_rgba())
+    }
+
     fn clear(&mut self) {
-        self.pixels.fill(0);
+        self.pixels.fill(PAGE_BACKGROUND);
     }
 
-    fn draw_item(&mut self, item: &super::DisplayItem) {
-        // Implement drawing logic for different display items
+    fn draw_item(&mut self, item: &DisplayItem) {
+        match item {
+            DisplayItem::Rectangle { x, y, width, height, color } => {
+                self.fill_rect(*x, *y, *width, *height, color_to_argb(color));
+            }
+            DisplayItem::Text { content, x, y, color } => {
+                let argb = color_to_argb(color);
+                for (i, ch) in content.chars().enumerate() {
+                    if !ch.is_whitespace() {
+                        let glyph_x = x + i as f32 * GLYPH_ADVANCE;
+                        self.fill_rect(glyph_x, y + GLYPH_TOP, GLYPH_WIDTH, GLYPH_HEIGHT, argb);
+                    }
+                }
+            }
+            DisplayItem::Image { x, y, width, height, .. } => {
+                self.fill_rect(*x, *y, *width, *height, IMAGE_PLACEHOLDER);
+                self.stroke_rect(*x, *y, *width, *height, CONTROL_BORDER);
+            }
+            DisplayItem::Button { x, y, width, height, .. } => {
+                self.fill_rect(*x, *y, *width, *height, CONTROL_BACKGROUND);
+                self.stroke_rect(*x, *y, *width, *height, CONTROL_BORDER);
+            }
+            DisplayItem::Link { .. } => {}
+        }
+    }
+
+    fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, argb: u32) {
+        let alpha = argb >> 24;
+        if alpha == 0 || width <= 0.0 || height <= 0.0 {
+            return;
+        }
+        // Clip to the buffer; coordinates round to whole pixels
+        let x0 = x.round().max(0.0) as u32;
+        let y0 = y.round().max(0.0) as u32;
+        let x1 = ((x + width).round().max(0.0) as u32).min(self.width);
+        let y1 = ((y + height).round().max(0.0) as u32).min(self.height);
+
+        for py in y0..y1 {
+            let row = (py * self.width) as usize;
+            for px in x0..x1 {
+                let pixel = &mut self.pixels[row + px as usize];
+                *pixel = blend(*pixel, argb);
+            }
+        }
     }
-} 
\ No newline at end of file
+
+    fn stroke_rect(&mut self, x: f32, y: f32, width: f32, height: f32, argb: u32) {
+        self.fill_rect(x, y, width, 1.0, argb);
+        self.fill_rect(x, y + height - 1.0, width, 1.0, argb);
+        self.fill_rect(x, y, 1.0, height, argb);
+        self.fill_rect(x + width - 1.0, y, 1.0, height, argb);
+    }
+}
+
+fn color_to_argb(color: &Color) -> u32 {
+    u32::from_be_bytes([color.a, color.r, color.g, color.b])
+}
+
+// Source-over blend of `src` onto an opaque `dst`
+fn blend(dst: u32, src: u32) -> u32 {
+    let [sa, sr, sg, sb] = src.to_be_bytes();
+    if sa == 255 {
+        return src;
+    }
+    let [_, dr, dg, db] = dst.to_be_bytes();
+    let mix = |s: u8, d: u8| ((s as u32 * sa as u32 + d as u32 * (255 - sa as u32)) / 255) as u8;
+    u32::from_be_bytes([255, mix(sr, dr), mix(sg, dg), mix(sb, db)])
+}
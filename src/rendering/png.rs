@@ -0,0 +1,75 @@
+// Minimal PNG encoder for headless screenshots.
+// Writes a single IDAT chunk of 8-bit RGBA scanlines with no filtering;
+// compression is handled by flate2's zlib encoder.
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::error::Error;
+use std::io::Write;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+/// Encode `width * height` RGBA pixels (4 bytes each, row-major) as a PNG file.
+pub fn encode_rgba(width: u32, height: u32, rgba: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let row_len = width as usize * 4;
+    if rgba.len() != row_len * height as usize {
+        return Err(format!("Pixel buffer has {} bytes, expected {}x{} RGBA", rgba.len(), width, height).into());
+    }
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    // Bit depth 8, color type 6 (RGBA), deflate, adaptive filtering, no interlace
+    ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    // Every scanline is prefixed with its filter type (0 = None)
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    for row in rgba.chunks(row_len.max(1)).take(height as usize) {
+        encoder.write_all(&[0])?;
+        encoder.write_all(row)?;
+    }
+    let idat = encoder.finish()?;
+
+    let mut png = SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &ihdr);
+    write_chunk(&mut png, b"IDAT", &idat);
+    write_chunk(&mut png, b"IEND", &[]);
+    Ok(png)
+}
+
+fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    // The CRC covers the chunk type and data, not the length
+    let crc = crc32(kind.iter().chain(data.iter()).copied());
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn crc32(bytes: impl Iterator<Item = u8>) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc32_known_value() {
+        // CRC of the empty IEND chunk is fixed by the spec
+        assert_eq!(crc32(b"IEND".iter().copied()), 0xAE42_6082);
+    }
+
+    #[test]
+    fn test_rejects_wrong_buffer_size() {
+        assert!(encode_rgba(2, 2, &[0; 4]).is_err());
+    }
+}
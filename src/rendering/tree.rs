@@ -1,4 +1,5 @@
 use crate::css::style::StyledNode;
+use crate::rendering::layout::{Visibility, WhiteSpace};
 use super::DisplayList;
 
 pub struct RenderTree {
@@ -7,8 +8,25 @@ pub struct RenderTree {
 
 pub struct RenderNode {
     node: StyledNode,
+    box_type: BoxType,
     children: Vec<RenderNode>,
     bounds: Bounds,
+    // Hidden boxes keep their bounds but paint nothing of their own
+    visibility: Visibility,
+}
+
+/// The kind of box a render node generates, derived from its computed `display`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BoxType {
+    /// Block-level box; stacks vertically in its container.
+    Block,
+    /// Inline-level box, including text runs; flows on lines.
+    Inline,
+    /// Block box with no element of its own, wrapping a run of inline boxes
+    /// that sits between block siblings.
+    Anonymous,
+    /// Element whose content comes from outside the tree (images, form controls).
+    Replaced,
 }
 
 #[derive(Clone, Copy)]
@@ -29,8 +47,8 @@ impl RenderTree {
     /// Build a RenderTree recursively from a StyledNode
     pub fn build_from_styled_node(styled_node: &StyledNode, x: f32, y: f32, layout_engine: &mut crate::rendering::layout::LayoutEngine) -> Self {
         log::debug!(target: "tree", "Building RenderTree with viewport {}x{}", layout_engine.viewport_width(), layout_engine.viewport_height());
-        let mut root = RenderNode::new(styled_node.clone());
-        Self::build_render_node_recursive(&mut root, styled_node, x, y, layout_engine);
+        let mut root = RenderNode::new(styled_node.without_children());
+        Self::build_render_node_recursive(&mut root, styled_node, x, y, WhiteSpace::Normal, layout_engine);
         Self { root }
     }
     
@@ -39,10 +57,14 @@ impl RenderTree {
         styled_node: &StyledNode,
         x: f32,
         y: f32,
+        parent_white_space: WhiteSpace,
         layout_engine: &mut crate::rendering::layout::LayoutEngine,
     ) {
         // Calculate bounds using layout engine
-        let computed = layout_engine.compute_style(styled_node);
+        let computed = layout_engine.compute_style(styled_node, parent_white_space);
+        render_node.box_type = box_type_for(styled_node, &computed.display);
+        render_node.visibility = computed.visibility;
+        let parent_visibility = layout_engine.inherit_visibility(computed.visibility);
         // Real browsers: Start from y + top margin
         let mut current_y = y + computed.margin.top;
         let left_padding = if x < 20.0 { 20.0 } else { computed.padding.left };
@@ -90,9 +112,17 @@ impl RenderTree {
             }
         }
 
-        for child in styled_node.node.children() {
-            let styled_child = crate::css::style::StyledNode::new(child.clone());
-            let mut child_render_node = RenderNode::new(styled_child.clone());
+        // Generated ::before/::after content becomes anonymous inline boxes
+        // around the element's own children
+        let styled_children = styled_node.before.as_deref().into_iter()
+            .chain(&styled_node.children)
+            .chain(styled_node.after.as_deref());
+        for styled_child in styled_children {
+            // display: none removes the element and its subtree from rendering
+            if matches!(layout_engine.compute_style(styled_child, computed.white_space).display, crate::rendering::layout::Display::None) {
+                continue;
+            }
+            let mut child_render_node = RenderNode::new(styled_child.without_children());
 
             // For skipped elements, use the same current_y for all children (don't accumulate)
             // For normal elements, use current_y which will be accumulated after
@@ -103,7 +133,7 @@ impl RenderTree {
             };
 
             // Recursively build child - pass block_x as the new x position
-            Self::build_render_node_recursive(&mut child_render_node, &styled_child, block_x, child_y, layout_engine);
+            Self::build_render_node_recursive(&mut child_render_node, styled_child, block_x, child_y, computed.white_space, layout_engine);
 
             // Get child bounds after recursive build
             let child_bounds = child_render_node.bounds().clone();
@@ -121,7 +151,7 @@ impl RenderTree {
             // Only accumulate Y and height for non-skipped elements
             if !is_skipped {
                 // Real browsers: Add child height + bottom margin for next element
-                let child_computed = layout_engine.compute_style(&styled_child);
+                let child_computed = layout_engine.compute_style(styled_child, computed.white_space);
                 let child_bottom_margin = child_computed.margin.bottom;
                 let child_total_height = child_height + child_bottom_margin;
                 current_y += child_total_height;
@@ -134,9 +164,15 @@ impl RenderTree {
 
             render_node.add_child(child_render_node);
         }
+        render_node.wrap_inline_runs();
+        layout_engine.inherit_visibility(parent_visibility);
 
         // Real browsers: Calculate node width including margins
-        let node_width = if x < 20.0 {
+        let node_width = if let crate::rendering::layout::Dimension::Length(w) = computed.width {
+            w
+        } else if let crate::rendering::layout::Dimension::Percentage(p) = computed.width {
+            available_width * p / 100.0
+        } else if x < 20.0 {
             // Root element: use full available width minus margins
             (available_width - computed.margin.left - computed.margin.right).max(100.0f32)
         } else {
@@ -146,14 +182,22 @@ impl RenderTree {
 
         // Real browsers: Height includes content + padding + margins
         let content_height = max_height.max(line_height);
-        let total_height = content_height + computed.padding.top + computed.padding.bottom + computed.margin.bottom;
+        let total_height = match computed.height {
+            // An explicit height replaces the content height
+            crate::rendering::layout::Dimension::Length(h) => h + computed.padding.top + computed.padding.bottom + computed.margin.bottom,
+            _ => content_height + computed.padding.top + computed.padding.bottom + computed.margin.bottom,
+        };
 
-        let bounds = Bounds {
+        let mut bounds = Bounds {
             x: block_x,
             y: current_y - computed.margin.top, // Adjust y to account for top margin
             width: node_width,
             height: total_height,
         };
+        // Buttons shrink-wrap their label
+        if let Some(label) = crate::rendering::layout::button_label(&styled_node.node) {
+            (bounds.width, bounds.height) = layout_engine.button_size(&label, &computed);
+        }
         render_node.set_bounds(bounds);
 
         // Debug logging
@@ -181,6 +225,7 @@ impl RenderNode {
     pub fn new(node: StyledNode) -> Self {
         Self {
             node,
+            box_type: BoxType::Block,
             children: Vec::new(),
             bounds: Bounds {
                 x: 0.0,
@@ -188,6 +233,7 @@ impl RenderNode {
                 width: 0.0,
                 height: 0.0,
             },
+            visibility: Visibility::Visible,
         }
     }
     
@@ -203,6 +249,66 @@ impl RenderNode {
         &self.children
     }
     
+    pub fn box_type(&self) -> BoxType {
+        self.box_type
+    }
+
+    pub fn is_inline_level(&self) -> bool {
+        matches!(self.box_type, BoxType::Inline | BoxType::Replaced)
+    }
+
+    /// Whether this block lays its children out in lines: it is a block
+    /// container and every child is inline-level.
+    pub fn establishes_inline_context(&self) -> bool {
+        matches!(self.box_type, BoxType::Block | BoxType::Anonymous)
+            && !self.children.is_empty()
+            && self.children.iter().all(RenderNode::is_inline_level)
+    }
+
+    // A block container holds either only block-level or only inline-level
+    // children; when they are mixed, each run of inline children moves into an
+    // anonymous block.
+    fn wrap_inline_runs(&mut self) {
+        if self.box_type != BoxType::Block
+            || self.children.iter().all(RenderNode::is_inline_level)
+            || !self.children.iter().any(RenderNode::is_inline_level)
+        {
+            return;
+        }
+        let mut wrapped = Vec::with_capacity(self.children.len());
+        let mut run: Vec<RenderNode> = Vec::new();
+        for child in std::mem::take(&mut self.children) {
+            if child.is_inline_level() {
+                run.push(child);
+            } else {
+                if !run.is_empty() {
+                    wrapped.push(RenderNode::anonymous(std::mem::take(&mut run)));
+                }
+                wrapped.push(child);
+            }
+        }
+        if !run.is_empty() {
+            wrapped.push(RenderNode::anonymous(run));
+        }
+        self.children = wrapped;
+    }
+
+    fn anonymous(children: Vec<RenderNode>) -> Self {
+        // Anonymous boxes have no DOM node; an empty text node stands in
+        let placeholder = StyledNode::new(crate::dom::Node::new(crate::dom::NodeType::Text(String::new())));
+        let x = children.iter().map(|c| c.bounds.x).fold(f32::INFINITY, f32::min);
+        let y = children.iter().map(|c| c.bounds.y).fold(f32::INFINITY, f32::min);
+        let right = children.iter().map(|c| c.bounds.x + c.bounds.width).fold(f32::NEG_INFINITY, f32::max);
+        let bottom = children.iter().map(|c| c.bounds.y + c.bounds.height).fold(f32::NEG_INFINITY, f32::max);
+        Self {
+            node: placeholder,
+            box_type: BoxType::Anonymous,
+            children,
+            bounds: Bounds { x, y, width: right - x, height: bottom - y },
+            visibility: Visibility::Visible,
+        }
+    }
+
     pub fn bounds(&self) -> &Bounds {
         &self.bounds
     }
@@ -212,6 +318,13 @@ impl RenderNode {
     }
     
     fn build_display_list(&self, display_list: &mut DisplayList) {
+        // Visible descendants of a hidden box still paint
+        if self.box_type == BoxType::Anonymous || self.visibility == Visibility::Hidden {
+            for child in &self.children {
+                child.build_display_list(display_list);
+            }
+            return;
+        }
         match self.node.node.node_type() {
             crate::dom::NodeType::Text(text) => {
                 let trimmed = text.trim();
@@ -241,6 +354,17 @@ impl RenderNode {
                 
                 // Handle special elements
                 match tag_lower.as_str() {
+                    "a" => {
+                        if let Some(href) = self.node.node.get_attribute("href") {
+                            display_list.add_item(super::DisplayItem::Link {
+                                href: href.trim().to_string(),
+                                x: self.bounds.x,
+                                y: self.bounds.y,
+                                width: self.bounds.width,
+                                height: self.bounds.height,
+                            });
+                        }
+                    }
                     "img" => {
                         let img_url = self.node.node.get_attribute("src").unwrap_or("").to_string();
                         let alt_text = self.node.node.get_attribute("alt").unwrap_or("").to_string();
@@ -261,28 +385,18 @@ impl RenderNode {
                         });
                     }
                     "button" | "input" => {
-                        let button_text = if tag_lower == "button" {
-                            let mut text = String::new();
-                            for child in self.node.node.children() {
-                                if let crate::dom::NodeType::Text(t) = child.node_type() {
-                                    text.push_str(t.trim());
-                                }
-                            }
-                            if text.is_empty() {
-                                self.node.node.get_attribute("value").unwrap_or("Button").to_string()
-                            } else {
-                                text
+                        let (button_text, button_width, button_height) = match super::layout::button_label(&self.node.node) {
+                            // Bounds were sized to the label when the tree was built
+                            Some(label) => (label, self.bounds.width, self.bounds.height),
+                            None => {
+                                let text = self.node.node.get_attribute("value")
+                                    .or_else(|| self.node.node.get_attribute("placeholder"))
+                                    .unwrap_or("Input")
+                                    .to_string();
+                                (text, 120.0, 32.0)
                             }
-                        } else {
-                            self.node.node.get_attribute("value")
-                                .or_else(|| self.node.node.get_attribute("placeholder"))
-                                .unwrap_or("Input")
-                                .to_string()
                         };
                         
-                        let button_width = 120.0;
-                        let button_height = 32.0;
-                        
                         display_list.add_item(super::DisplayItem::Button {
                             text: button_text,
                             x: self.bounds.x,
@@ -321,3 +435,118 @@ impl RenderNode {
     }
 }
 
+fn box_type_for(node: &StyledNode, display: &crate::rendering::layout::Display) -> BoxType {
+    use crate::rendering::layout::Display;
+    match node.node.node_type() {
+        crate::dom::NodeType::Element { tag_name, .. } => {
+            if matches!(
+                tag_name.to_ascii_lowercase().as_str(),
+                "img" | "input" | "button" | "textarea" | "select" | "video" | "canvas" | "iframe"
+            ) {
+                BoxType::Replaced
+            } else if matches!(display, Display::Inline) {
+                BoxType::Inline
+            } else {
+                BoxType::Block
+            }
+        }
+        // Text (and comments, which paint nothing) flow inline
+        _ => BoxType::Inline,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendering::layout::LayoutEngine;
+
+    fn render_tree(html: &str) -> RenderTree {
+        let dom = crate::html::parser::Parser::new(html.to_string()).parse();
+        let root = StyledNode::new(dom.root().expect("root").clone());
+        RenderTree::build_from_styled_node(&root, 0.0, 0.0, &mut LayoutEngine::new(800, 600))
+    }
+
+    fn find_element<'a>(node: &'a RenderNode, tag: &str) -> Option<&'a RenderNode> {
+        if let crate::dom::NodeType::Element { tag_name, .. } = node.node().node.node_type()
+            && *tag_name == tag
+            && node.box_type() != BoxType::Anonymous
+        {
+            return Some(node);
+        }
+        node.children().iter().find_map(|child| find_element(child, tag))
+    }
+
+    fn rendered_text(node: &RenderNode) -> String {
+        match node.node().node.node_type() {
+            crate::dom::NodeType::Text(text) => text.clone(),
+            _ => node.children().iter().map(rendered_text).collect(),
+        }
+    }
+
+    #[test]
+    fn test_before_content_prepends_inline_box() {
+        let dom = crate::html::parser::Parser::new("<p class=\"x\">item</p>".to_string()).parse();
+        fn find(node: &crate::dom::Node) -> Option<&crate::dom::Node> {
+            if node.is_element("p") {
+                return Some(node);
+            }
+            node.children().iter().find_map(find)
+        }
+        let p = find(dom.root().expect("root")).expect("paragraph");
+        let engine = crate::css::style::StyleEngine::new(
+            crate::css::parser::CssParser::new(".x::before { content: \"* \" }".to_string()).parse(),
+        );
+        let styled = engine.apply_styles(p);
+        let tree = RenderTree::build_from_styled_node(&styled, 0.0, 0.0, &mut LayoutEngine::new(800, 600));
+
+        assert_eq!(rendered_text(tree.root()), "* item");
+        let kinds: Vec<BoxType> = tree.root().children().iter().map(RenderNode::box_type).collect();
+        assert_eq!(kinds, vec![BoxType::Inline, BoxType::Inline]);
+    }
+
+    #[test]
+    fn test_block_with_inline_content_has_inline_context() {
+        let tree = render_tree("<div><span>x</span>text</div>");
+        let div = find_element(tree.root(), "div").expect("div box");
+        assert_eq!(div.box_type(), BoxType::Block);
+        let kinds: Vec<BoxType> = div.children().iter().map(RenderNode::box_type).collect();
+        assert_eq!(kinds, vec![BoxType::Inline, BoxType::Inline]);
+        assert!(div.establishes_inline_context());
+    }
+
+    #[test]
+    fn test_mixed_content_gets_anonymous_blocks() {
+        let tree = render_tree("<div>before<p>para</p>after<img src=\"a.png\"></div>");
+        let div = find_element(tree.root(), "div").expect("div box");
+        let kinds: Vec<BoxType> = div.children().iter().map(RenderNode::box_type).collect();
+        assert_eq!(kinds, vec![BoxType::Anonymous, BoxType::Block, BoxType::Anonymous]);
+        assert!(!div.establishes_inline_context());
+        let trailing: Vec<BoxType> = div.children()[2].children().iter().map(RenderNode::box_type).collect();
+        assert_eq!(trailing, vec![BoxType::Inline, BoxType::Replaced]);
+        assert!(div.children()[2].establishes_inline_context());
+    }
+
+    #[test]
+    fn test_visibility_hidden_keeps_space_but_paints_nothing() {
+        fn painted(html: &str) -> Vec<(String, f32)> {
+            render_tree(html)
+                .build_display_list()
+                .items()
+                .iter()
+                .filter_map(|item| match item {
+                    super::super::DisplayItem::Text { content, y, .. } => Some((content.clone(), *y)),
+                    _ => None,
+                })
+                .collect()
+        }
+        let shown = painted("<section><div>ghost<span>peek</span></div><p>after</p></section>");
+        let hidden = painted("<section><div style=\"visibility: hidden\">ghost<span style=\"visibility: visible\">peek</span></div><p>after</p></section>");
+
+        assert!(!hidden.iter().any(|(text, _)| text == "ghost"), "painted: {:?}", hidden);
+        let after = |items: &[(String, f32)]| items.iter().find(|(text, _)| text == "after").map(|(_, y)| *y);
+        assert_eq!(after(&hidden), after(&shown));
+        assert!(after(&hidden).expect("following sibling") > 0.0);
+        // A descendant may opt back in
+        assert!(hidden.iter().any(|(text, _)| text == "peek"));
+    }
+}